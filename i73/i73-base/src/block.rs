@@ -1,28 +1,40 @@
-pub const AIR: Block = Block(0);
-pub const STONE: Block = Block(1 * 16);
-pub const GRASS: Block = Block(2 * 16);
-pub const DIRT: Block = Block(3 * 16);
-pub const BEDROCK: Block = Block(7 * 16);
-pub const FLOWING_WATER: Block = Block(8 * 16);
-pub const STILL_WATER: Block = Block(9 * 16);
-pub const FLOWING_LAVA: Block = Block(10 * 16);
-pub const STILL_LAVA: Block = Block(11 * 16);
-pub const SAND: Block = Block(12 * 16);
-pub const GRAVEL: Block = Block(13 * 16);
-pub const OAK_LOG: Block = Block(17 * 16);
-pub const OAK_LEAVES: Block = Block(18 * 16);
-pub const SANDSTONE: Block = Block(24 * 16);
-pub const TALL_GRASS: Block = Block(31 * 16 + 1);
-pub const FARMLAND: Block = Block(60 * 16);
-pub const ICE: Block = Block(79 * 16);
-pub const CLAY: Block = Block(82 * 16);
-pub const NETHERRACK: Block = Block(87 * 16);
+pub use blockstate::Block;
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
-pub struct Block(u16);
+pub const AIR: Block = Block::from_anvil_id(0);
+pub const STONE: Block = Block::from_anvil_id(1 * 16);
+pub const GRASS: Block = Block::from_anvil_id(2 * 16);
+pub const DIRT: Block = Block::from_anvil_id(3 * 16);
+pub const BEDROCK: Block = Block::from_anvil_id(7 * 16);
+pub const FLOWING_WATER: Block = Block::from_anvil_id(8 * 16);
+pub const STILL_WATER: Block = Block::from_anvil_id(9 * 16);
+pub const FLOWING_LAVA: Block = Block::from_anvil_id(10 * 16);
+pub const STILL_LAVA: Block = Block::from_anvil_id(11 * 16);
+pub const SAND: Block = Block::from_anvil_id(12 * 16);
+pub const GRAVEL: Block = Block::from_anvil_id(13 * 16);
+pub const OAK_LOG: Block = Block::from_anvil_id(17 * 16);
+pub const OAK_LEAVES: Block = Block::from_anvil_id(18 * 16);
+pub const SANDSTONE: Block = Block::from_anvil_id(24 * 16);
+pub const TALL_GRASS: Block = Block::from_anvil_id(31 * 16 + 1);
+pub const FARMLAND: Block = Block::from_anvil_id(60 * 16);
+pub const ICE: Block = Block::from_anvil_id(79 * 16);
+pub const CLAY: Block = Block::from_anvil_id(82 * 16);
+pub const NETHERRACK: Block = Block::from_anvil_id(87 * 16);
 
-impl Block {
-	pub fn to_anvil_id(self) -> u16 {
-		self.0
+#[cfg(test)]
+mod test {
+	use super::*;
+	use blockstate::BlockRegistry;
+
+	#[test]
+	fn test_constants_registered() {
+		let registry = BlockRegistry::builtin();
+
+		for &constant in &[STONE, GRASS, DIRT, BEDROCK, SAND, ICE, CLAY, NETHERRACK] {
+			let state = registry.state(constant).unwrap();
+
+			assert_eq!(registry.parse(&state.to_string()), Ok(constant));
+		}
+
+		assert_eq!(registry.state(FLOWING_LAVA).unwrap().legacy(), STILL_LAVA);
 	}
 }
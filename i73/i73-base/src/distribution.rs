@@ -1,10 +1,32 @@
 use java_rand::Random;
+use std::fmt::{self, Display};
 
 /// A random distribution.
 pub trait Distribution {
 	fn next(&self, rng: &mut Random) -> u32;
 }
 
+/// A distribution whose shape is only known at runtime, such as one parsed with `parse`.
+pub type BoxedDistribution = Box<dyn Distribution + Send + Sync>;
+
+impl<D> Distribution for Box<D>
+where
+	D: Distribution + ?Sized,
+{
+	fn next(&self, rng: &mut Random) -> u32 {
+		(**self).next(rng)
+	}
+}
+
+impl<D> Distribution for &D
+where
+	D: Distribution + ?Sized,
+{
+	fn next(&self, rng: &mut Random) -> u32 {
+		(**self).next(rng)
+	}
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ChanceOrdering {
 	AlwaysGeneratePayload,
@@ -140,3 +162,535 @@ impl Distribution for Centered {
 			- self.radius
 	}
 }
+
+/// A random value below a bound taken from another distribution, written as `Ri(X)` in the decoration list.
+/// For example, `Ri(Ri(120) + 8)` is a `Nested` around an `Offset` around a `Nested`.
+/// A bound of 0 always results in 0.
+#[derive(Debug)]
+pub struct Nested<D>
+where
+	D: Distribution,
+{
+	pub bound: D,
+}
+
+impl<D> Distribution for Nested<D>
+where
+	D: Distribution,
+{
+	fn next(&self, rng: &mut Random) -> u32 {
+		match self.bound.next(rng) {
+			0 => 0,
+			bound => rng.next_u32_bound(bound),
+		}
+	}
+}
+
+/// Distribution shifted by a constant amount, written as `X + 8` in the decoration list.
+#[derive(Debug)]
+pub struct Offset<D>
+where
+	D: Distribution,
+{
+	pub base: D,
+	pub offset: u32,
+}
+
+impl<D> Distribution for Offset<D>
+where
+	D: Distribution,
+{
+	fn next(&self, rng: &mut Random) -> u32 {
+		self.base.next(rng).saturating_add(self.offset)
+	}
+}
+
+/// Sum of two distributions, written as `A + B` in the decoration list. The left side is generated first.
+#[derive(Debug)]
+pub struct Sum<A, B>
+where
+	A: Distribution,
+	B: Distribution,
+{
+	pub a: A,
+	pub b: B,
+}
+
+impl<A, B> Distribution for Sum<A, B>
+where
+	A: Distribution,
+	B: Distribution,
+{
+	fn next(&self, rng: &mut Random) -> u32 {
+		let a = self.a.next(rng);
+
+		a.saturating_add(self.b.next(rng))
+	}
+}
+
+/// Distribution that only returns its value if the condition passes on it, and `otherwise` if not.
+#[derive(Debug)]
+pub struct Conditional<D>
+where
+	D: Distribution,
+{
+	pub base: D,
+	pub condition: Condition,
+	pub otherwise: u32,
+}
+
+impl<D> Distribution for Conditional<D>
+where
+	D: Distribution,
+{
+	fn next(&self, rng: &mut Random) -> u32 {
+		let value = self.base.next(rng);
+
+		if self.condition.check(value, rng) {
+			value
+		} else {
+			self.otherwise
+		}
+	}
+}
+
+/// A condition on a generated value, such as `Y < 64 || Ri(10)` in the decoration list.
+/// Random conditions only call the RNG when they are reached, so `||` and `&&` short circuit like in Java.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Condition {
+	Always,
+	/// `Y < X`
+	Below(u32),
+	/// `Y >= X`
+	AtLeast(u32),
+	/// `Ri(X)`, which passes with a chance of 1 in X, like `rand.nextInt(X) == 0`.
+	Chance(u32),
+	Not(Box<Condition>),
+	And(Box<Condition>, Box<Condition>),
+	Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+	pub fn check(&self, value: u32, rng: &mut Random) -> bool {
+		match *self {
+			Condition::Always => true,
+			Condition::Below(limit) => value < limit,
+			Condition::AtLeast(limit) => value >= limit,
+			Condition::Chance(chance) => chance <= 1 || rng.next_u32_bound(chance) == 0,
+			Condition::Not(ref condition) => !condition.check(value, rng),
+			Condition::And(ref a, ref b) => a.check(value, rng) && b.check(value, rng),
+			Condition::Or(ref a, ref b) => a.check(value, rng) || b.check(value, rng),
+		}
+	}
+
+	/// Parses a condition in the notation of the decoration list, such as `Y < 64 || Ri(10)`.
+	/// Supports comparisons of `Y` against constants, `Ri(X)`, `!`, `&&`, `||`, and parentheses.
+	pub fn parse(source: &str) -> Result<Condition, ParseError> {
+		let mut parser = Parser { source, position: 0 };
+		let condition = parser.condition()?;
+
+		parser.finish()?;
+		Ok(condition)
+	}
+}
+
+/// Where and how often a decorator runs in each chunk, as written in an entry of the decoration list.
+pub struct Placement {
+	/// Amount of attempts in each chunk, such as `Rare(4)` or `Common(8)`.
+	pub count: BoxedDistribution,
+	/// The Y coordinate of each attempt. The X and Z coordinates are always `Ri(16)`.
+	pub height: BoxedDistribution,
+	/// Whether the position is wrapped in `Offs`, which moves it to the center of the decorated quad.
+	pub offset: bool,
+	/// Checked against the Y coordinate after the position is generated, see `Condition`.
+	pub condition: Condition,
+}
+
+impl Placement {
+	/// Parses the parts of a decoration list entry. For example, the lava lakes are
+	/// `Placement::parse("Rare(8)", "Offs(Ri(16), Ri(Ri(120) + 8), Ri(16))", Some("Y < 64 || Ri(10)"))`.
+	///
+	/// Positions are either a full `(X, Y, Z)` triple or one of the shorthands `Uniform`, `YRange(Low, High)`, and
+	/// `YCenter(T)`, optionally wrapped in `Offs`.
+	pub fn parse(count: &str, position: &str, condition: Option<&str>) -> Result<Placement, ParseError> {
+		let mut parser = Parser { source: position, position: 0 };
+		let (height, offset) = parser.position()?;
+
+		parser.finish()?;
+
+		Ok(Placement {
+			count: parse(count)?,
+			height,
+			offset,
+			condition: match condition {
+				Some(condition) => Condition::parse(condition)?,
+				None => Condition::Always,
+			},
+		})
+	}
+}
+
+/// Parses a distribution in the notation of the decoration list, such as `Ri(Ri(120) + 8)`.
+/// Besides constants, `Ri(X)`, and `+`, this understands the counts `Rare(X)` and `Common(X)`,
+/// and the heights of the position shorthands `Uniform`, `YRange(Low, High)`, and `YCenter(T)`.
+pub fn parse(source: &str) -> Result<BoxedDistribution, ParseError> {
+	let mut parser = Parser { source, position: 0 };
+	let distribution = parser.sum()?;
+
+	parser.finish()?;
+	Ok(distribution)
+}
+
+/// An error in the notation of a distribution, position, or condition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+	/// Byte offset of the error in the source.
+	pub position: usize,
+	pub expected: &'static str,
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "expected {} at offset {}", self.expected, self.position)
+	}
+}
+
+impl ::std::error::Error for ParseError {}
+
+/// Recursive descent parser for the decoration list notation.
+struct Parser<'a> {
+	source: &'a str,
+	position: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn rest(&self) -> &'a str {
+		&self.source[self.position..]
+	}
+
+	fn skip_whitespace(&mut self) {
+		let rest = self.rest();
+
+		self.position += rest.len() - rest.trim_start().len();
+	}
+
+	fn error<T>(&self, expected: &'static str) -> Result<T, ParseError> {
+		Err(ParseError { position: self.position, expected })
+	}
+
+	/// Consumes the token if it is next.
+	fn eat(&mut self, token: &str) -> bool {
+		self.skip_whitespace();
+
+		if self.rest().starts_with(token) {
+			self.position += token.len();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn expect(&mut self, token: &str, expected: &'static str) -> Result<(), ParseError> {
+		if self.eat(token) {
+			Ok(())
+		} else {
+			self.error(expected)
+		}
+	}
+
+	fn finish(&mut self) -> Result<(), ParseError> {
+		self.skip_whitespace();
+
+		if self.rest().is_empty() {
+			Ok(())
+		} else {
+			self.error("end of input")
+		}
+	}
+
+	fn identifier(&mut self) -> &'a str {
+		self.skip_whitespace();
+
+		let rest = self.rest();
+		let length = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+
+		self.position += length;
+		&rest[..length]
+	}
+
+	fn number(&mut self) -> Result<u32, ParseError> {
+		self.skip_whitespace();
+
+		let rest = self.rest();
+		let length = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+
+		match rest[..length].parse() {
+			Ok(value) => {
+				self.position += length;
+				Ok(value)
+			}
+			Err(_) => self.error("a number"),
+		}
+	}
+
+	/// Parses a number and adds one to it, for comparisons that include their bound.
+	fn successor(&mut self) -> Result<u32, ParseError> {
+		let start = self.position;
+
+		match self.number()?.checked_add(1) {
+			Some(value) => Ok(value),
+			None => {
+				self.position = start;
+				self.error("a number below 4294967295")
+			}
+		}
+	}
+
+	/// Parses the arguments of a shorthand after its opening parenthesis.
+	fn arguments(&mut self, count: usize) -> Result<Vec<u32>, ParseError> {
+		let mut arguments = Vec::with_capacity(count);
+
+		for index in 0..count {
+			if index != 0 {
+				self.expect(",", "`,`")?;
+			}
+
+			arguments.push(self.number()?);
+		}
+
+		self.expect(")", "`)`")?;
+		Ok(arguments)
+	}
+
+	fn sum(&mut self) -> Result<BoxedDistribution, ParseError> {
+		let mut distribution = self.term()?;
+
+		while self.eat("+") {
+			distribution = Box::new(Sum { a: distribution, b: self.term()? });
+		}
+
+		Ok(distribution)
+	}
+
+	fn term(&mut self) -> Result<BoxedDistribution, ParseError> {
+		self.skip_whitespace();
+
+		if self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+			return Ok(Box::new(self.number()?));
+		}
+
+		if self.eat("(") {
+			let distribution = self.sum()?;
+
+			self.expect(")", "`)`")?;
+			return Ok(distribution);
+		}
+
+		let start = self.position;
+		let name = self.identifier();
+
+		if name == "Uniform" {
+			return Ok(Box::new(Nested { bound: 128 }));
+		}
+
+		if !self.eat("(") {
+			self.position = start;
+			return self.error("a distribution");
+		}
+
+		Ok(match name {
+			"Ri" => {
+				let bound = self.sum()?;
+
+				self.expect(")", "`)`")?;
+				Box::new(Nested { bound })
+			}
+			"Common" => {
+				let count = self.sum()?;
+
+				self.expect(")", "`)`")?;
+				count
+			}
+			"Rare" => {
+				let chance = self.arguments(1)?[0];
+
+				Box::new(Chance { base: 1, chance, ordering: ChanceOrdering::AlwaysGeneratePayload })
+			}
+			"YRange" => {
+				let arguments = self.arguments(2)?;
+
+				if arguments[1] < arguments[0] {
+					return self.error("a range with High at least Low");
+				}
+
+				Box::new(Offset { base: Nested { bound: arguments[1] - arguments[0] }, offset: arguments[0] })
+			}
+			"YCenter" => {
+				let spread = self.arguments(1)?[0];
+
+				Box::new(Sum { a: Nested { bound: spread }, b: Nested { bound: spread } })
+			}
+			_ => {
+				self.position = start;
+				return self.error("a distribution");
+			}
+		})
+	}
+
+	/// Parses a position and returns its height, and whether it is wrapped in `Offs`.
+	fn position(&mut self) -> Result<(BoxedDistribution, bool), ParseError> {
+		let offset = self.eat("Offs(");
+		let parenthesized = offset || self.eat("(");
+
+		let checkpoint = self.position;
+		let first = self.sum()?;
+
+		let height = if self.eat(",") {
+			// The first component was X. Only the notation `Ri(16)` is supported horizontally, since decorators
+			// always place relative to a chunk.
+			if self.source[checkpoint..self.position - 1].split_whitespace().collect::<String>() != "Ri(16)" {
+				self.position = checkpoint;
+				return self.error("`Ri(16)` for X");
+			}
+
+			let height = self.sum()?;
+
+			self.expect(",", "`,`")?;
+
+			let z = self.position;
+
+			self.sum()?;
+
+			if self.source[z..self.position].split_whitespace().collect::<String>() != "Ri(16)" {
+				self.position = z;
+				return self.error("`Ri(16)` for Z");
+			}
+
+			height
+		} else {
+			first
+		};
+
+		// Closes either `Offs(` or `(`, which are optional around shorthands.
+		if parenthesized {
+			self.expect(")", "`)`")?;
+		}
+
+		Ok((height, offset))
+	}
+
+	fn condition(&mut self) -> Result<Condition, ParseError> {
+		let mut condition = self.conjunction()?;
+
+		while self.eat("||") {
+			condition = Condition::Or(Box::new(condition), Box::new(self.conjunction()?));
+		}
+
+		Ok(condition)
+	}
+
+	fn conjunction(&mut self) -> Result<Condition, ParseError> {
+		let mut condition = self.atom()?;
+
+		while self.eat("&&") {
+			condition = Condition::And(Box::new(condition), Box::new(self.atom()?));
+		}
+
+		Ok(condition)
+	}
+
+	fn atom(&mut self) -> Result<Condition, ParseError> {
+		if self.eat("!") {
+			return Ok(Condition::Not(Box::new(self.atom()?)));
+		}
+
+		if self.eat("(") {
+			let condition = self.condition()?;
+
+			self.expect(")", "`)`")?;
+			return Ok(condition);
+		}
+
+		let start = self.position;
+
+		match self.identifier() {
+			"true" => Ok(Condition::Always),
+			"Ri" => {
+				self.expect("(", "`(`")?;
+				Ok(Condition::Chance(self.arguments(1)?[0]))
+			}
+			"Y" => {
+				if self.eat("<=") {
+					Ok(Condition::Below(self.successor()?))
+				} else if self.eat(">=") {
+					Ok(Condition::AtLeast(self.number()?))
+				} else if self.eat("<") {
+					Ok(Condition::Below(self.number()?))
+				} else if self.eat(">") {
+					Ok(Condition::AtLeast(self.successor()?))
+				} else {
+					self.error("a comparison")
+				}
+			}
+			_ => {
+				self.position = start;
+				self.error("a condition")
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_notation() {
+		let placement =
+			Placement::parse("Rare(8)", "Offs(Ri(16), Ri(Ri(120) + 8), Ri(16))", Some("Y < 64 || Ri(10)")).unwrap();
+
+		assert!(placement.offset);
+		assert_eq!(
+			placement.condition,
+			Condition::Or(Box::new(Condition::Below(64)), Box::new(Condition::Chance(10)))
+		);
+
+		// The parsed distributions use the RNG in the same order as the hand written code.
+		let (mut parsed, mut manual) = (Random::new(73), Random::new(73));
+
+		for _ in 0..64 {
+			let count = placement.count.next(&mut parsed);
+			let height = placement.height.next(&mut parsed);
+
+			let expected_count = if manual.next_u32_bound(8) == 0 { 1 } else { 0 };
+			let bound = manual.next_u32_bound(120) + 8;
+
+			assert_eq!((count, height), (expected_count, manual.next_u32_bound(bound)));
+			assert_eq!(placement.condition.check(height, &mut parsed), height < 64 || manual.next_u32_bound(10) == 0);
+		}
+
+		let vein = Placement::parse("Common(20)", "YRange(0, 64)", None).unwrap();
+		let mut rng = Random::new(0);
+
+		assert!(!vein.offset);
+		assert_eq!(vein.count.next(&mut rng), 20);
+		assert!((0..256).all(|_| vein.height.next(&mut rng) < 64));
+
+		assert_eq!(parse("Ri(5) +").err().map(|error| error.position), Some(7));
+		assert_eq!(Condition::parse("Y > 3 && !Ri(1)"), Ok(Condition::And(
+			Box::new(Condition::AtLeast(4)),
+			Box::new(Condition::Not(Box::new(Condition::Chance(1))))
+		)));
+		assert!(Placement::parse("Common(1)", "(Ri(8), Ri(128), Ri(16))", None).is_err());
+
+		// Parentheses around positions must be closed.
+		assert_eq!(Placement::parse("Rare(4)", "Offs(Uniform", None).err().map(|error| error.position), Some(12));
+		assert!(Placement::parse("Common(1)", "(Ri(16), Ri(128), Ri(16)", None).is_err());
+		assert!(Placement::parse("Common(1)", "(Ri(16), Ri(128), Ri(16))", None).is_ok());
+
+		// Large constants saturate instead of overflowing.
+		let large = parse("Ri(5) + 4294967295").unwrap();
+		assert_eq!(large.next(&mut rng), u32::MAX);
+		assert!(Condition::parse("Y <= 4294967295").is_err());
+	}
+}
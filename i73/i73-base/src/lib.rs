@@ -1,4 +1,4 @@
-extern crate fxhash;
+extern crate blockstate;
 extern crate java_rand;
 extern crate vocs;
 
@@ -7,6 +7,8 @@ pub mod distribution;
 pub mod matcher;
 pub mod math;
 
+pub use blockstate::registry;
+
 use block::Block;
 use vocs::position::GlobalColumnPosition;
 use vocs::view::ColumnMut;
@@ -1,59 +1,258 @@
 //! Types for matching against specific block.
-//! TODO: Replace with sparse bit array in `vocs`.
 //! Generic types are not configurable and are a band aid.
 //! A component-based solution, in comparison, would be much more configurable.
 use crate::Block;
-use fxhash::FxHashSet;
 use std::iter::{FromIterator, IntoIterator, Iterator};
+use vocs::indexed::IndexedCube;
 
-#[derive(Debug, Clone)]
+/// A set of blocks, stored as a dense bitset over Anvil IDs. Matching a block is a single bit lookup, and matchers
+/// can be composed with `union`, `intersection`, and `invert`.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct BlockMatcher {
-	pub blocks: FxHashSet<Block>,
-	pub blacklist: bool,
+	/// One bit for each Anvil ID, up to the last word that differs from `rest`.
+	bits: Vec<u64>,
+	/// Whether blocks with IDs past the end of `bits` match.
+	rest: bool,
 }
 
 impl BlockMatcher {
 	pub fn all() -> Self {
-		BlockMatcher { blocks: FxHashSet::default(), blacklist: true }
+		BlockMatcher { bits: Vec::new(), rest: true }
 	}
 
 	pub fn none() -> Self {
-		BlockMatcher { blocks: FxHashSet::default(), blacklist: false }
+		BlockMatcher { bits: Vec::new(), rest: false }
 	}
 
 	pub fn is(block: Block) -> Self {
-		let mut blocks = FxHashSet::default();
-		blocks.insert(block);
+		let mut matcher = BlockMatcher::none();
+		matcher.insert(block);
 
-		BlockMatcher { blocks, blacklist: false }
+		matcher
 	}
 
 	pub fn is_not(block: Block) -> Self {
-		let mut blocks = FxHashSet::default();
-		blocks.insert(block);
+		let mut matcher = BlockMatcher::all();
+		matcher.remove(block);
 
-		BlockMatcher { blocks, blacklist: true }
+		matcher
 	}
 
 	pub fn include<'a, I>(blocks: I) -> Self
 	where
 		I: IntoIterator<Item = &'a Block>,
 	{
-		BlockMatcher { blocks: FxHashSet::from_iter(blocks.into_iter().cloned()), blacklist: false }
+		let mut matcher = BlockMatcher::none();
+
+		for &block in blocks {
+			matcher.insert(block);
+		}
+
+		matcher
 	}
 
 	pub fn exclude<'a, I>(blocks: I) -> Self
 	where
 		I: IntoIterator<Item = &'a Block>,
 	{
-		BlockMatcher { blocks: FxHashSet::from_iter(blocks.into_iter().cloned()), blacklist: true }
+		BlockMatcher::include(blocks).invert()
+	}
+
+	/// Makes the matcher match this block.
+	pub fn insert(&mut self, block: Block) {
+		self.set(block, true);
+	}
+
+	/// Makes the matcher stop matching this block.
+	pub fn remove(&mut self, block: Block) {
+		self.set(block, false);
+	}
+
+	fn set(&mut self, block: Block, value: bool) {
+		let (word, bit) = BlockMatcher::split(block);
+
+		if word >= self.bits.len() {
+			if value == self.rest {
+				return;
+			}
+
+			let fill = if self.rest { !0 } else { 0 };
+			self.bits.resize(word + 1, fill);
+		}
+
+		if value {
+			self.bits[word] |= 1 << bit;
+		} else {
+			self.bits[word] &= !(1 << bit);
+		}
+
+		self.trim();
+	}
+
+	fn split(block: Block) -> (usize, u32) {
+		let id = block.to_anvil_id();
+
+		((id >> 6) as usize, (id & 63) as u32)
+	}
+
+	/// Removes trailing words that are the same as `rest`, so that equal matchers compare as equal.
+	fn trim(&mut self) {
+		let fill = if self.rest { !0 } else { 0 };
+
+		while self.bits.last() == Some(&fill) {
+			self.bits.pop();
+		}
+	}
+
+	/// Combines two matchers word by word.
+	fn combine<F>(&self, other: &BlockMatcher, op: F) -> Self
+	where
+		F: Fn(u64, u64) -> u64,
+	{
+		let fill = |rest| if rest { !0 } else { 0 };
+		let len = self.bits.len().max(other.bits.len());
+
+		let word = |matcher: &BlockMatcher, index: usize| {
+			matcher.bits.get(index).copied().unwrap_or_else(|| fill(matcher.rest))
+		};
+
+		let mut combined = BlockMatcher {
+			bits: (0..len).map(|index| op(word(self, index), word(other, index))).collect(),
+			rest: op(fill(self.rest), fill(other.rest)) != 0,
+		};
+
+		combined.trim();
+		combined
+	}
+
+	/// Returns a matcher that matches blocks matched by either matcher.
+	pub fn union(&self, other: &BlockMatcher) -> Self {
+		self.combine(other, |a, b| a | b)
+	}
+
+	/// Returns a matcher that matches blocks matched by both matchers.
+	pub fn intersection(&self, other: &BlockMatcher) -> Self {
+		self.combine(other, |a, b| a & b)
+	}
+
+	/// Returns a matcher that matches every block that this matcher does not.
+	pub fn invert(&self) -> Self {
+		BlockMatcher { bits: self.bits.iter().map(|word| !word).collect(), rest: !self.rest }
 	}
 
 	pub fn matches(&self, block: &Block) -> bool {
-		// NotPresent, Whitelist => 0 ^ 0 => 0
-		// NotPresent, Blacklist => 0 ^ 1 => 1
-		// Contains, Whitelist => 1 ^ 0 => 1
-		// Contains, Blacklist => 1 ^ 1 => 0
-		self.blocks.contains(block) ^ self.blacklist
+		let (word, bit) = BlockMatcher::split(*block);
+
+		match self.bits.get(word) {
+			Some(word) => (word >> bit) & 1 == 1,
+			None => self.rest,
+		}
+	}
+
+	/// Matches every entry of a palette ahead of time, so that blocks can be matched by their palette index.
+	pub fn palette_mask(&self, entries: &[Option<Block>]) -> PaletteMask {
+		let mut mask = PaletteMask { bits: vec![0; entries.len().div_ceil(64)], present: 0, matching: 0 };
+
+		for (index, entry) in entries.iter().enumerate() {
+			let block = match entry {
+				Some(block) => block,
+				None => continue,
+			};
+
+			mask.present += 1;
+
+			if self.matches(block) {
+				mask.bits[index / 64] |= 1 << (index % 64);
+				mask.matching += 1;
+			}
+		}
+
+		mask
+	}
+
+	/// Returns the palette mask of a chunk, indexed by the values in its storage.
+	pub fn chunk_mask(&self, chunk: &IndexedCube<Block>) -> PaletteMask {
+		self.palette_mask(chunk.freeze().1)
+	}
+}
+
+impl FromIterator<Block> for BlockMatcher {
+	fn from_iter<I>(blocks: I) -> Self
+	where
+		I: IntoIterator<Item = Block>,
+	{
+		let mut matcher = BlockMatcher::none();
+
+		for block in blocks {
+			matcher.insert(block);
+		}
+
+		matcher
+	}
+}
+
+/// The result of a `BlockMatcher` for each entry of a palette, see `BlockMatcher::palette_mask`.
+/// Palettes may contain entries that are no longer used by any block, so `none` and `all` are exact in only one
+/// direction: if no entry matches, then no block in the chunk matches, and if every entry matches, then every block does.
+#[derive(Debug, Clone)]
+pub struct PaletteMask {
+	bits: Vec<u64>,
+	present: usize,
+	matching: usize,
+}
+
+impl PaletteMask {
+	/// Returns whether the palette entry at this index matches. Empty entries never match.
+	pub fn get(&self, index: u32) -> bool {
+		let index = index as usize;
+
+		self.bits.get(index / 64).map(|word| (word >> (index % 64)) & 1 == 1).unwrap_or(false)
+	}
+
+	/// Returns true if no palette entry matches, in which case no block in the chunk matches.
+	pub fn none(&self) -> bool {
+		self.matching == 0
+	}
+
+	/// Returns true if every palette entry matches, in which case every block in the chunk matches.
+	pub fn all(&self) -> bool {
+		self.matching == self.present
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::block;
+	use vocs::position::CubePosition;
+
+	#[test]
+	fn test_set_algebra() {
+		let liquids = BlockMatcher::include(&[block::FLOWING_WATER, block::STILL_WATER, block::STILL_LAVA]);
+		let water = BlockMatcher::include(&[block::FLOWING_WATER, block::STILL_WATER]);
+
+		assert!(liquids.matches(&block::STILL_LAVA) && !liquids.matches(&block::STONE));
+		assert_eq!(liquids.intersection(&water), water);
+		assert_eq!(water.union(&BlockMatcher::is(block::STILL_LAVA)), liquids);
+		assert_eq!(water.union(&water.invert()), BlockMatcher::all());
+		assert_eq!(water.intersection(&water.invert()), BlockMatcher::none());
+
+		let solid = BlockMatcher::exclude(&[block::AIR, block::FLOWING_WATER, block::STILL_WATER]);
+
+		assert_eq!(solid, BlockMatcher::is_not(block::AIR).intersection(&water.invert()));
+		assert!(solid.matches(&Block::from_legacy(4095, 15)));
+		assert!(!solid.matches(&block::AIR));
+
+		let mut chunk = IndexedCube::uniform(block::STONE);
+		assert!(water.chunk_mask(&chunk).none() && solid.chunk_mask(&chunk).all());
+
+		chunk.set_immediate(CubePosition::new(1, 2, 3), &block::STILL_WATER);
+
+		let mask = water.chunk_mask(&chunk);
+		let (storage, _) = chunk.freeze();
+
+		assert!(!mask.none() && !mask.all());
+		assert!(mask.get(storage.get(CubePosition::new(1, 2, 3))));
+		assert!(!mask.get(storage.get(CubePosition::new(0, 0, 0))));
 	}
 }
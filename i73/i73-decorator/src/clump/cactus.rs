@@ -1,9 +1,9 @@
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
 use i73_base::block::Block;
 use java_rand::Random;
-use vocs::position::{dir, Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{dir, NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 pub struct CactusDecorator {
 	pub blocks: CactusBlocks,
@@ -12,9 +12,10 @@ pub struct CactusDecorator {
 
 impl Decorator for CactusDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		if !self.blocks.replace.matches(quad.get(position)) {
+		if !self.blocks.replace.matches(block_at(neighborhood, position)) {
 			return Ok(());
 		}
 
@@ -29,8 +30,8 @@ impl Decorator for CactusDecorator {
 				None => return Ok(()),
 			};
 
-			if self.blocks.check(quad, position) {
-				quad.set_immediate(position, &self.blocks.block);
+			if self.blocks.check(neighborhood, position) {
+				neighborhood.set_immediate(position, &self.blocks.block)?;
 			}
 		}
 
@@ -46,31 +47,31 @@ pub struct CactusBlocks {
 }
 
 impl CactusBlocks {
-	pub fn check(&self, quad: &mut QuadMut<Block>, position: QuadPosition) -> bool {
-		if !self.replace.matches(quad.get(position)) {
+	pub fn check(&self, neighborhood: &NeighborhoodMut<Block>, position: NeighborhoodPosition) -> bool {
+		if !self.replace.matches(block_at(neighborhood, position)) {
 			return false;
 		}
 
 		if let Some(minus_x) = position.offset(dir::MinusX) {
-			if self.solid.matches(quad.get(minus_x)) {
+			if self.solid.matches(block_at(neighborhood, minus_x)) {
 				return false;
 			}
 		}
 
 		if let Some(plus_x) = position.offset(dir::PlusX) {
-			if self.solid.matches(quad.get(plus_x)) {
+			if self.solid.matches(block_at(neighborhood, plus_x)) {
 				return false;
 			}
 		}
 
 		if let Some(minus_z) = position.offset(dir::MinusZ) {
-			if self.solid.matches(quad.get(minus_z)) {
+			if self.solid.matches(block_at(neighborhood, minus_z)) {
 				return false;
 			}
 		}
 
 		if let Some(plus_z) = position.offset(dir::PlusZ) {
-			if self.solid.matches(quad.get(plus_z)) {
+			if self.solid.matches(block_at(neighborhood, plus_z)) {
 				return false;
 			}
 		}
@@ -80,7 +81,7 @@ impl CactusBlocks {
 			None => return false,
 		};
 
-		self.base.matches(quad.get(below))
+		self.base.matches(block_at(neighborhood, below))
 	}
 }
 
@@ -1,8 +1,8 @@
 use crate::{Decorator, Result};
 use i73_base::block::Block;
 use java_rand::Random;
-use vocs::position::{Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 pub mod cactus;
 pub mod plant;
@@ -14,7 +14,7 @@ where
 	D: Decorator,
 {
 	pub iterations: u32,
-	/// Horizontal variance. Must be 8 or below, or else spilling will occur.
+	/// Horizontal variance. Above 8, objects may be placed outside of the quad and spill.
 	pub horizontal: u8,
 	/// Vertical variance.
 	pub vertical: u8,
@@ -26,7 +26,8 @@ where
 	D: Decorator,
 {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
 		for _ in 0..self.iterations {
 			let offset = (
@@ -37,7 +38,7 @@ where
 					- rng.next_i32_bound(self.horizontal as i32),
 			);
 
-			if (position.y() as i32) + offset.1 < 0 {
+			if position.y() + offset.1 < 0 {
 				continue;
 			}
 
@@ -48,7 +49,7 @@ where
 				}
 			};
 
-			self.decorator.generate(quad, rng, at)?;
+			self.decorator.generate(neighborhood, rng, at)?;
 		}
 
 		Ok(())
@@ -60,7 +61,7 @@ where
 	D: Decorator,
 {
 	pub iterations: u32,
-	/// Horizontal variance. Must be 8 or below, or else spilling will occur.
+	/// Horizontal variance. Above 8, objects may be placed outside of the quad and spill.
 	pub horizontal: u8,
 	pub decorator: D,
 }
@@ -70,7 +71,8 @@ where
 	D: Decorator,
 {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
 		for _ in 0..self.iterations {
 			let offset = (
@@ -82,7 +84,7 @@ where
 
 			let at = position.offset((offset.0 as i8, 0, offset.1 as i8)).unwrap();
 
-			self.decorator.generate(quad, rng, at)?;
+			self.decorator.generate(neighborhood, rng, at)?;
 		}
 
 		Ok(())
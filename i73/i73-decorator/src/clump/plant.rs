@@ -1,9 +1,9 @@
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
 use i73_base::block::Block;
 use java_rand::Random;
-use vocs::position::{dir, Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{dir, NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 // Pumpkin: On grass, replacing air or {material:ground_cover}
 
@@ -15,25 +15,24 @@ pub struct PlantDecorator {
 
 impl Decorator for PlantDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, _: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, _: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
 		// TODO: Check if the block is above the heightmap (how?)
 
-		if !self.replace.matches(quad.get(position)) {
+		if !self.replace.matches(block_at(neighborhood, position)) {
 			return Ok(());
 		}
 
 		match position.offset(dir::Down) {
 			Some(below) => {
-				if !self.base.matches(quad.get(below)) {
+				if !self.base.matches(block_at(neighborhood, below)) {
 					return Ok(());
 				}
 			}
 			None => return Ok(()),
 		}
 
-		quad.set_immediate(position, &self.block);
-
-		Ok(())
+		neighborhood.set_immediate(position, &self.block)
 	}
 }
@@ -1,9 +1,9 @@
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
 use i73_base::block::Block;
 use java_rand::Random;
-use vocs::position::{dir, Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{dir, NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 pub struct SugarCaneDecorator {
 	pub block: Block,
@@ -16,9 +16,10 @@ pub struct SugarCaneDecorator {
 
 impl Decorator for SugarCaneDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		if !self.replace.matches(quad.get(position)) {
+		if !self.replace.matches(block_at(neighborhood, position)) {
 			return Ok(());
 		}
 
@@ -27,33 +28,33 @@ impl Decorator for SugarCaneDecorator {
 			None => return Ok(()),
 		};
 
-		if *quad.get(below) != self.block {
-			if !self.base.matches(quad.get(below)) {
+		if *block_at(neighborhood, below) != self.block {
+			if !self.base.matches(block_at(neighborhood, below)) {
 				return Ok(());
 			}
 
 			let mut valid = false;
 
 			if let Some(minus_x) = below.offset(dir::MinusX) {
-				if self.liquid.matches(quad.get(minus_x)) {
+				if self.liquid.matches(block_at(neighborhood, minus_x)) {
 					valid = true;
 				}
 			}
 
 			if let Some(plus_x) = below.offset(dir::PlusX) {
-				if self.liquid.matches(quad.get(plus_x)) {
+				if self.liquid.matches(block_at(neighborhood, plus_x)) {
 					valid = true;
 				}
 			}
 
 			if let Some(minus_z) = below.offset(dir::MinusZ) {
-				if self.liquid.matches(quad.get(minus_z)) {
+				if self.liquid.matches(block_at(neighborhood, minus_z)) {
 					valid = true;
 				}
 			}
 
 			if let Some(plus_z) = below.offset(dir::PlusZ) {
-				if self.liquid.matches(quad.get(plus_z)) {
+				if self.liquid.matches(block_at(neighborhood, plus_z)) {
 					valid = true;
 				}
 			}
@@ -69,11 +70,11 @@ impl Decorator for SugarCaneDecorator {
 		let mut position = position;
 
 		for _ in 0..height {
-			if !self.replace.matches(quad.get(position)) {
+			if !self.replace.matches(block_at(neighborhood, position)) {
 				return Ok(());
 			}
 
-			quad.set_immediate(position, &self.block);
+			neighborhood.set_immediate(position, &self.block)?;
 
 			if let Some(at) = position.offset(dir::Up) {
 				position = at;
@@ -1,9 +1,9 @@
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
 use i73_base::block::Block;
 use java_rand::Random;
-use vocs::position::{dir, Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{dir, NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 pub struct ExposedDecorator {
 	pub block: Block,
@@ -13,15 +13,16 @@ pub struct ExposedDecorator {
 
 impl Decorator for ExposedDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, _: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, _: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		if !self.stone.matches(quad.get(position)) {
+		if !self.stone.matches(block_at(neighborhood, position)) {
 			return Ok(());
 		}
 
 		match position.offset(dir::Down) {
 			Some(below) => {
-				if !self.stone.matches(quad.get(below)) {
+				if !self.stone.matches(block_at(neighborhood, below)) {
 					return Ok(());
 				}
 			}
@@ -30,7 +31,7 @@ impl Decorator for ExposedDecorator {
 
 		match position.offset(dir::Up) {
 			Some(above) => {
-				if !self.stone.matches(quad.get(above)) {
+				if !self.stone.matches(block_at(neighborhood, above)) {
 					return Ok(());
 				}
 			}
@@ -41,7 +42,7 @@ impl Decorator for ExposedDecorator {
 		let mut empty = 0;
 
 		if let Some(position) = position.offset(dir::MinusX) {
-			let block = quad.get(position);
+			let block = block_at(neighborhood, position);
 
 			if self.stone.matches(block) {
 				stone += 1;
@@ -54,7 +55,7 @@ impl Decorator for ExposedDecorator {
 		}
 
 		if let Some(position) = position.offset(dir::PlusX) {
-			let block = quad.get(position);
+			let block = block_at(neighborhood, position);
 
 			if self.stone.matches(block) {
 				stone += 1;
@@ -67,7 +68,7 @@ impl Decorator for ExposedDecorator {
 		}
 
 		if let Some(position) = position.offset(dir::MinusZ) {
-			let block = quad.get(position);
+			let block = block_at(neighborhood, position);
 
 			if self.stone.matches(block) {
 				stone += 1;
@@ -80,7 +81,7 @@ impl Decorator for ExposedDecorator {
 		}
 
 		if let Some(position) = position.offset(dir::PlusZ) {
-			let block = quad.get(position);
+			let block = block_at(neighborhood, position);
 
 			if self.stone.matches(block) {
 				stone += 1;
@@ -93,7 +94,7 @@ impl Decorator for ExposedDecorator {
 		}
 
 		if stone == 3 && empty == 1 {
-			quad.set_immediate(position, &self.block);
+			neighborhood.set_immediate(position, &self.block)?;
 		}
 
 		Ok(())
@@ -1,11 +1,11 @@
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
 use i73_base::block::Block;
 use java_rand::Random;
 use vocs::component::*;
 use vocs::mask::BitCube;
-use vocs::position::{CubePosition, ColumnPosition, QuadPosition, LayerPosition};
-use vocs::view::QuadMut;
+use vocs::position::{CubePosition, LayerPosition, NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 // Since lakes are always 16x8x16, they will never escape the Quad.
 
@@ -17,14 +17,13 @@ pub struct LakeDecorator {
 
 impl Decorator for LakeDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		let mut lower = position.to_centered().unwrap();
+		let mut lower = position.offset((-8, 0, -8)).unwrap();
 
-		while lower.y() > 0
-			&& quad.get(QuadPosition::new(lower.x(), lower.y(), lower.z())) == &self.blocks.carve
-		{
-			lower = ColumnPosition::new(lower.x(), lower.y() - 1, lower.z());
+		while lower.y() > 0 && block_at(neighborhood, lower) == &self.blocks.carve {
+			lower = NeighborhoodPosition::new(lower.x(), lower.y() - 1, lower.z());
 		}
 
 		// Trying to access blocks below Y=0 returns air, which would cause lake generation to fail.
@@ -32,20 +31,18 @@ impl Decorator for LakeDecorator {
 			return Ok(());
 		}
 
-		lower = ColumnPosition::new(lower.x(), lower.y() - 4, lower.z());
+		lower = NeighborhoodPosition::new(lower.x(), lower.y() - 4, lower.z());
 
 		let mut lake = Lake::new(self.settings.surface);
 
 		lake.fill(LakeSpheroids::new(rng, &self.settings));
 		lake.update_border();
 
-		if !self.blocks.check_border(&lake, quad, lower) {
+		if !self.blocks.check_border(&lake, neighborhood, lower) {
 			return Ok(());
 		}
 
-		self.blocks.fill_and_carve(&lake, quad, lower);
-
-		Ok(())
+		self.blocks.fill_and_carve(&lake, neighborhood, lower)
 	}
 }
 
@@ -61,13 +58,13 @@ pub struct LakeBlocks {
 
 impl LakeBlocks {
 	pub fn check_border(
-		&self, lake: &Lake, quad: &QuadMut<Block>, lower: ColumnPosition,
+		&self, lake: &Lake, neighborhood: &NeighborhoodMut<Block>, lower: NeighborhoodPosition,
 	) -> bool {
 		for x in 0..16 {
 			for z in 0..16 {
 				for y in 0..lake.surface {
-					let at = QuadPosition::new(lower.x() + x, lower.y() + y, lower.z() + z);
-					let block = quad.get(at);
+					let at = lake_position(lower, x, y, z);
+					let block = block_at(neighborhood, at);
 
 					if lake.get(border(x, y, z))
 						&& *block != self.liquid && !self.is_solid.matches(block)
@@ -77,9 +74,9 @@ impl LakeBlocks {
 				}
 
 				for y in lake.surface..8 {
-					let at = QuadPosition::new(lower.x() + x, lower.y() + y, lower.z() + z);
+					let at = lake_position(lower, x, y, z);
 
-					if lake.get(border(x, y, z)) && self.is_liquid.matches(quad.get(at)) {
+					if lake.get(border(x, y, z)) && self.is_liquid.matches(block_at(neighborhood, at)) {
 						return false;
 					}
 				}
@@ -89,35 +86,39 @@ impl LakeBlocks {
 		return true;
 	}
 
-	pub fn fill_and_carve(&self, lake: &Lake, quad: &mut QuadMut<Block>, lower: ColumnPosition) {
-		quad.ensure_available(self.liquid.clone());
-		quad.ensure_available(self.carve.clone());
-
-		let (mut blocks, palette) = quad.freeze_palette();
+	pub fn fill_and_carve(
+		&self, lake: &Lake, neighborhood: &mut NeighborhoodMut<Block>, lower: NeighborhoodPosition,
+	) -> Result {
+		neighborhood.ensure_available(self.liquid.clone());
+		neighborhood.ensure_available(self.carve.clone());
 
-		let liquid = palette.reverse_lookup(&self.liquid).unwrap();
-		let carve = palette.reverse_lookup(&self.carve).unwrap();
+		neighborhood.edit(|mut blocks, palette| {
+			let liquid = palette.reverse_lookup(&self.liquid).unwrap();
+			let carve = palette.reverse_lookup(&self.carve).unwrap();
 
-		for horizontal_position in LayerPosition::enumerate() {
-			let x = horizontal_position.x();
-			let z = horizontal_position.z();
+			for horizontal_position in LayerPosition::enumerate() {
+				let x = horizontal_position.x();
+				let z = horizontal_position.z();
 
-			for y in 0..lake.surface {
-				let at = QuadPosition::new(lower.x() + x, lower.y() + y, lower.z() + z);
+				for y in 0..lake.surface {
+					let at = lake_position(lower, x, y, z);
 
-				if lake.get(volume(x, y, z)) {
-					blocks.set(at, &liquid);
+					if lake.get(volume(x, y, z)) {
+						blocks.set(at, &liquid)?;
+					}
 				}
-			}
 
-			for y in lake.surface..8 {
-				let at = QuadPosition::new(lower.x() + x, lower.y() + y, lower.z() + z);
+				for y in lake.surface..8 {
+					let at = lake_position(lower, x, y, z);
 
-				if lake.get(volume(x, y, z)) {
-					blocks.set(at, &carve);
+					if lake.get(volume(x, y, z)) {
+						blocks.set(at, &carve)?;
+					}
 				}
 			}
-		}
+
+			Ok(())
+		})
 	}
 
 	// TODO: grow_grass, solidify_border
@@ -184,6 +185,11 @@ pub struct Spheroid {
 	pub radius: (f64, f64, f64),
 }
 
+/// Converts a position within the 16x8x16 volume of a lake to a position in the neighborhood.
+fn lake_position(lower: NeighborhoodPosition, x: u8, y: u8, z: u8) -> NeighborhoodPosition {
+	NeighborhoodPosition::new(lower.x() + x as i32, lower.y() + y as i32, lower.z() + z as i32)
+}
+
 pub fn volume(x: u8, y: u8, z: u8) -> CubePosition {
 	CubePosition::new(x, y % 8, z)
 }
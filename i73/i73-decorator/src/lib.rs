@@ -3,11 +3,11 @@ extern crate i73_trig;
 extern crate java_rand;
 extern crate vocs;
 
-use i73_base::distribution::Distribution;
-use i73_base::block::Block;
+use i73_base::distribution::{BoxedDistribution, Condition, Distribution, Placement};
+use i73_base::block::{self, Block};
 use java_rand::Random;
-use vocs::position::{ColumnPosition, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{ColumnPosition, NeighborhoodPosition, QuadPosition};
+use vocs::view::{NeighborhoodMut, Spilled};
 
 mod line;
 
@@ -20,10 +20,15 @@ pub mod vein;
 
 // TODO: MultiDispatcher
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub struct Spilled(pub QuadPosition);
+/// Decorators write through a `NeighborhoodMut`, so edits that fall outside of it or into missing chunks are handled
+/// by its spill policy. With `SpillPolicy::Record`, this only fails for edits that no chunk could ever contain.
 pub type Result = ::std::result::Result<(), Spilled>;
 
+/// Returns the block at the position. Like in Beta, blocks outside of the neighborhood or in missing chunks read as air.
+pub fn block_at<'n>(neighborhood: &'n NeighborhoodMut<'_, Block>, at: NeighborhoodPosition) -> &'n Block {
+	neighborhood.get(at).unwrap_or(&block::AIR)
+}
+
 pub struct Dispatcher<H, R>
 where
 	H: Distribution,
@@ -31,6 +36,10 @@ where
 {
 	pub height_distribution: H,
 	pub rarity: R,
+	/// Whether positions are moved to the center of the quad, written as `Offs` in the decoration list.
+	pub offset: bool,
+	/// Checked against the height of each position before running the decorator.
+	pub condition: Condition,
 	pub decorator: Box<dyn Decorator>,
 }
 
@@ -39,23 +48,48 @@ where
 	H: Distribution,
 	R: Distribution,
 {
-	pub fn generate(&self, quad: &mut QuadMut<Block>, rng: &mut Random) -> Result {
+	/// Runs the decorator at random positions in the quad in the -X, -Z corner of the neighborhood.
+	pub fn generate(&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random) -> Result {
 		for _ in 0..self.rarity.next(rng) {
-			let at = ColumnPosition::new(
+			let (x, y, z) = (
 				rng.next_u32_bound(16) as u8,
-				self.height_distribution.next(rng) as u8,
+				self.height_distribution.next(rng) as u16,
 				rng.next_u32_bound(16) as u8,
 			);
 
-			self.decorator.generate(quad, rng, QuadPosition::from_centered(at))?;
+			if !self.condition.check(y as u32, rng) {
+				continue;
+			}
+
+			let position = if self.offset {
+				QuadPosition::from_centered(ColumnPosition::new(x, y, z))
+			} else {
+				QuadPosition::new(x, y, z)
+			};
+
+			self.decorator.generate(neighborhood, rng, NeighborhoodPosition::from_quad(position))?;
 		}
 
 		Ok(())
 	}
 }
 
-pub trait Decorator {
+impl Dispatcher<BoxedDistribution, BoxedDistribution> {
+	/// Creates a dispatcher from an entry of the decoration list, see `Placement::parse`.
+	pub fn from_placement(placement: Placement, decorator: Box<dyn Decorator>) -> Self {
+		Dispatcher {
+			height_distribution: placement.height,
+			rarity: placement.count,
+			offset: placement.offset,
+			condition: placement.condition,
+			decorator,
+		}
+	}
+}
+
+pub trait Decorator: Send + Sync {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result;
 }
@@ -1,22 +1,18 @@
 use std::cmp;
-use vocs::position::QuadPosition;
+use vocs::position::NeighborhoodPosition;
 
 // TODO: This should be close enough, but is unverified.
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct Line {
-	pub from: QuadPosition,
-	pub to: QuadPosition,
+	pub from: NeighborhoodPosition,
+	pub to: NeighborhoodPosition,
 }
 
 impl Line {
 	/// Offset that needs to be applied to `from` to get `to`.
-	pub fn offset(&self) -> (i8, i8, i8) {
-		(
-			(self.to.x() as i8) - (self.from.x() as i8),
-			(self.to.y() as i8) - (self.from.y() as i8),
-			(self.to.z() as i8) - (self.from.z() as i8),
-		)
+	pub fn offset(&self) -> (i32, i32, i32) {
+		(self.to.x() - self.from.x(), self.to.y() - self.from.y(), self.to.z() - self.from.z())
 	}
 
 	pub fn trace(&self) -> LineTracer {
@@ -45,7 +41,7 @@ pub struct LineTracer {
 }
 
 impl Iterator for LineTracer {
-	type Item = QuadPosition;
+	type Item = NeighborhoodPosition;
 
 	fn next(&mut self) -> Option<Self::Item> {
 		if self.iterations >= self.steps {
@@ -59,10 +55,10 @@ impl Iterator for LineTracer {
 		];
 
 		self.position = (position[0], position[1], position[2]);
-		let position = QuadPosition::new(
-			(position[0] + 0.5).floor() as u8,
-			(position[1] + 0.5).floor() as u8,
-			(position[2] + 0.5).floor() as u8,
+		let position = NeighborhoodPosition::new(
+			(position[0] + 0.5).floor() as i32,
+			(position[1] + 0.5).floor() as i32,
+			(position[2] + 0.5).floor() as i32,
 		);
 
 		self.iterations += 1;
@@ -1,13 +1,13 @@
 use super::{FoliageLayer, TreeBlocks};
 use crate::line::Line;
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
-use i73_base::block::Block;
+use i73_base::block::{self, Block};
 use java_rand::Random;
 use std::cmp::min;
 use std::i32;
-use vocs::position::{dir, Offset, QuadPosition};
-use vocs::view::{QuadAssociation, QuadBlocks, QuadMut, QuadPalettes};
+use vocs::position::{dir, NeighborhoodPosition, Offset};
+use vocs::view::{NeighborhoodAssociation, NeighborhoodBlocks, NeighborhoodMut, NeighborhoodPalettes};
 
 const TAU: f64 = 2.0 * 3.14159;
 
@@ -18,19 +18,22 @@ pub struct LargeTreeDecorator {
 }
 
 impl LargeTreeDecorator {
-	fn place_trunk(
-		&self, position: QuadPosition, blocks: &mut QuadBlocks, palette: &QuadPalettes<Block>,
-		log: &QuadAssociation, trunk_height: i32,
-	) {
+	fn place_trunk<'b>(
+		&self, position: NeighborhoodPosition, blocks: &mut NeighborhoodBlocks<'_, 'b, Block>,
+		palette: &NeighborhoodPalettes<'_, 'b, Block>, log: &NeighborhoodAssociation<'b, Block>,
+		trunk_height: i32,
+	) -> Result {
 		let mut position = position;
 
 		for _ in 0..trunk_height {
-			if self.blocks.replace.matches(blocks.get(position, palette)) {
-				blocks.set(position, log);
+			if self.blocks.replace.matches(blocks.get(position, palette).unwrap_or(&block::AIR)) {
+				blocks.set(position, log)?;
 			}
 
 			position = position.offset(dir::Up).unwrap();
 		}
+
+		Ok(())
 	}
 
 	fn foliage_per_y(&self, height: f64) -> i32 {
@@ -41,7 +44,7 @@ impl LargeTreeDecorator {
 
 	fn foliage(
 		&self, trunk_height: i32, rng: &mut Random, spread: f64, y_offset: i32,
-		origin: QuadPosition,
+		origin: NeighborhoodPosition,
 	) -> Foilage {
 		let branch_factor = self.settings.branch_scale * spread * (rng.next_f32() as f64 + 0.328);
 		let angle = (rng.next_f32() as f64) * TAU;
@@ -64,7 +67,8 @@ impl LargeTreeDecorator {
 
 impl Decorator for LargeTreeDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
 		let mut rng = Random::new(rng.next_u64());
 		let height = self.settings.min_height + rng.next_i32_bound(self.settings.add_height + 1);
@@ -80,58 +84,56 @@ impl Decorator for LargeTreeDecorator {
 			None => return Ok(()),
 		};
 
-		if !self.blocks.soil.matches(quad.get(below)) {
+		if !self.blocks.soil.matches(block_at(neighborhood, below)) {
 			return Ok(());
 		}
 
 		// TODO: Check bounding box
 
-		quad.set_immediate(below, &self.blocks.new_soil);
+		neighborhood.set_immediate(below, &self.blocks.new_soil)?;
 
-		quad.ensure_available(self.blocks.log.clone());
-		quad.ensure_available(self.blocks.foliage.clone());
+		neighborhood.ensure_available(self.blocks.log.clone());
+		neighborhood.ensure_available(self.blocks.foliage.clone());
 
-		let (mut blocks, palette) = quad.freeze_palette();
+		neighborhood.edit(|mut blocks, palette| {
+			let log = palette.reverse_lookup(&self.blocks.log).unwrap();
+			let leaves = palette.reverse_lookup(&self.blocks.foliage).unwrap();
 
-		let log = palette.reverse_lookup(&self.blocks.log).unwrap();
-		let leaves = palette.reverse_lookup(&self.blocks.foliage).unwrap();
-
-		Foilage {
-			base: position.offset((0, (height - 4) as i8, 0)).unwrap(),
-			branch_y_offset: trunk_height,
-		}
-		.place(&mut blocks, &leaves, &palette, &self.blocks.replace);
+			Foilage {
+				base: position.offset((0, (height - 4) as i8, 0)).unwrap(),
+				branch_y_offset: trunk_height,
+			}
+			.place(&mut blocks, &leaves, &palette, &self.blocks.replace)?;
 
-		let clusters = self.foliage_per_y(height as f64);
+			let clusters = self.foliage_per_y(height as f64);
 
-		for y_offset in ((height * 3) / 10..=height - 4).rev() {
-			for _ in 0..clusters {
-				let spread =
-					0.5 * f64::sqrt((y_offset as f64) * (i32::abs(height - y_offset) as f64));
+			for y_offset in ((height * 3) / 10..=height - 4).rev() {
+				for _ in 0..clusters {
+					let spread =
+						0.5 * f64::sqrt((y_offset as f64) * (i32::abs(height - y_offset) as f64));
 
-				let foliage = self.foliage(trunk_height, &mut rng, spread, y_offset, position);
+					let foliage = self.foliage(trunk_height, &mut rng, spread, y_offset, position);
 
-				foliage.place(&mut blocks, &leaves, &palette, &self.blocks.replace);
+					foliage.place(&mut blocks, &leaves, &palette, &self.blocks.replace)?;
 
-				let tracer = Line {
-					from: QuadPosition::new(
-						position.x(),
-						foliage.branch_y_offset as u8 + position.y(),
-						position.z(),
-					),
-					to: foliage.base,
-				}
-				.trace();
+					let tracer = Line {
+						from: NeighborhoodPosition::new(
+							position.x(),
+							foliage.branch_y_offset + position.y(),
+							position.z(),
+						),
+						to: foliage.base,
+					}
+					.trace();
 
-				for limb in tracer {
-					blocks.set(limb, &log);
+					for limb in tracer {
+						blocks.set(limb, &log)?;
+					}
 				}
 			}
-		}
 
-		self.place_trunk(position, &mut blocks, &palette, &log, height - 4 + 1);
-
-		Ok(())
+			self.place_trunk(position, &mut blocks, &palette, &log, height - 4 + 1)
+		})
 	}
 }
 
@@ -139,27 +141,28 @@ impl Decorator for LargeTreeDecorator {
 #[derive(Debug)]
 pub struct Foilage {
 	/// Location of the leaf cluster, and the endpoint of the branch line. The Y is at the bottom of the cluster.
-	base: QuadPosition,
+	base: NeighborhoodPosition,
 	/// Y coordinate of the start of the branch line. The X and Z coordinate are always equal to the orgin of the tree.
 	branch_y_offset: i32,
 }
 
 impl Foilage {
-	fn place(
-		&self, blocks: &mut QuadBlocks, foliage: &QuadAssociation, palette: &QuadPalettes<Block>,
+	fn place<'b>(
+		&self, blocks: &mut NeighborhoodBlocks<'_, 'b, Block>,
+		foliage: &NeighborhoodAssociation<'b, Block>, palette: &NeighborhoodPalettes<'_, 'b, Block>,
 		replace: &BlockMatcher,
-	) {
+	) -> Result {
 		let mut position = self.base;
 
-		FoliageLayer { radius: 1, position }.place(blocks, foliage, palette, replace);
+		FoliageLayer { radius: 1, position }.place(blocks, foliage, palette, replace)?;
 
 		for _ in 0..3 {
 			position = position.offset(dir::Up).unwrap();
-			FoliageLayer { radius: 2, position }.place(blocks, foliage, palette, replace);
+			FoliageLayer { radius: 2, position }.place(blocks, foliage, palette, replace)?;
 		}
 
 		position = position.offset(dir::Up).unwrap();
-		FoliageLayer { radius: 1, position }.place(blocks, foliage, palette, replace);
+		FoliageLayer { radius: 1, position }.place(blocks, foliage, palette, replace)
 	}
 }
 
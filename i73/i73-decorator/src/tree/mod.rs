@@ -1,57 +1,63 @@
 mod large;
 mod normal;
 
+use crate::Result;
 use i73_base::matcher::BlockMatcher;
 use i73_base::block::{self, Block};
 pub use large::LargeTreeDecorator;
 pub use normal::NormalTreeDecorator;
 use std::i32;
-use vocs::position::{Offset, QuadPosition};
-use vocs::view::{QuadAssociation, QuadBlocks, QuadPalettes};
+use vocs::position::{NeighborhoodPosition, Offset};
+use vocs::view::{NeighborhoodAssociation, NeighborhoodBlocks, NeighborhoodPalettes};
 
 struct FoliageLayer {
-	position: QuadPosition,
+	position: NeighborhoodPosition,
 	radius: u8,
 }
 
 impl FoliageLayer {
-	fn place_corners<F>(
-		&self, blocks: &mut QuadBlocks, foliage: &QuadAssociation, palette: &QuadPalettes<Block>,
+	fn place_corners<'b, F>(
+		&self, blocks: &mut NeighborhoodBlocks<'_, 'b, Block>,
+		foliage: &NeighborhoodAssociation<'b, Block>, palette: &NeighborhoodPalettes<'_, 'b, Block>,
 		replace: &BlockMatcher, mut corner_predicate: F,
-	) where
-		F: FnMut(u8) -> bool,
+	) -> Result
+	where
+		F: FnMut(i32) -> bool,
 	{
 		let mut try_corner = |x_offset, z_offset| {
 			if !corner_predicate(self.position.y()) {
-				return;
+				return Ok(());
 			}
 
 			let position = match self.position.offset((x_offset, 0i8, z_offset)) {
 				Some(position) => position,
-				None => return,
+				None => return Ok(()),
 			};
 
-			if replace.matches(blocks.get(position, palette)) {
-				blocks.set(position, foliage);
+			if replace.matches(blocks.get(position, palette).unwrap_or(&block::AIR)) {
+				blocks.set(position, foliage)?;
 			}
+
+			Ok(())
 		};
 
 		let radius = self.radius as i8;
 
 		if radius == 0 {
-			try_corner(0, 0);
+			try_corner(0, 0)
 		} else {
-			try_corner(-radius, -radius);
-			try_corner(radius, -radius);
-			try_corner(-radius, radius);
-			try_corner(radius, radius);
+			try_corner(-radius, -radius)?;
+			try_corner(radius, -radius)?;
+			try_corner(-radius, radius)?;
+			try_corner(radius, radius)
 		}
 	}
 
-	fn place(
-		&self, blocks: &mut QuadBlocks, foliage: &QuadAssociation, palette: &QuadPalettes<Block>,
+	fn place<'b>(
+		&self, blocks: &mut NeighborhoodBlocks<'_, 'b, Block>,
+		foliage: &NeighborhoodAssociation<'b, Block>, palette: &NeighborhoodPalettes<'_, 'b, Block>,
 		replace: &BlockMatcher,
-	) {
+	) -> Result {
 		let radius = self.radius as i32;
 
 		for z_offset in -radius..=radius {
@@ -65,11 +71,13 @@ impl FoliageLayer {
 					None => continue,
 				};
 
-				if replace.matches(blocks.get(position, palette)) {
-					blocks.set(position, foliage);
+				if replace.matches(blocks.get(position, palette).unwrap_or(&block::AIR)) {
+					blocks.set(position, foliage)?;
 				}
 			}
 		}
+
+		Ok(())
 	}
 }
 
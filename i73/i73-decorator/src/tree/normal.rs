@@ -1,9 +1,9 @@
 use super::{FoliageLayer, TreeBlocks};
-use crate::{Decorator, Result};
-use i73_base::block::Block;
+use crate::{block_at, Decorator, Result};
+use i73_base::block::{self, Block};
 use java_rand::Random;
-use vocs::position::{dir, Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{dir, NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 #[derive(Default)]
 pub struct NormalTreeDecorator {
@@ -13,9 +13,10 @@ pub struct NormalTreeDecorator {
 
 impl Decorator for NormalTreeDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		let tree = self.settings.tree(rng, position.y());
+		let tree = self.settings.tree(rng, position.y() as u32);
 
 		if tree.leaves_max_y > 128 {
 			return Ok(());
@@ -26,44 +27,44 @@ impl Decorator for NormalTreeDecorator {
 			None => return Ok(()),
 		};
 
-		if !self.blocks.soil.matches(quad.get(below)) {
+		if !self.blocks.soil.matches(block_at(neighborhood, below)) {
 			return Ok(());
 		}
 
 		// TODO: Check bounding box
 
-		quad.set_immediate(below, &self.blocks.new_soil);
+		neighborhood.set_immediate(below, &self.blocks.new_soil)?;
 
-		quad.ensure_available(self.blocks.log.clone());
-		quad.ensure_available(self.blocks.foliage.clone());
+		neighborhood.ensure_available(self.blocks.log.clone());
+		neighborhood.ensure_available(self.blocks.foliage.clone());
 
-		let (mut blocks, palette) = quad.freeze_palette();
+		neighborhood.edit(|mut blocks, palette| {
+			let log = palette.reverse_lookup(&self.blocks.log).unwrap();
+			let foliage = palette.reverse_lookup(&self.blocks.foliage).unwrap();
 
-		let log = palette.reverse_lookup(&self.blocks.log).unwrap();
-		let foliage = palette.reverse_lookup(&self.blocks.foliage).unwrap();
+			for y in tree.leaves_min_y..=tree.leaves_max_y {
+				let radius = tree.foliage_radius(y);
 
-		for y in tree.leaves_min_y..=tree.leaves_max_y {
-			let radius = tree.foliage_radius(y);
+				let position = NeighborhoodPosition::new(position.x(), y as i32, position.z());
+				let layer = FoliageLayer { position, radius: radius as u8 };
 
-			let position = QuadPosition::new(position.x(), y as u8, position.z());
-			let layer = FoliageLayer { position, radius: radius as u8 };
-
-			layer.place(&mut blocks, &foliage, &palette, &self.blocks.replace);
-			layer.place_corners(&mut blocks, &foliage, &palette, &self.blocks.replace, |y| {
-				rng.next_u32_bound(self.settings.foliage_corner_chance) != 0
-					&& y < tree.trunk_top as u8
-			});
-		}
+				layer.place(&mut blocks, &foliage, &palette, &self.blocks.replace)?;
+				layer.place_corners(&mut blocks, &foliage, &palette, &self.blocks.replace, |y| {
+					rng.next_u32_bound(self.settings.foliage_corner_chance) != 0
+						&& y < tree.trunk_top as i32
+				})?;
+			}
 
-		for y in position.y()..(tree.trunk_top as u8) {
-			let position = QuadPosition::new(position.x(), y, position.z());
+			for y in position.y()..(tree.trunk_top as i32) {
+				let position = NeighborhoodPosition::new(position.x(), y, position.z());
 
-			if self.blocks.replace.matches(blocks.get(position, &palette)) {
-				blocks.set(position, &log);
+				if self.blocks.replace.matches(blocks.get(position, &palette).unwrap_or(&block::AIR)) {
+					blocks.set(position, &log)?;
+				}
 			}
-		}
 
-		Ok(())
+			Ok(())
+		})
 	}
 }
 
@@ -78,9 +79,9 @@ struct TreeSettings {
 }
 
 impl TreeSettings {
-	fn tree(&self, rng: &mut Random, origin_y: u8) -> Tree {
+	fn tree(&self, rng: &mut Random, origin_y: u32) -> Tree {
 		let trunk_height = self.min_trunk_height + rng.next_u32_bound(self.add_trunk_height + 1);
-		let trunk_top = (origin_y as u32) + trunk_height;
+		let trunk_top = origin_y + trunk_height;
 
 		Tree {
 			// full_height: trunk_height + self.foliage_layers_off_trunk,
@@ -1,11 +1,11 @@
-use crate::{Decorator, Result};
+use crate::{block_at, Decorator, Result};
 use i73_base::matcher::BlockMatcher;
 use i73_base::math;
-use i73_base::block::Block;
+use i73_base::block::{self, Block};
 use i73_trig as trig;
 use java_rand::Random;
-use vocs::position::{Offset, QuadPosition};
-use vocs::view::QuadMut;
+use vocs::position::{NeighborhoodPosition, Offset};
+use vocs::view::NeighborhoodMut;
 
 // TODO: Is this really 3.141593?
 /// For when you don't have the time to type out all the digits of π or Math.PI.
@@ -24,13 +24,14 @@ pub struct SeasideVeinDecorator {
 
 impl Decorator for SeasideVeinDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		if !self.ocean.matches(quad.get(position.offset((-8, 0, -8)).unwrap())) {
+		if !self.ocean.matches(block_at(neighborhood, position.offset((-8, 0, -8)).unwrap())) {
 			return Ok(());
 		}
 
-		self.vein.generate(quad, rng, position)
+		self.vein.generate(neighborhood, rng, position)
 	}
 }
 
@@ -42,14 +43,11 @@ pub struct VeinDecorator {
 
 impl Decorator for VeinDecorator {
 	fn generate(
-		&self, quad: &mut QuadMut<Block>, rng: &mut Random, position: QuadPosition,
+		&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+		position: NeighborhoodPosition,
 	) -> Result {
-		let vein = Vein::create(
-			self.size,
-			(position.x() as i32, position.y() as i32, position.z() as i32),
-			rng,
-		);
-		self.blocks.generate(&vein, quad, rng)
+		let vein = Vein::create(self.size, (position.x(), position.y(), position.z()), rng);
+		self.blocks.generate(&vein, neighborhood, rng)
 	}
 }
 
@@ -60,32 +58,34 @@ pub struct VeinBlocks {
 }
 
 impl VeinBlocks {
-	pub fn generate(&self, vein: &Vein, quad: &mut QuadMut<Block>, rng: &mut Random) -> Result {
-		quad.ensure_available(self.block.clone());
-
-		let (mut blocks, palette) = quad.freeze_palette();
+	pub fn generate(
+		&self, vein: &Vein, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+	) -> Result {
+		neighborhood.ensure_available(self.block.clone());
 
-		let block = palette.reverse_lookup(&self.block).unwrap();
+		neighborhood.edit(|mut blocks, palette| {
+			let block = palette.reverse_lookup(&self.block).unwrap();
 
-		for index in 0..(vein.size + 1) {
-			let spheroid = vein.spheroid(index, rng);
+			for index in 0..(vein.size + 1) {
+				let spheroid = vein.spheroid(index, rng);
 
-			for y in spheroid.lower.1..(spheroid.upper.1 + 1) {
-				for z in spheroid.lower.2..(spheroid.upper.2 + 1) {
-					for x in spheroid.lower.2..(spheroid.upper.2 + 1) {
-						let at = QuadPosition::new(x as u8, y as u8, z as u8); // TODO
+				for y in spheroid.lower.1..(spheroid.upper.1 + 1) {
+					for z in spheroid.lower.2..(spheroid.upper.2 + 1) {
+						for x in spheroid.lower.2..(spheroid.upper.2 + 1) {
+							let at = NeighborhoodPosition::new(x, y, z);
 
-						if spheroid.distance_squared((x, y, z)) < 1.0
-							&& self.replace.matches(blocks.get(at, &palette))
-						{
-							blocks.set(at, &block);
+							if spheroid.distance_squared((x, y, z)) < 1.0
+								&& self.replace.matches(blocks.get(at, &palette).unwrap_or(&block::AIR))
+							{
+								blocks.set(at, &block)?;
+							}
 						}
 					}
 				}
 			}
-		}
 
-		Ok(())
+			Ok(())
+		})
 	}
 }
 
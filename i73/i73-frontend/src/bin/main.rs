@@ -12,6 +12,7 @@ extern crate vocs;
 
 use std::fs::File;
 
+use i73_base::distribution::{BoxedDistribution, Placement};
 use i73_base::matcher::BlockMatcher;
 use i73_base::Pass;
 use i73_base::block::{self, Block};
@@ -21,16 +22,19 @@ use i73_terrain::overworld_173::{self, Settings};
 use vocs::indexed::IndexedCube;
 use vocs::nibbles::u4;
 use vocs::position::{
-	GlobalColumnPosition, GlobalSectorPosition, LayerPosition, QuadPosition,
+	GlobalColumnPosition, GlobalPosition, GlobalSectorPosition, LayerPosition,
+	NeighborhoodPosition, Rect,
 };
-use vocs::view::ColumnMut;
+use vocs::view::{ColumnMut, SpillPolicy};
 use vocs::world::world::World;
 
 use i73_decorator::tree::{LargeTreeDecorator, NormalTreeDecorator};
 use i73_decorator::Decorator;
 use i73_noise::sample::Sample;
 use std::collections::HashMap;
+use vocs::world::pending::PendingEdits;
 use vocs::world::shared::{NoPack, SharedWorld};
+use vocs::memory::MemoryUsage;
 use vocs::position::{dir, Offset};
 use vocs::unpacked::Layer;
 
@@ -38,13 +42,13 @@ fn main() {
 	time("Generating region (0, 0)", run);
 }
 
+/// The columns of region (0, 0).
+fn region_columns() -> Rect<GlobalColumnPosition> {
+	Rect::new(GlobalColumnPosition::new(0, 0), GlobalColumnPosition::new(31, 31))
+}
+
 fn run() {
-	let sectors = vec![
-		GlobalSectorPosition::new(0, 0),
-		GlobalSectorPosition::new(1, 0),
-		GlobalSectorPosition::new(0, 1),
-		GlobalSectorPosition::new(1, 1)
-	];
+	let sectors = region_columns().sectors().iter().collect();
 
 	let (mut world, world_biomes) = time("Generating terrain", || generate_terrain(sectors));
 
@@ -101,6 +105,8 @@ fn run() {
 	//let (sky_light, block_light) =
 	//	time("Computing lighting", || (compute_sky_light(), compute_block_light()));
 
+	report_memory_usage(&world, &sky_light, &block_light);
+
 	let compressed_chunks = time("Compressing chunks", || {
 		compress_chunks(&world, &sky_light, &block_light, &heightmaps, &world_biomes)
 	});
@@ -110,6 +116,21 @@ fn run() {
 	});
 }
 
+fn report_memory_usage(
+	world: &World<IndexedCube<Block>>, sky_light: &SharedWorld<NoPack<lumis::PackedNibbleCube>>,
+	block_light: &SharedWorld<NoPack<lumis::PackedNibbleCube>>,
+) {
+	let blocks = world.memory_usage();
+	let sky = sky_light.memory_usage();
+	let block = block_light.memory_usage();
+
+	println!("World memory usage statistics:");
+	println!("-      Blocks: {}", blocks);
+	println!("- Block light: {}", block);
+	println!("-   Sky light: {}", sky);
+	println!("-       Total: {}", blocks + block + sky);
+}
+
 fn time<T, F: FnOnce() -> T>(name: &str, task: F) -> T {
 	use std::time::Instant;
 
@@ -192,22 +213,22 @@ fn generate_terrain_for_sector(sector_position: GlobalSectorPosition, sector_blo
 		let column_position = GlobalColumnPosition::combine(sector_position, local_column_position);
 
 		let mut column_chunks = [
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
-			IndexedCube::<Block>::new(4, block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
+			IndexedCube::<Block>::uniform(block::AIR),
 		];
 
 		let climate = climates
@@ -262,8 +283,7 @@ fn generate_terrain(sectors: Vec<GlobalSectorPosition>) -> (World<IndexedCube<Bl
 	}
 
 	let world_biomes: HashMap<GlobalSectorPosition, Layer<[u8; 256]>> = world
-		.sectors_mut()
-		.par_bridge()
+		.par_sectors_mut()
 		.map(|(&sector_position, sector_blocks)| {
 			time_sector("Generating terrain", sector_position, || {
 				(sector_position, generate_terrain_for_sector(sector_position, sector_blocks))
@@ -274,6 +294,22 @@ fn generate_terrain(sectors: Vec<GlobalSectorPosition>) -> (World<IndexedCube<Bl
 	(world, world_biomes)
 }
 
+/// The amount of wavefronts needed to decorate the 31x31 quads, see `decoration_wave`.
+const DECORATION_WAVES: i32 = 4 * 30 + 30 + 1;
+
+/// The world seed, which the decoration RNG of each quad is derived from.
+const DECORATION_SEED: u64 = 8399452073110208023;
+
+/// Returns the quads on a wavefront, which are the quads with `4 * x + z == wave`.
+fn decoration_wave(wave: i32) -> Vec<(i32, i32)> {
+	(0..31).map(|x| (x, wave - 4 * x)).filter(|&(_, z)| (0..31).contains(&z)).collect()
+}
+
+/// Edits that a quad recorded outside of itself while it was decorated.
+type Spills = Vec<(GlobalPosition, Block)>;
+
+type BoxedDispatcher = ::i73_decorator::Dispatcher<BoxedDistribution, BoxedDistribution>;
+
 fn decorate_terrain(world: &mut World<IndexedCube<Block>>) {
 	/*let mut decorator_registry: ::std::collections::HashMap<String, Box<i73::config::decorator::DecoratorFactory>> = ::std::collections::HashMap::new();
 	decorator_registry.insert("vein".into(), Box::new(::i73::config::decorator::vein::VeinDecoratorFactory::default()));
@@ -310,15 +346,12 @@ fn decorate_terrain(world: &mut World<IndexedCube<Block>>) {
 		}
 	};*/
 
-	let mut decorators: Vec<
-		::i73_decorator::Dispatcher<
-			i73_base::distribution::Chance<i73_base::distribution::Baseline>,
-			i73_base::distribution::Chance<i73_base::distribution::Baseline>,
-		>,
-	> = Vec::new();
+	let mut decorators: Vec<BoxedDispatcher> = Vec::new();
 
-	decorators.push(::i73_decorator::Dispatcher {
-		decorator: Box::new(::i73_decorator::lake::LakeDecorator {
+	// Rare(4) Lake, see the decoration list in the documentation.
+	decorators.push(::i73_decorator::Dispatcher::from_placement(
+		Placement::parse("Rare(4)", "Offs(Uniform)", None).unwrap(),
+		Box::new(::i73_decorator::lake::LakeDecorator {
 			blocks: ::i73_decorator::lake::LakeBlocks {
 				is_liquid: BlockMatcher::include(
 					[
@@ -346,20 +379,7 @@ fn decorate_terrain(world: &mut World<IndexedCube<Block>>) {
 			},
 			settings: ::i73_decorator::lake::LakeSettings::default(),
 		}),
-		height_distribution: ::i73_base::distribution::Chance {
-			base: i73_base::distribution::Baseline::Linear(i73_base::distribution::Linear {
-				min: 0,
-				max: 127,
-			}),
-			ordering: i73_base::distribution::ChanceOrdering::AlwaysGeneratePayload,
-			chance: 1,
-		},
-		rarity: ::i73_base::distribution::Chance {
-			base: ::i73_base::distribution::Baseline::Constant { value: 1 },
-			chance: 4,
-			ordering: ::i73_base::distribution::ChanceOrdering::AlwaysGeneratePayload,
-		},
-	});
+	));
 
 	/*decorators.push (::i73_decorator::Dispatcher {
 		decorator: Box::new(::i73_decorator::vein::SeasideVeinDecorator {
@@ -421,58 +441,7 @@ fn decorate_terrain(world: &mut World<IndexedCube<Block>>) {
 		}
 	});*/
 
-	let mut decoration_rng = ::java_rand::Random::new(8399452073110208023);
-	let coefficients =
-		(((decoration_rng.next_i64() >> 1) << 1) + 1, ((decoration_rng.next_i64() >> 1) << 1) + 1);
-
-	for x in 0..31 {
-		for z in 0..31 {
-			let x_part = (x as i64).wrapping_mul(coefficients.0) as u64;
-			let z_part = (z as i64).wrapping_mul(coefficients.1) as u64;
-			decoration_rng =
-				::java_rand::Random::new((x_part.wrapping_add(z_part)) ^ 8399452073110208023);
-
-			let mut quad =
-				world.get_quad_mut(GlobalColumnPosition::new(x as i32, z as i32)).unwrap();
-
-			'outer: for _ in 0..8 {
-				let mut position = QuadPosition::new(
-					decoration_rng.next_u32_bound(16) as u8 + 8,
-					127,
-					decoration_rng.next_u32_bound(16) as u8 + 8,
-				);
-
-				while quad.get(position) == &block::AIR {
-					position = match position.offset(dir::Down) {
-						Some(pos) => pos,
-						None => break 'outer,
-					};
-				}
-
-				if decoration_rng.next_bool() {
-					LargeTreeDecorator::default()
-						.generate(
-							&mut quad,
-							&mut decoration_rng,
-							position.offset(dir::Up).unwrap_or(position),
-						)
-						.unwrap();
-				} else {
-					NormalTreeDecorator::default()
-						.generate(
-							&mut quad,
-							&mut decoration_rng,
-							position.offset(dir::Up).unwrap_or(position),
-						)
-						.unwrap();
-				}
-			}
-
-			for dispatcher in &decorators {
-				dispatcher.generate(&mut quad, &mut decoration_rng).unwrap();
-			}
-		}
-	}
+	decorate_quads(world, &decorators, DECORATION_SEED, false);
 
 	/*for x in 0..31 {
 		println!("{}", x);
@@ -491,6 +460,128 @@ fn decorate_terrain(world: &mut World<IndexedCube<Block>>) {
 	}*/
 }
 
+/// Decorates the 31x31 quads of the region. Quads are decorated in parallel unless `sequential` is set, which
+/// decorates them one at a time in the Beta order instead. Returns the amount of edits that spilled out of a quad.
+///
+/// Decorations are placed at least 8 blocks from the edges of a quad, and reach less than 24 blocks from there, so
+/// their edits stay within one column of the quad. Beta decorates the quads with X in the outer loop, so a quad has
+/// to come after every earlier quad that is close enough for their reach to overlap, which is the quads within 3
+/// columns. Quads on the same wavefront of
+/// `4 * x + z` are at least 4 columns apart, and every quad that has to come first is on an earlier wavefront.
+/// Since the edits that spill out of the quads of a wavefront are applied before the next wavefront starts, running
+/// the wavefronts in order gives the same output as the sequential loop.
+fn decorate_quads(
+	world: &mut World<IndexedCube<Block>>, decorators: &[BoxedDispatcher], seed: u64, sequential: bool,
+) -> usize {
+	let mut decoration_rng = ::java_rand::Random::new(seed);
+	let coefficients =
+		(((decoration_rng.next_i64() >> 1) << 1) + 1, ((decoration_rng.next_i64() >> 1) << 1) + 1);
+
+	let shared: SharedWorld<NoPack<IndexedCube<Block>>> =
+		SharedWorld::from_world(std::mem::replace(world, World::new()));
+	let mut pending = PendingEdits::new();
+	let (mut spilled, mut lost) = (0, 0);
+
+	let waves: Vec<Vec<(i32, i32)>> = if sequential {
+		(0..31).flat_map(|x| (0..31).map(move |z| vec![(x, z)])).collect()
+	} else {
+		(0..DECORATION_WAVES).map(decoration_wave).collect()
+	};
+
+	for quads in waves {
+		let decorated: Vec<(Spills, usize)> = quads
+			.into_par_iter()
+			.map(|(x, z)| decorate_quad(&shared, decorators, coefficients, seed, x, z))
+			.collect();
+
+		// The quads are no longer locked, so their spills can be applied before the next wavefront reads them.
+		for (quad_spills, quad_lost) in decorated {
+			spilled += quad_spills.len();
+			lost += quad_lost;
+
+			for (position, block) in quad_spills {
+				match position.checked_global_chunk().and_then(|chunk| shared.get(chunk)) {
+					Some(mut chunk) => chunk.set_immediate(position.local_block(), &block),
+					None => pending.push(position, block).expect("Spilled edits are only recorded if a chunk can contain them"),
+				}
+			}
+		}
+	}
+
+	*world = shared.into_world();
+
+	if !pending.is_empty() {
+		println!(
+			"{} spilled decorations are pending for chunks outside of the region ({})",
+			pending.len(),
+			pending.memory_usage()
+		);
+	}
+
+	if lost != 0 {
+		println!("{} decorations were cut short by edits that no chunk can contain", lost);
+	}
+
+	spilled
+}
+
+/// Decorates a single quad with trees and then the decorators. Returns the edits that fell outside of the quad,
+/// along with the amount of decorations that were cut short.
+fn decorate_quad(
+	shared: &SharedWorld<NoPack<IndexedCube<Block>>>, decorators: &[BoxedDispatcher], coefficients: (i64, i64),
+	seed: u64, x: i32, z: i32,
+) -> (Spills, usize) {
+	let x_part = (x as i64).wrapping_mul(coefficients.0) as u64;
+	let z_part = (z as i64).wrapping_mul(coefficients.1) as u64;
+	let mut decoration_rng = ::java_rand::Random::new((x_part.wrapping_add(z_part)) ^ seed);
+
+	// Edits outside of the quad are recorded, and applied by `decorate_quads` once the quad is unlocked.
+	let mut locked = shared.lock_quad(GlobalColumnPosition::new(x, z)).unwrap();
+	let mut neighborhood = locked.neighborhood_mut(SpillPolicy::Record);
+	let mut lost = 0;
+
+	'outer: for _ in 0..8 {
+		let mut position = NeighborhoodPosition::new(
+			decoration_rng.next_u32_bound(16) as i32 + 8,
+			127,
+			decoration_rng.next_u32_bound(16) as i32 + 8,
+		);
+
+		while i73_decorator::block_at(&neighborhood, position) == &block::AIR {
+			if position.y() == 0 {
+				break 'outer;
+			}
+
+			position = position.offset(dir::Down).unwrap();
+		}
+
+		let position = position.offset(dir::Up).unwrap();
+
+		let result = if decoration_rng.next_bool() {
+			LargeTreeDecorator::default().generate(&mut neighborhood, &mut decoration_rng, position)
+		} else {
+			NormalTreeDecorator::default().generate(&mut neighborhood, &mut decoration_rng, position)
+		};
+
+		lost += result.is_err() as usize;
+	}
+
+	for dispatcher in decorators {
+		lost += dispatcher.generate(&mut neighborhood, &mut decoration_rng).is_err() as usize;
+	}
+
+	let spills = neighborhood.take_spilled();
+
+	// Edits any further away could reach a quad on the same wavefront, see `decorate_quads`.
+	debug_assert!(spills.iter().all(|&(position, _)| {
+		let column = position.global_column();
+
+		(x - 1..=x + 2).contains(&column.x()) && (z - 1..=z + 2).contains(&column.z())
+	}));
+
+	(spills, lost)
+}
+
 /*fn write_classicworld(world: &World<IndexedCube<Block>>) {
 	use vocs::position::CubePosition;
 
@@ -563,26 +654,19 @@ use vocs::world::sector::Sector;
 use vocs::world::shared::SharedSector;
 use region::{RegionWriter, ZlibBuffer, ZlibOutput};
 use mca::{AnvilBlocks, Column, ColumnRoot, Section, SectionRef};
-use rayon::iter::{ParallelIterator, ParallelBridge};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use lumis::PackedNibbleCube;
 use std::ops::Deref;
 
 fn prune_chunk_palettes(world: &mut World<IndexedCube<Block>>) {
-	let sum: u32 = world.sectors_mut().par_bridge().map(|(_, sector): (&GlobalSectorPosition, &mut Sector<IndexedCube<Block>>)| {
-		let sum: u32 = sector.iter_mut().map(Option::as_mut).flatten().map(|chunk| {
-			let palette_before: u32 = chunk.palette().entries().iter().map(|entry| entry.is_some() as u32).sum();
-
-			chunk.prune_palette();
-
-			let palette_after: u32 = chunk.palette().entries().iter().map(|entry| entry.is_some() as u32).sum();
-
-			palette_before - palette_after
-		}).sum();
-
-		sum
-	}).sum();
-
-	println!("Removed {} palette entries across all chunks", sum);
+	let compaction = world.par_compact_palettes();
+
+	println!(
+		"Removed {} palette entries across all chunks, shrinking {} chunks and freeing {} KiB",
+		compaction.removed_entries,
+		compaction.shrunk_storages,
+		compaction.saved_bytes / 1024
+	);
 }
 
 fn compress_chunks_in_sector(
@@ -593,11 +677,14 @@ fn compress_chunks_in_sector(
 	height_maps: &Layer<lumis::heightmap::ColumnHeightMap>,
 	biomes: &Layer<[u8; 256]>
 ) -> Layer<ZlibBuffer> {
-	let mut unpacked_sky_lighting = 0;
-	let mut unpacked_block_lighting = 0;
-
 	let compressed_chunks: Layer<Option<ZlibBuffer>> = blocks.enumerate_columns().map(|(column_position, column)| {
-		let height_map = height_maps[column_position].as_inner();
+		// Anvil heightmaps are relative to Y=0, and this writer only handles the classic world height.
+		let mut height_map = [0u32; 256];
+
+		for (height, &absolute) in height_map.iter_mut().zip(height_maps[column_position].as_inner().iter()) {
+			*height = absolute.max(0) as u32;
+		}
+
 		let biomes = &biomes[column_position];
 
 		let mut sections = Vec::new();
@@ -612,14 +699,6 @@ fn compress_chunks_in_sector(
 			let sky_light = sky_light.get(chunk_position).unwrap()/*_or_else(NibbleCube::default)*/;
 			let block_light = block_light.get(chunk_position).unwrap()/*_or_else(NibbleCube::default)*/;
 
-			if !sky_light.is_packed() {
-				unpacked_sky_lighting += 1
-			}
-
-			if !block_light.is_packed() {
-				unpacked_block_lighting += 1;
-			}
-
 			if sky_light.deref() == &PackedNibbleCube::EntirelyLit && block_light.deref() == &PackedNibbleCube::EntirelyDark && anvil_blocks.is_none() {
 				// Don't bother writing this chunk section to a file, it holds no data of value
 				continue
@@ -651,7 +730,7 @@ fn compress_chunks_in_sector(
 			v: Some(1),
 			inhabited_time: 0,
 			biomes: biomes,
-			heightmap: height_map,
+			heightmap: &height_map,
 			sections: &section_refs,
 			tile_ticks: &[]
 		};
@@ -667,13 +746,13 @@ fn compress_chunks_in_sector(
 		(column_position, output.finish())
 	}).collect();
 
-	let sky_mb = unpacked_block_lighting as f32 * (2048.0 / 1048576.0);
-	let block_mb = unpacked_sky_lighting as f32 * (2048.0 / 1048576.0);
+	let sky_usage = sky_light.memory_usage();
+	let block_usage = block_light.memory_usage();
 
 	println!("Lighting memory usage statistics for sector {}:", sector_position);
-	println!("- Block light: {} unpacked light volumes requiring {:.3} MB of memory ({:.2}% of original size)", unpacked_block_lighting, sky_mb, sky_mb * 100.0 / 8.0);
-	println!("-   Sky light: {} unpacked light volumes requiring {:.3} MB of memory ({:.2}% of original size)", unpacked_sky_lighting, block_mb, block_mb * 100.0 / 8.0);
-	println!("-       Total: {} unpacked light volumes requiring {:.3} MB of memory ({:.2}% of original size)", unpacked_sky_lighting + unpacked_block_lighting, block_mb + sky_mb, (block_mb + sky_mb) * 100.0 / 16.0);
+	println!("- Block light: {}", block_usage);
+	println!("-   Sky light: {}", sky_usage);
+	println!("-       Total: {}", block_usage + sky_usage);
 
 	compressed_chunks.map(Option::unwrap)
 }
@@ -684,11 +763,11 @@ fn compress_chunks(
 	heightmaps: &HashMap<GlobalSectorPosition, Layer<lumis::heightmap::ColumnHeightMap>>,
 	world_biomes: &HashMap<GlobalSectorPosition, Layer<[u8; 256]>>,
 ) -> HashMap<GlobalSectorPosition, Layer<ZlibBuffer>> {
-	world.sectors().par_bridge().map(|(&sector_position, blocks)| {
+	world.par_sectors().map(|(&sector_position, blocks)| {
 		time_sector("Compressing chunks", sector_position, || {
 			let sky_light = sky_light.get_sector(sector_position).unwrap();
 			let block_light = block_light.get_sector(sector_position).unwrap();
-			let heightmaps = heightmaps.get(&sector_position).unwrap();
+			let heightmaps = heightmaps.get(&sector_position.with_y(0)).unwrap();
 			let biomes = world_biomes.get(&sector_position).unwrap();
 
 			let compressed = compress_chunks_in_sector(sector_position, blocks, sky_light, block_light, heightmaps, biomes);
@@ -719,17 +798,134 @@ fn write_region(compressed_chunks: &HashMap<GlobalSectorPosition, Layer<ZlibBuff
 
 	let mut writer = RegionWriter::start(region_file).unwrap();
 
-	for z in 0..32 {
+	for column_position in region_columns() {
+		let sector_position = column_position.global_sector();
+		let local_position = column_position.local_layer();
+
+		let compressed = &compressed_chunks.get(&sector_position).unwrap()[local_position];
+
+		writer.column(column_position.x() as u8, column_position.z() as u8, compressed).unwrap();
+	}
+
+	writer.finish().unwrap();
+}
+
+#[cfg(test)]
+mod test {
+	use super::{decorate_quads, decoration_wave, DECORATION_WAVES};
+	use i73_base::block::{self, Block};
+	use i73_base::distribution::Placement;
+	use i73_decorator::{block_at, Decorator, Dispatcher, Result};
+	use java_rand::Random;
+	use std::collections::HashMap;
+	use vocs::indexed::IndexedCube;
+	use vocs::position::{CubePosition, Cuboid, GlobalChunkPosition, GlobalPosition, NeighborhoodPosition};
+	use vocs::view::NeighborhoodMut;
+	use vocs::world::world::World;
+
+	#[test]
+	fn test_waves_keep_beta_order() {
+		let mut waves = HashMap::new();
+
+		for wave in 0..DECORATION_WAVES {
+			let quads = decoration_wave(wave);
+
+			for (index, &(x, z)) in quads.iter().enumerate() {
+				assert!(waves.insert((x, z), wave).is_none());
+
+				for &(other_x, other_z) in &quads[index + 1..] {
+					assert!((x - other_x).abs() > 3 || (z - other_z).abs() > 3, "Quads on a wavefront are too close");
+				}
+			}
+		}
+
+		assert_eq!(waves.len(), 31 * 31);
+
+		// Quads within reach of each other must be decorated in the order of the sequential loop, which has X on the outside.
+		for (&(x, z), &wave) in &waves {
+			for other_x in x - 3..=x {
+				for other_z in z - 3..=z + 3 {
+					if other_x == x && other_z >= z {
+						continue;
+					}
+
+					if let Some(&other_wave) = waves.get(&(other_x, other_z)) {
+						assert!(other_wave < wave);
+					}
+				}
+			}
+		}
+	}
+
+	/// A flat grassland, which every tree attempt succeeds on.
+	fn grassland() -> World<IndexedCube<Block>> {
+		let mut world = World::new();
+
 		for x in 0..32 {
-			let column_position = GlobalColumnPosition::new(x, z);
-			let sector_position = column_position.global_sector();
-			let local_position = column_position.local_layer();
+			for z in 0..32 {
+				for y in 0..16 {
+					world.set(GlobalChunkPosition::new(x, y, z), IndexedCube::uniform(block::AIR));
+				}
+			}
+		}
 
-			let compressed = &compressed_chunks.get(&sector_position).unwrap()[local_position];
+		world.fill(Cuboid::new(GlobalPosition::new(0, 0, 0), GlobalPosition::new(511, 62, 511)), &block::DIRT);
+		world.fill(Cuboid::new(GlobalPosition::new(0, 63, 0), GlobalPosition::new(511, 63, 511)), &block::GRASS);
 
-			writer.column(x as u8, z as u8, compressed).unwrap();
+		world
+	}
+
+	/// Fills the air along the X and Z axes up to 20 blocks away from the position with sand or gravel. Unlike trees,
+	/// this reaches past the edges of the quad, into the columns that the quads around it are decorating.
+	struct Cross;
+
+	impl Decorator for Cross {
+		fn generate(
+			&self, neighborhood: &mut NeighborhoodMut<Block>, rng: &mut Random,
+			position: NeighborhoodPosition,
+		) -> Result {
+			let target = if rng.next_bool() { block::SAND } else { block::GRAVEL };
+
+			for offset in -20..=20 {
+				for &(x, z) in &[(offset, 0), (0, offset)] {
+					let at = NeighborhoodPosition::new(position.x() + x, position.y(), position.z() + z);
+
+					// Blocks outside of the quad read as air, so spilled edits do not depend on the order either.
+					if block_at(neighborhood, at) == &block::AIR {
+						neighborhood.set_immediate(at, &target)?;
+					}
+				}
+			}
+
+			Ok(())
 		}
 	}
 
-	writer.finish().unwrap();
+	#[test]
+	fn test_wavefronts_match_sequential() {
+		let placement = || Placement::parse("2", "Offs(Ri(16), Ri(16) + 64, Ri(16))", None).unwrap();
+		let decorators = [Dispatcher::from_placement(placement(), Box::new(Cross))];
+
+		let mut sequential = grassland();
+		let mut wavefronts = grassland();
+
+		// The trees of neighboring quads overlap, and the crosses spill into the quads around them.
+		let spilled = decorate_quads(&mut sequential, &decorators, 1, true);
+		assert!(spilled > 0);
+
+		assert_eq!(decorate_quads(&mut wavefronts, &decorators, 1, false), spilled);
+
+		for x in 0..32 {
+			for z in 0..32 {
+				for y in 0..16 {
+					let position = GlobalChunkPosition::new(x, y, z);
+					let (expected, chunk) = (sequential.get(position).unwrap(), wavefronts.get(position).unwrap());
+
+					for block in CubePosition::enumerate() {
+						assert_eq!(chunk.get(block), expected.get(block), "Blocks differ at {:?} in {:?}", block, position);
+					}
+				}
+			}
+		}
+	}
 }
@@ -35,10 +35,12 @@ pub static RARITY_NETHER: Chance<Packed3> =
 /// Since the Nether has a high amount of solid blocks from bottom to top, caves spawn uniformly.
 pub static HEIGHT_NETHER: Linear = Linear { min: 0, max: 127 };
 
-struct CavesAssociations {
-	carve: ColumnAssociation,
-	lower: ColumnAssociation,
-	surface: ColumnAssociation,
+struct CavesAssociations<'b> {
+	carve: ColumnAssociation<'b>,
+	lower: ColumnAssociation<'b>,
+	surface: ColumnAssociation<'b>,
+	/// Whether any chunk in the column has an ocean block in its palette. If not, the ocean checks can be skipped.
+	ocean: bool,
 }
 
 // Overworld: CavesGenerator { carve: air, ocean: [ flowing_water, still_water ], carvable: [ stone, dirt, grass ], spheroid_size_multiplier: 1.0, vertical_multiplier: 1.0 }
@@ -58,9 +60,10 @@ pub struct CavesGenerator {
 }
 
 impl CavesGenerator {
-	fn carve_spheroid(
-		&self, spheroid: Spheroid, associations: &CavesAssociations, blocks: &mut ColumnBlocks,
-		palette: &ColumnPalettes<Block>, chunk: GlobalColumnPosition,
+	fn carve_spheroid<'b>(
+		&self, spheroid: Spheroid, associations: &CavesAssociations<'b>,
+		blocks: &mut ColumnBlocks<'_, 'b>, palette: &ColumnPalettes<'_, 'b, Block>,
+		chunk: GlobalColumnPosition,
 	) {
 		let chunk_block = ((chunk.x() * 16) as f64, (chunk.z() * 16) as f64);
 
@@ -70,8 +73,10 @@ impl CavesGenerator {
 		let y_top = spheroid.upper.y() + 2;
 		let y_bottom = spheroid.lower.y() - 1;
 
-		let check_water =
-			|x, y, z| self.ocean.matches(blocks.get(ColumnPosition::new(x, y, z), palette));
+		let check_water = |x, y, z| {
+			associations.ocean
+				&& self.ocean.matches(blocks.get(ColumnPosition::new(x, y, z), palette))
+		};
 
 		for z in spheroid.lower.z()..=spheroid.upper.z() {
 			for x in spheroid.lower.x()..=spheroid.upper.x() {
@@ -134,7 +139,7 @@ impl CavesGenerator {
 							continue;
 						}
 
-						if y < self.lower_surface {
+						if y < self.lower_surface as u16 {
 							blocks.set(position, &associations.lower);
 						} else {
 							blocks.set(position, &associations.carve);
@@ -153,10 +158,10 @@ impl CavesGenerator {
 		}
 	}
 
-	fn carve_tunnel(
-		&self, mut tunnel: Tunnel, caves: &mut Caves, associations: &CavesAssociations,
-		blocks: &mut ColumnBlocks, palette: &ColumnPalettes<Block>, chunk: GlobalColumnPosition,
-		from: GlobalColumnPosition, radius: u32,
+	fn carve_tunnel<'b>(
+		&self, mut tunnel: Tunnel, caves: &mut Caves, associations: &CavesAssociations<'b>,
+		blocks: &mut ColumnBlocks<'_, 'b>, palette: &ColumnPalettes<'_, 'b, Block>,
+		chunk: GlobalColumnPosition, from: GlobalColumnPosition, radius: u32,
 	) {
 		loop {
 			let outcome = tunnel.step(self.vertical_multiplier);
@@ -195,32 +200,36 @@ impl StructureGenerator for CavesGenerator {
 		column.ensure_available(self.lower.clone());
 		column.ensure_available(self.surface_block.clone());
 
-		let (mut blocks, palette) = column.freeze_palette();
+		// The palettes already contain the blocks placed by carving, so this stays correct as carving proceeds.
+		let ocean = column.0.iter().any(|chunk| !self.ocean.chunk_mask(chunk).none());
 
-		let associations = CavesAssociations {
-			carve: palette.reverse_lookup(&self.carve).unwrap(),
-			lower: palette.reverse_lookup(&self.lower).unwrap(),
-			surface: palette.reverse_lookup(&self.surface_block).unwrap(),
-		};
-
-		while let Some(start) = caves.next() {
-			match start {
-				Start::Tunnel(tunnel) => self.carve_tunnel(
-					tunnel,
-					&mut caves,
-					&associations,
-					&mut blocks,
-					&palette,
-					chunk,
-					from,
-					radius,
-				),
-				Start::Circular(Some(spheroid)) => {
-					self.carve_spheroid(spheroid, &associations, &mut blocks, &palette, chunk)
-				}
-				Start::Circular(None) => (),
+		column.edit(|mut blocks, palette| {
+			let associations = CavesAssociations {
+				carve: palette.reverse_lookup(&self.carve).unwrap(),
+				lower: palette.reverse_lookup(&self.lower).unwrap(),
+				surface: palette.reverse_lookup(&self.surface_block).unwrap(),
+				ocean,
 			};
-		}
+
+			while let Some(start) = caves.next() {
+				match start {
+					Start::Tunnel(tunnel) => self.carve_tunnel(
+						tunnel,
+						&mut caves,
+						&associations,
+						&mut blocks,
+						&palette,
+						chunk,
+						from,
+						radius,
+					),
+					Start::Circular(Some(spheroid)) => {
+						self.carve_spheroid(spheroid, &associations, &mut blocks, &palette, chunk)
+					}
+					Start::Circular(None) => (),
+				};
+			}
+		});
 	}
 }
 
@@ -598,13 +607,13 @@ impl Position {
 
 		let lower_clamped = (
 			min(max(lower.0, 0), 16) as u8,
-			min(max(lower.1, 1), 255) as u8,
+			min(max(lower.1, 1), 255) as u16,
 			min(max(lower.2, 0), 16) as u8,
 		);
 
 		let upper_clamped = (
 			min(max(upper.0, 0), 16) as u8,
-			min(max(upper.1, 0), 120) as u8,
+			min(max(upper.1, 0), 120) as u16,
 			min(max(upper.2, 0), 16) as u8,
 		);
 
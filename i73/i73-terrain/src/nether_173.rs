@@ -64,22 +64,22 @@ impl Pass<()> for ShapePass {
 		target.ensure_available(self.blocks.air.clone());
 		target.ensure_available(self.blocks.solid.clone());
 
-		let (mut blocks, palette) = target.freeze_palette();
+		target.edit(|mut blocks, palette| {
+			let air = palette.reverse_lookup(&self.blocks.air).unwrap();
+			let solid = palette.reverse_lookup(&self.blocks.solid).unwrap();
 
-		let air = palette.reverse_lookup(&self.blocks.air).unwrap();
-		let solid = palette.reverse_lookup(&self.blocks.solid).unwrap();
+			for i in 0..32768 {
+				let position = ColumnPosition::from_yzx(i);
 
-		for i in 0..32768 {
-			let position = ColumnPosition::from_yzx(i);
+				let block = if trilinear128(&field, position) > 0.0 {
+					&solid
+				} else {
+					&air
+				};
 
-			let block = if trilinear128(&field, position) > 0.0 {
-				&solid
-			} else {
-				&air
-			};
-
-			blocks.set(position, block);
-		}
+				blocks.set(position, block);
+			}
+		});
 	}
 }
 
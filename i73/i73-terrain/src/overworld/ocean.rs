@@ -51,44 +51,47 @@ impl Pass<Climate> for OceanPass {
 		let chunk = &mut target.0[chunk_base];
 
 		// Check if chunk has air at all!
-		if chunk.palette().reverse_lookup(&self.blocks.air).is_none() {
+		if chunk.reverse_lookup(&self.blocks.air).is_none() {
 			return;
 		}
 
-		// TODO: Try to use the Setter API here
 		chunk.ensure_available(self.blocks.ocean);
 
 		if has_ice {
 			chunk.ensure_available(self.blocks.ice);
 		}
 
-		let (chunk, palette) = chunk.freeze_palette();
-		let ocean = palette.reverse_lookup(&self.blocks.ocean).unwrap();
-		let ice =
-			if has_ice { Some(palette.reverse_lookup(&self.blocks.ice).unwrap()) } else { None };
-		let air = palette.reverse_lookup(&self.blocks.air).unwrap();
+		chunk.edit(|mut chunk, palette| {
+			let ocean = palette.reverse_lookup(&self.blocks.ocean).unwrap();
+			let ice = if has_ice {
+				Some(palette.reverse_lookup(&self.blocks.ice).unwrap())
+			} else {
+				None
+			};
+			let air = palette.reverse_lookup(&self.blocks.air).unwrap();
 
-		// Calculate how many layers of the chunk will have ocean.
-		let sea_layers = (self.sea_top - has_ice as usize) % 16;
+			// Calculate how many layers of the chunk will have ocean.
+			let sea_layers = (self.sea_top - has_ice as usize) % 16;
 
-		for index in 0..((sea_layers * 256) as u16) {
-			let position = CubePosition::from_yzx(index);
+			for index in 0..((sea_layers * 256) as u16) {
+				let position = CubePosition::from_yzx(index);
 
-			if chunk.get(position) == air {
-				chunk.set(position, ocean);
+				if chunk.get(position) == air {
+					chunk.set(position, ocean);
+				}
 			}
-		}
 
-		if let Some(ice) = ice {
-			let y = ((self.sea_top - 1) % 16) as u8;
+			if let Some(ice) = ice {
+				let y = ((self.sea_top - 1) % 16) as u8;
 
-			for layer_position in LayerPosition::enumerate() {
-				let position = CubePosition::from_layer(y, layer_position);
+				for layer_position in LayerPosition::enumerate() {
+					let position = CubePosition::from_layer(y, layer_position);
 
-				if chunk.get(position) == air {
-					chunk.set(position, if ice_mask[layer_position] { ice } else { ocean });
+					if chunk.get(position) == air {
+						chunk.set(position, if ice_mask[layer_position] { ice } else { ocean });
+					}
 				}
 			}
-		}
+		});
 	}
 }
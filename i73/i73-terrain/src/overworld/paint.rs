@@ -104,14 +104,14 @@ impl Default for PaintBlocks {
 	}
 }
 
-struct SurfaceAssociations {
-	pub top: ColumnAssociation,
-	pub fill: ColumnAssociation,
-	pub chain: Vec<FollowupAssociation>,
+struct SurfaceAssociations<'b> {
+	pub top: ColumnAssociation<'b>,
+	pub fill: ColumnAssociation<'b>,
+	pub chain: Vec<FollowupAssociation<'b>>,
 }
 
-impl SurfaceAssociations {
-	fn lookup(surface: &Surface, palette: &ColumnPalettes<Block>) -> Self {
+impl<'b> SurfaceAssociations<'b> {
+	fn lookup(surface: &Surface, palette: &ColumnPalettes<'_, 'b, Block>) -> Self {
 		let mut chain = Vec::new();
 
 		for followup in &surface.chain {
@@ -129,8 +129,8 @@ impl SurfaceAssociations {
 	}
 }
 
-struct FollowupAssociation {
-	pub block: ColumnAssociation,
+struct FollowupAssociation<'b> {
+	pub block: ColumnAssociation<'b>,
 	pub max_depth: u32,
 }
 
@@ -150,10 +150,11 @@ impl PaintPass {
 		&self.lookup
 	}
 
-	fn paint_stack(
-		&self, rng: &mut Random, blocks: &mut ColumnBlocks, palette: &ColumnPalettes<Block>,
-		bedrock: &ColumnAssociation, layer: LayerPosition, surface: &SurfaceAssociations,
-		beach: &SurfaceAssociations, basin: &SurfaceAssociations, thickness: i32, max_y: u8,
+	fn paint_stack<'b>(
+		&self, rng: &mut Random, blocks: &mut ColumnBlocks<'_, 'b>,
+		palette: &ColumnPalettes<'_, 'b, Block>, bedrock: &ColumnAssociation<'b>,
+		layer: LayerPosition, surface: &SurfaceAssociations<'b>, beach: &SurfaceAssociations<'b>,
+		basin: &SurfaceAssociations<'b>, thickness: i32, max_y: u8,
 	) {
 		let reset_remaining = match thickness {
 			-1 => None,
@@ -186,7 +187,7 @@ impl PaintPass {
 		}
 
 		for y in (0..max_y).rev() {
-			let position = ColumnPosition::from_layer(y, layer);
+			let position = ColumnPosition::from_layer(y as u16, layer);
 
 			if let Some(chance) = self.max_bedrock_height {
 				if (y as u32) <= rng.next_u32_bound(chance as u32) {
@@ -297,81 +298,84 @@ impl Pass<Climate> for PaintPass {
 			}
 		}
 
-		let (mut blocks, palette) = target.freeze_palette();
+		target.edit(|mut blocks, palette| {
+			let mut surfaces = Vec::new();
 
-		let mut surfaces = Vec::new();
-
-		for entry in biome_palette {
-			surfaces.push(
-				entry.as_ref().map(|biome| SurfaceAssociations::lookup(&biome.surface, &palette)),
-			);
-		}
-
-		let bedrock = palette.reverse_lookup(&self.blocks.bedrock).unwrap();
+			for entry in biome_palette {
+				surfaces.push(
+					entry
+						.as_ref()
+						.map(|biome| SurfaceAssociations::lookup(&biome.surface, &palette)),
+				);
+			}
 
-		let gravel_beach = SurfaceAssociations {
-			top: palette.reverse_lookup(&self.blocks.air).unwrap(),
-			fill: palette.reverse_lookup(&self.blocks.gravel).unwrap(),
-			chain: vec![],
-		};
+			let bedrock = palette.reverse_lookup(&self.blocks.bedrock).unwrap();
 
-		let sand_beach = SurfaceAssociations {
-			top: palette.reverse_lookup(&self.blocks.sand).unwrap(),
-			fill: palette.reverse_lookup(&self.blocks.sand).unwrap(),
-			chain: vec![FollowupAssociation {
-				block: palette.reverse_lookup(&self.blocks.sandstone).unwrap(),
-				max_depth: 3,
-			}],
-		};
+			let gravel_beach = SurfaceAssociations {
+				top: palette.reverse_lookup(&self.blocks.air).unwrap(),
+				fill: palette.reverse_lookup(&self.blocks.gravel).unwrap(),
+				chain: vec![],
+			};
 
-		let basin = SurfaceAssociations {
-			top: palette.reverse_lookup(&self.blocks.air).unwrap(),
-			fill: palette.reverse_lookup(&self.blocks.stone).unwrap(),
-			chain: vec![],
-		};
+			let sand_beach = SurfaceAssociations {
+				top: palette.reverse_lookup(&self.blocks.sand).unwrap(),
+				fill: palette.reverse_lookup(&self.blocks.sand).unwrap(),
+				chain: vec![FollowupAssociation {
+					block: palette.reverse_lookup(&self.blocks.sandstone).unwrap(),
+					max_depth: 3,
+				}],
+			};
 
-		for position in LayerPosition::enumerate() {
-			// TODO: BeachSelector
-
-			let (x, z) = (position.x() as f64, position.z() as f64);
-
-			let (sand_variation, gravel_variation, thickness_variation) =
-				(rng.next_f64() * 0.2, rng.next_f64() * 0.2, rng.next_f64() * 0.25);
-
-			let sand = sand_vertical.generate_override(
-				vertical_offset + Vector3::new(x, z, 0.0),
-				position.z() as usize,
-			) + sand_variation
-				> 0.0;
-			let gravel =
-				self.gravel.sample(horizontal_offset + Vector2::new(x, z)) + gravel_variation > 3.0;
-			let thickness = (thickness_vertical.generate_override(
-				vertical_offset + Vector3::new(x, z, 0.0),
-				position.z() as usize,
-			) / 3.0 + 3.0 + thickness_variation) as i32;
-
-			let surface = surfaces[biomes.get(position) as usize].as_ref().unwrap();
-
-			let beach = if sand {
-				&sand_beach
-			} else if gravel {
-				&gravel_beach
-			} else {
-				surface
+			let basin = SurfaceAssociations {
+				top: palette.reverse_lookup(&self.blocks.air).unwrap(),
+				fill: palette.reverse_lookup(&self.blocks.stone).unwrap(),
+				chain: vec![],
 			};
 
-			self.paint_stack(
-				&mut rng,
-				&mut blocks,
-				&palette,
-				&bedrock,
-				position,
-				surface,
-				beach,
-				&basin,
-				thickness,
-				max_y,
-			);
-		}
+			for position in LayerPosition::enumerate() {
+				// TODO: BeachSelector
+
+				let (x, z) = (position.x() as f64, position.z() as f64);
+
+				let (sand_variation, gravel_variation, thickness_variation) =
+					(rng.next_f64() * 0.2, rng.next_f64() * 0.2, rng.next_f64() * 0.25);
+
+				let sand = sand_vertical.generate_override(
+					vertical_offset + Vector3::new(x, z, 0.0),
+					position.z() as usize,
+				) + sand_variation
+					> 0.0;
+				let gravel = self.gravel.sample(horizontal_offset + Vector2::new(x, z))
+					+ gravel_variation
+					> 3.0;
+				let thickness = (thickness_vertical.generate_override(
+					vertical_offset + Vector3::new(x, z, 0.0),
+					position.z() as usize,
+				) / 3.0 + 3.0 + thickness_variation) as i32;
+
+				let surface = surfaces[biomes.get(position) as usize].as_ref().unwrap();
+
+				let beach = if sand {
+					&sand_beach
+				} else if gravel {
+					&gravel_beach
+				} else {
+					surface
+				};
+
+				self.paint_stack(
+					&mut rng,
+					&mut blocks,
+					&palette,
+					&bedrock,
+					position,
+					surface,
+					beach,
+					&basin,
+					thickness,
+					max_y,
+				);
+			}
+		});
 	}
 }
@@ -2,18 +2,19 @@ use bit_vec::BitVec;
 
 use crate::heightmap::{CubeHeightMap, ColumnHeightMap, HeightMapBuilder};
 
-use rayon::iter::ParallelBridge;
-use rayon::prelude::ParallelIterator;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use vocs::indexed::{IndexedCube, Target};
 use vocs::mask::BitLayer;
-use vocs::position::{GlobalSectorPosition, LayerPosition};
+use vocs::position::{CubePosition, GlobalSectorPosition, Height, LayerPosition};
 use vocs::unpacked::Layer;
 use vocs::world::sector::Sector;
 use vocs::world::world::World;
 
+/// Computes the heightmaps of every column in the world. The heightmaps of each stack of sectors
+/// are keyed by the position of the sector at Y=0.
 pub fn compute_world_heightmaps<'a, B, F>(
 	blocks: &'a World<IndexedCube<B>>, predicate: &'a F,
 ) -> HashMap<GlobalSectorPosition, Layer<ColumnHeightMap>>
@@ -21,26 +22,46 @@ where
 	B: 'a + Target + Send + Sync,
 	F: Fn(&'a B) -> bool + Sync,
 {
+	let positions: HashSet<GlobalSectorPosition> =
+		blocks.sectors().map(|(position, _)| position.with_y(0)).collect();
+
 	let compute_sector_heightmaps =
-		|(&position, sector)| (position, compute_sector_heightmaps(sector, predicate));
+		|position: GlobalSectorPosition| (position, compute_sector_heightmaps(blocks, position, predicate));
 
-	// TODO: Remove need for par_bridge
-	blocks.sectors().par_bridge().map(compute_sector_heightmaps).collect()
+	positions.into_par_iter().map(compute_sector_heightmaps).collect()
 }
 
+/// Computes the heightmaps of every column in the stack of sectors at the horizontal position of `position`.
 pub fn compute_sector_heightmaps<'a, B, F>(
-	blocks: &'a Sector<IndexedCube<B>>, predicate: &'a F,
+	blocks: &'a World<IndexedCube<B>>, position: GlobalSectorPosition, predicate: &'a F,
 ) -> Layer<ColumnHeightMap>
 where
 	B: 'a + Target + Send + Sync,
 	F: Fn(&'a B) -> bool + Sync,
 {
-	let compute_column_heightmap =
-		|(position, column)| (position, compute_column_heightmap(column, predicate));
-
-	// TODO: Remove need for par_bridge
-	let unordered_heightmaps: Vec<(LayerPosition, ColumnHeightMap)> =
-		blocks.enumerate_columns().par_bridge().map(compute_column_heightmap).collect();
+	let height = blocks.height();
+	let sectors: Vec<(i8, Option<&'a Sector<IndexedCube<B>>>)> = height
+		.sectors()
+		.map(|sector_y| (sector_y, blocks.get_sector(position.with_y(sector_y))))
+		.collect();
+
+	let compute_column_heightmap = |position: LayerPosition| {
+		let mut column = Vec::with_capacity(height.sections() as usize);
+
+		for &(sector_y, sector) in &sectors {
+			for y in height.sector_layers(sector_y) {
+				column.push(sector.and_then(|sector| sector[CubePosition::from_layer(y, position)].as_ref()));
+			}
+		}
+
+		(position, compute_column_heightmap(&column, height, predicate))
+	};
+
+	let unordered_heightmaps: Vec<(LayerPosition, ColumnHeightMap)> = (0..=255u8)
+		.into_par_iter()
+		.map(LayerPosition::from_zx)
+		.map(compute_column_heightmap)
+		.collect();
 
 	// We've received an unordered list of heightmaps from the parallel iterator.
 	// It's necessary to properly sort them before returning.
@@ -56,15 +77,16 @@ where
 	ordered_heightmaps.map(Option::unwrap)
 }
 
+/// Computes the heightmap of a single column. The chunks are ordered from the bottom of the world to the top.
 pub fn compute_column_heightmap<'a, B, F>(
-	column: [Option<&'a IndexedCube<B>>; 16], predicate: &'a F,
+	column: &[Option<&'a IndexedCube<B>>], height: Height, predicate: &'a F,
 ) -> ColumnHeightMap
 where
 	B: 'a + Target + Send + Sync,
 	F: Fn(&'a B) -> bool,
 {
 	let mut mask = BitLayer::default();
-	let mut heightmap_builder = HeightMapBuilder::new();
+	let mut heightmap_builder = HeightMapBuilder::new(height);
 
 	for chunk in column.iter().rev() {
 		let (blocks, palette) = match chunk {
@@ -2,12 +2,14 @@ mod compute;
 
 use bit_vec::BitVec;
 use std::cmp;
+use std::io::{self, Read, Write};
 use std::ops::{Index, IndexMut};
 use vocs::component::LayerStorage;
 use vocs::mask::{BitLayer, Mask};
 use vocs::nibbles::{u4, NibbleLayer};
 use vocs::packed::PackedCube;
-use vocs::position::{CubePosition, LayerPosition};
+use vocs::position::{CubePosition, Height, LayerPosition};
+use vocs::snapshot::Codec;
 
 pub use compute::*;
 
@@ -83,20 +85,23 @@ impl CubeHeightMap {
 	}
 }
 
+/// The height of the highest matching block in each position of a column, plus one.
+/// Heights are absolute block Y values, so a height equal to the bottom of the world means that
+/// there are no matching blocks in the column.
 pub struct ColumnHeightMap {
-	heights: Box<[u32; 256]>,
+	heights: Box<[i32; 256]>,
 }
 
 impl ColumnHeightMap {
-	fn new() -> Self {
-		ColumnHeightMap { heights: Box::new([0; 256]) }
+	fn new(min_y: i32) -> Self {
+		ColumnHeightMap { heights: Box::new([min_y; 256]) }
 	}
 
-	pub fn slice(&self, chunk_y: u4) -> CubeHeightMap {
+	pub fn slice(&self, chunk_y: i8) -> CubeHeightMap {
 		let mut sliced =
 			CubeHeightMap { heights: NibbleLayer::default(), is_filled: BitLayer::default() };
 
-		let base = chunk_y.raw() as u32 * 16;
+		let base = chunk_y as i32 * 16;
 
 		for position in LayerPosition::enumerate() {
 			let full_height = self[position];
@@ -114,74 +119,93 @@ impl ColumnHeightMap {
 		sliced
 	}
 
-	pub fn into_inner(self) -> Box<[u32; 256]> {
+	pub fn into_inner(self) -> Box<[i32; 256]> {
 		self.heights
 	}
 
-	pub fn as_inner(&self) -> &[u32; 256] {
+	pub fn as_inner(&self) -> &[i32; 256] {
 		&self.heights
 	}
 }
 
 impl Index<LayerPosition> for ColumnHeightMap {
-	type Output = u32;
+	type Output = i32;
 
-	fn index(&self, index: LayerPosition) -> &u32 {
+	fn index(&self, index: LayerPosition) -> &i32 {
 		&self.heights[index.zx() as usize]
 	}
 }
 
 impl IndexMut<LayerPosition> for ColumnHeightMap {
-	fn index_mut(&mut self, index: LayerPosition) -> &mut u32 {
+	fn index_mut(&mut self, index: LayerPosition) -> &mut i32 {
 		&mut self.heights[index.zx() as usize]
 	}
 }
 
+impl Codec for ColumnHeightMap {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()>
+	where
+		W: Write,
+	{
+		self.heights.encode(out)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self>
+	where
+		R: Read,
+	{
+		<[i32; 256]>::decode(input).map(|heights| ColumnHeightMap { heights: Box::new(heights) })
+	}
+}
+
+/// Builds a ColumnHeightMap from the CubeHeightMap of each chunk in the column, from the top down.
 pub struct HeightMapBuilder {
 	heightmap: ColumnHeightMap,
-	chunk_y: u8,
+	height: Height,
+	remaining: u8,
 }
 
 impl HeightMapBuilder {
-	pub fn new() -> Self {
-		HeightMapBuilder { heightmap: ColumnHeightMap::new(), chunk_y: 15 }
+	pub fn new(height: Height) -> Self {
+		HeightMapBuilder {
+			heightmap: ColumnHeightMap::new(height.min_y()),
+			height,
+			remaining: height.sections(),
+		}
 	}
 
 	pub fn add(&mut self, slice: CubeHeightMap) -> BitLayer {
 		assert_ne!(
-			self.chunk_y, 255,
+			self.remaining, 0,
 			"Tried to add too many CubeHeightMap slices to HeightMapBuilder"
 		);
 
-		let base = (self.chunk_y as u32) * 16;
+		self.remaining -= 1;
+
+		let min_y = self.height.min_y();
+		let base = (self.height.section_y(self.remaining) as i32) * 16;
 
 		for position in LayerPosition::enumerate() {
 			let height = &mut self.heightmap[position];
 			let chunk_height = slice.heights.get(position);
 
-			if *height != 0 {
+			if *height != min_y {
 				continue;
 			}
 
 			if slice.is_filled[position] {
 				*height = base + 16;
 			} else if chunk_height != u4::new(0) {
-				*height = base + (chunk_height.raw() as u32);
+				*height = base + (chunk_height.raw() as i32);
 			}
 		}
 
-		if self.chunk_y > 0 {
-			self.chunk_y -= 1;
-		} else {
-			self.chunk_y = 255;
-		}
-
 		slice.into_mask()
 	}
 
 	pub fn build(self) -> ColumnHeightMap {
 		assert_eq!(
-			self.chunk_y, 255,
+			self.remaining, 0,
 			"HeightMapBuilder::build called before all CubeHeightMap slices were provided"
 		);
 
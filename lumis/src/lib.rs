@@ -10,6 +10,9 @@ pub use monolith::{compute_world_skylight, compute_world_blocklight, IgnoreTrace
 use vocs::component::CubeStorage;
 use vocs::nibbles::{u4, NibbleCube};
 use vocs::position::CubePosition;
+use vocs::snapshot::Codec;
+use vocs::memory::{MemoryReport, MemoryUsage};
+use std::io::{self, Error, ErrorKind, Read, Write};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum PackedNibbleCube {
@@ -93,3 +96,36 @@ impl Default for PackedNibbleCube {
 		PackedNibbleCube::EntirelyDark
 	}
 }
+
+/// Uniform chunks are stored inline, so only unpacked chunks use heap memory.
+impl MemoryUsage for PackedNibbleCube {
+	fn memory_usage(&self) -> MemoryReport {
+		match *self {
+			PackedNibbleCube::Unpacked(ref unpacked) => unpacked.memory_usage(),
+			_ => MemoryReport::default()
+		}
+	}
+}
+
+/// Uniform chunks are written as a single tag byte, so that fully dark or lit chunks take up almost no space.
+impl Codec for PackedNibbleCube {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		match *self {
+			PackedNibbleCube::EntirelyDark => 0u8.encode(out),
+			PackedNibbleCube::EntirelyLit => 1u8.encode(out),
+			PackedNibbleCube::Unpacked(ref unpacked) => {
+				2u8.encode(out)?;
+				unpacked.encode(out)
+			}
+		}
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		match u8::decode(input)? {
+			0 => Ok(PackedNibbleCube::EntirelyDark),
+			1 => Ok(PackedNibbleCube::EntirelyLit),
+			2 => NibbleCube::decode(input).map(PackedNibbleCube::Unpacked),
+			_ => Err(Error::new(ErrorKind::InvalidData, "Invalid light chunk tag"))
+		}
+	}
+}
@@ -4,7 +4,6 @@ use crate::queue::{CubeQueue, SectorQueue, WorldQueue};
 use crate::sources::{LightSources, BlockLightSources, EmissionPalette, SkyLightSources};
 use crate::PackedNibbleCube;
 
-use rayon::iter::ParallelBridge;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
 use std::collections::HashMap;
@@ -23,8 +22,15 @@ use vocs::world::world::World;
 
 // TODO: This whole file should be split up / refactored at some point
 
+/// The blocks of a sector, along with its position. Sectors at the top and bottom of the world may be missing chunks.
+#[derive(Copy, Clone)]
+struct BlockSector<'a, B> where B: Target {
+	position: GlobalSectorPosition,
+	blocks: &'a Sector<IndexedCube<B>>,
+}
+
 fn initial_sector<B, F, S>(
-	block_sector: &Sector<IndexedCube<B>>, light: &SharedSector<NoPack<PackedNibbleCube>>,
+	block_sector: BlockSector<B>, light: &SharedSector<NoPack<PackedNibbleCube>>,
 	sector_sources: &S::SectorSources, emission_palette: &S::EmissionPalette, opacities: &F,
 ) -> SectorQueue
 where
@@ -32,17 +38,15 @@ where
 	F: Fn(&B) -> u4 + Sync,
 	S: LightSources,
 {
+	let BlockSector { position: sector, blocks: block_sector } = block_sector;
 	let empty_neighbors = Directional::splat(&PackedNibbleCube::EntirelyDark);
 
 	let sector_queue = Mutex::new(SectorQueue::new());
 
+	// Sectors at the top and bottom of the world may be partially empty.
 	block_sector
-		.iter()
-		.enumerate()
-		.par_bridge()
-		.map(|(index, chunk)| {
-			(CubePosition::from_yzx(index as u16), chunk.as_ref().expect("TODO").freeze())
-		})
+		.par_enumerate_present()
+		.map(|(position, chunk)| (position, chunk.freeze()))
 		.for_each(|(position, (blocks, palette))| {
 			let mut opacity = NibbleArray::new(palette.len());
 
@@ -52,7 +56,7 @@ where
 				opacity.set(index, opacity_value);
 			}
 
-			let sources = S::chunk_sources(sector_sources, emission_palette, position);
+			let sources = S::chunk_sources(sector_sources, emission_palette, sector, position);
 
 			// TODO: Reuse this!
 			let mut queue = CubeQueue::new();
@@ -74,7 +78,7 @@ where
 }
 
 fn full_sector<B, F, S>(
-	block_sector: &Sector<IndexedCube<B>>, light: &SharedSector<NoPack<PackedNibbleCube>>,
+	block_sector: BlockSector<B>, light: &SharedSector<NoPack<PackedNibbleCube>>,
 	light_neighbors: Directional<&SharedSector<NoPack<PackedNibbleCube>>>,
 	sector_queue: &mut SectorQueue, sector_sources: &S::SectorSources, emission_palette: &S::EmissionPalette, opacities: &F,
 ) -> (u32, u32)
@@ -83,6 +87,7 @@ where
 	F: Fn(&B) -> u4 + Sync,
 	S: LightSources,
 {
+	let BlockSector { position: sector, blocks: block_sector } = block_sector;
 	let mut iterations = 0;
 	let mut chunk_operations = 0;
 
@@ -92,8 +97,12 @@ where
 		while let Some((position, incomplete)) = sector_queue.pop_first() {
 			chunk_operations += 1;
 
-			let blocks = block_sector[position].as_ref().unwrap();
-			let sources = S::chunk_sources(sector_sources, emission_palette, position);
+			// Light may spill into chunks above or below the world, which do not exist.
+			let blocks = match block_sector[position].as_ref() {
+				Some(blocks) => blocks,
+				None => continue,
+			};
+			let sources = S::chunk_sources(sector_sources, emission_palette, sector, position);
 
 			let mut queue = complete_chunk(
 				position,
@@ -207,10 +216,9 @@ impl LightTraces for PrintTraces {
 		let us = Self::us(duration);
 
 		println!(
-			"Initial {} lighting for ({}, {}) done in {}us ({}us per column)",
+			"Initial {} lighting for {} done in {}us ({}us per column)",
 			self.0,
-			position.x(),
-			position.z(),
+			position,
 			us,
 			us / 256
 		);
@@ -222,7 +230,7 @@ impl LightTraces for PrintTraces {
 	) {
 		let us = Self::us(duration);
 
-		println!("Inner full {} lighting for ({}, {}) done in {}us ({}us per column): {} iterations, {} post-initial chunk light operations", self.0, position.x(), position.z(), us, us / 256, iterations, chunk_operations);
+		println!("Inner full {} lighting for {} done in {}us ({}us per column): {} iterations, {} post-initial chunk light operations", self.0, position, us, us / 256, iterations, chunk_operations);
 	}
 
 	fn complete_sector(
@@ -231,7 +239,7 @@ impl LightTraces for PrintTraces {
 	) {
 		let us = Self::us(duration);
 
-		println!("Full {} lighting for ({}, {}) [iteration {}] done in {}us ({}us per column): {} iterations, {} post-initial chunk light operations", self.0, position.x(), position.z(), iteration, us, us / 256, inner_iterations, chunk_operations);
+		println!("Full {} lighting for {} [iteration {}] done in {}us ({}us per column): {} iterations, {} post-initial chunk light operations", self.0, position, iteration, us, us / 256, inner_iterations, chunk_operations);
 	}
 }
 
@@ -259,27 +267,27 @@ where
 	let empty_sector: SharedSector<NoPack<PackedNibbleCube>> = SharedSector::new();
 	let empty_light_neighbors = Directional::splat(&empty_sector);
 
-	let mut sky_light: SharedWorld<NoPack<PackedNibbleCube>> = SharedWorld::new();
+	let mut sky_light: SharedWorld<NoPack<PackedNibbleCube>> = SharedWorld::with_height(world.height());
 	let world_queue = Mutex::new(WorldQueue::new());
 
 	world.sectors().map(|entry| *entry.0).for_each(|position| {
 		sky_light.get_or_create_sector_mut(position);
 	});
 
-	world.sectors().par_bridge().for_each(|(&position, block_sector)| {
+	world.par_sectors().for_each(|(&position, block_sector)| {
 		let initial_start = Instant::now();
 
 		let sky_light = sky_light.get_sector(position).unwrap();
 		let sector_sources = S::sector_sources(world_sources, position);
 
 		let mut sector_queue =
-			initial_sector::<B, F, S>(block_sector, sky_light, &sector_sources, emission_palette, opacities);
+			initial_sector::<B, F, S>(BlockSector { position, blocks: block_sector }, sky_light, &sector_sources, emission_palette, opacities);
 
 		let inner_start = Instant::now();
 		tracer.initial_sector(position, inner_start.duration_since(initial_start));
 
 		let (iterations, chunk_operations) = full_sector::<B, F, S>(
-			block_sector,
+			BlockSector { position, blocks: block_sector },
 			sky_light,
 			empty_light_neighbors,
 			&mut sector_queue,
@@ -321,19 +329,25 @@ where
 
 				let sky_light_neighbors = Directional::combine(SplitDirectional {
 					minus_x: sky_light
-						.get_sector(GlobalSectorPosition::new(position.x() - 1, position.z()))
+						.get_sector(GlobalSectorPosition::new(position.x() - 1, position.z()).with_y(position.y()))
 						.unwrap_or(&empty_sector),
 					plus_x: sky_light
-						.get_sector(GlobalSectorPosition::new(position.x() + 1, position.z()))
+						.get_sector(GlobalSectorPosition::new(position.x() + 1, position.z()).with_y(position.y()))
 						.unwrap_or(&empty_sector),
 					minus_z: sky_light
-						.get_sector(GlobalSectorPosition::new(position.x(), position.z() - 1))
+						.get_sector(GlobalSectorPosition::new(position.x(), position.z() - 1).with_y(position.y()))
 						.unwrap_or(&empty_sector),
 					plus_z: sky_light
-						.get_sector(GlobalSectorPosition::new(position.x(), position.z() + 1))
+						.get_sector(GlobalSectorPosition::new(position.x(), position.z() + 1).with_y(position.y()))
+						.unwrap_or(&empty_sector),
+					down: position
+						.checked_offset(0, -1, 0)
+						.and_then(|below| sky_light.get_sector(below))
+						.unwrap_or(&empty_sector),
+					up: position
+						.checked_offset(0, 1, 0)
+						.and_then(|above| sky_light.get_sector(above))
 						.unwrap_or(&empty_sector),
-					down: &empty_sector,
-					up: &empty_sector,
 				});
 
 				let mut sector_queue = SectorQueue::new();
@@ -341,7 +355,7 @@ where
 				let sector_sources = S::sector_sources(world_sources, position);
 
 				let (inner_iterations, chunk_operations) = full_sector::<B, F, S>(
-					block_sector,
+					BlockSector { position, blocks: block_sector },
 					sky_light_center,
 					sky_light_neighbors,
 					&mut sector_queue,
@@ -16,7 +16,7 @@ enum Phase {
 
 impl Phase {
 	fn from_position(position: GlobalSectorPosition) -> Self {
-		let is_odd = position.x().wrapping_add(position.z()) & 1 == 1;
+		let is_odd = position.x().wrapping_add(position.y() as i32).wrapping_add(position.z()) & 1 == 1;
 
 		if is_odd {
 			Phase::Odd
@@ -47,29 +47,47 @@ impl WorldQueue {
 	pub fn enqueue_spills(&mut self, position: GlobalSectorPosition, spills: SectorSpills) {
 		let spills = spills.0.split();
 
+		if let Some(above) = position.checked_offset(0, 1, 0) {
+			self.spill(
+				above,
+				spills.up,
+				|layer_position| CubePosition::from_layer(0, layer_position),
+				|mask, layer| mask.layer_zx_mut(0).combine(&layer),
+			);
+		}
+
+		if let Some(below) = position.checked_offset(0, -1, 0) {
+			self.spill(
+				below,
+				spills.down,
+				|layer_position| CubePosition::from_layer(15, layer_position),
+				|mask, layer| mask.layer_zx_mut(15).combine(&layer),
+			);
+		}
+
 		self.spill(
-			GlobalSectorPosition::new(position.x() + 1, position.z()),
+			GlobalSectorPosition::new(position.x() + 1, position.z()).with_y(position.y()),
 			spills.plus_x,
 			|layer_position| CubePosition::new(0, layer_position.x(), layer_position.z()),
 			|mask, layer| mask.layer_zy_mut(0).combine(&layer),
 		);
 
 		self.spill(
-			GlobalSectorPosition::new(position.x() - 1, position.z()),
+			GlobalSectorPosition::new(position.x() - 1, position.z()).with_y(position.y()),
 			spills.minus_x,
 			|layer_position| CubePosition::new(15, layer_position.x(), layer_position.z()),
 			|mask, layer| mask.layer_zy_mut(15).combine(&layer),
 		);
 
 		self.spill(
-			GlobalSectorPosition::new(position.x(), position.z() + 1),
+			GlobalSectorPosition::new(position.x(), position.z() + 1).with_y(position.y()),
 			spills.plus_z,
 			|layer_position| CubePosition::new(layer_position.x(), layer_position.z(), 0),
 			|mask, layer| mask.layer_yx_mut(0).combine(&layer),
 		);
 
 		self.spill(
-			GlobalSectorPosition::new(position.x(), position.z() - 1),
+			GlobalSectorPosition::new(position.x(), position.z() - 1).with_y(position.y()),
 			spills.minus_z,
 			|layer_position| CubePosition::new(layer_position.x(), layer_position.z(), 15),
 			|mask, layer| mask.layer_yx_mut(15).combine(&layer),
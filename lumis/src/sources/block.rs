@@ -49,7 +49,7 @@ impl<B: Target + Sync, E: EmissionPalette<B> + Sync> LightSources for BlockLight
 		world_sources.get_sector(position).unwrap()
 	}
 
-	fn chunk_sources(sector_sources: &Self::SectorSources, emission_palette: &Self::EmissionPalette, position: CubePosition) -> Self {
+	fn chunk_sources(sector_sources: &Self::SectorSources, emission_palette: &Self::EmissionPalette, _sector: GlobalSectorPosition, position: CubePosition) -> Self {
 		let chunk = &sector_sources[position];
 		let (blocks, palette) = chunk.as_ref().unwrap().freeze();
 
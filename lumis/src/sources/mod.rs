@@ -16,7 +16,7 @@ pub trait LightSources {
 	type EmissionPalette: Sync;
 
 	fn sector_sources(world_sources: &Self::WorldSources, position: GlobalSectorPosition) -> &Self::SectorSources;
-	fn chunk_sources(sector_sources: &Self::SectorSources, emission_palette: &Self::EmissionPalette, position: CubePosition) -> Self;
+	fn chunk_sources(sector_sources: &Self::SectorSources, emission_palette: &Self::EmissionPalette, sector: GlobalSectorPosition, position: CubePosition) -> Self;
 
 	fn emission(&self, blocks: &PackedCube, position: CubePosition) -> u4;
 	fn initial(&self, blocks: &PackedCube, mask: &mut SpillBitCube) -> PackedNibbleCube;
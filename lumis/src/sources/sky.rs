@@ -34,13 +34,15 @@ impl LightSources for SkyLightSources {
 	type EmissionPalette = ();
 
 	fn sector_sources(world_sources: &HashMap<GlobalSectorPosition, Layer<ColumnHeightMap>>, position: GlobalSectorPosition) -> &Self::SectorSources {
-		world_sources.get(&position).unwrap()
+		// Heightmaps cover the entire height of the world, so they are shared by every sector in the stack.
+		world_sources.get(&position.with_y(0)).unwrap()
 	}
 
-	fn chunk_sources(sector_sources: &Layer<ColumnHeightMap>, _emission_palette: &(), position: CubePosition) -> Self {
+	fn chunk_sources(sector_sources: &Layer<ColumnHeightMap>, _emission_palette: &(), sector: GlobalSectorPosition, position: CubePosition) -> Self {
 		let column_heightmap = &sector_sources[position.layer()];
+		let chunk_y = (sector.y() as i16) * 16 + (position.y() as i16);
 	
-		let heightmap = column_heightmap.slice(u4::new(position.y()));
+		let heightmap = column_heightmap.slice(chunk_y as i8);
 		SkyLightSources::new(heightmap)
 	}
 
@@ -0,0 +1,77 @@
+use crate::registry::ParseStateError;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use vocs::snapshot::Codec;
+
+/// A block, stored as its legacy Anvil ID: `id * 16 + meta`. Use a `BlockRegistry` to convert between blocks and
+/// namespaced block states such as `minecraft:oak_log[axis=y]`.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+pub struct Block(u16);
+
+impl Block {
+	/// Creates a block from a legacy ID and metadata value. IDs range from 0 to 4095, and metadata from 0 to 15.
+	pub fn from_legacy(id: u16, meta: u8) -> Self {
+		assert!(id < 4096 && meta < 16, "Legacy block {}:{} is out of range", id, meta);
+
+		Block(id * 16 + meta as u16)
+	}
+
+	pub const fn from_anvil_id(anvil: u16) -> Self {
+		Block(anvil)
+	}
+
+	pub fn to_anvil_id(self) -> u16 {
+		self.0
+	}
+
+	/// Returns the legacy ID of this block, without the metadata.
+	pub fn id(self) -> u16 {
+		self.0 >> 4
+	}
+
+	pub fn meta(self) -> u8 {
+		(self.0 & 15) as u8
+	}
+}
+
+/// Formats the block in the legacy `id:meta` notation.
+impl Display for Block {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.id(), self.meta())
+	}
+}
+
+/// Parses the legacy `id:meta` notation. The metadata is optional, and defaults to 0.
+/// For namespaced block states, use `BlockRegistry::parse`.
+impl FromStr for Block {
+	type Err = ParseStateError;
+
+	fn from_str(s: &str) -> Result<Self, ParseStateError> {
+		let (id, meta) = match s.find(':') {
+			Some(index) => (&s[..index], &s[index + 1..]),
+			None => (s, "0"),
+		};
+
+		match (id.parse::<u16>(), meta.parse::<u8>()) {
+			(Ok(id), Ok(meta)) if id < 4096 && meta < 16 => Ok(Block::from_legacy(id, meta)),
+			_ => Err(ParseStateError::Syntax(s.to_string())),
+		}
+	}
+}
+
+impl Codec for Block {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()>
+	where
+		W: Write,
+	{
+		self.0.encode(out)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self>
+	where
+		R: Read,
+	{
+		u16::decode(input).map(Block)
+	}
+}
@@ -0,0 +1,10 @@
+//! Blocks stored as legacy Anvil IDs, and a registry that maps them to namespaced block states with typed
+//! properties, such as `minecraft:oak_log[axis=y]`. Shared by the generator and the level writers and renderers,
+//! so that all of them can name blocks the same way.
+
+mod block;
+
+pub mod registry;
+
+pub use block::Block;
+pub use registry::{BlockRegistry, BlockState, ParseStateError};
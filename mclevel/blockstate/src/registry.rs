@@ -0,0 +1,588 @@
+//! Named block states with typed properties, and their mapping to the legacy IDs stored in `Block`.
+//! The builtin registry is loaded from `data/blocks.txt`, and other registries can be loaded from files
+//! in the same format with `BlockRegistry::load`.
+use crate::block::Block;
+use fxhash::FxHashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// The type of the values of a property, inferred from the values used by the states of a block.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PropertyKind {
+	/// Either `true` or `false`, such as `lit`.
+	Bool,
+	/// Small integers, such as `age` or `level`.
+	Int,
+	/// Named values, such as `facing`, `half`, or `axis`.
+	Enum,
+}
+
+#[derive(Debug, Clone)]
+pub struct Property {
+	name: String,
+	values: Vec<String>,
+	kind: PropertyKind,
+}
+
+impl Property {
+	fn new(name: &str) -> Self {
+		Property { name: name.to_string(), values: Vec::new(), kind: PropertyKind::Bool }
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Returns every value of this property, in the order they were first used.
+	pub fn values(&self) -> &[String] {
+		&self.values
+	}
+
+	pub fn kind(&self) -> PropertyKind {
+		self.kind
+	}
+
+	fn index(&self, value: &str) -> Option<u8> {
+		self.values.iter().position(|existing| existing == value).map(|index| index as u8)
+	}
+
+	/// Values are indexed by a `u8`, so a property can have at most 256 values.
+	fn check_room(&self, value: &str) -> Result<(), ParseStateError> {
+		if self.index(value).is_none() && self.values.len() > u8::MAX as usize {
+			return Err(ParseStateError::TooManyValues(self.name.clone()));
+		}
+
+		Ok(())
+	}
+
+	fn index_or_insert(&mut self, value: &str) -> Result<u8, ParseStateError> {
+		if let Some(index) = self.index(value) {
+			return Ok(index);
+		}
+
+		self.check_room(value)?;
+		self.values.push(value.to_string());
+
+		self.kind = if self.values.iter().all(|value| value == "true" || value == "false") {
+			PropertyKind::Bool
+		} else if self.values.iter().all(|value| value.parse::<u8>().is_ok()) {
+			PropertyKind::Int
+		} else {
+			PropertyKind::Enum
+		};
+
+		Ok((self.values.len() - 1) as u8)
+	}
+}
+
+/// A value that can be read from a property with `BlockState::get`.
+pub trait PropertyValue: Sized {
+	fn parse_value(value: &str) -> Option<Self>;
+}
+
+impl PropertyValue for bool {
+	fn parse_value(value: &str) -> Option<Self> {
+		match value {
+			"true" => Some(true),
+			"false" => Some(false),
+			_ => None,
+		}
+	}
+}
+
+impl PropertyValue for u8 {
+	fn parse_value(value: &str) -> Option<Self> {
+		value.parse().ok()
+	}
+}
+
+/// Values of the `axis` property, used by logs and portals.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Axis {
+	X,
+	Y,
+	Z,
+}
+
+impl PropertyValue for Axis {
+	fn parse_value(value: &str) -> Option<Self> {
+		match value {
+			"x" => Some(Axis::X),
+			"y" => Some(Axis::Y),
+			"z" => Some(Axis::Z),
+			_ => None,
+		}
+	}
+}
+
+/// Values of the `facing` property, used by stairs, furnaces, and other directional blocks.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Facing {
+	North,
+	South,
+	West,
+	East,
+	Up,
+	Down,
+}
+
+impl PropertyValue for Facing {
+	fn parse_value(value: &str) -> Option<Self> {
+		match value {
+			"north" => Some(Facing::North),
+			"south" => Some(Facing::South),
+			"west" => Some(Facing::West),
+			"east" => Some(Facing::East),
+			"up" => Some(Facing::Up),
+			"down" => Some(Facing::Down),
+			_ => None,
+		}
+	}
+}
+
+/// Values of the `half` property, used by stairs and trapdoors.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Half {
+	Top,
+	Bottom,
+}
+
+impl PropertyValue for Half {
+	fn parse_value(value: &str) -> Option<Self> {
+		match value {
+			"top" => Some(Half::Top),
+			"bottom" => Some(Half::Bottom),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseStateError {
+	/// The string is neither a legacy `id:meta` pair nor a block state like `minecraft:oak_log[axis=y]`.
+	Syntax(String),
+	UnknownBlock(String),
+	UnknownProperty(String),
+	/// The block exists, but none of its states has the specified property values.
+	UnknownState(String),
+	/// The legacy block is already mapped to a state.
+	Duplicate(Block),
+	/// The property already has 256 values, the most that a state can refer to.
+	TooManyValues(String),
+}
+
+impl Display for ParseStateError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ParseStateError::Syntax(state) => write!(f, "malformed block state \"{}\"", state),
+			ParseStateError::UnknownBlock(name) => write!(f, "unknown block \"{}\"", name),
+			ParseStateError::UnknownProperty(name) => write!(f, "unknown property \"{}\"", name),
+			ParseStateError::UnknownState(state) => write!(f, "no legacy block matches \"{}\"", state),
+			ParseStateError::Duplicate(block) => write!(f, "legacy block {} is already registered", block),
+			ParseStateError::TooManyValues(name) => write!(f, "property \"{}\" has too many values", name),
+		}
+	}
+}
+
+impl Error for ParseStateError {}
+
+/// An error in a registry data file, see `BlockRegistry::load`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LoadError {
+	/// The line containing the error, starting from 1.
+	pub line: usize,
+	pub error: ParseStateError,
+}
+
+impl Display for LoadError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.error)
+	}
+}
+
+impl Error for LoadError {}
+
+/// The name and value of each property specified in a block state.
+type Pairs<'a> = Vec<(&'a str, &'a str)>;
+
+/// Splits a block state into its namespaced name and its properties. Names without a namespace are in `minecraft`.
+fn split_state(state: &str) -> Result<(String, Pairs<'_>), ParseStateError> {
+	let syntax = || ParseStateError::Syntax(state.to_string());
+
+	let (name, properties) = match state.find('[') {
+		Some(index) if state.ends_with(']') => (&state[..index], &state[index + 1..state.len() - 1]),
+		Some(_) => return Err(syntax()),
+		None => (state, ""),
+	};
+
+	let valid = |part: &str| {
+		!part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '/')
+	};
+
+	let name = match name.find(':') {
+		Some(index) if valid(&name[..index]) && valid(&name[index + 1..]) => name.to_string(),
+		None if valid(name) => format!("minecraft:{}", name),
+		_ => return Err(syntax()),
+	};
+
+	let mut pairs = Vec::new();
+
+	for pair in properties.split(',').filter(|pair| !pair.trim().is_empty()) {
+		let index = pair.find('=').ok_or_else(syntax)?;
+		let (key, value) = (pair[..index].trim(), pair[index + 1..].trim());
+
+		if !valid(key) || !valid(value) || pairs.iter().any(|&(existing, _)| existing == key) {
+			return Err(syntax());
+		}
+
+		pairs.push((key, value));
+	}
+
+	Ok((name, pairs))
+}
+
+#[derive(Debug, Clone)]
+struct BlockType {
+	name: String,
+	properties: Vec<Property>,
+	/// Indices of the states of this block, in the order they were registered.
+	states: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+	block: usize,
+	/// The index of the value of each property, in the same order as the properties of the block.
+	values: Vec<u8>,
+	/// The first legacy block that was mapped to this state.
+	legacy: Block,
+}
+
+/// Maps legacy blocks to namespaced block states and back, so that blocks can be written as either `17:0` or
+/// `minecraft:oak_log[axis=y]`. Only states with a legacy equivalent can be registered, since blocks are stored
+/// as legacy IDs.
+#[derive(Debug, Clone, Default)]
+pub struct BlockRegistry {
+	blocks: Vec<BlockType>,
+	names: FxHashMap<String, usize>,
+	states: Vec<State>,
+	legacy: FxHashMap<Block, usize>,
+}
+
+impl BlockRegistry {
+	pub fn new() -> Self {
+		BlockRegistry::default()
+	}
+
+	/// Returns the registry bundled with i73, which covers the blocks of Beta 1.7.3 using their modern names.
+	pub fn builtin() -> Self {
+		BlockRegistry::load(include_str!("../data/blocks.txt")).expect("The builtin block registry is malformed")
+	}
+
+	/// Loads a registry from a data file. Each line maps a legacy block to a state, such as
+	/// `17:0 minecraft:oak_log[axis=y]`. Empty lines and lines starting with `#` are ignored.
+	pub fn load(data: &str) -> Result<Self, LoadError> {
+		let mut registry = BlockRegistry::new();
+
+		for (index, line) in data.lines().enumerate() {
+			let line = line.trim();
+
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let mut parts = line.split_whitespace();
+
+			let result = match (parts.next(), parts.next(), parts.next()) {
+				(Some(legacy), Some(state), None) => {
+					legacy.parse::<Block>().and_then(|legacy| registry.register(legacy, state))
+				}
+				_ => Err(ParseStateError::Syntax(line.to_string())),
+			};
+
+			result.map_err(|error| LoadError { line: index + 1, error })?;
+		}
+
+		Ok(registry)
+	}
+
+	/// Maps a legacy block to a state. The properties of a block are fixed by its first registered state, and every
+	/// other state must specify the same properties.
+	pub fn register(&mut self, legacy: Block, state: &str) -> Result<(), ParseStateError> {
+		if self.legacy.contains_key(&legacy) {
+			return Err(ParseStateError::Duplicate(legacy));
+		}
+
+		let (name, pairs) = split_state(state)?;
+
+		let block = match self.names.get(&name) {
+			Some(&block) => block,
+			None => {
+				self.blocks.push(BlockType {
+					name: name.clone(),
+					properties: pairs.iter().map(|&(key, _)| Property::new(key)).collect(),
+					states: Vec::new(),
+				});
+
+				self.names.insert(name, self.blocks.len() - 1);
+				self.blocks.len() - 1
+			}
+		};
+
+		let properties = &mut self.blocks[block].properties;
+
+		if pairs.len() != properties.len() {
+			return Err(ParseStateError::UnknownState(state.to_string()));
+		}
+
+		// Check every key before inserting any value, so that a rejected state leaves the properties unchanged.
+		let indices = pairs
+			.iter()
+			.map(|&(key, _)| {
+				properties
+					.iter()
+					.position(|property| property.name == key)
+					.ok_or_else(|| ParseStateError::UnknownProperty(key.to_string()))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		for (&index, &(_, value)) in indices.iter().zip(&pairs) {
+			properties[index].check_room(value)?;
+		}
+
+		let mut values = vec![0; properties.len()];
+
+		for (&index, &(_, value)) in indices.iter().zip(&pairs) {
+			values[index] = properties[index].index_or_insert(value)?;
+		}
+
+		let existing = self.blocks[block].states.iter().copied().find(|&index| self.states[index].values == values);
+
+		let index = match existing {
+			Some(index) => index,
+			None => {
+				self.states.push(State { block, values, legacy });
+				self.blocks[block].states.push(self.states.len() - 1);
+
+				self.states.len() - 1
+			}
+		};
+
+		self.legacy.insert(legacy, index);
+
+		Ok(())
+	}
+
+	/// Parses either a legacy `id:meta` pair or a block state, such as `minecraft:oak_log[axis=y]`.
+	/// Properties that are not specified take their value from the first registered state that matches the rest.
+	/// Legacy pairs are returned as is, even if they are not registered.
+	pub fn parse(&self, state: &str) -> Result<Block, ParseStateError> {
+		if state.starts_with(|c: char| c.is_ascii_digit()) {
+			return state.parse();
+		}
+
+		let (name, pairs) = split_state(state)?;
+		let block = &self.blocks[*self.names.get(&name).ok_or(ParseStateError::UnknownBlock(name))?];
+
+		let mut required = Vec::with_capacity(pairs.len());
+
+		for &(key, value) in &pairs {
+			let index = block
+				.properties
+				.iter()
+				.position(|property| property.name == key)
+				.ok_or_else(|| ParseStateError::UnknownProperty(key.to_string()))?;
+
+			match block.properties[index].index(value) {
+				Some(value) => required.push((index, value)),
+				None => return Err(ParseStateError::UnknownState(state.to_string())),
+			}
+		}
+
+		block
+			.states
+			.iter()
+			.map(|&index| &self.states[index])
+			.find(|candidate| required.iter().all(|&(index, value)| candidate.values[index] == value))
+			.map(|state| state.legacy)
+			.ok_or_else(|| ParseStateError::UnknownState(state.to_string()))
+	}
+
+	/// Returns the state of a legacy block, or None if it is not registered.
+	pub fn state(&self, block: Block) -> Option<BlockState<'_>> {
+		self.legacy.get(&block).map(|&index| BlockState { registry: self, index })
+	}
+
+	/// Returns the properties of a block, or None if it is not registered.
+	pub fn properties(&self, name: &str) -> Option<&[Property]> {
+		self.names.get(name).map(|&block| &self.blocks[block].properties[..])
+	}
+
+	/// Iterates over every registered state.
+	pub fn states(&self) -> impl Iterator<Item = BlockState<'_>> {
+		(0..self.states.len()).map(move |index| BlockState { registry: self, index })
+	}
+}
+
+/// A registered block state. Formats as a namespaced state, such as `minecraft:oak_log[axis=y]`.
+#[derive(Debug, Copy, Clone)]
+pub struct BlockState<'a> {
+	registry: &'a BlockRegistry,
+	index: usize,
+}
+
+impl<'a> BlockState<'a> {
+	fn state(&self) -> &'a State {
+		&self.registry.states[self.index]
+	}
+
+	fn block(&self) -> &'a BlockType {
+		&self.registry.blocks[self.state().block]
+	}
+
+	/// Returns the namespaced name of the block, such as `minecraft:oak_log`.
+	pub fn name(&self) -> &'a str {
+		&self.block().name
+	}
+
+	/// Returns the legacy block of this state.
+	pub fn legacy(&self) -> Block {
+		self.state().legacy
+	}
+
+	/// Iterates over the name and value of each property.
+	pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+		let (block, state) = (self.block(), self.state());
+
+		block
+			.properties
+			.iter()
+			.zip(state.values.iter())
+			.map(|(property, &value)| (&property.name[..], &property.values[value as usize][..]))
+	}
+
+	/// Returns the value of a property as a string.
+	pub fn property(&self, name: &str) -> Option<&'a str> {
+		self.properties().find(|&(key, _)| key == name).map(|(_, value)| value)
+	}
+
+	/// Returns the value of a property, such as `state.get::<Axis>("axis")`.
+	/// Returns None if the block does not have the property, or if its value is not of the requested type.
+	pub fn get<T>(&self, name: &str) -> Option<T>
+	where
+		T: PropertyValue,
+	{
+		self.property(name).and_then(T::parse_value)
+	}
+
+	/// Returns the state of the same block with a property changed, if such a state is registered.
+	pub fn with(&self, name: &str, value: &str) -> Option<BlockState<'a>> {
+		let (block, state) = (self.block(), self.state());
+
+		let property = block.properties.iter().position(|property| property.name == name)?;
+		let value = block.properties[property].index(value)?;
+
+		block
+			.states
+			.iter()
+			.copied()
+			.find(|&index| {
+				let candidate = &self.registry.states[index].values;
+
+				candidate
+					.iter()
+					.enumerate()
+					.all(|(index, &existing)| existing == if index == property { value } else { state.values[index] })
+			})
+			.map(|index| BlockState { registry: self.registry, index })
+	}
+}
+
+impl<'a> Display for BlockState<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.name())?;
+
+		for (index, (key, value)) in self.properties().enumerate() {
+			write!(f, "{}{}={}", if index == 0 { "[" } else { "," }, key, value)?;
+		}
+
+		if !self.block().properties.is_empty() {
+			f.write_str("]")?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_builtin() {
+		let registry = BlockRegistry::builtin();
+
+		assert_eq!(registry.parse("minecraft:oak_log[axis=y]"), Ok(Block::from_legacy(17, 0)));
+		assert_eq!(registry.parse("oak_log"), Ok(Block::from_legacy(17, 0)));
+		assert_eq!(registry.parse("17:0"), Ok(Block::from_legacy(17, 0)));
+		assert_eq!(registry.parse("minecraft:water"), Ok(Block::from_legacy(9, 0)));
+		assert_eq!(registry.parse("minecraft:grass"), Ok(Block::from_legacy(31, 1)));
+		assert_eq!(registry.parse("minecraft:oak_stairs[half=top,facing=north]"), Ok(Block::from_legacy(53, 7)));
+
+		assert_eq!(registry.parse("minecraft:oak_log[axis=w]"), Err(ParseStateError::UnknownState("minecraft:oak_log[axis=w]".into())));
+		assert_eq!(registry.parse("minecraft:oak_log[color=red]"), Err(ParseStateError::UnknownProperty("color".into())));
+		assert_eq!(registry.parse("minecraft:unknown"), Err(ParseStateError::UnknownBlock("minecraft:unknown".into())));
+		assert_eq!(registry.parse("minecraft:oak_log[axis"), Err(ParseStateError::Syntax("minecraft:oak_log[axis".into())));
+
+		let log = registry.state(Block::from_legacy(17, 4)).unwrap();
+
+		assert_eq!(log.to_string(), "minecraft:oak_log[axis=x]");
+		assert_eq!(log.get::<Axis>("axis"), Some(Axis::X));
+		assert_eq!(log.with("axis", "z").map(|state| state.legacy()), Some(Block::from_legacy(17, 8)));
+		assert_eq!(registry.properties("minecraft:oak_log").unwrap()[0].kind(), PropertyKind::Enum);
+
+		let stairs = registry.state(Block::from_legacy(67, 6)).unwrap();
+
+		assert_eq!(stairs.get::<Facing>("facing"), Some(Facing::South));
+		assert_eq!(stairs.get::<Half>("half"), Some(Half::Top));
+		assert_eq!(registry.state(Block::from_legacy(60, 0)).unwrap().get::<u8>("moisture"), Some(0));
+		assert_eq!(registry.state(Block::from_legacy(0, 0)).unwrap().to_string(), "minecraft:air");
+
+		// Flowing and still liquids share states, which convert back to the still block.
+		assert_eq!(registry.state(Block::from_legacy(10, 0)).unwrap().legacy(), Block::from_legacy(11, 0));
+
+		assert_eq!(BlockRegistry::load("1:0 minecraft:stone\n1:0 minecraft:granite").unwrap_err().line, 2);
+	}
+
+	#[test]
+	fn test_register_rejects_without_changes() {
+		let mut registry = BlockRegistry::new();
+
+		assert_eq!(registry.register(Block::from_legacy(1, 0), "minecraft:test[a=1,a=2]"), Err(ParseStateError::Syntax("minecraft:test[a=1,a=2]".into())));
+		assert!(registry.properties("minecraft:test").is_none());
+
+		registry.register(Block::from_legacy(1, 0), "minecraft:test[a=1,b=1]").unwrap();
+
+		assert_eq!(registry.register(Block::from_legacy(1, 1), "minecraft:test[a=2,c=1]"), Err(ParseStateError::UnknownProperty("c".into())));
+		assert_eq!(registry.properties("minecraft:test").unwrap()[0].values().len(), 1);
+		assert_eq!(registry.parse("minecraft:test[a=2]"), Err(ParseStateError::UnknownState("minecraft:test[a=2]".into())));
+	}
+
+	#[test]
+	fn test_too_many_values() {
+		let mut registry = BlockRegistry::new();
+
+		for value in 0..256u16 {
+			registry.register(Block::from_legacy(1 + value, 0), &format!("minecraft:test[a={},b=x]", value)).unwrap();
+		}
+
+		let state = "minecraft:test[a=256,b=y]";
+
+		assert_eq!(registry.register(Block::from_legacy(300, 0), state), Err(ParseStateError::TooManyValues("a".into())));
+		assert_eq!(registry.properties("minecraft:test").unwrap()[1].values().len(), 1);
+
+		// Existing values can still be used.
+		registry.register(Block::from_legacy(300, 0), "minecraft:test[a=255,b=y]").unwrap();
+		assert_eq!(registry.parse("minecraft:test[a=255,b=y]"), Ok(Block::from_legacy(300, 0)));
+	}
+}
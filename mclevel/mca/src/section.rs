@@ -67,8 +67,31 @@ impl AnvilBlocks {
 		}
 	}
 
+	/// Creates blocks where every position has the same Anvil ID, returning None for air.
+	fn uniform(anvil: u16) -> Option<Self> {
+		if anvil == 0 {
+			return None;
+		}
+
+		let nibbles = |value: u16| {
+			let value = (value & 0xF) as u8;
+
+			NibbleCube::from_raw(Box::new([value | (value << 4); 2048]))
+		};
+
+		Some(AnvilBlocks {
+			blocks: Box::new([(anvil >> 4) as u8; 4096]),
+			data: nibbles(anvil),
+			add: if anvil > 4095 { Some(nibbles(anvil >> 12)) } else { None },
+		})
+	}
+
 	pub fn from_paletted<'b, B, F>(chunk: &'b IndexedCube<B>, to_anvil_id: &'b F) -> Option<Self> 
 	where B: 'b + Target, F: Fn(&'b B) -> u16, {
+		if let Some(block) = chunk.uniform_value() {
+			return AnvilBlocks::uniform(to_anvil_id(block));
+		}
+
 		let mut blocks = Box::new([0u8; 4096]);
 		let mut meta = NibbleCube::default();
 	
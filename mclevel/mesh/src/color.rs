@@ -0,0 +1,89 @@
+use crate::Quad;
+use blockstate::{BlockRegistry, ParseStateError};
+use std::collections::HashMap;
+
+/// Maps block IDs to colors, for viewers that display vertex colors.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ColorTable {
+	colors: HashMap<u32, [u8; 3]>,
+	default: [u8; 3],
+}
+
+impl ColorTable {
+	/// Creates an empty table. Blocks without an entry are given the default color.
+	pub fn new(default: [u8; 3]) -> Self {
+		ColorTable { colors: HashMap::new(), default }
+	}
+
+	pub fn insert(&mut self, block: u32, color: [u8; 3]) -> Option<[u8; 3]> {
+		self.colors.insert(block, color)
+	}
+
+	/// Gives every state of a block the same color, such as all axes of `minecraft:oak_log`. States are keyed by
+	/// the Anvil ID of their legacy block, so the mesh should be built with `Block::to_anvil_id` as its block IDs.
+	pub fn insert_block(
+		&mut self, registry: &BlockRegistry, name: &str, color: [u8; 3],
+	) -> Result<(), ParseStateError> {
+		let block = registry.parse(name)?;
+		let name = registry
+			.state(block)
+			.map(|state| state.name())
+			.ok_or(ParseStateError::UnknownBlock(name.to_string()))?;
+
+		for state in registry.states().filter(|state| state.name() == name) {
+			self.insert(state.legacy().to_anvil_id() as u32, color);
+		}
+
+		Ok(())
+	}
+
+	pub fn get(&self, block: u32) -> [u8; 3] {
+		self.colors.get(&block).copied().unwrap_or(self.default)
+	}
+
+	/// Returns the color of the quad in linear 0.0 to 1.0 units. If the quad has a light value,
+	/// the color is darkened by 20% for each level below full brightness.
+	pub fn shade(&self, quad: &Quad) -> [f32; 3] {
+		let brightness = match quad.light {
+			Some(light) => 0.8f32.powi(15 - light.raw() as i32),
+			None => 1.0,
+		};
+
+		let [r, g, b] = self.get(quad.block);
+
+		[
+			r as f32 / 255.0 * brightness,
+			g as f32 / 255.0 * brightness,
+			b as f32 / 255.0 * brightness,
+		]
+	}
+}
+
+impl Default for ColorTable {
+	fn default() -> Self {
+		ColorTable::new([255, 0, 255])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use blockstate::Block;
+
+	#[test]
+	fn test_insert_block() {
+		let registry = BlockRegistry::builtin();
+		let mut colors = ColorTable::default();
+
+		colors.insert_block(&registry, "oak_log", [102, 81, 50]).unwrap();
+
+		assert_eq!(colors.get(Block::from_legacy(17, 0).to_anvil_id() as u32), [102, 81, 50]);
+		assert_eq!(colors.get(Block::from_legacy(17, 8).to_anvil_id() as u32), [102, 81, 50]);
+		assert_eq!(colors.get(Block::from_legacy(1, 0).to_anvil_id() as u32), [255, 0, 255]);
+
+		assert_eq!(
+			colors.insert_block(&registry, "minecraft:unknown", [0, 0, 0]),
+			Err(ParseStateError::UnknownBlock("minecraft:unknown".into()))
+		);
+	}
+}
@@ -0,0 +1,201 @@
+//! Binary glTF 2.0 (GLB) export. The mesh is written as a single primitive with positions,
+//! normals, vertex colors, and 32-bit indices.
+
+use crate::{ColorTable, Mesh};
+use std::io::{self, Write};
+
+const MAGIC: u32 = 0x4654_6C67;
+const VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+fn push_f32s(buffer: &mut Vec<u8>, values: &[f32]) {
+	for value in values {
+		buffer.extend_from_slice(&value.to_le_bytes());
+	}
+}
+
+fn write_chunk<W>(out: &mut W, kind: u32, data: &[u8], padding: u8) -> io::Result<()>
+where
+	W: Write,
+{
+	let padded = (data.len() + 3) & !3;
+
+	out.write_all(&(padded as u32).to_le_bytes())?;
+	out.write_all(&kind.to_le_bytes())?;
+	out.write_all(data)?;
+
+	for _ in data.len()..padded {
+		out.write_all(&[padding])?;
+	}
+
+	Ok(())
+}
+
+/// Builds the JSON chunk, given the amount of vertices and the bounds of the positions.
+fn json(vertices: usize, min: [f32; 3], max: [f32; 3]) -> String {
+	let asset = r#""asset":{"version":"2.0","generator":"flashcube mesh"}"#;
+
+	if vertices == 0 {
+		// Accessors may not be empty, so leave out the mesh entirely.
+		return format!(r#"{{{},"scene":0,"scenes":[{{"nodes":[]}}]}}"#, asset);
+	}
+
+	let indices = vertices / 4 * 6;
+	let attribute = vertices * 12;
+
+	let view = |offset: usize, length: usize, target: u32| {
+		format!(
+			r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+			offset, length, target
+		)
+	};
+
+	let views = [
+		view(0, attribute, ARRAY_BUFFER),
+		view(attribute, attribute, ARRAY_BUFFER),
+		view(attribute * 2, attribute, ARRAY_BUFFER),
+		view(attribute * 3, indices * 4, ELEMENT_ARRAY_BUFFER),
+	];
+
+	let accessors = [
+		format!(
+			r#"{{"bufferView":0,"componentType":{},"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+			FLOAT, vertices, min[0], min[1], min[2], max[0], max[1], max[2]
+		),
+		format!(
+			r#"{{"bufferView":1,"componentType":{},"count":{},"type":"VEC3"}}"#,
+			FLOAT, vertices
+		),
+		format!(
+			r#"{{"bufferView":2,"componentType":{},"count":{},"type":"VEC3"}}"#,
+			FLOAT, vertices
+		),
+		format!(
+			r#"{{"bufferView":3,"componentType":{},"count":{},"type":"SCALAR"}}"#,
+			UNSIGNED_INT, indices
+		),
+	];
+
+	format!(
+		concat!(
+			r#"{{{},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+			r#""meshes":[{{"primitives":[{{"attributes":{{"POSITION":0,"NORMAL":1,"COLOR_0":2}},"indices":3,"mode":4}}]}}],"#,
+			r#""buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}]}}"#
+		),
+		asset,
+		attribute * 3 + indices * 4,
+		views.join(","),
+		accessors.join(",")
+	)
+}
+
+pub fn write_glb<W>(mesh: &Mesh, colors: &ColorTable, out: &mut W) -> io::Result<()>
+where
+	W: Write,
+{
+	let vertices = mesh.vertex_count();
+
+	let mut positions = Vec::with_capacity(vertices * 12);
+	let mut normals = Vec::with_capacity(vertices * 12);
+	let mut shades = Vec::with_capacity(vertices * 12);
+	let mut indices = Vec::with_capacity(vertices / 4 * 24);
+
+	let mut min = [f32::MAX; 3];
+	let mut max = [f32::MIN; 3];
+
+	for (index, quad) in mesh.quads.iter().enumerate() {
+		let normal = quad.normal();
+		let shade = colors.shade(quad);
+
+		for vertex in quad.vertices().iter() {
+			push_f32s(&mut positions, vertex);
+			push_f32s(&mut normals, &normal);
+			push_f32s(&mut shades, &shade);
+
+			for axis in 0..3 {
+				min[axis] = min[axis].min(vertex[axis]);
+				max[axis] = max[axis].max(vertex[axis]);
+			}
+		}
+
+		let first = (index * 4) as u32;
+
+		for offset in [0, 1, 2, 0, 2, 3].iter() {
+			indices.extend_from_slice(&(first + offset).to_le_bytes());
+		}
+	}
+
+	let json = json(vertices, min, max);
+
+	let mut binary = positions;
+	binary.append(&mut normals);
+	binary.append(&mut shades);
+	binary.append(&mut indices);
+
+	let padded = |length: usize| (length + 3) & !3;
+	let mut length = 12 + 8 + padded(json.len());
+
+	if !binary.is_empty() {
+		length += 8 + padded(binary.len());
+	}
+
+	out.write_all(&MAGIC.to_le_bytes())?;
+	out.write_all(&VERSION.to_le_bytes())?;
+	out.write_all(&(length as u32).to_le_bytes())?;
+
+	write_chunk(out, CHUNK_JSON, json.as_bytes(), b' ')?;
+
+	if !binary.is_empty() {
+		write_chunk(out, CHUNK_BIN, &binary, 0)?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Quad;
+	use vocs::position::{Dir, GlobalPosition};
+
+	#[test]
+	fn test_glb_layout() {
+		let mut mesh = Mesh::new();
+
+		mesh.quads.push(Quad {
+			origin: GlobalPosition::new(1, 2, 3),
+			face: Dir::Up,
+			width: 2,
+			height: 1,
+			block: 1,
+			light: None,
+		});
+
+		let mut out = Vec::new();
+		write_glb(&mesh, &ColorTable::default(), &mut out).unwrap();
+
+		let word = |offset: usize| {
+			u32::from_le_bytes([out[offset], out[offset + 1], out[offset + 2], out[offset + 3]])
+		};
+
+		assert_eq!(word(0), MAGIC);
+		assert_eq!(word(8) as usize, out.len());
+
+		let json_length = word(12) as usize;
+		assert_eq!(word(16), CHUNK_JSON);
+		assert_eq!(json_length % 4, 0);
+
+		let bin = 20 + json_length;
+		assert_eq!(word(bin + 4), CHUNK_BIN);
+		assert_eq!(word(bin) as usize, 4 * 36 + 6 * 4);
+
+		let json = String::from_utf8(out[20..bin].to_vec()).unwrap();
+		assert!(json.contains(r#""min":[1,3,3],"max":[3,3,4]"#));
+	}
+}
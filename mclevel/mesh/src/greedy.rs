@@ -0,0 +1,175 @@
+use crate::{LightFn, Quad};
+use vocs::indexed::{IndexedCube, Target};
+use vocs::position::{Axis, CubePosition, Cuboid, Dir, GlobalChunkPosition, GlobalPosition};
+use vocs::view::SplitDirectional;
+
+const FACES: [Dir; 6] = [Dir::PlusX, Dir::MinusX, Dir::Up, Dir::Down, Dir::PlusZ, Dir::MinusZ];
+
+/// Converts slice, U, and V coordinates to X, Y, and Z coordinates for faces along the axis.
+fn compose(axis: Axis, slice: i32, u: i32, v: i32) -> (i32, i32, i32) {
+	match axis {
+		Axis::X => (slice, v, u),
+		Axis::Y => (u, slice, v),
+		Axis::Z => (u, v, slice),
+	}
+}
+
+fn offset(face: Dir) -> (i32, i32, i32) {
+	match face {
+		Dir::PlusX => (1, 0, 0),
+		Dir::MinusX => (-1, 0, 0),
+		Dir::Up => (0, 1, 0),
+		Dir::Down => (0, -1, 0),
+		Dir::PlusZ => (0, 0, 1),
+		Dir::MinusZ => (0, 0, -1),
+	}
+}
+
+/// Meshes a single chunk, appending the quads to the list. The neighbors are only used to cull
+/// faces on the edges of the chunk, missing neighbors are treated as if they contained no blocks.
+pub fn mesh_chunk<B, I>(
+	position: GlobalChunkPosition, chunk: &IndexedCube<B>,
+	neighbors: SplitDirectional<Option<&IndexedCube<B>>>, ids: &I, light: Option<LightFn>,
+	quads: &mut Vec<Quad>,
+) where
+	B: Target,
+	I: Fn(&B) -> Option<u32>,
+{
+	mesh_chunk_in(position, chunk, neighbors, ids, light, None, quads)
+}
+
+/// Like `mesh_chunk`, but only meshes the blocks inside of the region. Blocks outside of the
+/// region are still used for face culling.
+pub(crate) fn mesh_chunk_in<B, I>(
+	position: GlobalChunkPosition, chunk: &IndexedCube<B>,
+	neighbors: SplitDirectional<Option<&IndexedCube<B>>>, ids: &I, light: Option<LightFn>,
+	region: Option<&Cuboid>, quads: &mut Vec<Quad>,
+) where
+	B: Target,
+	I: Fn(&B) -> Option<u32>,
+{
+	if chunk.is_filled_heuristic(chunk.get(CubePosition::new(0, 0, 0)))
+		&& ids(chunk.get(CubePosition::new(0, 0, 0))).is_none()
+	{
+		return;
+	}
+
+	// Look up each palette entry once, instead of once per block.
+	let (storage, entries) = chunk.freeze();
+	let palette: Vec<Option<u32>> =
+		entries.iter().map(|entry| entry.as_ref().and_then(ids)).collect();
+	let blocks: Vec<Option<u32>> =
+		CubePosition::enumerate().map(|at| palette[storage.get(at) as usize]).collect();
+
+	let base = (position.x() * 16, position.y() as i32 * 16, position.z() * 16);
+
+	let id = |(x, y, z): (i32, i32, i32)| -> Option<u32> {
+		let inside = |value: i32| (0..16).contains(&value);
+
+		if inside(x) && inside(y) && inside(z) {
+			return blocks[CubePosition::new(x as u8, y as u8, z as u8).yzx() as usize];
+		}
+
+		let neighbor = if x > 15 {
+			neighbors.plus_x
+		} else if x < 0 {
+			neighbors.minus_x
+		} else if y > 15 {
+			neighbors.up
+		} else if y < 0 {
+			neighbors.down
+		} else if z > 15 {
+			neighbors.plus_z
+		} else {
+			neighbors.minus_z
+		};
+
+		neighbor.and_then(|neighbor| {
+			ids(neighbor.get(CubePosition::new((x & 15) as u8, (y & 15) as u8, (z & 15) as u8)))
+		})
+	};
+
+	let mut mask: [Option<(u32, Option<_>)>; 256] = [None; 256];
+
+	for &face in FACES.iter() {
+		let axis = face.axis();
+		let (dx, dy, dz) = offset(face);
+
+		for slice in 0..16 {
+			for v in 0..16 {
+				for u in 0..16 {
+					let (x, y, z) = compose(axis, slice, u, v);
+
+					let included = match region {
+						Some(region) => {
+							region.contains(GlobalPosition::new(base.0 + x, base.1 + y, base.2 + z))
+						}
+						None => true,
+					};
+
+					mask[(v * 16 + u) as usize] = match id((x, y, z)) {
+						Some(block) if included && id((x + dx, y + dy, z + dz)).is_none() => {
+							let light = light.map(|light| {
+								light(GlobalPosition::new(
+									base.0 + x + dx,
+									base.1 + y + dy,
+									base.2 + z + dz,
+								))
+							});
+
+							Some((block, light))
+						}
+						_ => None,
+					};
+				}
+			}
+
+			for v in 0..16 {
+				let mut u = 0;
+
+				while u < 16 {
+					let key = match mask[v * 16 + u] {
+						Some(key) => key,
+						None => {
+							u += 1;
+							continue;
+						}
+					};
+
+					let mut width = 1;
+
+					while u + width < 16 && mask[v * 16 + u + width] == Some(key) {
+						width += 1;
+					}
+
+					let mut height = 1;
+
+					while v + height < 16
+						&& (u..u + width).all(|u| mask[(v + height) * 16 + u] == Some(key))
+					{
+						height += 1;
+					}
+
+					for row in v..v + height {
+						for entry in &mut mask[row * 16 + u..row * 16 + u + width] {
+							*entry = None;
+						}
+					}
+
+					let (x, y, z) = compose(axis, slice, u as i32, v as i32);
+
+					quads.push(Quad {
+						origin: GlobalPosition::new(base.0 + x, base.1 + y, base.2 + z),
+						face,
+						width: width as u8,
+						height: height as u8,
+						block: key.0,
+						light: key.1,
+					});
+
+					u += width;
+				}
+			}
+		}
+	}
+}
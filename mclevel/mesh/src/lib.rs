@@ -0,0 +1,197 @@
+//! Turns chunks into greedy meshed quads on the CPU, for previewing terrain in a 3D viewer without
+//! launching Minecraft. Meshes can be exported to Wavefront OBJ or binary glTF.
+//!
+//! Block IDs are supplied by a caller provided closure in the same way as `packet::pack`. Blocks
+//! without an ID are not meshed, and faces next to them are visible. Light values from lumis can
+//! optionally be baked into the vertex colors, see `lumis_light`. Colors can be assigned by block name
+//! through a `BlockRegistry`, see `ColorTable::insert_block`.
+
+use vocs::indexed::{IndexedCube, Target};
+use vocs::nibbles::u4;
+use vocs::position::{Axis, Cuboid, Dir, GlobalPosition};
+use vocs::view::SplitDirectional;
+use vocs::world::world::World;
+
+mod color;
+mod greedy;
+mod light;
+
+pub mod gltf;
+pub mod obj;
+
+pub use color::ColorTable;
+pub use greedy::mesh_chunk;
+pub use light::lumis_light;
+
+/// Looks up the light value in front of a face.
+pub type LightFn<'a> = &'a dyn Fn(GlobalPosition) -> u4;
+
+/// A rectangle of identical block faces, merged by the greedy mesher.
+///
+/// Quads span two axes besides the axis of the face: along U and V respectively, these are
+/// Z and Y for X faces, X and Z for Y faces, and X and Y for Z faces.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Quad {
+	/// The block at the -U, -V corner of the quad.
+	pub origin: GlobalPosition,
+	/// The direction that the quad faces.
+	pub face: Dir,
+	/// Size along the U axis in blocks.
+	pub width: u8,
+	/// Size along the V axis in blocks.
+	pub height: u8,
+	pub block: u32,
+	/// The light in front of the quad, if light was requested while meshing.
+	pub light: Option<u4>,
+}
+
+impl Quad {
+	pub fn normal(&self) -> [f32; 3] {
+		match self.face {
+			Dir::PlusX => [1.0, 0.0, 0.0],
+			Dir::MinusX => [-1.0, 0.0, 0.0],
+			Dir::Up => [0.0, 1.0, 0.0],
+			Dir::Down => [0.0, -1.0, 0.0],
+			Dir::PlusZ => [0.0, 0.0, 1.0],
+			Dir::MinusZ => [0.0, 0.0, -1.0],
+		}
+	}
+
+	/// Returns the corners of the quad, in counter-clockwise order when viewed from the front.
+	pub fn vertices(&self) -> [[f32; 3]; 4] {
+		let (x, y, z) = (self.origin.x() as f32, self.origin.y() as f32, self.origin.z() as f32);
+		let (w, h) = (self.width as f32, self.height as f32);
+		let offset = if self.face.plus() { 1.0 } else { 0.0 };
+
+		let corner = |u: f32, v: f32| match self.face.axis() {
+			Axis::X => [x + offset, y + v, z + u],
+			Axis::Y => [x + u, y + offset, z + v],
+			Axis::Z => [x + u, y + v, z + offset],
+		};
+
+		// U cross V points towards -X, -Y, and +Z, so the other faces need the opposite winding.
+		match self.face {
+			Dir::PlusX | Dir::Up | Dir::MinusZ => {
+				[corner(0.0, 0.0), corner(0.0, h), corner(w, h), corner(w, 0.0)]
+			}
+			_ => [corner(0.0, 0.0), corner(w, 0.0), corner(w, h), corner(0.0, h)],
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Mesh {
+	pub quads: Vec<Quad>,
+}
+
+impl Mesh {
+	pub fn new() -> Self {
+		Mesh { quads: Vec::new() }
+	}
+
+	/// Meshes every block inside of the region. Blocks outside of the region are only used for
+	/// face culling, and missing chunks are treated as if they contained no blocks.
+	pub fn from_world<B, I>(
+		world: &World<IndexedCube<B>>, region: Cuboid, ids: &I, light: Option<LightFn>,
+	) -> Self
+	where
+		B: Target,
+		I: Fn(&B) -> Option<u32>,
+	{
+		let mut mesh = Mesh::new();
+
+		for (position, _, _) in region.chunks() {
+			let chunk = match world.get(position) {
+				Some(chunk) => chunk,
+				None => continue,
+			};
+
+			let neighbor = |position: Option<_>| position.and_then(|position| world.get(position));
+
+			let neighbors = SplitDirectional {
+				plus_x: neighbor(position.plus_x()),
+				minus_x: neighbor(position.minus_x()),
+				up: neighbor(position.plus_y()),
+				down: neighbor(position.minus_y()),
+				plus_z: neighbor(position.plus_z()),
+				minus_z: neighbor(position.minus_z()),
+			};
+
+			greedy::mesh_chunk_in(position, chunk, neighbors, ids, light, Some(&region), &mut mesh.quads);
+		}
+
+		mesh
+	}
+
+	/// Returns the amount of vertices that the mesh has when exported.
+	pub fn vertex_count(&self) -> usize {
+		self.quads.len() * 4
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use vocs::position::GlobalChunkPosition;
+
+	fn ids(block: &u16) -> Option<u32> {
+		if *block == 0 {
+			None
+		} else {
+			Some(*block as u32)
+		}
+	}
+
+	#[test]
+	fn test_greedy_merge() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		world.set(GlobalChunkPosition::new(0, 0, 0), IndexedCube::new(4, 0));
+		world.set(GlobalChunkPosition::new(1, 0, 0), IndexedCube::new(4, 0));
+
+		// A 3x1x2 slab that crosses a chunk boundary, with a different block on the end.
+		let region = Cuboid::new(GlobalPosition::new(14, 4, 4), GlobalPosition::new(16, 4, 5));
+
+		world.fill(region, &1);
+		world.fill(Cuboid::new(GlobalPosition::new(16, 4, 4), GlobalPosition::new(16, 4, 5)), &2);
+
+		let mesh = Mesh::from_world(&world, region, &ids, None);
+
+		// Top, bottom, and the Z faces are split by chunk and block. The faces between blocks are culled.
+		assert_eq!(mesh.quads.len(), 4 * 2 + 2);
+
+		let top: Vec<_> = mesh.quads.iter().filter(|quad| quad.face == Dir::Up).collect();
+
+		assert_eq!(top.len(), 2);
+		assert_eq!(
+			(top[0].origin, top[0].width, top[0].height),
+			(GlobalPosition::new(14, 4, 4), 2, 2)
+		);
+		assert_eq!(top[0].vertices()[2], [16.0, 5.0, 6.0]);
+
+		let side = mesh.quads.iter().find(|quad| quad.face == Dir::PlusX).unwrap();
+
+		assert_eq!(side.block, 2);
+		assert_eq!(side.origin, GlobalPosition::new(16, 4, 4));
+		assert_eq!((side.width, side.height), (2, 1));
+	}
+
+	#[test]
+	fn test_clip_to_region() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		world.set(GlobalChunkPosition::new(0, 0, 0), IndexedCube::new(4, 0));
+		world.fill(Cuboid::new(GlobalPosition::new(4, 4, 4), GlobalPosition::new(6, 4, 5)), &1);
+
+		// Only the corner block is meshed, and its neighbors in the slab still cull its faces.
+		let corner = GlobalPosition::new(4, 4, 4);
+		let mesh = Mesh::from_world(&world, Cuboid::new(corner, corner), &ids, None);
+
+		let faces: Vec<_> = mesh.quads.iter().map(|quad| (quad.face, quad.origin)).collect();
+
+		assert_eq!(
+			faces,
+			vec![(Dir::MinusX, corner), (Dir::Up, corner), (Dir::Down, corner), (Dir::MinusZ, corner)]
+		);
+	}
+}
@@ -0,0 +1,85 @@
+use lumis::PackedNibbleCube;
+use std::cell::RefCell;
+use std::cmp;
+use vocs::nibbles::u4;
+use vocs::position::{GlobalChunkPosition, GlobalPosition};
+use vocs::world::shared::{NoPack, SharedWorld};
+
+/// Meshing a chunk looks up light in the chunk itself and its six neighbors.
+const CACHED_CHUNKS: usize = 7;
+
+/// Copies of the sky and block light of a chunk, taken while it was locked.
+type CachedLight = (GlobalChunkPosition, Option<PackedNibbleCube>, Option<PackedNibbleCube>);
+
+/// Combines the sky and block light computed by lumis into a light function for meshing, taking
+/// the brighter of the two. Missing sky light chunks are treated as fully lit, and missing block
+/// light chunks as fully dark. Positions above or below the range of chunks are missing.
+///
+/// The light of the chunks around the one being meshed is copied out once, instead of locking
+/// both worlds for every face.
+pub fn lumis_light<'a>(
+	sky_light: &'a SharedWorld<NoPack<PackedNibbleCube>>,
+	block_light: &'a SharedWorld<NoPack<PackedNibbleCube>>,
+) -> impl Fn(GlobalPosition) -> u4 + 'a {
+	let cache: RefCell<Vec<CachedLight>> = RefCell::new(Vec::with_capacity(CACHED_CHUNKS));
+
+	move |position: GlobalPosition| {
+		let chunk = match position.checked_global_chunk() {
+			Some(chunk) => chunk,
+			None => return u4::MAX,
+		};
+
+		let local = position.local_block();
+		let mut cache = cache.borrow_mut();
+
+		let index = match cache.iter().position(|&(cached, _, _)| cached == chunk) {
+			Some(index) => index,
+			None => {
+				// Once meshing moves on to the next chunk, start over.
+				if cache.len() == CACHED_CHUNKS {
+					cache.clear();
+				}
+
+				cache.push((
+					chunk,
+					sky_light.get(chunk).map(|light| light.clone()),
+					block_light.get(chunk).map(|light| light.clone()),
+				));
+
+				cache.len() - 1
+			}
+		};
+
+		let (_, ref sky, ref block) = cache[index];
+
+		let sky = sky.as_ref().map(|light| light.get(local)).unwrap_or(u4::MAX);
+		let block = block.as_ref().map(|light| light.get(local)).unwrap_or(u4::ZERO);
+
+		cmp::max(sky, block)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use vocs::position::CubePosition;
+
+	#[test]
+	fn test_lumis_light() {
+		let mut sky_light = SharedWorld::new();
+		let block_light = SharedWorld::new();
+
+		let mut dark = PackedNibbleCube::default();
+		dark.set(CubePosition::new(1, 2, 3), u4::new(7));
+		sky_light.set(GlobalChunkPosition::new(0, 0, 0), NoPack(dark));
+
+		let light = lumis_light(&sky_light, &block_light);
+
+		assert_eq!(light(GlobalPosition::new(1, 2, 3)), u4::new(7));
+		assert_eq!(light(GlobalPosition::new(1, 2, 4)), u4::ZERO);
+
+		// Missing chunks and positions past the range of chunks are fully lit by the sky.
+		assert_eq!(light(GlobalPosition::new(16, 2, 3)), u4::MAX);
+		assert_eq!(light(GlobalPosition::new(1, 4096, 3)), u4::MAX);
+	}
+}
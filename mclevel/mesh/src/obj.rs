@@ -0,0 +1,46 @@
+//! Wavefront OBJ export. Vertex colors are written with the common `v x y z r g b` extension,
+//! which is understood by Blender and MeshLab among others.
+
+use crate::{ColorTable, Mesh};
+use std::io::{self, Write};
+
+/// Normals in the order of `Dir`, so that a face can refer to its normal by index.
+const NORMALS: [&str; 6] = ["1 0 0", "-1 0 0", "0 1 0", "0 -1 0", "0 0 1", "0 0 -1"];
+
+pub fn write_obj<W>(mesh: &Mesh, colors: &ColorTable, out: &mut W) -> io::Result<()>
+where
+	W: Write,
+{
+	for normal in NORMALS.iter() {
+		writeln!(out, "vn {}", normal)?;
+	}
+
+	for quad in &mesh.quads {
+		let [r, g, b] = colors.shade(quad);
+
+		for [x, y, z] in quad.vertices().iter() {
+			writeln!(out, "v {} {} {} {} {} {}", x, y, z, r, g, b)?;
+		}
+	}
+
+	for (index, quad) in mesh.quads.iter().enumerate() {
+		// OBJ indices start at 1.
+		let first = index * 4 + 1;
+		let normal = quad.face as usize + 1;
+
+		writeln!(
+			out,
+			"f {}//{} {}//{} {}//{} {}//{}",
+			first,
+			normal,
+			first + 1,
+			normal,
+			first + 2,
+			normal,
+			first + 3,
+			normal
+		)?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::{ColumnRef, EncodeError, Era};
+use lumis::PackedNibbleCube;
+use vocs::indexed::Target;
+use vocs::packed::Layout;
+use vocs::position::CubePosition;
+
+const HEIGHT: usize = 128;
+const VOLUME: usize = 16 * 16 * HEIGHT;
+
+fn index(section_y: i8, position: CubePosition) -> usize {
+	let y = (section_y as usize) * 16 + position.y() as usize;
+
+	((position.x() as usize) << 11) | ((position.z() as usize) << 7) | y
+}
+
+fn set_nibble(array: &mut [u8], index: usize, value: u8) {
+	array[index >> 1] |= (value & 15) << ((index & 1) * 4);
+}
+
+fn light_nibble(light: &PackedNibbleCube, position: CubePosition) -> u8 {
+	light.get(position).raw()
+}
+
+/// Columns are 128 blocks tall. Absent sections are sent as air, lit by the sky if the column has sky light.
+pub fn encode<B, L, F>(column: &ColumnRef<B, L>, global_id: &F) -> Result<Vec<u8>, EncodeError>
+where
+	B: Target,
+	L: Layout,
+	F: Fn(&B) -> u32,
+{
+	let mut blocks = vec![0u8; VOLUME];
+	let mut meta = vec![0u8; VOLUME / 2];
+	let mut block_light = vec![0u8; VOLUME / 2];
+	let mut sky_light = vec![if column.has_sky_light { 0xFF } else { 0x00 }; VOLUME / 2];
+
+	let (sections, _) = column.sorted_sections(Era::Beta.sections())?;
+
+	for section in sections {
+		let (storage, palette) = section.blocks.freeze();
+
+		let ids: Vec<u32> =
+			palette.iter().map(|entry| entry.as_ref().map(global_id).unwrap_or(0)).collect();
+
+		for position in CubePosition::enumerate() {
+			let index = index(section.y, position);
+			let id = ids[storage.get(position) as usize];
+
+			blocks[index] = (id >> 4) as u8;
+			set_nibble(&mut meta, index, id as u8);
+			set_nibble(&mut block_light, index, light_nibble(section.block_light, position));
+
+			if column.has_sky_light {
+				// Clear the default full brightness before setting the real value.
+				sky_light[index >> 1] &= !(15 << ((index & 1) * 4));
+				set_nibble(&mut sky_light, index, light_nibble(section.sky_light, position));
+			}
+		}
+	}
+
+	let mut data = blocks;
+	data.extend_from_slice(&meta);
+	data.extend_from_slice(&block_light);
+	data.extend_from_slice(&sky_light);
+
+	let compressed = deflate::deflate_bytes_zlib(&data);
+
+	let mut out = Vec::with_capacity(compressed.len() + 17);
+
+	out.extend_from_slice(&(column.x * 16).to_be_bytes());
+	out.extend_from_slice(&0i16.to_be_bytes());
+	out.extend_from_slice(&(column.z * 16).to_be_bytes());
+	out.push(15);
+	out.push((HEIGHT - 1) as u8);
+	out.push(15);
+	out.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+	out.extend_from_slice(&compressed);
+
+	Ok(out)
+}
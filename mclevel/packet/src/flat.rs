@@ -0,0 +1,54 @@
+use crate::light::write_light;
+use crate::varint::write_varint;
+use crate::{ColumnRef, EncodeError, Era};
+use vocs::indexed::Target;
+use vocs::packed::Layout;
+use vocs::position::CubePosition;
+
+/// Unlike later eras, 1.8 groups the data by kind: the blocks of every section come first,
+/// followed by all of the block light, then all of the sky light, then the biomes.
+pub fn encode<B, L, F>(column: &ColumnRef<B, L>, global_id: &F) -> Result<Vec<u8>, EncodeError>
+where
+	B: Target,
+	L: Layout,
+	F: Fn(&B) -> u32,
+{
+	let (sections, mask) = column.sorted_sections(Era::Flat.sections())?;
+
+	let light_arrays = if column.has_sky_light { 2 } else { 1 };
+	let mut data = Vec::with_capacity(sections.len() * (8192 + 2048 * light_arrays) + 256);
+
+	for section in &sections {
+		let (storage, palette) = section.blocks.freeze();
+
+		let ids: Vec<u16> =
+			palette.iter().map(|entry| entry.as_ref().map(global_id).unwrap_or(0) as u16).collect();
+
+		for position in CubePosition::enumerate() {
+			data.extend_from_slice(&ids[storage.get(position) as usize].to_le_bytes());
+		}
+	}
+
+	for section in &sections {
+		write_light(&mut data, section.block_light);
+	}
+
+	if column.has_sky_light {
+		for section in &sections {
+			write_light(&mut data, section.sky_light);
+		}
+	}
+
+	data.extend_from_slice(column.biomes);
+
+	let mut out = Vec::with_capacity(data.len() + 16);
+
+	out.extend_from_slice(&column.x.to_be_bytes());
+	out.extend_from_slice(&column.z.to_be_bytes());
+	out.push(1); // Ground-up continuous
+	out.extend_from_slice(&mask.to_be_bytes());
+	write_varint(&mut out, data.len() as i32);
+	out.extend_from_slice(&data);
+
+	Ok(out)
+}
@@ -0,0 +1,266 @@
+//! Encodes columns into the body of the chunk data packet sent by the server, for several eras of
+//! the Minecraft protocol. The packet ID and framing (length prefix, compression, encryption)
+//! are left to the connection; see `Era::packet_id`.
+//!
+//! Block IDs are supplied by a caller provided closure in the same way as `mca::AnvilBlocks`,
+//! and light is read straight out of lumis without unpacking.
+
+use lumis::PackedNibbleCube;
+use std::error::Error;
+use std::fmt::{self, Display};
+use vocs::indexed::{IndexedCube, Target};
+use vocs::packed::{Layout, Spanning};
+
+mod beta;
+mod flat;
+mod light;
+mod paletted;
+mod section;
+mod varint;
+
+pub use section::{pack, EncodedBlocks, Packing};
+pub use varint::write_varint;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Era {
+	/// Beta 1.0 through release 1.1: a zlib compressed volume ordered XZY, 128 blocks tall.
+	/// Global IDs are `id << 4 | meta`.
+	Beta,
+	/// Release 1.8: each block is a little endian `u16` of `id << 4 | meta`.
+	Flat,
+	/// Release 1.9.4 through 1.12.2: paletted sections with entries that may span two longs,
+	/// and light stored alongside the blocks.
+	Spanning,
+	/// Release 1.13 through 1.13.2: like `Spanning`, but with flattened block state IDs and a biome
+	/// array of ints. Direct sections no longer carry an empty palette.
+	Flattening,
+	/// Release 1.14 through 1.14.4: each section starts with its count of non-air blocks, the column
+	/// has heightmaps, and light is sent separately by `encode_light_update`.
+	Heightmaps,
+	/// Release 1.15 through 1.15.2: like `Heightmaps`, but biomes are stored per 4x4x4 cell and sent
+	/// before the section data.
+	CellBiomes,
+	/// Release 1.16.2 through 1.16.5: paletted sections with entries that never span two longs,
+	/// and light sent separately by `encode_light_update`.
+	Aligned,
+}
+
+impl Era {
+	pub fn packet_id(self) -> u8 {
+		match self {
+			Era::Beta => 0x33,
+			Era::Flat => 0x21,
+			Era::Spanning => 0x20,
+			Era::Flattening => 0x22,
+			Era::Heightmaps => 0x21,
+			Era::CellBiomes => 0x22,
+			Era::Aligned => 0x20,
+		}
+	}
+
+	/// ID of the packet produced by `encode_light_update`, if this era sends light separately.
+	pub fn light_packet_id(self) -> Option<u8> {
+		match self {
+			Era::Heightmaps => Some(0x24),
+			Era::CellBiomes => Some(0x25),
+			Era::Aligned => Some(0x23),
+			_ => None,
+		}
+	}
+
+	/// Bits per entry used for sections that are too varied for an indirect palette.
+	/// This is the number of bits needed to hold every block state in the vanilla registry.
+	pub fn global_bits(self) -> u8 {
+		match self {
+			Era::Beta | Era::Flat => 16,
+			Era::Spanning => 13,
+			Era::Flattening | Era::Heightmaps | Era::CellBiomes => 14,
+			Era::Aligned => 15,
+		}
+	}
+
+	/// Number of sections in a column, starting from Y=0.
+	pub fn sections(self) -> u8 {
+		match self {
+			Era::Beta => 8,
+			_ => 16,
+		}
+	}
+}
+
+/// An error that prevents a column from being encoded.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EncodeError {
+	/// The section is below Y=0 or above the top of the column, see `Era::sections`.
+	SectionOutOfRange(i8),
+	DuplicateSection(i8),
+	/// The era sends light inside the chunk data packet, so there is no light update packet to encode.
+	NoLightPacket(Era),
+}
+
+impl Display for EncodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EncodeError::SectionOutOfRange(y) => write!(f, "section at Y={} is outside of the column", y),
+			EncodeError::DuplicateSection(y) => write!(f, "duplicate section at Y={}", y),
+			EncodeError::NoLightPacket(era) => write!(f, "the {:?} era has no light update packet", era),
+		}
+	}
+}
+
+impl Error for EncodeError {}
+
+// TODO: Cannot derive Debug (IndexedCube)
+#[derive(Clone)]
+pub struct SectionRef<'c, B, L = Spanning>
+where
+	B: Target,
+	L: Layout,
+{
+	/// Must be within the column height of the era, see `Era::sections`.
+	pub y: i8,
+	/// Chunks using the layout that matches the era's packing are copied without repacking.
+	pub blocks: &'c IndexedCube<B, L>,
+	pub block_light: &'c PackedNibbleCube,
+	/// Ignored if the column has no sky light.
+	pub sky_light: &'c PackedNibbleCube,
+}
+
+#[derive(Clone)]
+pub struct ColumnRef<'c, B, L = Spanning>
+where
+	B: Target,
+	L: Layout,
+{
+	pub x: i32,
+	pub z: i32,
+	/// False in dimensions without a sky, such as the Nether.
+	pub has_sky_light: bool,
+	pub biomes: &'c [u8; 256],
+	pub heightmap: &'c [u32; 256],
+	/// Sections may be in any order, and absent sections are treated as empty.
+	pub sections: &'c [SectionRef<'c, B, L>],
+}
+
+/// Sections in ascending order, along with the bit mask of the sections that are present.
+type SortedSections<'r, 'c, B, L> = (Vec<&'r SectionRef<'c, B, L>>, u16);
+
+impl<'c, B, L> ColumnRef<'c, B, L>
+where
+	B: Target,
+	L: Layout,
+{
+	/// Returns the sections in ascending order along with their bit mask, or an error if any section is outside of
+	/// the bottom `count` sections.
+	fn sorted_sections(&self, count: u8) -> Result<SortedSections<'_, 'c, B, L>, EncodeError> {
+		let mut sections: Vec<_> = self.sections.iter().collect();
+		sections.sort_by_key(|section| section.y);
+
+		let mut mask = 0;
+
+		for section in &sections {
+			if section.y < 0 || section.y as u8 >= count {
+				return Err(EncodeError::SectionOutOfRange(section.y));
+			}
+
+			if mask & (1 << section.y) != 0 {
+				return Err(EncodeError::DuplicateSection(section.y));
+			}
+
+			mask |= 1 << section.y;
+		}
+
+		Ok((sections, mask))
+	}
+}
+
+/// Encodes the body of the chunk data packet for the specified era.
+pub fn encode<B, L, F>(era: Era, column: &ColumnRef<B, L>, global_id: &F) -> Result<Vec<u8>, EncodeError>
+where
+	B: Target,
+	L: Layout,
+	F: Fn(&B) -> u32,
+{
+	match era {
+		Era::Beta => beta::encode(column, global_id),
+		Era::Flat => flat::encode(column, global_id),
+		_ => paletted::encode(era, column, global_id),
+	}
+}
+
+/// Encodes the body of the light update packet that accompanies each chunk in eras that send light separately,
+/// see `Era::light_packet_id`.
+pub fn encode_light_update<B, L>(era: Era, column: &ColumnRef<B, L>) -> Result<Vec<u8>, EncodeError>
+where
+	B: Target,
+	L: Layout,
+{
+	if era.light_packet_id().is_none() {
+		return Err(EncodeError::NoLightPacket(era));
+	}
+
+	paletted::encode_light_update(era, column)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn column<'c>(sections: &'c [SectionRef<'c, u16>]) -> ColumnRef<'c, u16> {
+		static BIOMES: [u8; 256] = [1; 256];
+		static HEIGHTMAP: [u32; 256] = [64; 256];
+
+		ColumnRef {
+			x: 0,
+			z: 0,
+			has_sky_light: true,
+			biomes: &BIOMES,
+			heightmap: &HEIGHTMAP,
+			sections,
+		}
+	}
+
+	#[test]
+	fn test_section_range() {
+		let blocks = IndexedCube::<u16>::new(4, 1);
+		let light = PackedNibbleCube::EntirelyDark;
+		let section = |y| SectionRef { y, blocks: &blocks, block_light: &light, sky_light: &light };
+
+		let below = [section(-1)];
+		let above = [section(16)];
+		let duplicate = [section(3), section(3)];
+		let tall = [section(8)];
+
+		assert_eq!(encode(Era::Aligned, &column(&below), &|&id| id as u32), Err(EncodeError::SectionOutOfRange(-1)));
+		assert_eq!(encode(Era::Spanning, &column(&above), &|&id| id as u32), Err(EncodeError::SectionOutOfRange(16)));
+		assert_eq!(encode(Era::Flat, &column(&duplicate), &|&id| id as u32), Err(EncodeError::DuplicateSection(3)));
+		assert_eq!(encode(Era::Beta, &column(&tall), &|&id| id as u32), Err(EncodeError::SectionOutOfRange(8)));
+		assert!(encode(Era::Flat, &column(&tall), &|&id| id as u32).is_ok());
+	}
+
+	#[test]
+	fn test_light_placement() {
+		let blocks = IndexedCube::<u16>::new(4, 1);
+		let light = PackedNibbleCube::EntirelyLit;
+		let sections = [SectionRef { y: 0, blocks: &blocks, block_light: &light, sky_light: &light }];
+		let column = column(&sections);
+
+		// A uniform section: bits, a palette of 1 entry, and 256 longs for 4 bits per entry.
+		let blocks_len = 1 + 2 + 2 + 256 * 8;
+
+		let flattening = encode(Era::Flattening, &column, &|&id| id as u32).unwrap();
+		let heightmaps = encode(Era::Heightmaps, &column, &|&id| id as u32).unwrap();
+
+		// Light and int biomes follow the blocks in 1.13, while 1.14 starts each section with its block count.
+		assert_eq!(flattening.len(), 4 + 4 + 1 + 1 + 2 + blocks_len + 2048 * 2 + 256 * 4 + 1);
+		assert!(heightmaps.windows(2 + 1 + 2).any(|window| window == [0x10, 0x00, 4, 1, 1]));
+
+		assert_eq!(encode_light_update(Era::Flattening, &column), Err(EncodeError::NoLightPacket(Era::Flattening)));
+
+		let update_1_14 = encode_light_update(Era::Heightmaps, &column).unwrap();
+		let update_1_16 = encode_light_update(Era::Aligned, &column).unwrap();
+
+		// 1.16 adds the trust edges flag.
+		assert_eq!(update_1_14.len() + 1, update_1_16.len());
+	}
+}
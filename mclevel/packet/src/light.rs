@@ -0,0 +1,12 @@
+use lumis::PackedNibbleCube;
+
+/// Writes a light volume as the 2048 byte nibble array used by every protocol version. The
+/// in-memory layout of NibbleCube already matches the protocol (YZX order, low nibble first),
+/// so unpacked volumes are copied directly.
+pub fn write_light(out: &mut Vec<u8>, light: &PackedNibbleCube) {
+	match light {
+		PackedNibbleCube::Unpacked(cube) => out.extend_from_slice(&cube.raw()[..]),
+		PackedNibbleCube::EntirelyDark => out.resize(out.len() + 2048, 0x00),
+		PackedNibbleCube::EntirelyLit => out.resize(out.len() + 2048, 0xFF),
+	}
+}
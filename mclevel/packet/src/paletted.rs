@@ -0,0 +1,187 @@
+use crate::light::write_light;
+use crate::section::{pack_entries, EncodedBlocks, Packing};
+use crate::varint::write_varint;
+use crate::{ColumnRef, EncodeError, Era};
+use lumis::PackedNibbleCube;
+use nbt_turbo::writer::CompoundWriter;
+use vocs::indexed::Target;
+use vocs::packed::Layout;
+
+pub fn encode<B, L, F>(era: Era, column: &ColumnRef<B, L>, global_id: &F) -> Result<Vec<u8>, EncodeError>
+where
+	B: Target,
+	L: Layout,
+	F: Fn(&B) -> u32,
+{
+	let (sections, mask) = column.sorted_sections(era.sections())?;
+
+	let packing = match era {
+		Era::Aligned => Packing::Aligned,
+		_ => Packing::Spanning,
+	};
+
+	// Eras that send light separately also count the non-air blocks of each section.
+	let separate_light = era.light_packet_id().is_some();
+
+	let mut data = Vec::new();
+
+	for section in &sections {
+		let blocks = EncodedBlocks::encode(section.blocks, global_id, packing, era.global_bits());
+
+		if separate_light {
+			data.extend_from_slice(&blocks.non_air.to_be_bytes());
+		}
+
+		blocks.write(&mut data, era == Era::Spanning);
+
+		if !separate_light {
+			write_light(&mut data, section.block_light);
+
+			if column.has_sky_light {
+				write_light(&mut data, section.sky_light);
+			}
+		}
+	}
+
+	let mut out = Vec::with_capacity(data.len() + 4096);
+
+	out.extend_from_slice(&column.x.to_be_bytes());
+	out.extend_from_slice(&column.z.to_be_bytes());
+	out.push(1); // Full chunk
+	write_varint(&mut out, mask as i32);
+
+	match era {
+		Era::Spanning => data.extend_from_slice(column.biomes),
+		Era::Flattening => write_biome_ints(&mut data, column.biomes),
+		Era::Heightmaps => {
+			write_heightmaps(&mut out, column.heightmap, packing);
+			write_biome_ints(&mut data, column.biomes);
+		}
+		Era::CellBiomes => {
+			write_heightmaps(&mut out, column.heightmap, packing);
+
+			for biome in cell_biomes(column.biomes) {
+				out.extend_from_slice(&biome.to_be_bytes());
+			}
+		}
+		Era::Aligned => {
+			write_heightmaps(&mut out, column.heightmap, packing);
+			write_varint(&mut out, 1024);
+
+			for biome in cell_biomes(column.biomes) {
+				write_varint(&mut out, biome);
+			}
+		}
+		Era::Beta | Era::Flat => unreachable!("{:?} does not use paletted sections", era),
+	}
+
+	write_varint(&mut out, data.len() as i32);
+	out.extend_from_slice(&data);
+
+	// TODO: Block entities
+	write_varint(&mut out, 0);
+
+	Ok(out)
+}
+
+fn write_biome_ints(out: &mut Vec<u8>, biomes: &[u8; 256]) {
+	for &biome in biomes.iter() {
+		out.extend_from_slice(&(biome as i32).to_be_bytes());
+	}
+}
+
+/// Biomes stored per 4x4x4 cell, in YZX order. The 2D biomes are sampled at the corner of each cell and repeated
+/// up the column.
+fn cell_biomes(biomes: &[u8; 256]) -> impl Iterator<Item = i32> + '_ {
+	(0..1024).map(move |index| {
+		let x = index & 3;
+		let z = (index >> 2) & 3;
+
+		biomes[((z * 4) << 4) | (x * 4)] as i32
+	})
+}
+
+/// Heightmaps have 9 bits per entry, packed in the same way as the block data of the era.
+fn write_heightmaps(out: &mut Vec<u8>, heightmap: &[u32; 256], packing: Packing) {
+	let longs: Vec<i64> = pack_entries(heightmap.iter().copied(), 256, 9, packing)
+		.into_iter()
+		.map(|long| long as i64)
+		.collect();
+
+	CompoundWriter::write("", out, |writer| {
+		writer.i64_array("MOTION_BLOCKING", &longs);
+	});
+}
+
+pub fn encode_light_update<B, L>(era: Era, column: &ColumnRef<B, L>) -> Result<Vec<u8>, EncodeError>
+where
+	B: Target,
+	L: Layout,
+{
+	let (sections, _) = column.sorted_sections(era.sections())?;
+
+	// Bit 0 of each mask refers to the section below the world, so every section is offset by 1.
+	let mut sky_mask = 0;
+	let mut block_mask = 0;
+	let mut empty_sky_mask = 0;
+	let mut empty_block_mask = 0;
+
+	let mut sky_data = Vec::new();
+	let mut block_data = Vec::new();
+
+	for section in &sections {
+		let bit = 1 << (section.y + 1);
+
+		if column.has_sky_light {
+			write_light_array(
+				&mut sky_data,
+				section.sky_light,
+				bit,
+				&mut sky_mask,
+				&mut empty_sky_mask,
+			);
+		}
+
+		write_light_array(
+			&mut block_data,
+			section.block_light,
+			bit,
+			&mut block_mask,
+			&mut empty_block_mask,
+		);
+	}
+
+	let mut out = Vec::with_capacity(sky_data.len() + block_data.len() + 32);
+
+	write_varint(&mut out, column.x);
+	write_varint(&mut out, column.z);
+
+	// Added in 1.16.
+	if era == Era::Aligned {
+		out.push(1); // Trust edges
+	}
+
+	write_varint(&mut out, sky_mask);
+	write_varint(&mut out, block_mask);
+	write_varint(&mut out, empty_sky_mask);
+	write_varint(&mut out, empty_block_mask);
+	out.extend_from_slice(&sky_data);
+	out.extend_from_slice(&block_data);
+
+	Ok(out)
+}
+
+/// Entirely dark volumes only need a bit in the empty mask, instead of a full array.
+fn write_light_array(
+	out: &mut Vec<u8>, light: &PackedNibbleCube, bit: i32, mask: &mut i32, empty_mask: &mut i32,
+) {
+	if let PackedNibbleCube::EntirelyDark = light {
+		*empty_mask |= bit;
+		return;
+	}
+
+	*mask |= bit;
+
+	write_varint(out, 2048);
+	write_light(out, light);
+}
@@ -0,0 +1,239 @@
+use crate::varint::write_varint;
+use vocs::indexed::{IndexedCube, Target};
+use vocs::packed::Layout;
+use vocs::position::CubePosition;
+
+/// Smallest bit count the client accepts for an indirect palette.
+const MIN_PALETTE_BITS: u8 = 4;
+/// Largest bit count that still uses an indirect palette. Above this, the client expects global IDs.
+const MAX_PALETTE_BITS: u8 = 8;
+
+/// How entries are laid out in the array of longs sent for a section.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Packing {
+	/// Entries may straddle two longs (1.9 through 1.15). This matches vocs::packed::Spanning.
+	Spanning,
+	/// Entries never straddle two longs, leaving the top bits of each long unused (1.16 and above).
+	/// This matches vocs::packed::Aligned.
+	Aligned,
+}
+
+impl Packing {
+	fn longs(self, entries: usize, bits: u8) -> usize {
+		match self {
+			Packing::Spanning => (entries * bits as usize).div_ceil(64),
+			Packing::Aligned => {
+				let per_long = 64 / bits as usize;
+
+				entries.div_ceil(per_long)
+			}
+		}
+	}
+}
+
+/// Packs 4096 values into an array of longs with the specified number of bits per entry.
+pub fn pack<I>(values: I, bits: u8, packing: Packing) -> Vec<u64>
+where
+	I: Iterator<Item = u32>,
+{
+	pack_entries(values, 4096, bits, packing)
+}
+
+/// Packs an arbitrary amount of values, such as the 256 entries of a heightmap. Values past `entries` are ignored.
+pub(crate) fn pack_entries<I>(values: I, entries: usize, bits: u8, packing: Packing) -> Vec<u64>
+where
+	I: Iterator<Item = u32>,
+{
+	let values = values.take(entries);
+	let mut longs = vec![0u64; packing.longs(entries, bits)];
+	let mask = (1u64 << bits) - 1;
+	let bits = bits as usize;
+
+	match packing {
+		Packing::Spanning => {
+			for (index, value) in values.enumerate() {
+				let value = value as u64 & mask;
+				let bit_index = index * bits;
+				let start = bit_index / 64;
+				let sub_index = bit_index % 64;
+
+				longs[start] |= value << sub_index;
+
+				if sub_index + bits > 64 {
+					longs[start + 1] |= value >> (64 - sub_index);
+				}
+			}
+		}
+		Packing::Aligned => {
+			let per_long = 64 / bits;
+
+			for (index, value) in values.enumerate() {
+				longs[index / per_long] |= (value as u64 & mask) << ((index % per_long) * bits);
+			}
+		}
+	}
+
+	longs
+}
+
+/// The result of translating a chunk to global IDs, ready to be written out.
+pub struct EncodedBlocks {
+	/// Bits per entry in the long array.
+	pub bits: u8,
+	/// The indirect palette, or None when the long array holds global IDs directly.
+	pub palette: Option<Vec<u32>>,
+	pub longs: Vec<u64>,
+	/// Number of entries with a global ID other than 0 (air).
+	pub non_air: u16,
+}
+
+impl EncodedBlocks {
+	pub fn encode<B, L, F>(
+		chunk: &IndexedCube<B, L>, global_id: &F, packing: Packing, global_bits: u8,
+	) -> Self
+	where
+		B: Target,
+		L: Layout,
+		F: Fn(&B) -> u32,
+	{
+		let (storage, palette) = chunk.freeze();
+
+		let ids: Vec<u32> =
+			palette.iter().map(|entry| entry.as_ref().map(global_id).unwrap_or(0)).collect();
+		let values = || CubePosition::enumerate().map(|position| storage.get(position));
+
+		let non_air = values().filter(|&value| ids[value as usize] != 0).count() as u16;
+
+		let bits = storage.bits();
+
+		if bits > MAX_PALETTE_BITS {
+			let longs = pack(values().map(|value| ids[value as usize]), global_bits, packing);
+
+			return EncodedBlocks { bits: global_bits, palette: None, longs, non_air };
+		}
+
+		// Trailing holes in the palette are never referenced, so they need not be sent.
+		let used = palette.iter().rposition(Option::is_some).map(|last| last + 1).unwrap_or(0);
+		let palette = ids[..used].to_vec();
+
+		let encoded_bits = bits.max(MIN_PALETTE_BITS);
+
+		let longs = if (packing == Packing::Spanning) == L::SPANNING && bits == encoded_bits {
+			// Fast path: the storage already has the protocol layout.
+			storage.raw_storage().to_vec()
+		} else {
+			pack(values(), encoded_bits, packing)
+		};
+
+		EncodedBlocks { bits: encoded_bits, palette: Some(palette), longs, non_air }
+	}
+
+	/// Writes the bits per entry, palette, and data array of a section.
+	/// Before 1.13, a direct section still carried a palette length of 0, see `empty_direct_palette`.
+	pub fn write(&self, out: &mut Vec<u8>, empty_direct_palette: bool) {
+		out.push(self.bits);
+
+		match &self.palette {
+			Some(palette) => {
+				write_varint(out, palette.len() as i32);
+
+				for &id in palette {
+					write_varint(out, id as i32);
+				}
+			}
+			None if empty_direct_palette => write_varint(out, 0),
+			None => (),
+		}
+
+		write_varint(out, self.longs.len() as i32);
+
+		for &long in &self.longs {
+			out.extend_from_slice(&long.to_be_bytes());
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use vocs::packed::Aligned;
+
+	#[test]
+	fn test_spanning_fast_path() {
+		let mut chunk = IndexedCube::<u16>::new(4, 0);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 11));
+		}
+
+		let fast = EncodedBlocks::encode(&chunk, &|&block| block as u32 + 1, Packing::Spanning, 13);
+
+		let (storage, _) = chunk.freeze();
+		let slow = pack(
+			CubePosition::enumerate().map(|position| storage.get(position)),
+			4,
+			Packing::Spanning,
+		);
+
+		assert_eq!(fast.bits, 4);
+		assert_eq!(fast.longs, slow);
+		assert_eq!(fast.non_air, 4096);
+	}
+
+	#[test]
+	fn test_aligned_fast_path() {
+		let mut chunk = IndexedCube::<u16, Aligned>::new(5, 0);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 20));
+		}
+
+		let spanning: IndexedCube<u16> = chunk.to_layout();
+
+		let fast = EncodedBlocks::encode(&chunk, &|&block| block as u32, Packing::Aligned, 15);
+		let slow = EncodedBlocks::encode(&spanning, &|&block| block as u32, Packing::Aligned, 15);
+
+		assert_eq!(fast.bits, 5);
+		assert_eq!(fast.longs, chunk.freeze().0.raw_storage());
+		assert_eq!(fast.longs, slow.longs);
+		assert_eq!(fast.palette, slow.palette);
+	}
+
+	#[test]
+	fn test_aligned_packing() {
+		let longs = pack((0..4096).map(|index| index % 32), 5, Packing::Aligned);
+
+		// 12 entries per long, with the top 4 bits of each long left empty.
+		assert_eq!(longs.len(), 342);
+		assert_eq!(longs[0] >> 60, 0);
+		assert_eq!((longs[1] & 31) as u32, 12);
+		assert_eq!((longs[0] >> 55) & 31, 11);
+	}
+
+	#[test]
+	fn test_spanning_partial_long() {
+		// 256 entries of 9 bits fill exactly 36 longs, so the last entry ends at the top of the last long.
+		let longs = pack_entries((0..256).map(|index| index + 256), 256, 9, Packing::Spanning);
+
+		assert_eq!(longs.len(), 36);
+		assert_eq!(longs[0] & 511, 256);
+		assert_eq!(longs[35] >> 55, 511);
+	}
+
+	#[test]
+	fn test_direct_palette() {
+		let mut chunk = IndexedCube::<u16>::new(4, 0);
+
+		for index in 0..512u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &index);
+		}
+
+		let encoded = EncodedBlocks::encode(&chunk, &|&block| block as u32, Packing::Aligned, 15);
+
+		assert_eq!(encoded.bits, 15);
+		assert!(encoded.palette.is_none());
+		assert_eq!(encoded.non_air, 511);
+		assert_eq!(encoded.longs[0] & 0x7FFF, 0);
+		assert_eq!((encoded.longs[0] >> 15) & 0x7FFF, 1);
+	}
+}
@@ -0,0 +1,38 @@
+/// Writes a protocol VarInt: 7 bits per byte, least significant group first, with the high bit
+/// marking that another byte follows. Negative values always take 5 bytes.
+pub fn write_varint(out: &mut Vec<u8>, value: i32) {
+	let mut value = value as u32;
+
+	loop {
+		let byte = (value & 0x7F) as u8;
+		value >>= 7;
+
+		if value == 0 {
+			out.push(byte);
+			return;
+		}
+
+		out.push(byte | 0x80);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::write_varint;
+
+	fn encoded(value: i32) -> Vec<u8> {
+		let mut out = Vec::new();
+		write_varint(&mut out, value);
+		out
+	}
+
+	#[test]
+	fn test_varint() {
+		assert_eq!(encoded(0), [0x00]);
+		assert_eq!(encoded(127), [0x7F]);
+		assert_eq!(encoded(128), [0x80, 0x01]);
+		assert_eq!(encoded(2097151), [0xFF, 0xFF, 0x7F]);
+		assert_eq!(encoded(2147483647), [0xFF, 0xFF, 0xFF, 0xFF, 0x07]);
+		assert_eq!(encoded(-1), [0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+	}
+}
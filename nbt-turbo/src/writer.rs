@@ -156,6 +156,17 @@ impl<T: Output> CompoundWriter<T> {
 		self
 	}
 
+	pub fn i64_array(&mut self, name: &str, value: &[i64]) -> &mut Self {
+		self.header(Kind::I64Array, name);
+		self.array_length(value.len());
+
+		for &entry in value {
+			self.out.extend_from_slice(&entry.to_be_bytes());
+		}
+
+		self
+	}
+
 	pub fn compound_writer(&mut self, name: &str) -> CompoundWriter<&mut T> {
 		CompoundWriter::start(name, &mut self.out)
 	}
@@ -1,11 +1,11 @@
 // TODO: Basic ECS: Allow common tasks to fall under common 7 types, but provide extension with specs. This can avoid dynamic type casting in most cases.
 // bool: BitCube
 // u4: NibbleCube
-// u8: [u8; 4096]
-// uXX: Packed
-// f32: [f32; 4096]
-// f64: [f64; 4096]
-// String: HashMap<CubePosition, String>
+// u8: unpacked::Cube<u8>
+// uXX: PackedUint<XX> in FixedPackedCube<XX>
+// f32: unpacked::Cube<f32>
+// f64: unpacked::Cube<f64>
+// String: SparseCube<String> (HashMap<CubePosition, String>)
 // Entity: A complex struct stored in the local specs ECS.
 
 use crate::position::{LayerPosition, CubePosition};
@@ -0,0 +1,113 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::packed::Layout;
+use crate::position::CubePosition;
+use rustc_hash::FxHashMap;
+
+/// The blocks that differ between two versions of a chunk, suitable for undo or for sending incremental updates.
+/// Each distinct block is stored once in a small palette, and each change refers to its old and new block by index.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Delta<B> where B: Target {
+	palette: Vec<B>,
+	/// Position in YZX order, followed by the palette indices of the old and new block.
+	changes: Vec<(u16, u16, u16)>
+}
+
+impl<B> Delta<B> where B: Target {
+	/// Compares two versions of a chunk.
+	pub fn between<L>(old: &IndexedCube<B, L>, new: &IndexedCube<B, L>) -> Self where L: Layout {
+		Delta::from_changes(CubePosition::enumerate().filter_map(|position| {
+			let (old, new) = (old.get(position), new.get(position));
+
+			if old != new { Some((position, old, new)) } else { None }
+		}))
+	}
+
+	/// Builds a delta from changes in ascending YZX order.
+	pub(crate) fn from_changes<'a, I>(changes: I) -> Self where I: IntoIterator<Item=(CubePosition, &'a B, &'a B)>, B: 'a {
+		let mut palette = Vec::new();
+		let mut indices: FxHashMap<&B, u16> = FxHashMap::default();
+
+		let mut index = |target: &'a B| *indices.entry(target).or_insert_with(|| {
+			palette.push(target.clone());
+
+			(palette.len() - 1) as u16
+		});
+
+		let changes = changes.into_iter().map(|(position, old, new)| (position.yzx(), index(old), index(new))).collect();
+
+		Delta { palette, changes }
+	}
+
+	/// Recreates a delta from the parts returned by `palette` and `raw_changes`.
+	/// Returns None if the changes are not in ascending YZX order, or refer to a missing palette entry.
+	pub fn from_raw_parts(palette: Vec<B>, changes: Vec<(u16, u16, u16)>) -> Option<Self> {
+		let in_palette = |index: u16| (index as usize) < palette.len();
+
+		let valid = changes.iter().all(|&(position, old, new)| position < 4096 && in_palette(old) && in_palette(new))
+			&& changes.windows(2).all(|pair| pair[0].0 < pair[1].0);
+
+		if valid { Some(Delta { palette, changes }) } else { None }
+	}
+
+	/// Returns the amount of changed positions.
+	pub fn len(&self) -> usize {
+		self.changes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+
+	/// Returns every distinct block that appears in the delta.
+	pub fn palette(&self) -> &[B] {
+		&self.palette
+	}
+
+	pub fn raw_changes(&self) -> &[(u16, u16, u16)] {
+		&self.changes
+	}
+
+	/// Iterates over the changed positions in YZX order, along with their old and new blocks.
+	pub fn iter(&self) -> impl Iterator<Item=(CubePosition, &B, &B)> {
+		self.changes.iter().map(move |&(position, old, new)| (
+			CubePosition::from_yzx(position),
+			&self.palette[old as usize],
+			&self.palette[new as usize]
+		))
+	}
+
+	/// Returns a delta that undoes this one.
+	pub fn inverse(&self) -> Self {
+		Delta {
+			palette: self.palette.clone(),
+			changes: self.changes.iter().map(|&(position, old, new)| (position, new, old)).collect()
+		}
+	}
+
+	/// Sets every changed position to its new block. If a position in the chunk does not hold the expected old block,
+	/// nothing is changed and the first such position is returned.
+	pub fn apply<L>(&self, chunk: &mut IndexedCube<B, L>) -> Result<(), CubePosition> where L: Layout {
+		if let Some((position, _, _)) = self.iter().find(|&(position, old, _)| chunk.get(position) != old) {
+			return Err(position);
+		}
+
+		for (position, _, new) in self.iter() {
+			chunk.set_immediate(position, new);
+		}
+
+		Ok(())
+	}
+
+	/// Sets every changed position back to its old block, the reverse of `apply`.
+	pub fn revert<L>(&self, chunk: &mut IndexedCube<B, L>) -> Result<(), CubePosition> where L: Layout {
+		if let Some((position, _, _)) = self.iter().find(|&(position, _, new)| chunk.get(position) != new) {
+			return Err(position);
+		}
+
+		for (position, old, _) in self.iter() {
+			chunk.set_immediate(position, old);
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,175 @@
+use crate::indexed::{Registry, Target};
+use crate::packed::{Layout, PackedIndex, PackedStorage};
+use std::marker::PhantomData;
+
+/// Ties associations to the palette that created them. The lifetime is invariant, and is only ever
+/// introduced by a higher ranked closure in one of the `edit` functions, so no two editing
+/// sessions can share a brand.
+pub(crate) type Brand<'b> = PhantomData<fn(&'b ()) -> &'b ()>;
+
+/// An index into a frozen palette. Associations can only be created by the palette they refer to,
+/// so setting one into the matching storage can never produce an invalid index.
+///
+/// ```
+/// use vocs::indexed::IndexedCube;
+/// use vocs::position::CubePosition;
+///
+/// let mut chunk = IndexedCube::<u16>::new(4, 0);
+/// chunk.ensure_available(7);
+///
+/// chunk.edit(|mut blocks, palette| {
+///     let seven = palette.reverse_lookup(&7).unwrap();
+///     blocks.set(CubePosition::new(1, 2, 3), seven);
+/// });
+///
+/// assert_eq!(*chunk.get(CubePosition::new(1, 2, 3)), 7);
+/// ```
+///
+/// Associations from one storage cannot be used with another:
+///
+/// ```compile_fail
+/// use vocs::indexed::IndexedCube;
+/// use vocs::position::CubePosition;
+///
+/// let mut a = IndexedCube::<u16>::new(4, 0);
+/// let mut b = IndexedCube::<u16>::new(4, 0);
+///
+/// a.edit(|_, palette| {
+///     let zero = palette.reverse_lookup(&0).unwrap();
+///
+///     b.edit(|mut blocks, _| blocks.set(CubePosition::new(0, 0, 0), zero));
+/// });
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Association<'b> {
+	value: u32,
+	brand: Brand<'b>
+}
+
+impl<'b> Association<'b> {
+	pub(crate) fn new(value: u32) -> Self {
+		Association { value, brand: PhantomData }
+	}
+
+	pub fn raw(&self) -> u32 {
+		self.value
+	}
+}
+
+/// Mutable access to the storage of an IndexedStorage while the palette is frozen.
+#[derive(Debug)]
+pub struct FrozenStorage<'a, 'b, P, L> where P: 'a + PackedIndex, L: 'a + Layout {
+	storage: &'a mut PackedStorage<P, L>,
+	brand: Brand<'b>
+}
+
+impl<'a, 'b, P, L> FrozenStorage<'a, 'b, P, L> where P: 'a + PackedIndex, L: 'a + Layout {
+	pub(crate) fn new(storage: &'a mut PackedStorage<P, L>) -> Self {
+		FrozenStorage { storage, brand: PhantomData }
+	}
+
+	pub fn get(&self, position: P) -> Association<'b> {
+		Association::new(self.storage.get(position))
+	}
+
+	pub fn set(&mut self, position: P, association: Association<'b>) {
+		self.storage.set(position, association.value)
+	}
+
+	pub fn fill(&mut self, association: Association<'b>) {
+		self.storage.fill(association.value)
+	}
+
+	pub fn storage(&self) -> &PackedStorage<P, L> {
+		self.storage
+	}
+}
+
+/// The palette of an IndexedStorage, frozen for the duration of an edit.
+/// In direct mode, this refers to the registry instead.
+#[derive(Debug)]
+pub struct FrozenPalette<'a, 'b, B> where B: 'a + Target {
+	lookup: &'a dyn Registry<B>,
+	brand: Brand<'b>
+}
+
+impl<'a, 'b, B> FrozenPalette<'a, 'b, B> where B: 'a + Target {
+	pub(crate) fn new(lookup: &'a dyn Registry<B>) -> Self {
+		FrozenPalette { lookup, brand: PhantomData }
+	}
+
+	/// Gets an association that will reference back to the target. Note that several indices may point to the same target, this returns one of them.
+	pub fn reverse_lookup(&self, target: &B) -> Option<Association<'b>> {
+		self.lookup.id(target).map(Association::new)
+	}
+
+	pub fn get(&self, association: Association<'b>) -> &'a B {
+		self.lookup.entries()[association.value as usize].as_ref().expect("IndexedStorage is corrupted; an association referred to an empty palette entry")
+	}
+
+	/// Returns the entries that associations refer to, indexed by their raw value.
+	pub fn entries(&self) -> &'a [Option<B>] {
+		self.lookup.entries()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::indexed::{IndexedCube, Palette};
+	use crate::position::CubePosition;
+	use std::sync::Arc;
+
+	#[test]
+	fn test_edit() {
+		let mut chunk = IndexedCube::<u16>::new(2, 0);
+		chunk.ensure_available(7);
+
+		let filled = chunk.edit(|mut blocks, palette| {
+			let zero = palette.reverse_lookup(&0).unwrap();
+			let seven = palette.reverse_lookup(&7).unwrap();
+
+			assert_eq!(palette.reverse_lookup(&9), None);
+			assert_eq!(*palette.get(seven), 7);
+			assert_eq!(palette.entries()[seven.raw() as usize], Some(7));
+
+			blocks.fill(seven);
+			blocks.set(CubePosition::new(1, 2, 3), zero);
+
+			assert_eq!(blocks.get(CubePosition::new(1, 2, 3)), zero);
+			assert_eq!(*palette.get(blocks.get(CubePosition::new(3, 2, 1))), 7);
+
+			blocks.storage().bits()
+		});
+
+		assert_eq!(filled, 2);
+		assert_eq!(*chunk.get(CubePosition::new(1, 2, 3)), 0);
+		assert_eq!(*chunk.get(CubePosition::new(3, 2, 1)), 7);
+	}
+
+	#[test]
+	fn test_edit_direct() {
+		let mut registry = Palette::new(6, 0u16);
+
+		for id in 1..40u16 {
+			registry.try_insert(id).unwrap();
+		}
+
+		let mut chunk = IndexedCube::<u16>::new(6, 0);
+		chunk.set_registry(Arc::new(registry), 4);
+
+		assert!(chunk.is_direct());
+
+		chunk.edit(|mut blocks, palette| {
+			let association = palette.reverse_lookup(&33).unwrap();
+
+			// In direct mode, associations are registry IDs, and every registered entry can be looked up.
+			assert_eq!(association.raw(), 33);
+			assert_eq!(palette.entries().len(), 64);
+
+			blocks.set(CubePosition::new(4, 5, 6), association);
+		});
+
+		assert_eq!(*chunk.get(CubePosition::new(4, 5, 6)), 33);
+		assert_eq!(*chunk.get(CubePosition::new(6, 5, 4)), 0);
+	}
+}
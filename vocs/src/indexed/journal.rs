@@ -0,0 +1,182 @@
+use crate::indexed::{Delta, IndexedCube, Target};
+use crate::mask::{BitCube, Mask};
+use crate::memory::{MemoryReport, MemoryUsage};
+use crate::packed::Layout;
+use crate::position::CubePosition;
+use std::fmt::{self, Debug};
+use std::mem;
+
+/// A single block change recorded by a `Journal`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Change<B> where B: Target {
+	pub position: CubePosition,
+	pub old: B,
+	pub new: B
+}
+
+/// The storage as it was before a bulk edit, such as `edit` or `fill`. Bulk edits write to the packed storage directly,
+/// so their changes are found by comparing against this copy once the edit is done.
+#[derive(Debug, Clone)]
+pub(crate) struct Baseline<B> where B: Target {
+	pub bits: u8,
+	pub words: Box<[u64]>,
+	pub entries: Vec<Option<B>>
+}
+
+impl<B> Baseline<B> where B: Target {
+	pub fn get<L>(&self, index: usize) -> &B where L: Layout {
+		let value = if self.bits == 0 { 0 } else { L::get(&self.words, index, self.bits) };
+
+		self.entries[value as usize].as_ref().expect("IndexedStorage is corrupted; the storage referred to an empty palette entry")
+	}
+}
+
+/// The changes made to a chunk since change tracking was enabled, in the order that they were made, along with a mask
+/// of every position that was changed. See `IndexedStorage::track_changes`.
+#[derive(Clone)]
+pub struct Journal<B> where B: Target {
+	dirty: BitCube,
+	changes: Vec<Change<B>>,
+	pub(crate) baseline: Option<Baseline<B>>
+}
+
+impl<B> Journal<B> where B: Target {
+	pub fn new() -> Self {
+		Journal {
+			dirty: BitCube::default(),
+			changes: Vec::new(),
+			baseline: None
+		}
+	}
+
+	/// Returns a mask of every position that was changed. Positions that were later changed back are still marked.
+	pub fn dirty(&self) -> &BitCube {
+		&self.dirty
+	}
+
+	pub fn changes(&self) -> &[Change<B>] {
+		&self.changes
+	}
+
+	pub fn len(&self) -> usize {
+		self.changes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+
+	pub(crate) fn record(&mut self, position: CubePosition, old: B, new: B) {
+		self.dirty.set_true(position);
+		self.changes.push(Change { position, old, new });
+	}
+
+	/// Collapses the journal into a delta from the first old value to the last new value of each position.
+	/// Positions that ended up with their original value are left out.
+	pub fn to_delta(&self) -> Delta<B> {
+		let mut first: Vec<Option<(&B, &B)>> = vec![None; 4096];
+
+		for change in &self.changes {
+			let slot = &mut first[change.position.yzx() as usize];
+
+			*slot = Some(match *slot {
+				Some((old, _)) => (old, &change.new),
+				None => (&change.old, &change.new)
+			});
+		}
+
+		Delta::from_changes(CubePosition::enumerate().zip(first).filter_map(|(position, change)| match change {
+			Some((old, new)) if old != new => Some((position, old, new)),
+			_ => None
+		}))
+	}
+
+	/// Restores the old values of every change to the chunk, latest change first.
+	pub fn revert<L>(&self, chunk: &mut IndexedCube<B, L>) where L: Layout {
+		for change in self.changes.iter().rev() {
+			chunk.set_immediate(change.position, &change.old);
+		}
+	}
+}
+
+impl<B> Default for Journal<B> where B: Target {
+	fn default() -> Self {
+		Journal::new()
+	}
+}
+
+impl<B> MemoryUsage for Journal<B> where B: Target {
+	fn memory_usage(&self) -> MemoryReport {
+		let baseline = self.baseline.as_ref().map(|baseline| {
+			baseline.words.len() * mem::size_of::<u64>() + baseline.entries.capacity() * mem::size_of::<Option<B>>()
+		});
+
+		MemoryReport {
+			journals: self.changes.capacity() * mem::size_of::<Change<B>>() + baseline.unwrap_or(0),
+			..MemoryReport::default()
+		}
+	}
+}
+
+impl<B> Debug for Journal<B> where B: Target {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Journal").field("changes", &self.changes).field("pending", &self.baseline.is_some()).finish()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::position::ColumnPosition;
+	use crate::view::ColumnMut;
+
+	#[test]
+	fn test_journal_and_delta() {
+		let mut column = [IndexedCube::new(4, 0u16), IndexedCube::new(4, 0u16)];
+		let original = column[1].clone();
+
+		{
+			let mut view = ColumnMut::from_slice(&mut column);
+			view.track_changes();
+
+			view.set_immediate(ColumnPosition::new(1, 20, 1), &3);
+			view.set_immediate(ColumnPosition::new(1, 20, 1), &4);
+			view.set_immediate(ColumnPosition::new(2, 20, 1), &0);
+
+			view.ensure_available(7);
+			view.edit(|mut blocks, palettes| {
+				let seven = palettes.reverse_lookup(&7).unwrap();
+
+				blocks.set(ColumnPosition::new(5, 30, 5), &seven);
+				blocks.set(ColumnPosition::new(6, 30, 5), &seven);
+			});
+
+			let journals = view.take_journals();
+			let upper = journals[1].as_ref().unwrap();
+
+			assert!(journals[0].as_ref().unwrap().is_empty());
+			assert_eq!(upper.len(), 4);
+			assert_eq!(upper.dirty().count_ones(), 3);
+			assert_eq!(upper.changes()[1], Change { position: CubePosition::new(1, 4, 1), old: 3, new: 4 });
+
+			let delta = upper.to_delta();
+
+			assert_eq!(delta.len(), 3);
+			assert_eq!(delta, Delta::between(&original, view.0[1]));
+		}
+
+		let delta = Delta::between(&original, &column[1]);
+		let mut copy = original.clone();
+
+		assert_eq!(delta.revert(&mut copy), Err(CubePosition::new(1, 4, 1)));
+		assert_eq!(delta.apply(&mut copy), Ok(()));
+		assert!(Delta::between(&copy, &column[1]).is_empty());
+
+		column[1].fill(9);
+		assert_eq!(column[1].journal().unwrap().len(), 4096);
+
+		assert!(column[1].undo());
+		assert!(Delta::between(&copy, &column[1]).is_empty());
+		assert!(column[1].journal().unwrap().is_empty());
+	}
+}
@@ -1,38 +1,219 @@
+mod frozen;
 mod palette;
+mod registry;
+mod journal;
+mod delta;
 
+use std::borrow::Cow;
 use std::hash::Hash;
+use std::cmp;
 use std::mem;
+use std::sync::Arc;
 use std::fmt::Debug;
-use crate::packed::{PackedStorage, PackedIndex, Setter};
+use std::iter::Sum;
+use std::ops::Add;
+use rustc_hash::FxHashMap;
+use crate::packed::{Layout, PackedStorage, PackedIndex, Setter, Spanning};
 use crate::position::{CubePosition, LayerPosition};
+use crate::memory::{MemoryReport, MemoryUsage};
 
+pub use self::frozen::{Association, FrozenPalette, FrozenStorage};
 pub use self::palette::Palette;
+pub use self::registry::Registry;
+pub use self::journal::{Change, Journal};
+pub use self::delta::Delta;
 
-pub type IndexedCube<B> = IndexedStorage<B, CubePosition>;
-pub type IndexedLayer<B> = IndexedStorage<B, LayerPosition>;
+use self::journal::Baseline;
+
+pub type IndexedCube<B, L = Spanning> = IndexedStorage<B, CubePosition, L>;
+pub type IndexedLayer<B, L = Spanning> = IndexedStorage<B, LayerPosition, L>;
+/// Bits per entry, palette, and packed words of a chunk section, as sent in the protocol.
+pub type ProtocolSection<'a> = (u8, Vec<i32>, Cow<'a, [u64]>);
 
 pub trait Target: Eq + Hash + Clone + Debug {}
 impl<T> Target for T where T: Eq + Hash + Clone + Debug {}
 
 #[derive(Debug, Clone)]
-pub struct IndexedStorage<B, P> where B: Target, P: PackedIndex {
-	storage: PackedStorage<P>,
-	palette: Palette<B>
+pub struct IndexedStorage<B, P, L = Spanning> where B: Target, P: PackedIndex, L: Layout {
+	storage: PackedStorage<P, L>,
+	palette: Palette<B>,
+	global: Option<Global<B>>,
+	/// Present while change tracking is enabled, see `track_changes`.
+	journal: Option<Box<Journal<B>>>
+}
+
+/// Registry configuration of an IndexedStorage, see `IndexedStorage::set_registry`.
+#[derive(Debug, Clone)]
+struct Global<B> where B: Target {
+	registry: Arc<dyn Registry<B> + Send + Sync>,
+	threshold: u8,
+	/// Whether the storage currently holds IDs from the registry instead of palette indices.
+	direct: bool
 }
 
-impl<B, P> IndexedStorage<B, P> where B: Target, P: PackedIndex {
+impl<B, P, L> IndexedStorage<B, P, L> where B: Target, P: PackedIndex, L: Layout {
 	pub fn new(bits: u8, default: B) -> Self {
 		IndexedStorage {
 			storage: PackedStorage::new(bits),
-			palette: Palette::new(bits, default)
+			palette: Palette::new(bits, default),
+			global: None,
+			journal: None
+		}
+	}
+
+	/// Creates a storage where every position holds the same value. Uniform storages use zero bits, so they allocate
+	/// nothing for the packed storage and only a single palette entry. The first write of a different value promotes
+	/// the storage to a regular one, just like any other storage growing its palette.
+	pub fn uniform(value: B) -> Self {
+		IndexedStorage::new(0, value)
+	}
+
+	/// Returns true if this storage uses the zero-bit representation, see `uniform`.
+	pub fn is_uniform(&self) -> bool {
+		self.storage.bits() == 0 && !self.is_direct()
+	}
+
+	/// Returns the value of every position if this storage uses the zero-bit representation.
+	/// Regular storages that happen to hold a single value return None, use `compact` to convert them.
+	pub fn uniform_value(&self) -> Option<&B> {
+		if self.is_uniform() {
+			self.palette.entries()[0].as_ref()
+		} else {
+			None
+		}
+	}
+
+	/// Configures this storage to switch to direct mode once it needs more than `threshold` bits.
+	/// In direct mode, the storage holds IDs from the registry and the local palette is bypassed,
+	/// which avoids the cost of maintaining a large palette for very diverse chunks.
+	/// If the storage is already larger than the threshold, it switches immediately.
+	/// Values missing from the registry are still allowed: while the storage holds any of them, it stays in
+	/// palette mode and keeps growing its palette past the threshold instead.
+	pub fn set_registry(&mut self, registry: Arc<dyn Registry<B> + Send + Sync>, threshold: u8) {
+		if self.is_direct() {
+			self.leave_direct();
+		}
+
+		self.global = Some(Global { registry, threshold, direct: false });
+
+		if self.storage.bits() > threshold {
+			let _ = self.enter_direct();
+		}
+	}
+
+	/// Returns true if the storage holds IDs from the registry instead of palette indices.
+	pub fn is_direct(&self) -> bool {
+		self.global.as_ref().map(|global| global.direct).unwrap_or(false)
+	}
+
+	fn direct_registry(&self) -> Option<&(dyn Registry<B> + Send + Sync)> {
+		match self.global {
+			Some(ref global) if global.direct => Some(&*global.registry),
+			_ => None
+		}
+	}
+
+	/// Returns the registry or palette that values in the storage currently refer to.
+	fn lookup(&self) -> &dyn Registry<B> {
+		match self.direct_registry() {
+			Some(registry) => registry,
+			None => &self.palette
+		}
+	}
+
+	fn entries(&self) -> &[Option<B>] {
+		match self.direct_registry() {
+			Some(registry) => registry.entries(),
+			None => self.palette.entries()
+		}
+	}
+
+	/// Translates the storage from palette indices to registry IDs, returning the old storage.
+	/// Returns None and leaves the storage unchanged if any palette entry is missing from the registry.
+	fn enter_direct(&mut self) -> Option<PackedStorage<P, L>> {
+		let global = self.global.as_mut().expect("Cannot enter direct mode without a registry");
+		let entries = self.palette.entries();
+
+		let mut translation = Vec::with_capacity(entries.len());
+
+		for entry in entries {
+			translation.push(match entry {
+				Some(entry) => global.registry.id(entry)?,
+				None => 0
+			});
+		}
+
+		let mut storage = PackedStorage::new(global.registry.bits());
+
+		for position in P::enumerate() {
+			storage.set(position, translation[self.storage.get(position) as usize]);
+		}
+
+		self.palette = Palette::from_entries(0, Vec::new());
+		global.direct = true;
+
+		Some(mem::replace(&mut self.storage, storage))
+	}
+
+	/// Translates the storage from registry IDs back to a minimal local palette.
+	fn leave_direct(&mut self) {
+		let global = self.global.as_mut().expect("Cannot leave direct mode without a registry");
+		let entries = global.registry.entries();
+
+		let mut remap: FxHashMap<u32, u32> = FxHashMap::default();
+		let mut kept: Vec<B> = Vec::new();
+
+		for position in P::enumerate() {
+			let id = self.storage.get(position);
+
+			remap.entry(id).or_insert_with(|| {
+				kept.push(entries[id as usize].clone().expect("IndexedStorage is corrupted; the storage referred to an unregistered ID"));
+
+				(kept.len() - 1) as u32
+			});
+		}
+
+		let mut storage = PackedStorage::new(Palette::<B>::bits_for(kept.len()));
+
+		for position in P::enumerate() {
+			storage.set(position, remap[&self.storage.get(position)]);
 		}
+
+		self.palette = Palette::from_entries(storage.bits(), kept);
+		self.storage = storage;
+		global.direct = false;
 	}
 
 	/// Increases the capacity of this chunk's storage by the specified amount of bits, and returns the old storage for reuse purposes.
-	pub fn reserve_bits(&mut self, bits: u8) -> PackedStorage<P> {
+	/// If this would exceed the registry threshold, the storage switches to direct mode instead, unless
+	/// a palette entry is missing from the registry.
+	/// In direct mode, the storage already uses the full width of the registry, so it is kept as is and None is returned.
+	pub fn reserve_bits(&mut self, bits: u8) -> Option<PackedStorage<P, L>> {
+		if self.is_direct() {
+			return None;
+		}
+
+		let exceeds_threshold = match self.global {
+			Some(ref global) => self.storage.bits() + bits > global.threshold,
+			None => false
+		};
+
+		if exceeds_threshold {
+			if let Some(storage) = self.enter_direct() {
+				return Some(storage);
+			}
+		}
+
+		Some(self.expand_palette(bits))
+	}
+
+	/// Grows the local palette and storage by the specified amount of bits, regardless of the registry threshold.
+	fn expand_palette(&mut self, bits: u8) -> PackedStorage<P, L> {
+		let new_bits = self.storage.bits() + bits;
+
 		self.palette.expand(bits);
 
-		let mut replacement_storage = PackedStorage::new(self.storage.bits() + bits);
+		let mut replacement_storage = PackedStorage::new(new_bits);
 
 		replacement_storage.clone_from(&self.storage, None, 0);
 
@@ -41,79 +222,231 @@ impl<B, P> IndexedStorage<B, P> where B: Target, P: PackedIndex {
 	
 	/// Makes sure that a future lookup for the target will succeed, unless the entry has been removed since this call.
 	pub fn ensure_available(&mut self, target: B) {
-		 if let Err(target) = self.palette.try_insert(target) {
-		 	self.reserve_bits(1);
-		 	self.palette.try_insert(target).expect("There should be room for a new entry, we just made some!");
-		 }
+		self.insert(target);
+	}
+
+	/// Returns true if the target can be stored in direct mode. Without a registry, nothing can.
+	fn is_registered(&self, target: &B) -> bool {
+		match self.global {
+			Some(ref global) => global.registry.id(target).is_some(),
+			None => false
+		}
+	}
+
+	/// Returns the value that refers to the target in the storage, adding the target to the palette if needed.
+	/// Targets missing from the registry return the storage to palette mode.
+	fn insert(&mut self, target: B) -> u32 {
+		if let Some(registry) = self.direct_registry() {
+			match registry.id(&target) {
+				Some(id) => return id,
+				None => self.leave_direct()
+			}
+		}
+
+		match self.palette.try_insert(target) {
+			Ok(value) => value,
+			Err(target) => {
+				if self.is_registered(&target) {
+					self.reserve_bits(1);
+				} else {
+					self.expand_palette(1);
+				}
+
+				match self.direct_registry() {
+					Some(registry) => registry.id(&target).expect("Target should be registered, we just checked!"),
+					None => self.palette.try_insert(target).expect("There should be room for a new entry, we just made some!")
+				}
+			}
+		}
 	}
 	
 	pub fn get(&self, position: P) -> &B {
-		self.palette.entries()[self.storage.get(position) as usize].as_ref().expect("IndexedStorage is corrupted; A user of freeze_palette has likely violated the API contract!")
+		if let Some(value) = self.uniform_value() {
+			return value;
+		}
+
+		self.entries()[self.storage.get(position) as usize].as_ref().expect("IndexedStorage is corrupted; the storage referred to an empty palette entry")
+	}
+
+	/// Gets the value that refers to the target in the storage, if there is one.
+	/// In direct mode, this is the ID of the target in the registry.
+	pub fn reverse_lookup(&self, target: &B) -> Option<u32> {
+		self.lookup().id(target)
 	}
 
 	pub fn fill(&mut self, block: B) {
-		self.palette.clear();
+		self.begin_bulk();
+		self.fill_untracked(block);
+		self.settle_journal();
+	}
 
-		if self.palette.entries().len() == 0 {
-			self.palette.expand(1);
+	/// Fills the storage by switching to the zero-bit representation, freeing the packed storage. See `uniform`.
+	fn fill_untracked(&mut self, block: B) {
+		if let Some(ref mut global) = self.global {
+			// A single entry always fits in the local palette.
+			global.direct = false;
 		}
 
-		self.palette.replace(0, block);
-		self.storage.fill(0);
+		self.storage = PackedStorage::new(0);
+		self.palette = Palette::new(0, block);
 	}
 
 	/// Tests if this storage is filled with the specified entry. May return false negatives, ie. not filled when the chunk is truly filled.
+	/// This is exact for uniform storages.
 	pub fn is_filled_heuristic(&self, target: &B) -> bool {
-		self.palette.has_single_entry(target)
+		if let Some(value) = self.uniform_value() {
+			return value == target;
+		}
+
+		!self.is_direct() && self.palette.has_single_entry(target)
 	}
 
-	pub fn palette(&self) -> &Palette<B> {
-		&self.palette
+	/// Returns the local palette, or None if the palette is bypassed in direct mode.
+	pub fn palette(&self) -> Option<&Palette<B>> {
+		if self.is_direct() {
+			None
+		} else {
+			Some(&self.palette)
+		}
 	}
 	
-	pub fn freeze(&self) -> (&PackedStorage<P>, &[Option<B>]) {
-		(&self.storage, self.palette.entries())
+	/// Returns the storage along with the entries its values refer to. In direct mode, these are
+	/// the entries of the registry. A uniform storage has zero bits and no words, so every value in it is 0 and
+	/// refers to the single entry.
+	pub fn freeze(&self) -> (&PackedStorage<P, L>, &[Option<B>]) {
+		if self.is_uniform() {
+			return (&self.storage, &self.palette.entries()[..1]);
+		}
+
+		(&self.storage, self.entries())
 	}
 
-	/// Freezes the palette, and returns a mutable storage.
-	/// Setting invalid values in the PackedStorage will lead to errors.
-	/// This is the only API that can set invalid values in the storage.
+	/// Recreates a storage from the parts returned by `freeze`, without repacking. Storages that were in direct mode
+	/// come back as regular storages holding the registry entries as their palette.
+	/// Returns None if there are more entries than the storage can refer to, or if the storage refers to an empty entry.
+	pub fn from_raw_parts(storage: PackedStorage<P, L>, entries: Vec<Option<B>>) -> Option<Self> {
+		let mut entries = entries;
+		let slots = 1usize << storage.bits();
+
+		if entries.len() > slots {
+			return None;
+		}
+
+		entries.resize(slots, None);
+
+		let valid = P::enumerate().all(|position|
+			entries.get(storage.get(position) as usize).map(Option::is_some).unwrap_or(false)
+		);
+
+		if !valid {
+			return None;
+		}
+
+		Some(IndexedStorage {
+			storage,
+			palette: Palette::from_slots(entries.into_boxed_slice()),
+			global: None,
+			journal: None
+		})
+	}
+
+	/// Freezes the palette, and passes a mutable storage to the editor along with the palette.
+	/// Associations looked up in the frozen palette are branded, so that they can only be set into
+	/// this storage, and only during this call. Use `ensure_available` beforehand for any entries
+	/// that will be looked up.
 	/// If only setting one value, then use IndexedStorage::setter instead.
-	// TODO: Fix the corruption hole.
-	pub fn freeze_palette(&mut self) -> (&mut PackedStorage<P>, &Palette<B>) {
-		(&mut self.storage, &self.palette)
+	pub fn edit<F, R>(&mut self, editor: F) -> R where F: for<'b> FnOnce(FrozenStorage<'_, 'b, P, L>, FrozenPalette<'_, 'b, B>) -> R {
+		let result = {
+			let (storage, palette) = self.split_mut();
+
+			editor(storage, palette)
+		};
+
+		self.settle_journal();
+
+		result
+	}
+
+	/// Unbranded version of `edit`, for views that brand several storages at once.
+	/// If changes are tracked, call `settle_journal` once the storage is no longer borrowed.
+	pub(crate) fn split_mut<'b>(&mut self) -> (FrozenStorage<'_, 'b, P, L>, FrozenPalette<'_, 'b, B>) {
+		self.begin_bulk();
+
+		let lookup: &dyn Registry<B> = match self.global {
+			Some(ref global) if global.direct => &*global.registry,
+			_ => &self.palette
+		};
+
+		(FrozenStorage::new(&mut self.storage), FrozenPalette::new(lookup))
 	}
 
 	/// Configures a setter to set a certain block in this storage.
 	/// This has the same performance cost as set_immediate for a single `set`,
 	/// but is cheaper for multiple `set` operations.
-	pub fn setter(&mut self, target: B) -> (Setter<P>, &[Option<B>]) {
-		let value = self.palette.try_insert(target).unwrap_or_else(|target| {
-			self.reserve_bits(1);
-			self.palette.try_insert(target).expect("There should be room for a new entry, we just made some!")
-		});
+	pub fn setter(&mut self, target: B) -> (Setter<P, L>, &[Option<B>]) {
+		self.begin_bulk();
+
+		let value = self.insert(target);
+
+		let entries = match self.global {
+			Some(ref global) if global.direct => global.registry.entries(),
+			_ => self.palette.entries()
+		};
 
-		(self.storage.setter(value), self.palette.entries())
+		(self.storage.setter(value), entries)
 	}
 	
 	/// Preforms the ensure_available, reverse_lookup, and set calls all in one.
-	/// Prefer freezing the palette for larger scale block sets, or using a setter.
+	/// Prefer `edit` for larger scale block sets, or using a setter.
 	pub fn set_immediate(&mut self, position: P, target: &B) {
-		self.ensure_available(target.clone());
-		let association = self.palette.reverse_lookup(&target).unwrap();
+		let old = match self.journal {
+			Some(_) => {
+				self.settle_journal();
+				Some(self.get(position).clone())
+			},
+			None => None
+		};
+
+		let association = self.insert(target.clone());
 		
 		self.storage.set(position, association);
+
+		if let (Some(journal), Some(old)) = (self.journal.as_mut(), old) {
+			if &old != target {
+				journal.record(CubePosition::from_yzx(position.to_usize() as u16), old, target.clone());
+			}
+		}
 	}
 
 	/// Replaces all occurrences of the first block with the second block. This will attempt to
 	/// simply exchange the palette values, but if needed it will update the block storage.
+	/// In direct mode, the block storage is always updated.
 	pub fn replace(&mut self, from: &B, to: B) {
-		let old_index = match self.palette.reverse_lookup(&from) {
+		self.begin_bulk();
+		self.replace_untracked(from, to);
+		self.settle_journal();
+	}
+
+	fn replace_untracked(&mut self, from: &B, to: B) {
+		if self.reverse_lookup(from).is_none() {
+			return;
+		}
+
+		if self.is_direct() && !self.is_registered(&to) {
+			self.leave_direct();
+		}
+
+		let old_index = match self.reverse_lookup(from) {
 			Some(index) => index,
 			None => return
 		};
 
-		match self.palette.reverse_lookup(&to) {
+		let new_index = match self.direct_registry() {
+			Some(registry) => registry.id(&to),
+			None => self.palette.reverse_lookup(&to)
+		};
+
+		match new_index {
 			None => self.palette.replace(old_index, to),
 			Some(new_index) => for index in 0..P::size_factor()*64 {
 
@@ -126,8 +459,12 @@ impl<B, P> IndexedStorage<B, P> where B: Target, P: PackedIndex {
 		}
 	}
 
-	/// Removes all unused entries from the palette
+	/// Removes all unused entries from the palette. Does nothing in direct mode.
 	pub fn prune_palette(&mut self) {
+		if self.is_direct() {
+			return;
+		}
+
 		let mut present = bit_vec::BitVec::from_elem(self.palette.entries().len(), false);
 
 		for position in P::enumerate() {
@@ -141,11 +478,280 @@ impl<B, P> IndexedStorage<B, P> where B: Target, P: PackedIndex {
 		}
 	}
 
+	/// Removes all unused entries from the palette, remaps the remaining entries densely, and
+	/// reallocates the storage at the minimum bit width able to hold them, but never below 1 bit.
+	/// Storages left with a single value use the zero-bit representation instead, see `uniform`.
+	/// Duplicate palette entries are merged. Unlike `prune_palette`, this always allocates a new
+	/// storage when the bit width can be reduced.
+	/// In direct mode, the storage returns to a local palette if one would fit within the threshold.
+	pub fn compact(&mut self) -> Compaction {
+		if let Some(ref global) = self.global {
+			if global.direct {
+				let mut present = bit_vec::BitVec::from_elem(global.registry.entries().len(), false);
+
+				for position in P::enumerate() {
+					present.set(self.storage.get(position) as usize, true);
+				}
+
+				let used = present.iter().filter(|&present| present).count();
+
+				if Palette::<B>::bits_for(used) > global.threshold {
+					return Compaction::default();
+				}
+
+				let old_words = self.storage.raw_storage().len();
+				self.leave_direct();
+
+				return Compaction {
+					removed_entries: 0,
+					shrunk_storages: 1,
+					saved_bytes: old_words.saturating_sub(self.storage.raw_storage().len()) * 8
+				};
+			}
+		}
+
+		let entries = self.palette.entries();
+		let mut present = bit_vec::BitVec::from_elem(entries.len(), false);
+
+		for position in P::enumerate() {
+			present.set(self.storage.get(position) as usize, true);
+		}
+
+		let mut remap = vec![0u32; entries.len()];
+		let mut kept: Vec<B> = Vec::new();
+		let mut kept_indices: FxHashMap<&B, u32> = FxHashMap::default();
+		let mut unused = 0;
+		let mut used = 0;
+
+		for (index, entry) in entries.iter().enumerate() {
+			let entry = match (present[index], entry) {
+				(true, Some(entry)) => entry,
+				(true, None) => panic!("IndexedStorage is corrupted; the storage referred to an empty palette entry"),
+				(false, Some(_)) => {
+					unused += 1;
+					continue;
+				},
+				(false, None) => continue
+			};
+
+			used += 1;
+
+			remap[index] = *kept_indices.entry(entry).or_insert_with(|| {
+				kept.push(entry.clone());
+
+				(kept.len() - 1) as u32
+			});
+		}
+
+		let merged = used - kept.len() as u32;
+		let removed_entries = unused + merged;
+
+		let old_bits = self.storage.bits();
+
+		// Storages left with a single value switch to the zero-bit representation instead.
+		if kept.len() == 1 && old_bits != 0 {
+			let saved_bytes = self.storage.raw_storage().len() * 8;
+
+			self.storage = PackedStorage::new(0);
+			self.palette = Palette::new(0, kept.remove(0));
+
+			return Compaction { removed_entries, shrunk_storages: 1, saved_bytes };
+		}
+
+		// Otherwise, compaction keeps at least 1 bit, so the storage stays writable without reallocating.
+		let new_bits = cmp::max(Palette::<B>::bits_for(kept.len()), 1);
+
+		if new_bits >= old_bits && merged == 0 {
+			if unused != 0 {
+				self.prune_palette();
+			}
+
+			return Compaction { removed_entries, ..Compaction::default() };
+		}
+
+		// Merging duplicates requires remapping the storage, even if the bit width stays the same.
+		let new_bits = cmp::min(new_bits, old_bits);
+		let mut storage = PackedStorage::new(new_bits);
+
+		for position in P::enumerate() {
+			storage.set(position, remap[self.storage.get(position) as usize]);
+		}
+
+		let saved_bytes = (self.storage.raw_storage().len() - storage.raw_storage().len()) * 8;
+		let shrunk_storages = if new_bits < old_bits { 1 } else { 0 };
+
+		self.storage = storage;
+		self.palette = Palette::from_entries(new_bits, kept);
+
+		Compaction { removed_entries, shrunk_storages, saved_bytes }
+	}
+
 	pub fn bits(&self) -> u8 {
 		self.storage.bits()
 	}
+
+	/// Returns true if changes to this storage are being recorded, see `track_changes`.
+	pub fn is_tracking(&self) -> bool {
+		self.journal.is_some()
+	}
+
+	/// Copies the storage before a bulk edit that writes to the packed storage directly.
+	fn begin_bulk(&mut self) {
+		if self.journal.is_none() {
+			return;
+		}
+
+		self.settle_journal();
+
+		let baseline = Baseline {
+			bits: self.storage.bits(),
+			words: self.storage.raw_storage().into(),
+			entries: self.entries().to_vec()
+		};
+
+		if let Some(journal) = self.journal.as_mut() {
+			journal.baseline = Some(baseline);
+		}
+	}
+
+	/// Records the changes made by a bulk edit since `begin_bulk`, by comparing against the copy it made.
+	pub(crate) fn settle_journal(&mut self) {
+		let baseline = match self.journal.as_mut().and_then(|journal| journal.baseline.take()) {
+			Some(baseline) => baseline,
+			None => return
+		};
+
+		let mut changes = Vec::new();
+
+		for index in 0..P::size_factor()*64 {
+			let (old, new) = (baseline.get::<L>(index), self.get(P::from_usize(index)));
+
+			if old != new {
+				changes.push((CubePosition::from_yzx(index as u16), old.clone(), new.clone()));
+			}
+		}
+
+		if let Some(journal) = self.journal.as_mut() {
+			for (position, old, new) in changes {
+				journal.record(position, old, new);
+			}
+		}
+	}
+
+	/// Converts this storage to a different packed layout, keeping the same palette and bit count.
+	pub fn to_layout<T>(&self) -> IndexedStorage<B, P, T> where T: Layout {
+		IndexedStorage {
+			storage: self.storage.to_layout(),
+			palette: self.palette.clone(),
+			global: self.global.clone(),
+			journal: None
+		}
+	}
+
+	/// Returns a uniform storage holding the block, with the same registry configuration as this storage.
+	/// Unlike cloning this storage and calling `fill`, the packed storage is never copied. Changes are not tracked.
+	pub fn filled(&self, block: B) -> Self {
+		IndexedStorage {
+			storage: PackedStorage::new(0),
+			palette: Palette::new(0, block),
+			// A single entry always fits in the local palette.
+			global: self.global.as_ref().map(|global| Global { direct: false, ..global.clone() }),
+			journal: None
+		}
+	}
+}
+
+impl<B, L> IndexedStorage<B, CubePosition, L> where B: Target, L: Layout {
+	/// Starts recording every change to this chunk in a `Journal`, if not already recording.
+	/// Bulk edits such as `edit`, `fill`, and `setter` are recorded by comparing against a copy of the storage
+	/// taken when they begin, so they cost an extra copy of the storage while tracking.
+	pub fn track_changes(&mut self) {
+		if self.journal.is_none() {
+			self.journal = Some(Box::new(Journal::new()));
+		}
+	}
+
+	/// Stops recording changes, returning the journal.
+	pub fn stop_tracking(&mut self) -> Option<Journal<B>> {
+		self.settle_journal();
+		self.journal.take().map(|journal| *journal)
+	}
+
+	/// Returns the changes recorded so far.
+	pub fn journal(&mut self) -> Option<&Journal<B>> {
+		self.settle_journal();
+		self.journal.as_deref()
+	}
+
+	/// Returns the changes recorded so far, and starts a new journal.
+	pub fn take_journal(&mut self) -> Option<Journal<B>> {
+		let journal = self.stop_tracking()?;
+		self.track_changes();
+
+		Some(journal)
+	}
+
+	/// Reverts every change recorded so far and starts a new journal. Returns false if changes are not tracked.
+	pub fn undo(&mut self) -> bool {
+		let journal = match self.stop_tracking() {
+			Some(journal) => journal,
+			None => return false
+		};
+
+		journal.revert(self);
+		self.track_changes();
+
+		true
+	}
 }
 
+impl<B, P, L> MemoryUsage for IndexedStorage<B, P, L> where B: Target, P: PackedIndex, L: Layout {
+	fn memory_usage(&self) -> MemoryReport {
+		let mut report = self.storage.memory_usage() + self.palette.memory_usage();
+
+		if let Some(journal) = self.journal.as_ref() {
+			report.journals += mem::size_of::<Journal<B>>() + journal.memory_usage().journals;
+		}
+
+		report
+	}
+}
+
+/// Statistics reported by `IndexedStorage::compact`. These can be summed to report on many storages.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Compaction {
+	/// Number of palette entries that were removed because they were unused, or merged into an identical entry.
+	pub removed_entries: u32,
+	/// Number of storages that were reallocated at a smaller bit width.
+	pub shrunk_storages: u32,
+	/// Bytes of packed storage freed by reducing the bit width.
+	pub saved_bytes: usize
+}
+
+impl Add for Compaction {
+	type Output = Compaction;
+
+	fn add(self, rhs: Compaction) -> Compaction {
+		Compaction {
+			removed_entries: self.removed_entries + rhs.removed_entries,
+			shrunk_storages: self.shrunk_storages + rhs.shrunk_storages,
+			saved_bytes: self.saved_bytes + rhs.saved_bytes
+		}
+	}
+}
+
+impl Sum for Compaction {
+	fn sum<I>(iter: I) -> Self where I: Iterator<Item = Compaction> {
+		iter.fold(Compaction::default(), Add::add)
+	}
+}
+
+/// Smallest amount of bits per entry accepted by the 1.9+ protocol for a paletted section.
+const MIN_PROTOCOL_BITS: u8 = 4;
+
+/// The data of a 4 bit protocol section where every value is 0.
+static UNIFORM_PROTOCOL_SECTION: [u64; 256] = [0; 256];
+
 impl IndexedCube<u16> {
 	pub fn anvil_empty(&self) -> bool {
 		/*if let Some(assoc) = self.palette.reverse_lookup(&0) {
@@ -156,10 +762,16 @@ impl IndexedCube<u16> {
 		false /*TODO*/
 	}
 
-	pub fn to_protocol_section(&self) -> Result<(u8, Vec<i32>, &[u64]), u8> {
+	/// Storages with less than 4 bits per entry, the smallest size the protocol accepts, are widened to 4 bits.
+	/// Uniform storages are sent with every value referring to the single palette entry.
+	pub fn to_protocol_section(&self) -> Result<ProtocolSection<'_>, u8> {
+		if let Some(&value) = self.uniform_value() {
+			return Ok((MIN_PROTOCOL_BITS, vec![value as i32], Cow::Borrowed(&UNIFORM_PROTOCOL_SECTION[..])));
+		}
+
 		let bits = self.bits();
 
-		if bits > 8 {
+		if bits > 8 || self.is_direct() {
 			// Only support 8 bits or less, because the palette may be scrambled at higher levels.
 			return Err(bits);
 		}
@@ -183,6 +795,259 @@ impl IndexedCube<u16> {
 			}
 		}
 
-		Ok((bits, palette, &self.storage.raw_storage()))
+		if bits < MIN_PROTOCOL_BITS {
+			let mut widened = PackedStorage::<CubePosition>::new(MIN_PROTOCOL_BITS);
+			widened.clone_from(&self.storage, None, 0);
+
+			return Ok((MIN_PROTOCOL_BITS, palette, Cow::Owned(widened.raw_storage().to_vec())));
+		}
+
+		Ok((bits, palette, Cow::Borrowed(self.storage.raw_storage())))
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+	use crate::indexed::{Compaction, IndexedCube, Palette};
+	use std::borrow::Cow;
+	use crate::packed::PackedCube;
+	use crate::position::CubePosition;
+	use std::sync::Arc;
+
+	#[test]
+	fn test_compact() {
+		let mut chunk = IndexedCube::<u16>::new(4, 0);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 40));
+		}
+
+		assert_eq!(chunk.bits(), 6);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 3));
+		}
+
+		let palette_entries = chunk.palette().unwrap().entries().iter().filter(|entry| entry.is_some()).count();
+		assert_eq!(palette_entries, 40);
+
+		let compaction = chunk.compact();
+
+		assert_eq!(compaction, Compaction { removed_entries: 40 - 3, shrunk_storages: 1, saved_bytes: 4 * 512 });
+		assert_eq!(chunk.bits(), 2);
+
+		for index in 0..4096u16 {
+			assert_eq!(*chunk.get(CubePosition::from_yzx(index)), index % 3);
+		}
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &7);
+		}
+
+		chunk.set_immediate(CubePosition::new(3, 4, 5), &8);
+
+		assert_eq!(chunk.compact().shrunk_storages, 1);
+		assert_eq!(chunk.bits(), 1);
+		assert_eq!(*chunk.get(CubePosition::new(3, 4, 5)), 8);
+		assert_eq!(*chunk.get(CubePosition::new(0, 0, 0)), 7);
+	}
+
+	#[test]
+	fn test_compact_duplicates() {
+		let mut storage: PackedCube = PackedCube::new(2);
+
+		for position in CubePosition::enumerate() {
+			storage.set(position, position.yzx() as u32 % 4);
+		}
+
+		// Entries 0 and 1 are identical, so compaction must merge them even though 3 entries still need 2 bits.
+		let mut chunk = IndexedCube::from_raw_parts(storage, vec![Some(5u16), Some(5), Some(6), Some(7)]).unwrap();
+
+		assert_eq!(chunk.compact(), Compaction { removed_entries: 1, shrunk_storages: 0, saved_bytes: 0 });
+		assert_eq!(chunk.bits(), 2);
+		assert_eq!(chunk.palette().unwrap().entries(), &[Some(5), Some(6), Some(7), None]);
+
+		for position in CubePosition::enumerate() {
+			assert_eq!(*chunk.get(position), [5, 5, 6, 7][position.yzx() as usize % 4]);
+		}
+
+		assert_eq!(chunk.compact(), Compaction::default());
+	}
+
+	#[test]
+	fn test_protocol_section_widened() {
+		let mut chunk = IndexedCube::<u16>::new(4, 0);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 2 + 8));
+		}
+
+		chunk.compact();
+		assert_eq!(chunk.bits(), 1);
+
+		let (bits, palette, data) = chunk.to_protocol_section().unwrap();
+
+		assert_eq!(bits, 4);
+		assert_eq!(palette, vec![8, 9]);
+		assert_eq!(data.len(), 256);
+
+		let protocol: PackedCube = PackedCube::from_raw_storage(4, data.into_owned().into_boxed_slice()).unwrap();
+
+		for position in CubePosition::enumerate() {
+			assert_eq!(protocol.get(position), position.yzx() as u32 % 2);
+		}
+	}
+
+	#[test]
+	fn test_direct() {
+		let mut registry = Palette::new(7, 0u16);
+
+		for id in 1..100u16 {
+			registry.try_insert(id).unwrap();
+		}
+
+		let mut chunk = IndexedCube::<u16>::new(2, 0);
+		chunk.set_registry(Arc::new(registry), 4);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 12));
+		}
+
+		assert!(!chunk.is_direct());
+		assert_eq!(chunk.bits(), 4);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 40));
+		}
+
+		assert!(chunk.is_direct());
+		assert!(chunk.palette().is_none());
+		assert_eq!(chunk.bits(), 7);
+		assert_eq!(chunk.reverse_lookup(&39), Some(39));
+
+		for index in 0..4096u16 {
+			assert_eq!(*chunk.get(CubePosition::from_yzx(index)), index % 40);
+		}
+
+		chunk.replace(&39, 99);
+		assert_eq!(*chunk.get(CubePosition::from_yzx(39)), 99);
+
+		chunk.edit(|mut blocks, palette| {
+			let five = palette.reverse_lookup(&5).unwrap();
+
+			for index in 0..4096u16 {
+				blocks.set(CubePosition::from_yzx(index), five);
+			}
+		});
+
+		let compaction = chunk.compact();
+
+		assert_eq!(compaction.shrunk_storages, 1);
+		assert!(!chunk.is_direct());
+		assert_eq!(chunk.bits(), 0);
+		assert_eq!(*chunk.get(CubePosition::new(1, 2, 3)), 5);
+	}
+
+	#[test]
+	fn test_direct_unregistered() {
+		let mut registry = Palette::new(7, 0u16);
+
+		for id in 1..100u16 {
+			registry.try_insert(id).unwrap();
+		}
+
+		let registry = Arc::new(registry);
+
+		// An unregistered value keeps the storage in palette mode past the threshold.
+		let mut chunk = IndexedCube::<u16>::new(2, 0);
+		chunk.set_registry(registry.clone(), 4);
+		chunk.set_immediate(CubePosition::from_yzx(0), &500);
+
+		for index in 1..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 40));
+		}
+
+		assert!(!chunk.is_direct());
+		assert_eq!(chunk.bits(), 6);
+		assert_eq!(*chunk.get(CubePosition::from_yzx(0)), 500);
+
+		for index in 1..4096u16 {
+			assert_eq!(*chunk.get(CubePosition::from_yzx(index)), index % 40);
+		}
+
+		// Writing an unregistered value in direct mode returns the storage to palette mode.
+		let mut chunk = IndexedCube::<u16>::new(2, 0);
+		chunk.set_registry(registry, 4);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 40));
+		}
+
+		assert!(chunk.is_direct());
+		assert!(chunk.reserve_bits(1).is_none());
+		assert!(chunk.is_direct());
+		assert_eq!(chunk.bits(), 7);
+
+		chunk.set_immediate(CubePosition::from_yzx(7), &500);
+
+		assert!(!chunk.is_direct());
+		assert_eq!(*chunk.get(CubePosition::from_yzx(7)), 500);
+		assert_eq!(*chunk.get(CubePosition::from_yzx(8)), 8);
+
+		// Once the unregistered value is gone from the palette, the storage can switch back.
+		chunk.set_immediate(CubePosition::from_yzx(7), &7);
+		chunk.prune_palette();
+		chunk.reserve_bits(1);
+
+		assert!(chunk.is_direct());
+
+		chunk.replace(&39, 600);
+
+		assert!(!chunk.is_direct());
+		assert_eq!(*chunk.get(CubePosition::from_yzx(39)), 600);
+		assert_eq!(*chunk.get(CubePosition::from_yzx(38)), 38);
+	}
+
+	#[test]
+	fn test_uniform() {
+		let mut chunk = IndexedCube::<u16>::uniform(0);
+
+		assert!(chunk.is_uniform());
+		assert!(chunk.is_filled_heuristic(&0));
+		assert_eq!(chunk.freeze().0.raw_storage().len(), 0);
+		assert_eq!(chunk.freeze().1, &[Some(0)]);
+		assert_eq!(chunk.to_protocol_section(), Ok((4, vec![0], Cow::Borrowed(&[0u64; 256][..]))));
+
+		// Writing the same value keeps the zero-bit representation.
+		chunk.set_immediate(CubePosition::new(1, 2, 3), &0);
+		assert!(chunk.is_uniform());
+
+		chunk.set_immediate(CubePosition::new(1, 2, 3), &6);
+
+		assert!(!chunk.is_uniform());
+		assert_eq!(chunk.bits(), 1);
+		assert_eq!(*chunk.get(CubePosition::new(1, 2, 3)), 6);
+		assert_eq!(*chunk.get(CubePosition::new(3, 2, 1)), 0);
+		assert!(!chunk.is_filled_heuristic(&0));
+
+		chunk.fill(9);
+
+		assert_eq!(chunk.uniform_value(), Some(&9));
+		assert!(chunk.is_filled_heuristic(&9));
+
+		// A regular storage that happens to hold a single value becomes uniform once compacted.
+		let mut chunk = IndexedCube::<u16>::new(4, 0);
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &(index % 5));
+		}
+
+		for index in 0..4096u16 {
+			chunk.set_immediate(CubePosition::from_yzx(index), &7);
+		}
+
+		assert_eq!(chunk.uniform_value(), None);
+		assert_eq!(chunk.compact(), Compaction { removed_entries: 5, shrunk_storages: 1, saved_bytes: 4 * 512 });
+		assert_eq!(chunk.uniform_value(), Some(&7));
+		assert_eq!(chunk.compact(), Compaction::default());
+	}
+}
@@ -1,11 +1,12 @@
 use crate::indexed::Target;
+use crate::memory::{self, MemoryReport, MemoryUsage};
 use rustc_hash::FxHashMap;
 use std::collections::hash_map::Entry;
 use std::mem;
 
 /// A palette that provides a two-way mapping for efficient access.
 /// This implementation does no allocation automatically, the only allocation happens in
-/// `new`, `expand`, and `try_shrink`.
+/// `new`, `from_entries`, `expand`, and `try_shrink`.
 #[derive(Debug, Clone)]
 pub struct Palette<B> where B: Target {
 	entries: Box<[Option<B>]>,
@@ -23,6 +24,41 @@ impl<B> Palette<B> where B: Target {
 		Palette { entries, reverse }
 	}
 
+	/// Creates a palette holding exactly the provided entries, which must be unique.
+	pub(crate) fn from_entries(bits: u8, entries: Vec<B>) -> Self {
+		let mut reverse = FxHashMap::default();
+		let mut slots = vec![None; 1<<bits].into_boxed_slice();
+
+		for (index, entry) in entries.into_iter().enumerate() {
+			reverse.insert(entry.clone(), index as u32);
+			slots[index] = Some(entry);
+		}
+
+		Palette { entries: slots, reverse }
+	}
+
+	/// Creates a palette from raw slots, such as those returned by `entries`. Empty slots are kept as they are.
+	pub(crate) fn from_slots(slots: Box<[Option<B>]>) -> Self {
+		let mut reverse = FxHashMap::default();
+
+		for (index, entry) in slots.iter().enumerate() {
+			if let Some(entry) = entry {
+				reverse.entry(entry.clone()).or_insert(index as u32);
+			}
+		}
+
+		Palette { entries: slots, reverse }
+	}
+
+	/// Returns the minimum amount of bits needed to index the specified number of entries.
+	pub fn bits_for(entries: usize) -> u8 {
+		if entries <= 1 {
+			0
+		} else {
+			(usize::BITS - (entries - 1).leading_zeros()) as u8
+		}
+	}
+
 	pub fn expand(&mut self, extra_bits: u8) -> Box<[Option<B>]> {
 		let mut entries = vec![None; self.entries.len()<<extra_bits].into_boxed_slice();
 
@@ -146,3 +182,12 @@ impl<B> Palette<B> where B: Target {
 		self.entries[index].take().map(|entry| self.reverse.remove(&entry));
 	}
 }
+
+impl<B> MemoryUsage for Palette<B> where B: Target {
+	fn memory_usage(&self) -> MemoryReport {
+		MemoryReport {
+			palettes: self.entries.len() * mem::size_of::<Option<B>>() + memory::hash_map_bytes(&self.reverse),
+			..MemoryReport::default()
+		}
+	}
+}
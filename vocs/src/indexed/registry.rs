@@ -0,0 +1,31 @@
+use crate::indexed::{Palette, Target};
+use std::fmt::Debug;
+
+/// A global mapping between targets and IDs, shared by every storage in direct mode.
+/// Once a storage has switched to direct mode, its values are IDs from the registry, so the
+/// registry must not remove or reassign entries, or grow past `bits`, while it is in use.
+pub trait Registry<B>: Debug where B: Target {
+	/// Returns the amount of bits needed to store any ID in this registry.
+	fn bits(&self) -> u8;
+
+	/// Returns the ID of the target, or None if the target is not registered.
+	fn id(&self, target: &B) -> Option<u32>;
+
+	/// Returns every registered target, indexed by ID. Unassigned IDs are None.
+	fn entries(&self) -> &[Option<B>];
+}
+
+/// A palette can act as a registry, which is useful when the set of targets is not known ahead of time.
+impl<B> Registry<B> for Palette<B> where B: Target {
+	fn bits(&self) -> u8 {
+		Palette::<B>::bits_for(self.entries().len())
+	}
+
+	fn id(&self, target: &B) -> Option<u32> {
+		self.reverse_lookup(target)
+	}
+
+	fn entries(&self) -> &[Option<B>] {
+		Palette::entries(self)
+	}
+}
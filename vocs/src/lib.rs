@@ -57,6 +57,10 @@ extern crate rustc_hash;
 // Access multiple distinct hash map entries at same time
 extern crate splitmut;
 
+// Optional data parallelism
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
 pub mod world;
 pub mod position;
 
@@ -88,3 +92,8 @@ pub mod view;
 pub mod component;
 
 pub mod sparse;
+
+pub mod snapshot;
+
+/// Accounting of the heap memory used by storages, worlds, and sectors.
+pub mod memory;
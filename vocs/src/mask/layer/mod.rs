@@ -21,6 +21,11 @@ impl BitLayer {
 	pub fn blocks_mut(&mut self) -> &mut [u64; 4] {
 		&mut self.0
 	}
+
+	/// Returns true if any position is set in both layers.
+	pub fn intersects(&self, other: &BitLayer) -> bool {
+		self.0.iter().zip(other.0.iter()).any(|(a, b)| a & b != 0)
+	}
 }
 
 impl LayerStorage<bool> for BitLayer {
@@ -0,0 +1,244 @@
+use crate::position::{GlobalChunkPosition, GlobalPosition};
+use crate::mask::{Mask, BitCube, ChunkScan, Scan, ScanClear};
+use std::collections::HashMap;
+use std::collections::hash_map::{Entry, Iter};
+use std::ops::Index;
+use std::{fmt, iter, vec};
+
+const FALSE_REF: &bool = &false;
+
+/// A sparse mask for marking individual blocks. A BitCube is stored for each chunk that has at least one
+/// marked block, and chunks without any marked blocks take up no space.
+/// For entire chunks, use ChunksMask.
+#[derive(Default, Clone, PartialEq)]
+pub struct BlocksMask {
+	chunks: HashMap<GlobalChunkPosition, BitCube>
+}
+
+impl BlocksMask {
+	pub fn new() -> Self {
+		BlocksMask {
+			chunks: HashMap::new()
+		}
+	}
+
+	/// Iterates over the chunks that have at least one marked block.
+	pub fn chunks(&self) -> Iter<'_, GlobalChunkPosition, BitCube> {
+		self.chunks.iter()
+	}
+
+	pub fn chunk(&self, position: GlobalChunkPosition) -> Option<&BitCube> {
+		self.chunks.get(&position)
+	}
+
+	/// Marks every block that is set in the chunk mask.
+	pub fn combine_chunk(&mut self, position: GlobalChunkPosition, mask: &BitCube) {
+		if mask.empty() {
+			return;
+		}
+
+		match self.chunks.entry(position) {
+			Entry::Occupied(mut entry) => entry.get_mut().combine(mask),
+			Entry::Vacant(entry) => { entry.insert(mask.clone()); }
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.chunks.is_empty()
+	}
+
+	/// Returns the chunks with at least one marked block, sorted in YZX order so that scans are deterministic.
+	fn sorted_chunks(&self) -> Vec<GlobalChunkPosition> {
+		let mut chunks: Vec<GlobalChunkPosition> = self.chunks.keys().cloned().collect();
+		chunks.sort_unstable_by_key(|chunk| (chunk.y(), chunk.z(), chunk.x()));
+
+		chunks
+	}
+}
+
+/// Lists the marked blocks in scan order.
+impl fmt::Debug for BlocksMask {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_set().entries(self.scan()).finish()
+	}
+}
+
+/// Positions outside of the range of chunks can not be marked, and setting them does nothing. Reading them with
+/// `Index` always returns false.
+impl Mask<GlobalPosition> for BlocksMask {
+	fn set_true(&mut self, position: GlobalPosition) {
+		if let Some(chunk) = position.checked_global_chunk() {
+			self.chunks.entry(chunk).or_default().set_true(position.local_block());
+		}
+	}
+
+	fn set_false(&mut self, position: GlobalPosition) {
+		let chunk = match position.checked_global_chunk() {
+			Some(chunk) => chunk,
+			None => return
+		};
+
+		if let Entry::Occupied(mut entry) = self.chunks.entry(chunk) {
+			entry.get_mut().set_false(position.local_block());
+
+			if entry.get().empty() {
+				entry.remove();
+			}
+		}
+	}
+
+	fn set_or(&mut self, position: GlobalPosition, value: bool) {
+		if value {
+			self.set_true(position);
+		}
+	}
+
+	fn count_ones(&self) -> u32 {
+		self.chunks.values().fold(0, |state, value| state + value.count_ones())
+	}
+
+	fn count_zeros(&self) -> u32 {
+		self.chunks.values().fold(0, |state, value| state + value.count_zeros())
+	}
+}
+
+/// Scans the chunks in YZX order, and the blocks within each chunk in YZX order.
+impl<'a> IntoIterator for Scan<'a, BlocksMask, GlobalPosition> {
+	type Item = GlobalPosition;
+	type IntoIter = BlocksScan<'a>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		let chunks = self.0.sorted_chunks();
+
+		BlocksScan { mask: self.0, chunks: chunks.into_iter(), current: None }
+	}
+}
+
+pub struct BlocksScan<'a> {
+	mask: &'a BlocksMask,
+	chunks: vec::IntoIter<GlobalChunkPosition>,
+	current: Option<(GlobalChunkPosition, ChunkScan<'a>)>
+}
+
+impl<'a> Iterator for BlocksScan<'a> {
+	type Item = GlobalPosition;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some((chunk, ref mut scan)) = self.current {
+				if let Some(local) = scan.next() {
+					return Some(GlobalPosition::combine(chunk, local));
+				}
+			}
+
+			let chunk = self.chunks.next()?;
+			self.current = Some((chunk, ChunkScan::new(&self.mask.chunks[&chunk])));
+		}
+	}
+}
+
+/// Scans in the same order as `Scan`, clearing each block as it is returned.
+impl<'a> IntoIterator for ScanClear<'a, BlocksMask, GlobalPosition> {
+	type Item = GlobalPosition;
+	type IntoIter = BlocksScanClear<'a>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		let chunks = self.0.sorted_chunks();
+
+		BlocksScanClear { mask: self.0, chunks: chunks.into_iter().peekable() }
+	}
+}
+
+pub struct BlocksScanClear<'a> {
+	mask: &'a mut BlocksMask,
+	chunks: iter::Peekable<vec::IntoIter<GlobalChunkPosition>>
+}
+
+impl<'a> Iterator for BlocksScanClear<'a> {
+	type Item = GlobalPosition;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let chunk = *self.chunks.peek()?;
+
+			if let Entry::Occupied(mut entry) = self.mask.chunks.entry(chunk) {
+				let local = entry.get_mut().pop_first();
+
+				if entry.get().empty() {
+					entry.remove();
+				}
+
+				if let Some(local) = local {
+					return Some(GlobalPosition::combine(chunk, local));
+				}
+			}
+
+			self.chunks.next();
+		}
+	}
+}
+
+impl Index<GlobalPosition> for BlocksMask {
+	type Output = bool;
+
+	fn index(&self, position: GlobalPosition) -> &bool {
+		position.checked_global_chunk()
+			.and_then(|chunk| self.chunks.get(&chunk))
+			.map(|mask| &mask[position.local_block()])
+			.unwrap_or(FALSE_REF)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::mask::Mask;
+	use crate::mask::sparse::BlocksMask;
+	use crate::position::GlobalPosition;
+
+	#[test]
+	fn test_scan() {
+		let positions = [
+			GlobalPosition::new(-1, -20, 5),
+			GlobalPosition::new(3, 0, 0),
+			GlobalPosition::new(2, 0, 0),
+			GlobalPosition::new(17, 0, -1),
+			GlobalPosition::new(0, 300, 0)
+		];
+
+		let mut mask = BlocksMask::new();
+
+		for &position in positions.iter() {
+			mask.set_true(position);
+		}
+
+		// Chunks in YZX order, then blocks in YZX order within each chunk.
+		let expected = vec![positions[0], positions[3], positions[2], positions[1], positions[4]];
+
+		assert_eq!(mask.scan().into_iter().collect::<Vec<_>>(), expected);
+
+		let mut cleared = mask.scan_clear().into_iter();
+
+		assert_eq!(cleared.next(), Some(positions[0]));
+		assert_eq!(cleared.next(), Some(positions[3]));
+		drop(cleared);
+
+		assert_eq!(mask.count_ones(), 3);
+		assert_eq!(mask.scan_clear().into_iter().collect::<Vec<_>>(), &expected[2..]);
+		assert!(mask.is_empty());
+	}
+
+	#[test]
+	fn test_out_of_range() {
+		let mut mask = BlocksMask::new();
+		let outside = GlobalPosition::new(0, 2048, 0);
+
+		mask.set_true(outside);
+		mask.set_false(outside);
+
+		assert!(mask.is_empty());
+		assert!(!mask[outside]);
+
+		mask.set_true(GlobalPosition::new(1, 2, 3));
+		assert_eq!(format!("{:?}", mask), "{GlobalPosition { x: 1, z: 3, y: 2 }}");
+	}
+}
@@ -1,5 +1,7 @@
+pub mod blocks;
 pub mod chunks;
 pub mod columns;
 mod recycle;
 
+pub use self::blocks::BlocksMask;
 pub use self::columns::ColumnsMask;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::iter::Sum;
+use std::mem;
+use std::ops::{Add, AddAssign};
+
+/// Heap memory owned by a storage, broken down by what it is used for. Reports can be summed, so the report of a
+/// `World` is the sum of the reports of its sectors and chunks, plus the sector table itself.
+///
+/// Only memory owned directly by the storage is counted. Memory owned by the blocks themselves (for example, a block
+/// type containing a `String`) and memory shared through an `Arc`, such as a registry, is not included. Hash map sizes
+/// are estimates, since the standard library does not expose their exact layout.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct MemoryReport {
+	/// Bytes of packed block storage.
+	pub storage: usize,
+	/// Bytes of palette entries and their reverse lookup tables.
+	pub palettes: usize,
+	/// Bytes of unpacked nibble arrays, such as light.
+	pub light: usize,
+	/// Bytes of change journals, see `IndexedStorage::track_changes`.
+	pub journals: usize,
+	/// Bytes of edits waiting for their chunk to be created, see `PendingEdits`.
+	pub pending: usize,
+	/// Bytes of sector tables, locks, and other bookkeeping that does not hold chunk data.
+	pub structure: usize,
+	/// Number of chunks included in the report.
+	pub chunks: usize
+}
+
+impl MemoryReport {
+	/// Returns the total amount of bytes in every category.
+	pub fn total(&self) -> usize {
+		self.storage + self.palettes + self.light + self.journals + self.pending + self.structure
+	}
+}
+
+impl Add for MemoryReport {
+	type Output = MemoryReport;
+
+	fn add(self, rhs: MemoryReport) -> MemoryReport {
+		MemoryReport {
+			storage: self.storage + rhs.storage,
+			palettes: self.palettes + rhs.palettes,
+			light: self.light + rhs.light,
+			journals: self.journals + rhs.journals,
+			pending: self.pending + rhs.pending,
+			structure: self.structure + rhs.structure,
+			chunks: self.chunks + rhs.chunks
+		}
+	}
+}
+
+impl AddAssign for MemoryReport {
+	fn add_assign(&mut self, rhs: MemoryReport) {
+		*self = *self + rhs;
+	}
+}
+
+impl Sum for MemoryReport {
+	fn sum<I>(iter: I) -> Self where I: Iterator<Item = MemoryReport> {
+		iter.fold(MemoryReport::default(), Add::add)
+	}
+}
+
+impl Display for MemoryReport {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mib = |bytes: usize| bytes as f64 / 1048576.0;
+
+		write!(
+			f, "{:.3} MiB in {} chunks ({:.3} MiB storage, {:.3} MiB palettes, {:.3} MiB light, {:.3} MiB journals, {:.3} MiB pending, {:.3} MiB structure)",
+			mib(self.total()), self.chunks, mib(self.storage), mib(self.palettes), mib(self.light), mib(self.journals), mib(self.pending), mib(self.structure)
+		)
+	}
+}
+
+/// Reports the heap memory used by a storage.
+pub trait MemoryUsage {
+	/// Returns the heap memory owned by this value, not counting the size of the value itself.
+	fn memory_usage(&self) -> MemoryReport;
+}
+
+/// Estimates the bytes allocated by a hash map: each bucket holds a key, a value, and one control byte.
+pub(crate) fn hash_map_bytes<K, V, S>(map: &HashMap<K, V, S>) -> usize {
+	if map.capacity() == 0 {
+		return 0;
+	}
+
+	let buckets = (map.capacity() * 8 / 7).next_power_of_two();
+
+	buckets * (mem::size_of::<(K, V)>() + 1)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::indexed::IndexedCube;
+	use crate::nibbles::NibbleCube;
+	use crate::position::GlobalChunkPosition;
+	use crate::world::shared::{NoPack, SharedWorld};
+	use crate::world::world::World;
+
+	#[test]
+	fn test_world_report() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		world.set(GlobalChunkPosition::new(0, 0, 0), IndexedCube::new(4, 0));
+		world.set(GlobalChunkPosition::new(1, 0, 0), IndexedCube::new(8, 0));
+		world.set(GlobalChunkPosition::new(0, 0, 16), IndexedCube::new(4, 0));
+
+		let report = world.memory_usage();
+
+		assert_eq!(report.chunks, 3);
+		assert_eq!(report.storage, 2048 + 4096 + 2048);
+		assert_eq!(report.journals, 0);
+		assert!(report.palettes >= (16 + 256 + 16) * mem::size_of::<Option<u16>>());
+		assert!(report.structure >= 2 * 4096 * mem::size_of::<Option<IndexedCube<u16>>>());
+
+		world.get_mut(GlobalChunkPosition::new(0, 0, 0)).unwrap().track_changes();
+		assert!(world.memory_usage().journals > 0);
+
+		let mut light: World<NibbleCube> = World::new();
+		light.set(GlobalChunkPosition::new(0, 0, 0), NibbleCube::default());
+
+		let shared: SharedWorld<NoPack<NibbleCube>> = SharedWorld::from_world(light);
+		let report = shared.memory_usage();
+
+		assert_eq!(report.chunks, 1);
+		assert_eq!(report.light, 2048);
+		assert_eq!(report.total(), report.light + report.structure);
+	}
+}
@@ -2,6 +2,7 @@ use std::fmt::{Debug, Formatter, Result};
 use crate::position::CubePosition;
 use super::{u4, nibble_index};
 use crate::component::CubeStorage;
+use crate::memory::{MemoryReport, MemoryUsage};
 
 /// A 16x16 collection of nibbles (`u4`s).
 /// Indexed with CubePosition.
@@ -77,6 +78,15 @@ impl CubeStorage<u4> for NibbleCube {
 	}
 }
 
+impl MemoryUsage for NibbleCube {
+	fn memory_usage(&self) -> MemoryReport {
+		MemoryReport {
+			light: 2048,
+			..MemoryReport::default()
+		}
+	}
+}
+
 impl Default for NibbleCube {
 	fn default() -> Self {
 		NibbleCube(Box::new([0; 2048]))
@@ -0,0 +1,127 @@
+use crate::component::{Component, CubeStorage, LayerStorage};
+use crate::packed::{PackedIndex, PackedStorage};
+use crate::position::{CubePosition, LayerPosition};
+
+/// An unsigned integer with a fixed amount of bits, up to 32. This is the `uXX` component, for values that would
+/// waste space as a whole `u8` or `u16`, such as fluid levels. Values are truncated to the amount of bits.
+///
+/// Using a width of 0 or more than 32 bits fails to compile.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PackedUint<const BITS: u8>(u32);
+
+impl<const BITS: u8> PackedUint<BITS> {
+	pub const MAX: Self = PackedUint(Self::MASK);
+
+	/// Evaluated by everything that creates a value or storage, so that invalid widths are rejected at compile time.
+	const VALID_BITS: () = assert!(BITS > 0 && BITS <= 32, "PackedUint must have between 1 and 32 bits");
+
+	const MASK: u32 = {
+		let () = Self::VALID_BITS;
+
+		if BITS >= 32 { u32::MAX } else { (1 << BITS) - 1 }
+	};
+
+	/// Creates a new value, truncating it to the amount of bits.
+	pub const fn new(value: u32) -> Self {
+		PackedUint(value & Self::MASK)
+	}
+
+	pub fn get(self) -> u32 {
+		self.0
+	}
+}
+
+impl<const BITS: u8> Default for PackedUint<BITS> {
+	fn default() -> Self {
+		PackedUint::new(0)
+	}
+}
+
+impl<const BITS: u8> Component for PackedUint<BITS> {
+	type Chunk = FixedPacked<CubePosition, BITS>;
+	type Layer = FixedPacked<LayerPosition, BITS>;
+	type Bulk = ();
+}
+
+/// Packed storage that always uses the same amount of bits, so that it can be created with `Default` like the other
+/// component storages.
+#[derive(Debug, Clone)]
+pub struct FixedPacked<P, const BITS: u8>(PackedStorage<P>) where P: PackedIndex;
+
+pub type FixedPackedCube<const BITS: u8> = FixedPacked<CubePosition, BITS>;
+pub type FixedPackedLayer<const BITS: u8> = FixedPacked<LayerPosition, BITS>;
+
+impl<P, const BITS: u8> FixedPacked<P, BITS> where P: PackedIndex {
+	pub fn inner(&self) -> &PackedStorage<P> {
+		&self.0
+	}
+
+	pub fn into_inner(self) -> PackedStorage<P> {
+		self.0
+	}
+}
+
+impl<P, const BITS: u8> Default for FixedPacked<P, BITS> where P: PackedIndex {
+	fn default() -> Self {
+		let () = PackedUint::<BITS>::VALID_BITS;
+
+		FixedPacked(PackedStorage::new(BITS))
+	}
+}
+
+impl<const BITS: u8> CubeStorage<PackedUint<BITS>> for FixedPacked<CubePosition, BITS> {
+	fn get(&self, position: CubePosition) -> PackedUint<BITS> {
+		PackedUint(self.0.get(position))
+	}
+
+	fn set(&mut self, position: CubePosition, value: PackedUint<BITS>) {
+		self.0.set(position, value.0)
+	}
+
+	fn fill(&mut self, value: PackedUint<BITS>) {
+		self.0.fill(value.0)
+	}
+}
+
+impl<const BITS: u8> LayerStorage<PackedUint<BITS>> for FixedPacked<LayerPosition, BITS> {
+	fn get(&self, position: LayerPosition) -> PackedUint<BITS> {
+		PackedUint(self.0.get(position))
+	}
+
+	fn is_filled(&self, value: PackedUint<BITS>) -> bool {
+		LayerPosition::enumerate().all(|position| self.0.get(position) == value.0)
+	}
+
+	fn set(&mut self, position: LayerPosition, value: PackedUint<BITS>) {
+		self.0.set(position, value.0)
+	}
+
+	fn fill(&mut self, value: PackedUint<BITS>) {
+		self.0.fill(value.0)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_fixed_packed() {
+		let mut levels: <PackedUint<3> as Component>::Chunk = Default::default();
+
+		assert_eq!(levels.inner().bits(), 3);
+		assert_eq!(PackedUint::<3>::new(9), PackedUint::new(1));
+
+		levels.fill(PackedUint::new(7));
+		levels.set(CubePosition::new(1, 2, 3), PackedUint::new(2));
+
+		assert_eq!(levels.get(CubePosition::new(1, 2, 3)).get(), 2);
+		assert_eq!(levels.get(CubePosition::new(3, 2, 1)), PackedUint::MAX);
+
+		let mut layer = FixedPackedLayer::<3>::default();
+
+		assert!(layer.is_filled(PackedUint::new(0)));
+		layer.set(LayerPosition::new(4, 4), PackedUint::new(5));
+		assert!(!layer.is_filled(PackedUint::new(0)));
+	}
+}
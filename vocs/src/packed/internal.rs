@@ -1,10 +1,9 @@
 use std::collections::HashMap;
+use std::marker::PhantomData;
+use crate::packed::layout::{Layout, Spanning};
 use crate::packed::setter::Setter;
-
-struct Indices {
-	start: usize,
-	end: usize
-}
+use crate::memory::{MemoryReport, MemoryUsage};
+use std::mem;
 
 pub trait PackedIndex: Copy {
 	type Enumerate: Iterator<Item = Self>;
@@ -17,83 +16,73 @@ pub trait PackedIndex: Copy {
 }
 
 #[derive(Debug, Clone)]
-pub struct PackedStorage<P>(Box<[u64]>, ::std::marker::PhantomData<P>) where P: PackedIndex;
+pub struct PackedStorage<P, L = Spanning> where P: PackedIndex, L: Layout {
+	words: Box<[u64]>,
+	bits: u8,
+	phantom: PhantomData<(P, L)>
+}
 
-impl<P> PackedStorage<P> where P: PackedIndex {
+impl<P, L> PackedStorage<P, L> where P: PackedIndex, L: Layout {
 	pub fn new(bits: u8) -> Self {
-		PackedStorage (
-			vec![0; (bits as usize) * P::size_factor()].into_boxed_slice(),
-			::std::marker::PhantomData
-		)
+		PackedStorage {
+			words: vec![0; L::words(Self::entries(), bits)].into_boxed_slice(),
+			bits,
+			phantom: PhantomData
+		}
 	}
 
-	fn indices(&self, index: usize, bits: u8) -> (Indices, u8) {
-		let bits = bits as usize;
+	/// Creates a storage from words previously returned by `raw_storage`, without repacking them.
+	/// Returns None if there are more than 32 bits, or if the amount of words does not match the layout.
+	pub fn from_raw_storage(bits: u8, words: Box<[u64]>) -> Option<Self> {
+		if bits > 32 || words.len() != L::words(Self::entries(), bits) {
+			return None;
+		}
 
-		let bit_index = index * bits;
-		// Calculate the indices to the u64 array.
-		let start = bit_index / 64;
-		let end = ((bit_index + bits) - 1) / 64;
-		let sub_index = (bit_index % 64) as u8;
+		Some(PackedStorage {
+			words,
+			bits,
+			phantom: PhantomData
+		})
+	}
 
-		(Indices { start, end }, sub_index)
+	fn entries() -> usize {
+		P::size_factor() * 64
 	}
 
-	/// Calculates the bit count from the internal array size.
+	/// Returns the amount of bits used by each entry.
 	pub fn bits(&self) -> u8 {
-		(self.0.len() / P::size_factor()) as u8
+		self.bits
 	}
 
+	/// Returns the backing words, arranged according to the layout.
 	pub fn raw_storage(&self) -> &[u64] {
-		&self.0
+		&self.words
 	}
 
 	pub fn get(&self, position: P) -> u32 {
-		if self.0.len() == 0 {
+		if self.bits == 0 {
 			return 0;
 		}
 
-		let bits = self.bits();
-		let bitmask = (1u64 << bits) - 1;
-		let index = position.to_usize();
-
-		let (indices, sub_index) = self.indices(index, bits);
-
-		let mut raw = self.0[indices.start] >> sub_index;
-
-		if indices.start != indices.end {
-			raw |= self.0[indices.end] << (64 - sub_index);
-		}
-
-		(raw & bitmask) as u32
+		L::get(&self.words, position.to_usize(), self.bits)
 	}
 
 	pub fn set(&mut self, position: P, value: u32) {
-		if self.0.len() == 0 {
+		if self.bits == 0 {
 			return;
 		}
 
-		let bits = self.bits();
-		let bitmask = (1u64 << bits) - 1;
-		let value = value as u64 & bitmask;
-		let index = position.to_usize();
-
-		let (indices, sub_index) = self.indices(index, bits);
+		let bitmask = (1u64 << self.bits) - 1;
 
-		self.0[indices.start] = self.0[indices.start] & !(bitmask << sub_index) | value << sub_index;
-
-		if indices.start != indices.end {
-			let end_sub_index = 64 - sub_index;
-			self.0[indices.end] = self.0[indices.end] >> end_sub_index << end_sub_index | value >> end_sub_index;
-		}
+		L::set(&mut self.words, position.to_usize(), self.bits, value as u64 & bitmask)
 	}
 
-	pub fn setter(&mut self, value: u32) -> Setter<P> {
+	pub fn setter(&mut self, value: u32) -> Setter<P, L> {
 		Setter::new(self, value)
 	}
 
 	pub fn clear(&mut self) {
-		for value in self.0.iter_mut() {
+		for value in self.words.iter_mut() {
 			*value = 0;
 		}
 	}
@@ -105,7 +94,7 @@ impl<P> PackedStorage<P> where P: PackedIndex {
 		}
 
 		// TODO: Possibly repeat values into a bit pattern?
-		for index in 0..P::size_factor()*64 {
+		for index in 0..Self::entries() {
 			self.set(P::from_usize(index), value)
 		}
 	}
@@ -113,21 +102,135 @@ impl<P> PackedStorage<P> where P: PackedIndex {
 	/// Clones from another storage into this storage using the provided translation table.
 	/// Alternately, truncates the values.
 	/// Any missing translations are replaced with the default.
-	pub fn clone_from(&mut self, from: &PackedStorage<P>, translation: Option<&HashMap<u32, u32>>, default: u32) {
-		if self.0.len() == from.0.len() {
-			self.0.clone_from(&from.0);
+	pub fn clone_from(&mut self, from: &PackedStorage<P, L>, translation: Option<&HashMap<u32, u32>>, default: u32) {
+		if self.bits == from.bits {
+			self.words.clone_from(&from.words);
 			return;
 		}
 
+		self.copy_entries(from, translation, default);
+	}
+
+	/// Copies all entries into a new storage with a different layout, keeping the same bit count.
+	pub fn to_layout<T>(&self) -> PackedStorage<P, T> where T: Layout {
+		let mut target = PackedStorage::new(self.bits);
+
+		target.copy_entries(self, None, 0);
+
+		target
+	}
+
+	fn copy_entries<F>(&mut self, from: &PackedStorage<P, F>, translation: Option<&HashMap<u32, u32>>, default: u32) where F: Layout {
 		match translation {
-			Some(translation) => for index in 0..P::size_factor()*64 {
+			Some(translation) => for index in 0..Self::entries() {
 				let position = P::from_usize(index);
 				self.set(position, *translation.get(&from.get(position)).unwrap_or(&default));
 			},
-			None => for index in 0..P::size_factor()*64 {
+			None => for index in 0..Self::entries() {
 				let position = P::from_usize(index);
 				self.set(position, from.get(position));
 			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+impl<P, L> MemoryUsage for PackedStorage<P, L> where P: PackedIndex, L: Layout {
+	fn memory_usage(&self) -> MemoryReport {
+		MemoryReport {
+			storage: self.words.len() * mem::size_of::<u64>(),
+			..MemoryReport::default()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::packed::{Aligned, PackedCube, PackedStorage};
+	use crate::position::CubePosition;
+
+	#[test]
+	fn test_aligned_roundtrip() {
+		for bits in 1..17 {
+			let mut storage = PackedStorage::<CubePosition, Aligned>::new(bits);
+			let mask = (1u32 << bits) - 1;
+
+			for position in CubePosition::enumerate() {
+				storage.set(position, (position.yzx() as u32).wrapping_mul(2654435761) & mask);
+			}
+
+			for position in CubePosition::enumerate() {
+				assert_eq!(storage.get(position), (position.yzx() as u32).wrapping_mul(2654435761) & mask);
+			}
+
+			let per_word = 64 / bits as usize;
+			assert_eq!(storage.raw_storage().len(), 4096_usize.div_ceil(per_word));
+
+			// Padding bits at the top of each word are never touched.
+			let padding = 64 - per_word * bits as usize;
+			if padding != 0 {
+				for &word in storage.raw_storage() {
+					assert_eq!(word >> (64 - padding), 0);
+				}
+			}
+
+			let spanning: PackedCube = storage.to_layout();
+			assert_eq!(spanning.bits(), bits);
+
+			for position in CubePosition::enumerate() {
+				assert_eq!(spanning.get(position), storage.get(position));
+			}
+		}
+	}
+
+	#[test]
+	fn test_spanning_overwrite() {
+		for bits in 1..17 {
+			let mut storage: PackedCube = PackedCube::new(bits);
+			let mask = (1u32 << bits) - 1;
+
+			storage.fill(mask);
+
+			for position in CubePosition::enumerate() {
+				storage.set(position, position.yzx() as u32 & mask);
+			}
+
+			for position in CubePosition::enumerate() {
+				assert_eq!(storage.get(position), position.yzx() as u32 & mask);
+			}
+		}
+	}
+
+	/// Setting an entry that straddles two words must only clear its own bits in the second word, and leave the
+	/// entries after it untouched. Writing in descending order makes sure that those entries are never rewritten.
+	#[test]
+	fn test_spanning_set_keeps_neighbors() {
+		for bits in 1..17 {
+			let mut storage: PackedCube = PackedCube::new(bits);
+			let mask = (1u32 << bits) - 1;
+
+			for position in CubePosition::enumerate().collect::<Vec<_>>().into_iter().rev() {
+				storage.set(position, (position.yzx() as u32).wrapping_mul(2654435761) & mask);
+			}
+
+			for position in CubePosition::enumerate() {
+				assert_eq!(storage.get(position), (position.yzx() as u32).wrapping_mul(2654435761) & mask);
+			}
+		}
+	}
+
+	#[test]
+	fn test_aligned_clone_from() {
+		let mut small = PackedStorage::<CubePosition, Aligned>::new(5);
+
+		for position in CubePosition::enumerate() {
+			small.set(position, position.y() as u32);
+		}
+
+		let mut large = PackedStorage::<CubePosition, Aligned>::new(12);
+		large.clone_from(&small, None, 0);
+
+		for position in CubePosition::enumerate() {
+			assert_eq!(large.get(position), position.y() as u32);
+		}
+	}
+}
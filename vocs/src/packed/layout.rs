@@ -0,0 +1,109 @@
+/// Describes how fixed-width entries are arranged inside an array of u64 words.
+pub trait Layout: Copy {
+	/// Whether entries are allowed to straddle the boundary between two words.
+	const SPANNING: bool;
+
+	/// Number of words needed to store the specified number of entries.
+	fn words(entries: usize, bits: u8) -> usize;
+
+	fn get(words: &[u64], index: usize, bits: u8) -> u32;
+
+	/// Sets the entry at the index. The value must already be masked to the bit count.
+	fn set(words: &mut [u64], index: usize, bits: u8, value: u64);
+}
+
+/// Entries are packed end to end, with an entry spanning two words when it does not fit in the
+/// remaining bits of the first. This is the densest layout, and matches the layout used by
+/// Minecraft 1.9 through 1.15.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Spanning;
+
+struct Indices {
+	start: usize,
+	end: usize
+}
+
+impl Spanning {
+	fn indices(index: usize, bits: u8) -> (Indices, u8) {
+		let bits = bits as usize;
+
+		let bit_index = index * bits;
+		// Calculate the indices to the u64 array.
+		let start = bit_index / 64;
+		let end = ((bit_index + bits) - 1) / 64;
+		let sub_index = (bit_index % 64) as u8;
+
+		(Indices { start, end }, sub_index)
+	}
+}
+
+impl Layout for Spanning {
+	const SPANNING: bool = true;
+
+	fn words(entries: usize, bits: u8) -> usize {
+		(entries * bits as usize) / 64
+	}
+
+	fn get(words: &[u64], index: usize, bits: u8) -> u32 {
+		let bitmask = (1u64 << bits) - 1;
+		let (indices, sub_index) = Spanning::indices(index, bits);
+
+		let mut raw = words[indices.start] >> sub_index;
+
+		if indices.start != indices.end {
+			raw |= words[indices.end] << (64 - sub_index);
+		}
+
+		(raw & bitmask) as u32
+	}
+
+	fn set(words: &mut [u64], index: usize, bits: u8, value: u64) {
+		let bitmask = (1u64 << bits) - 1;
+		let (indices, sub_index) = Spanning::indices(index, bits);
+
+		words[indices.start] = words[indices.start] & !(bitmask << sub_index) | value << sub_index;
+
+		if indices.start != indices.end {
+			// The low bits of the end word hold the remaining high bits of the entry.
+			let end_sub_index = 64 - sub_index;
+			words[indices.end] = words[indices.end] & !(bitmask >> end_sub_index) | value >> end_sub_index;
+		}
+	}
+}
+
+/// Each word holds `64 / bits` whole entries, and any remaining high bits are left as padding.
+/// This matches the layout used by Minecraft 1.16 and above, so storages using it can be sent
+/// over the network or written to disk without repacking.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Aligned;
+
+impl Layout for Aligned {
+	const SPANNING: bool = false;
+
+	fn words(entries: usize, bits: u8) -> usize {
+		if bits == 0 {
+			return 0;
+		}
+
+		let per_word = 64 / bits as usize;
+
+		entries.div_ceil(per_word)
+	}
+
+	fn get(words: &[u64], index: usize, bits: u8) -> u32 {
+		let bitmask = (1u64 << bits) - 1;
+		let per_word = 64 / bits as usize;
+		let sub_index = (index % per_word) * bits as usize;
+
+		((words[index / per_word] >> sub_index) & bitmask) as u32
+	}
+
+	fn set(words: &mut [u64], index: usize, bits: u8, value: u64) {
+		let bitmask = (1u64 << bits) - 1;
+		let per_word = 64 / bits as usize;
+		let sub_index = (index % per_word) * bits as usize;
+		let word = &mut words[index / per_word];
+
+		*word = *word & !(bitmask << sub_index) | value << sub_index;
+	}
+}
@@ -1,12 +1,16 @@
 use crate::position::{LayerPosition, CubePosition};
 
 mod internal;
+mod layout;
 mod setter;
+mod fixed;
 
 pub use self::internal::{PackedIndex, PackedStorage};
+pub use self::layout::{Aligned, Layout, Spanning};
 pub use self::setter::Setter;
+pub use self::fixed::{FixedPacked, FixedPackedCube, FixedPackedLayer, PackedUint};
 
-pub type PackedCube = self::internal::PackedStorage<CubePosition>;
-pub type LayerPacked = self::internal::PackedStorage<LayerPosition>;
+pub type PackedCube<L = Spanning> = self::internal::PackedStorage<CubePosition, L>;
+pub type LayerPacked<L = Spanning> = self::internal::PackedStorage<LayerPosition, L>;
 
-pub type PackedBlockStorage<P> = self::internal::PackedStorage<P>;
\ No newline at end of file
+pub type PackedBlockStorage<P, L = Spanning> = self::internal::PackedStorage<P, L>;
\ No newline at end of file
@@ -1,12 +1,12 @@
-use crate::packed::{PackedStorage, PackedIndex};
+use crate::packed::{Layout, PackedStorage, PackedIndex};
 
-pub struct Setter<'s, P> where P: 's + PackedIndex {
-	storage: &'s mut PackedStorage<P>,
+pub struct Setter<'s, P, L> where P: 's + PackedIndex, L: 's + Layout {
+	storage: &'s mut PackedStorage<P, L>,
 	value: u32
 }
 
-impl<'s, P> Setter<'s, P> where P: 's + PackedIndex {
-	pub fn new(storage: &'s mut PackedStorage<P>, value: u32) -> Self {
+impl<'s, P, L> Setter<'s, P, L> where P: 's + PackedIndex, L: 's + Layout {
+	pub fn new(storage: &'s mut PackedStorage<P, L>, value: u32) -> Self {
 		Setter { storage, value }
 	}
 
@@ -18,7 +18,7 @@ impl<'s, P> Setter<'s, P> where P: 's + PackedIndex {
 		self.storage.get(position)
 	}
 
-	pub fn storage(&self) -> &PackedStorage<P> {
+	pub fn storage(&self) -> &PackedStorage<P, L> {
 		&self.storage
 	}
 }
@@ -2,42 +2,49 @@ use crate::position::CubePosition;
 use crate::position::{LayerPosition, Offset, dir};
 use std::fmt;
 
+/// A position within a column of chunks. The Y component is relative to the bottom of the column,
+/// so it ranges from 0 up to the column height, at most 4096 blocks. See `Height`.
 #[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
-pub struct ColumnPosition(u16);
+pub struct ColumnPosition(u32);
 
 impl ColumnPosition {
+	/// The highest Y value that can be represented.
+	pub const MAX_Y: u16 = 4095;
+
 	/// Creates a new ColumnPosition from the X, Y, and Z components.
 	/// ### Out of bounds behavior
 	/// If the arguments are out of bounds, then they are truncated.
-	pub fn new(x: u8, y: u8, z: u8) -> Self {
+	pub fn new(x: u8, y: u16, z: u8) -> Self {
 		ColumnPosition(
-			(      (y as u16) << 8) |
-			(((z&0xF) as u16) << 4) | 
-			 ((x&0xF) as u16)
+			(((y&0xFFF) as u32) << 8) |
+			(((z&0xF)   as u32) << 4) |
+			 ((x&0xF)   as u32)
 		)
 	}
 	
 	/// Creates a new ColumnPosition from the Y component and LayerPosition containing the X and Z components.
-	/// Out of bounds is not possible with this function.
-	pub fn from_layer(y: u8, layer: LayerPosition) -> Self {
+	/// ### Out of bounds behavior
+	/// If the Y component is out of bounds, then it is truncated.
+	pub fn from_layer(y: u16, layer: LayerPosition) -> Self {
 		ColumnPosition(
-			((y as u16) << 8) | (layer.zx() as u16)
+			(((y&0xFFF) as u32) << 8) | (layer.zx() as u32)
 		)
 	}
 
 	/// Creates a new ColumnPosition from the given CubePosition with an additional section_y value,
-	/// which determines the column "section" (from 0-16) in which this position resides.
+	/// which determines the column "section" (counting from the bottom of the column) in which this position resides.
 	pub fn from_chunk(section_y: u8, chunk: CubePosition) -> Self {
 		ColumnPosition::from_yzx (
-			((section_y as u16) << 12) |
-			chunk.yzx()
+			((section_y as u32) << 12) |
+			(chunk.yzx() as u32)
 		)
 	}
 	
 	/// Creates a new ColumnPosition from a YZX index.
-	/// Out of bounds is not possible with this function.
-	pub fn from_yzx(yzx: u16) -> Self {
-		ColumnPosition(yzx)
+	/// ### Out of bounds behavior
+	/// If the index is out of bounds, it is truncated.
+	pub fn from_yzx(yzx: u32) -> Self {
+		ColumnPosition(yzx & 0xFFFFF)
 	}
 	
 	/// Creates a new ColumnPosition from a chunk XYZ index.
@@ -45,7 +52,7 @@ impl ColumnPosition {
 	/// ### Out of bounds behavior
 	/// If the index is out of bounds, it is truncated.
 	pub fn from_chunk_xyz(xyz: u16) -> Self {
-		let xyz = xyz & 0xFFF; // Truncate the value if too large
+		let xyz = (xyz & 0xFFF) as u32; // Truncate the value if too large
 		// X YZ - Start
 		// YZ X - End
 		ColumnPosition(((xyz & 0xF00) >> 8) | ((xyz & 0x0FF) << 4))
@@ -62,18 +69,18 @@ impl ColumnPosition {
 	}
 	
 	/// Returns the Y component.
-	pub fn y(&self) -> u8 {
-		(self.0 >> 8) as u8
+	pub fn y(&self) -> u16 {
+		(self.0 >> 8) as u16
 	}
 	
-	/// Returns the Y component >> 4, the chunk Y.
+	/// Returns the Y component >> 4, the index of the chunk counting from the bottom of the column.
 	pub fn chunk_y(&self) -> u8 {
 		(self.0 >> 12) as u8
 	}
 	
 	/// Returns the Y and Z components, represented as `(Y<<4) | Z`.
 	pub fn yz(&self) -> u16 {
-		self.0 >> 4
+		(self.0 >> 4) as u16
 	}
 
 	/// Returns the Y and Z components, with Y capped to 15, represented as `(Y<<4) | Z`.
@@ -83,7 +90,7 @@ impl ColumnPosition {
 
 	/// Returns the Y and X components, with Y capped to 15, represented as `(Y<<4) | X`.
 	pub fn chunk_yx(&self) -> u8 {
-		(self.chunk_y() << 4) | (self.x())
+		(((self.0 >> 4) & 0xF0) as u8) | (self.x())
 	}
 	
 	/// Returns the index represented as `(Z<<4) | X`.
@@ -112,13 +119,13 @@ impl ColumnPosition {
 	}
 	
 	/// Returns the index represented as `(Y<<8) | (Z<<4) | X`.
-	pub fn yzx(&self) -> u16 {
+	pub fn yzx(&self) -> u32 {
 		self.0
 	}
 	
 	/// Returns the index represented as `(Y<<8) | (Z<<4) | X` modulo 4096, for in-chunk indices.
 	pub fn chunk_yzx(&self) -> u16 {
-		self.0 & 4095
+		(self.0 & 4095) as u16
 	}
 	
 	/// Returns the index represented as `(X<<8) | (Y<<4) | Z`.
@@ -142,8 +149,9 @@ impl fmt::Debug for ColumnPosition {
 impl Offset<dir::Up> for ColumnPosition {
 	type Spill = LayerPosition;
 
+	/// Note that this is only bounded by `MAX_Y`, views check against their own height.
 	fn offset(self, _: dir::Up) -> Option<Self> {
-		if self.y() < 255 {
+		if self.y() < Self::MAX_Y {
 			Some(ColumnPosition(self.0 + 0x0100))
 		} else {
 			None
@@ -155,7 +163,7 @@ impl Offset<dir::Up> for ColumnPosition {
 	}
 
 	fn offset_spilling(self, _: dir::Up) -> Result<Self, LayerPosition> {
-		if self.y() < 255 {
+		if self.y() < Self::MAX_Y {
 			Ok(ColumnPosition(self.0 + 0x0100))
 		} else {
 			Err(self.layer())
@@ -199,8 +207,8 @@ impl Offset<dir::PlusX> for ColumnPosition {
 	}
 
 	fn offset_wrapping(self, _: dir::PlusX) -> Self {
-		let base = self.0 & 0xFFF0;
-		let add = ((self.x() + 1) & 15) as u16;
+		let base = self.0 & 0xFFFF0;
+		let add = ((self.x() + 1) & 15) as u32;
 
 		ColumnPosition(base | add)
 	}
@@ -222,8 +230,8 @@ impl Offset<dir::MinusX> for ColumnPosition {
 	}
 
 	fn offset_wrapping(self, _: dir::MinusX) -> Self {
-		let base = self.0 & 0xFFF0;
-		let add = ((self.x().wrapping_sub(1)) & 15) as u16;
+		let base = self.0 & 0xFFFF0;
+		let add = ((self.x().wrapping_sub(1)) & 15) as u32;
 
 		ColumnPosition(base | add)
 	}
@@ -245,8 +253,8 @@ impl Offset<dir::PlusZ> for ColumnPosition {
 	}
 
 	fn offset_wrapping(self, _: dir::PlusZ) -> Self {
-		let base = self.0 & 0xFF0F;
-		let add = ((self.z() + 1) & 15) as u16;
+		let base = self.0 & 0xFFF0F;
+		let add = ((self.z() + 1) & 15) as u32;
 
 		ColumnPosition(base | (add << 4))
 	}
@@ -268,8 +276,8 @@ impl Offset<dir::MinusZ> for ColumnPosition {
 	}
 
 	fn offset_wrapping(self, _: dir::MinusZ) -> Self {
-		let base = self.0 & 0xFF0F;
-		let add = ((self.z().wrapping_sub(1)) & 15) as u16;
+		let base = self.0 & 0xFFF0F;
+		let add = ((self.z().wrapping_sub(1)) & 15) as u32;
 
 		ColumnPosition(base | (add << 4))
 	}
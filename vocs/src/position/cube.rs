@@ -2,7 +2,7 @@ use std::fmt;
 use crate::position::{LayerPosition, Offset, dir};
 use crate::packed::PackedIndex;
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct CubePosition(u16);
 
 impl CubePosition {
@@ -0,0 +1,165 @@
+use crate::position::{CubePosition, GlobalChunkPosition, GlobalColumnPosition, GlobalPosition, Rect};
+use std::cmp;
+
+/// An axis aligned box of blocks in global coordinates. Both corners are inclusive.
+/// ### Out of bounds behavior
+/// Chunk Y values are only defined for Y=-2048 to Y=2047. The parts of a cuboid outside of that range do not belong to
+/// any chunk, and are skipped by `chunks`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Cuboid {
+	min: GlobalPosition,
+	max: GlobalPosition
+}
+
+impl Cuboid {
+	/// Creates a cuboid spanning both corners, which may be passed in any order.
+	pub fn new(a: GlobalPosition, b: GlobalPosition) -> Self {
+		Cuboid {
+			min: GlobalPosition::new(cmp::min(a.x(), b.x()), cmp::min(a.y(), b.y()), cmp::min(a.z(), b.z())),
+			max: GlobalPosition::new(cmp::max(a.x(), b.x()), cmp::max(a.y(), b.y()), cmp::max(a.z(), b.z()))
+		}
+	}
+
+	/// Creates a cuboid from the minimum corner and the size along each axis. Sizes must be at least 1.
+	pub fn from_size(min: GlobalPosition, size_x: u32, size_y: u32, size_z: u32) -> Self {
+		assert!(size_x > 0 && size_y > 0 && size_z > 0, "Cuboids must contain at least one block");
+
+		Cuboid {
+			min,
+			max: GlobalPosition::new(
+				min.x() + (size_x - 1) as i32,
+				min.y() + (size_y - 1) as i32,
+				min.z() + (size_z - 1) as i32
+			)
+		}
+	}
+
+	/// Returns the corner with the lowest coordinates.
+	pub fn min(&self) -> GlobalPosition {
+		self.min
+	}
+
+	/// Returns the corner with the highest coordinates.
+	pub fn max(&self) -> GlobalPosition {
+		self.max
+	}
+
+	pub fn size_x(&self) -> u32 {
+		(self.max.x() - self.min.x()) as u32 + 1
+	}
+
+	pub fn size_y(&self) -> u32 {
+		(self.max.y() - self.min.y()) as u32 + 1
+	}
+
+	pub fn size_z(&self) -> u32 {
+		(self.max.z() - self.min.z()) as u32 + 1
+	}
+
+	/// Returns the amount of blocks in the cuboid.
+	pub fn volume(&self) -> u64 {
+		(self.size_x() as u64) * (self.size_y() as u64) * (self.size_z() as u64)
+	}
+
+	pub fn contains(&self, position: GlobalPosition) -> bool {
+		position.x() >= self.min.x() && position.x() <= self.max.x() &&
+		position.y() >= self.min.y() && position.y() <= self.max.y() &&
+		position.z() >= self.min.z() && position.z() <= self.max.z()
+	}
+
+	/// Returns the columns that intersect this cuboid.
+	pub fn columns(&self) -> Rect<GlobalColumnPosition> {
+		Rect::new(self.min.global_column(), self.max.global_column())
+	}
+
+	/// Iterates over the chunks that intersect this cuboid, along with the inclusive local bounds of the intersection.
+	/// Layers of chunks above or below the range of chunk Y values are skipped.
+	pub fn chunks(&self) -> CuboidChunks {
+		let min = (self.min.x() >> 4, cmp::max(self.min.y() >> 4, i8::MIN as i32), self.min.z() >> 4);
+		let max = (self.max.x() >> 4, cmp::min(self.max.y() >> 4, i8::MAX as i32), self.max.z() >> 4);
+
+		CuboidChunks {
+			cuboid: *self,
+			min,
+			max,
+			next: if min.1 <= max.1 { Some(min) } else { None }
+		}
+	}
+}
+
+/// Iterator over the chunks that a cuboid intersects, in YZX order. See `Cuboid::chunks`.
+#[derive(Debug, Clone)]
+pub struct CuboidChunks {
+	cuboid: Cuboid,
+	min: (i32, i32, i32),
+	max: (i32, i32, i32),
+	next: Option<(i32, i32, i32)>
+}
+
+impl CuboidChunks {
+	/// Returns the local bounds of the intersection along one axis.
+	fn bounds(chunk: i32, min: i32, max: i32) -> (u8, u8) {
+		let low = if chunk == min >> 4 { (min & 15) as u8 } else { 0 };
+		let high = if chunk == max >> 4 { (max & 15) as u8 } else { 15 };
+
+		(low, high)
+	}
+}
+
+impl Iterator for CuboidChunks {
+	type Item = (GlobalChunkPosition, CubePosition, CubePosition);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (x, y, z) = self.next?;
+
+		self.next = if x < self.max.0 {
+			Some((x + 1, y, z))
+		} else if z < self.max.2 {
+			Some((self.min.0, y, z + 1))
+		} else if y < self.max.1 {
+			Some((self.min.0, y + 1, self.min.2))
+		} else {
+			None
+		};
+
+		let (min, max) = (self.cuboid.min, self.cuboid.max);
+
+		let (low_x, high_x) = CuboidChunks::bounds(x, min.x(), max.x());
+		let (low_y, high_y) = CuboidChunks::bounds(y, min.y(), max.y());
+		let (low_z, high_z) = CuboidChunks::bounds(z, min.z(), max.z());
+
+		Some((
+			GlobalChunkPosition::new(x, y as i8, z),
+			CubePosition::new(low_x, low_y, low_z),
+			CubePosition::new(high_x, high_y, high_z)
+		))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::position::{CubePosition, Cuboid, GlobalChunkPosition, GlobalPosition};
+
+	#[test]
+	fn test_chunks() {
+		let chunks: Vec<_> = Cuboid::new(GlobalPosition::new(-1, 4, 15), GlobalPosition::new(0, 20, 15)).chunks().collect();
+
+		assert_eq!(chunks.len(), 4);
+		assert_eq!(chunks[0], (GlobalChunkPosition::new(-1, 0, 0), CubePosition::new(15, 4, 15), CubePosition::new(15, 15, 15)));
+		assert_eq!(chunks[3], (GlobalChunkPosition::new(0, 1, 0), CubePosition::new(0, 0, 15), CubePosition::new(0, 4, 15)));
+	}
+
+	#[test]
+	fn test_chunks_out_of_range_y() {
+		// Layers of chunks outside of the range of chunk Y values are skipped.
+		let chunks: Vec<_> = Cuboid::new(GlobalPosition::new(0, -2060, 0), GlobalPosition::new(0, -2040, 0)).chunks().collect();
+
+		assert_eq!(chunks, vec![(GlobalChunkPosition::new(0, -128, 0), CubePosition::new(0, 0, 0), CubePosition::new(0, 8, 0))]);
+
+		let above = Cuboid::new(GlobalPosition::new(0, 2048, 0), GlobalPosition::new(0, 4000, 0));
+		assert_eq!(above.chunks().count(), 0);
+
+		let below = Cuboid::new(GlobalPosition::new(0, i32::MIN, 0), GlobalPosition::new(0, -2049, 0));
+		assert_eq!(below.chunks().count(), 0);
+	}
+}
@@ -1,38 +1,57 @@
 use crate::position::{CubePosition, LayerPosition};
+use std::convert::TryFrom;
 use std::fmt::{Debug, Display, Result, Formatter};
 
 const MAX_U56: u64 =  72057594037927935;
 const MAX_U28: u64 =  268435455;
 
-// Note: Due to alignment, this will still be 12 bytes as if it were an (i32, i32, i32).
+/// Adds an offset to a horizontal coordinate, returning None if the result is outside of `min..=max`.
+fn checked_h(value: i32, offset: i32, min: i32, max: i32) -> Option<i32> {
+	value.checked_add(offset).filter(|value| (min..=max).contains(value))
+}
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub struct GlobalPosition {
 	x: i32,
 	z: i32,
-	y: u8
+	y: i32
 }
 
 impl GlobalPosition {
-	pub fn new(x: i32, y: u8, z: i32) -> Self {
+	pub fn new(x: i32, y: i32, z: i32) -> Self {
 		GlobalPosition { x, y, z }
 	}
 
+	/// Combines the position of a chunk with a position inside it.
+	pub fn combine(chunk: GlobalChunkPosition, local: CubePosition) -> Self {
+		Self::new (
+			(chunk.x() << 4) + (local.x() as i32),
+			((chunk.y() as i32) << 4) + (local.y() as i32),
+			(chunk.z() << 4) + (local.z() as i32)
+		)
+	}
+
 	pub fn local_block(&self) -> CubePosition {
 		CubePosition::new(
 			((self.x) & 15) as u8,
-			  self.y  & 15,
+			((self.y) & 15) as u8,
 			((self.z) & 15) as u8
 		)
 	}
 
+	/// Returns the position of the containing chunk.
+	/// ### Panics
+	/// Chunk Y values are only defined for Y=-2048 to Y=2047, use `checked_global_chunk` for positions that may
+	/// be outside of that range.
 	pub fn global_chunk(&self) -> GlobalChunkPosition {
-		let (x, y, z) = (
-			self.x >> 4,
-			self.y >> 4,
-			self.z >> 4
-		);
+		self.checked_global_chunk().expect("Block Y is outside of the range that chunk positions can represent")
+	}
+
+	/// Returns the position of the containing chunk, or None if Y is outside of Y=-2048 to Y=2047.
+	pub fn checked_global_chunk(&self) -> Option<GlobalChunkPosition> {
+		let y = i8::try_from(self.y >> 4).ok()?;
 
-		GlobalChunkPosition::new(x, y, z)
+		Some(GlobalChunkPosition::new(self.x >> 4, y, self.z >> 4))
 	}
 
 	pub fn global_column(&self) -> GlobalColumnPosition {
@@ -48,7 +67,7 @@ impl GlobalPosition {
 		self.x
 	}
 
-	pub fn y(&self) -> u8 {
+	pub fn y(&self) -> i32 {
 		self.y
 	}
 
@@ -63,7 +82,7 @@ impl Display for GlobalPosition {
 	}
 }
 
-// Y << 56 | Z << 28 | X
+// Y << 56 | Z << 28 | X, where Y is signed.
 #[derive(Eq, PartialEq, Copy, Clone, Hash)]
 pub struct GlobalChunkPosition(u64);
 
@@ -74,16 +93,22 @@ impl GlobalChunkPosition {
 	const Z_MASK: u64 = 268435455 << 28;
 	const NOT_Z_MASK: u64 = !Self::Z_MASK;
 
-	const Y_MASK: u64 = 15 << 56;
-	const NOT_Y_MASK: u64 = !Self::Y_MASK;
-
 	const  LOWEST_H_VAL: u64 = 134217728;
 	const HIGHEST_H_VAL: u64 = 134217727;
+	// Z values are compared in place, without shifting them down first.
+	const  LOWEST_Z_VAL: u64 = Self::LOWEST_H_VAL << 28;
+	const HIGHEST_Z_VAL: u64 = Self::HIGHEST_H_VAL << 28;
+
+	/// The lowest horizontal chunk coordinate. Horizontal coordinates are stored in 28 bits, and wrap around outside
+	/// of `MIN_H..=MAX_H`.
+	pub const MIN_H: i32 = -134217728;
+	/// The highest horizontal chunk coordinate.
+	pub const MAX_H: i32 = 134217727;
 
-	pub fn new(x: i32, y: u8, z: i32) -> Self {
+	pub fn new(x: i32, y: i8, z: i32) -> Self {
 		let (x, y, z) = (
 			(x as u64) & MAX_U28,
-			(y as u64) & 15,
+			(y as u8) as u64,
 			(z as u64) & MAX_U28
 		);
 
@@ -94,7 +119,16 @@ impl GlobalChunkPosition {
 		)
 	}
 
-	pub fn from_column(column: GlobalColumnPosition, y: u8) -> Self {
+	/// Combines the position of a sector with the position of a chunk inside it.
+	pub fn combine(global: GlobalSectorPosition, local: CubePosition) -> Self {
+		Self::new (
+			(global.x() << 4) + (local.x() as i32),
+			(global.y() << 4) + (local.y() as i8),
+			(global.z() << 4) + (local.z() as i32)
+		)
+	}
+
+	pub fn from_column(column: GlobalColumnPosition, y: i8) -> Self {
 		Self::new(column.x(), y, column.z())
 	}
 
@@ -103,10 +137,8 @@ impl GlobalChunkPosition {
 		(unsigned << 4) >> 4
 	}
 
-	pub fn y(&self) -> u8 {
-		let value = (self.0 >> 56) & 15;
-
-		value as u8
+	pub fn y(&self) -> i8 {
+		(self.0 >> 56) as u8 as i8
 	}
 
 	pub fn z(&self) -> i32 {
@@ -124,11 +156,11 @@ impl GlobalChunkPosition {
 			 self.0        & 15
 		);
 
-		CubePosition::new(x as u8, self.y(), z as u8)
+		CubePosition::new(x as u8, (self.y() & 15) as u8, z as u8)
 	}
 
 	pub fn global_sector(&self) -> GlobalSectorPosition {
-		GlobalSectorPosition::new(self.x() >> 4, self.z() >> 4)
+		GlobalSectorPosition::new(self.x() >> 4, self.z() >> 4).with_y(self.y() >> 4)
 	}
 
 	pub fn plus_x(&self) -> Option<GlobalChunkPosition> {
@@ -158,7 +190,7 @@ impl GlobalChunkPosition {
 	pub fn plus_z(&self) -> Option<GlobalChunkPosition> {
 		let (keep, z_val) = (self.0 & Self::NOT_Z_MASK, self.0 & Self::Z_MASK);
 
-		if z_val != Self::HIGHEST_H_VAL {
+		if z_val != Self::HIGHEST_Z_VAL {
 			let z_val = z_val.wrapping_add(1 << 28) & Self::Z_MASK;
 
 			Some(GlobalChunkPosition(keep | z_val))
@@ -170,7 +202,7 @@ impl GlobalChunkPosition {
 	pub fn minus_z(&self) -> Option<GlobalChunkPosition> {
 		let (keep, z_val) = (self.0 & Self::NOT_Z_MASK, self.0 & Self::Z_MASK);
 
-		if z_val != Self::LOWEST_H_VAL {
+		if z_val != Self::LOWEST_Z_VAL {
 			let z_val = z_val.wrapping_sub(1 << 28) & Self::Z_MASK;
 
 			Some(GlobalChunkPosition(keep | z_val))
@@ -179,28 +211,24 @@ impl GlobalChunkPosition {
 		}
 	}
 
+	/// Returns the chunk above this one. Note that this does not check against the `Height` of a world.
 	pub fn plus_y(&self) -> Option<GlobalChunkPosition> {
-		let (keep, y_val) = (self.0 & Self::NOT_Y_MASK, self.0 & Self::Y_MASK);
-
-		if y_val != 15 << 56 {
-			let y_val = y_val.wrapping_add(1 << 56) & Self::Y_MASK;
-
-			Some(GlobalChunkPosition(keep | y_val))
-		} else {
-			None
-		}
+		self.y().checked_add(1).map(|y| GlobalChunkPosition((self.0 & MAX_U56) | ((y as u8 as u64) << 56)))
 	}
 
+	/// Returns the chunk below this one. Note that this does not check against the `Height` of a world.
 	pub fn minus_y(&self) -> Option<GlobalChunkPosition> {
-		let (keep, y_val) = (self.0 & Self::NOT_Y_MASK, self.0 & Self::Y_MASK);
-
-		if y_val != 0 {
-			let y_val = y_val.wrapping_sub(1 << 56) & Self::Y_MASK;
+		self.y().checked_sub(1).map(|y| GlobalChunkPosition((self.0 & MAX_U56) | ((y as u8 as u64) << 56)))
+	}
 
-			Some(GlobalChunkPosition(keep | y_val))
-		} else {
-			None
-		}
+	/// Offsets this position by the specified amount of chunks, returning None instead of wrapping around if the
+	/// result is outside of the representable range.
+	pub fn checked_offset(&self, x: i32, y: i8, z: i32) -> Option<GlobalChunkPosition> {
+		Some(GlobalChunkPosition::new(
+			checked_h(self.x(), x, Self::MIN_H, Self::MAX_H)?,
+			self.y().checked_add(y)?,
+			checked_h(self.z(), z, Self::MIN_H, Self::MAX_H)?
+		))
 	}
 }
 
@@ -221,6 +249,12 @@ impl Debug for GlobalChunkPosition {
 pub struct GlobalColumnPosition(u64);
 
 impl GlobalColumnPosition {
+	/// The lowest horizontal column coordinate. Coordinates are stored in 28 bits, and wrap around outside of
+	/// `MIN_H..=MAX_H`.
+	pub const MIN_H: i32 = GlobalChunkPosition::MIN_H;
+	/// The highest horizontal column coordinate.
+	pub const MAX_H: i32 = GlobalChunkPosition::MAX_H;
+
 	pub fn new(x: i32, z: i32) -> Self {
 		let (x, z) = (
 			(x as u64) & MAX_U28,
@@ -258,9 +292,20 @@ impl GlobalColumnPosition {
 		LayerPosition::new(x as u8, z as u8)
 	}
 
+	/// Returns the position of the sector containing this column at sector Y=0. Columns span every
+	/// sector in the vertical stack, use `GlobalSectorPosition::with_y` to reach the others.
 	pub fn global_sector(&self) -> GlobalSectorPosition {
 		GlobalSectorPosition::new(self.x() >> 4, self.z() >> 4)
 	}
+
+	/// Offsets this position by the specified amount of columns, returning None instead of wrapping around if the
+	/// result is outside of the representable range.
+	pub fn checked_offset(&self, x: i32, z: i32) -> Option<GlobalColumnPosition> {
+		Some(GlobalColumnPosition::new(
+			checked_h(self.x(), x, Self::MIN_H, Self::MAX_H)?,
+			checked_h(self.z(), z, Self::MIN_H, Self::MAX_H)?
+		))
+	}
 }
 
 impl Display for GlobalColumnPosition {
@@ -275,16 +320,30 @@ impl Debug for GlobalColumnPosition {
 	}
 }
 
-// This is split up into 3 u16s so that this takes up 48 bits (6 bytes) instead of 64 bits (8 bytes).
+// The horizontal components are split up into 3 u16s so that this takes up 64 bits (8 bytes) instead of 96 bits (12 bytes).
+// Sectors are 16 chunks tall, so worlds taller than 256 blocks or extending below Y=0 stack several sectors vertically.
 #[derive(Eq, PartialEq, Copy, Clone, Hash)]
 pub struct GlobalSectorPosition {
 	x_high: u16,
 	z_high: u16,
 	// X << 8 | Z
-	zx_low: u16
+	zx_low: u16,
+	y: i8
 }
 
 impl GlobalSectorPosition {
+	/// The lowest horizontal sector coordinate. Horizontal coordinates are stored in 24 bits, and wrap around outside
+	/// of `MIN_H..=MAX_H`.
+	pub const MIN_H: i32 = -8388608;
+	/// The highest horizontal sector coordinate.
+	pub const MAX_H: i32 = 8388607;
+
+	/// The lowest sector Y value that contains chunks.
+	pub const MIN_Y: i8 = -8;
+	/// The highest sector Y value that contains chunks.
+	pub const MAX_Y: i8 = 7;
+
+	/// Creates a new GlobalSectorPosition at sector Y=0.
 	pub fn new(x: i32, z: i32) -> Self {
 		let x_low = (x & 255) as u16;
 		let z_low = (z & 255) as u16;
@@ -292,10 +351,19 @@ impl GlobalSectorPosition {
 		GlobalSectorPosition {
 			x_high: (x >> 8) as u16,
 			z_high: (z >> 8) as u16,
-			zx_low: (z_low << 8) | x_low
+			zx_low: (z_low << 8) | x_low,
+			y: 0
 		}
 	}
 
+	/// Replaces the Y component with the specified value. Sector Y values range from -8 to 7, use `checked_offset` for
+	/// values that might be outside of this range.
+	pub fn with_y(&self, y: i8) -> Self {
+		debug_assert!((Self::MIN_Y..=Self::MAX_Y).contains(&y), "Sector Y {} is outside of the range of sectors", y);
+
+		GlobalSectorPosition { y, ..*self }
+	}
+
 	pub fn x(&self) -> i32 {
 		let high = (self.x_high as u32) << 8;
 		let low  = (self.zx_low as u32) & 255;
@@ -313,17 +381,32 @@ impl GlobalSectorPosition {
 
 		(unsigned << 8) >> 8
 	}
+
+	pub fn y(&self) -> i8 {
+		self.y
+	}
+
+	/// Offsets this position by the specified amount of sectors, returning None if the result is outside of the
+	/// representable range or outside of `MIN_Y..=MAX_Y`.
+	pub fn checked_offset(&self, x: i32, y: i8, z: i32) -> Option<GlobalSectorPosition> {
+		let y = self.y.checked_add(y).filter(|y| (Self::MIN_Y..=Self::MAX_Y).contains(y))?;
+
+		Some(GlobalSectorPosition::new(
+			checked_h(self.x(), x, Self::MIN_H, Self::MAX_H)?,
+			checked_h(self.z(), z, Self::MIN_H, Self::MAX_H)?
+		).with_y(y))
+	}
 }
 
 impl Display for GlobalSectorPosition {
 	fn fmt(&self, f: &mut Formatter) -> Result {
-		write!(f, "({}, {})", self.x(), self.z())
+		write!(f, "({}, {}, {})", self.x(), self.y(), self.z())
 	}
 }
 
 impl Debug for GlobalSectorPosition {
 	fn fmt(&self, f: &mut Formatter) -> Result {
-		write!(f, "GlobalSectorPosition {{ x: {}, z: {} }}", self.x(), self.z())
+		write!(f, "GlobalSectorPosition {{ x: {}, y: {}, z: {} }}", self.x(), self.y(), self.z())
 	}
 }
 
@@ -364,10 +447,12 @@ mod test {
 		// Y
 
 		assert_eq!(GlobalChunkPosition::new(0, 0, 0).plus_y(), Some(GlobalChunkPosition::new(0, 1, 0)));
-		assert_eq!(GlobalChunkPosition::new(-1, 15, -1).plus_y(), None);
+		assert_eq!(GlobalChunkPosition::new(-1, 15, -1).plus_y(), Some(GlobalChunkPosition::new(-1, 16, -1)));
+		assert_eq!(GlobalChunkPosition::new(-1, 127, -1).plus_y(), None);
 
-		assert_eq!(GlobalChunkPosition::new(0, 0, 0).minus_y(), None);
+		assert_eq!(GlobalChunkPosition::new(0, 0, 0).minus_y(), Some(GlobalChunkPosition::new(0, -1, 0)));
 		assert_eq!(GlobalChunkPosition::new(-1, 7, -1).minus_y(), Some(GlobalChunkPosition::new(-1, 6, -1)));
+		assert_eq!(GlobalChunkPosition::new(-1, -128, -1).minus_y(), None);
 
 		// Z
 
@@ -378,6 +463,51 @@ mod test {
 		assert_eq!(GlobalChunkPosition::new(-1, 7, -1).minus_z(), Some(GlobalChunkPosition::new(-1, 7, -2)));
 	}
 
+	#[test]
+	fn test_global_chunk_edges() {
+		let (min, max) = (GlobalChunkPosition::MIN_H, GlobalChunkPosition::MAX_H);
+
+		assert_eq!(GlobalChunkPosition::new(max, 0, 0).plus_x(), None);
+		assert_eq!(GlobalChunkPosition::new(min, 0, 0).minus_x(), None);
+		assert_eq!(GlobalChunkPosition::new(max - 1, 0, 0).plus_x(), Some(GlobalChunkPosition::new(max, 0, 0)));
+		assert_eq!(GlobalChunkPosition::new(min + 1, 0, 0).minus_x(), Some(GlobalChunkPosition::new(min, 0, 0)));
+
+		// The Z edges used to be compared against unshifted values, so plus_z and minus_z wrapped around to the
+		// opposite edge instead of returning None. Check them with other X and Y values in the packed position too.
+		for &(x, y) in [(0, 0), (min, -128), (max, 127), (-1, 7)].iter() {
+			assert_eq!(GlobalChunkPosition::new(x, y, max).plus_z(), None);
+			assert_eq!(GlobalChunkPosition::new(x, y, min).minus_z(), None);
+			assert_eq!(GlobalChunkPosition::new(x, y, max - 1).plus_z(), Some(GlobalChunkPosition::new(x, y, max)));
+			assert_eq!(GlobalChunkPosition::new(x, y, min + 1).minus_z(), Some(GlobalChunkPosition::new(x, y, min)));
+		}
+	}
+
+	#[test]
+	fn test_checked_offset() {
+		let (min, max) = (GlobalColumnPosition::MIN_H, GlobalColumnPosition::MAX_H);
+
+		assert_eq!(GlobalColumnPosition::new(max - 1, 0).checked_offset(1, 0), Some(GlobalColumnPosition::new(max, 0)));
+		assert_eq!(GlobalColumnPosition::new(max, 0).checked_offset(1, 0), None);
+		assert_eq!(GlobalColumnPosition::new(0, min).checked_offset(0, -1), None);
+		assert_eq!(GlobalColumnPosition::new(0, 0).checked_offset(i32::MAX, 0), None);
+
+		assert_eq!(GlobalChunkPosition::new(0, 127, 0).checked_offset(0, 1, 0), None);
+		assert_eq!(GlobalChunkPosition::new(-5, 3, 5).checked_offset(5, -4, -6), Some(GlobalChunkPosition::new(0, -1, -1)));
+
+		let sector = GlobalSectorPosition::new(GlobalSectorPosition::MAX_H, 0).with_y(GlobalSectorPosition::MAX_Y);
+
+		assert_eq!(sector.checked_offset(1, 0, 0), None);
+		assert_eq!(sector.checked_offset(0, 1, 0), None);
+		assert_eq!(sector.checked_offset(-1, -1, 3), Some(GlobalSectorPosition::new(GlobalSectorPosition::MAX_H - 1, 3).with_y(6)));
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic]
+	fn test_sector_y_out_of_range() {
+		GlobalSectorPosition::new(0, 0).with_y(GlobalSectorPosition::MAX_Y + 1);
+	}
+
 	#[test]
 	fn test_conversion() {
 		let block = GlobalPosition::new(213179, 44, 952109);
@@ -394,4 +524,30 @@ mod test {
 		assert_eq!(local_column, LayerPosition::new(11, 2));
 		assert_eq!(global_sector, GlobalSectorPosition::new(832, 3719));
 	}
+
+	#[test]
+	fn test_negative_y() {
+		let block = GlobalPosition::new(-3, -61, 17);
+
+		assert_eq!(block.local_block(), CubePosition::new(13, 3, 1));
+		assert_eq!(block.global_chunk(), GlobalChunkPosition::new(-1, -4, 1));
+
+		let chunk = block.global_chunk();
+
+		assert_eq!((chunk.x(), chunk.y(), chunk.z()), (-1, -4, 1));
+		assert_eq!(chunk.local_chunk(), CubePosition::new(15, 12, 1));
+		assert_eq!(chunk.global_sector(), GlobalSectorPosition::new(-1, 0).with_y(-1));
+		assert_eq!(chunk.column(), GlobalColumnPosition::new(-1, 1));
+
+		let high = GlobalPosition::new(0, 319, 0).global_chunk();
+
+		assert_eq!(high.y(), 19);
+		assert_eq!(high.local_chunk(), CubePosition::new(0, 3, 0));
+		assert_eq!(high.global_sector().y(), 1);
+
+		assert_eq!(GlobalPosition::new(0, 2047, 0).checked_global_chunk().map(|chunk| chunk.y()), Some(127));
+		assert_eq!(GlobalPosition::new(0, -2048, 0).checked_global_chunk().map(|chunk| chunk.y()), Some(-128));
+		assert_eq!(GlobalPosition::new(0, 2048, 0).checked_global_chunk(), None);
+		assert_eq!(GlobalPosition::new(0, -2049, 0).checked_global_chunk(), None);
+	}
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+use std::fmt::{Display, Formatter, Result};
+use std::ops::Range;
+use std::cmp;
+
+/// The vertical extent of a world, as a range of 16 block high sections.
+///
+/// Section Y values are signed, so worlds may extend below Y=0. For example, the classic
+/// 0..256 world is 16 sections starting at section 0, while a -64..320 world is 24 sections
+/// starting at section -4. Column positions and views are relative to the lowest section.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Height {
+	min_section: i8,
+	sections: u8
+}
+
+impl Height {
+	/// The 0..256 extent used by Minecraft before 1.18.
+	pub const CLASSIC: Height = Height { min_section: 0, sections: 16 };
+
+	/// Creates a new extent covering `sections` sections, starting at `min_section`.
+	/// ### Panics
+	/// If the extent is empty, or if the highest section would not fit in an i8.
+	pub fn new(min_section: i8, sections: u8) -> Self {
+		assert!(sections > 0, "A world must have at least one section");
+		assert!((min_section as i16) + (sections as i16) <= 128, "Height extends above the highest representable section");
+
+		Height { min_section, sections }
+	}
+
+	/// Creates a new extent from a block range. Both ends must be multiples of 16.
+	pub fn from_blocks(min_y: i32, max_y: i32) -> Self {
+		assert!(min_y % 16 == 0 && max_y % 16 == 0, "Height must be aligned to section boundaries");
+		assert!(max_y > min_y, "A world must have at least one section");
+
+		Height::new((min_y >> 4) as i8, ((max_y - min_y) >> 4) as u8)
+	}
+
+	/// Returns the lowest section Y.
+	pub fn min_section(&self) -> i8 {
+		self.min_section
+	}
+
+	/// Returns the highest section Y, inclusive.
+	pub fn max_section(&self) -> i8 {
+		(self.min_section as i16 + self.sections as i16 - 1) as i8
+	}
+
+	/// Returns the amount of sections.
+	pub fn sections(&self) -> u8 {
+		self.sections
+	}
+
+	/// Returns the lowest block Y, inclusive.
+	pub fn min_y(&self) -> i32 {
+		(self.min_section as i32) * 16
+	}
+
+	/// Returns the highest block Y, exclusive.
+	pub fn max_y(&self) -> i32 {
+		self.min_y() + (self.sections as i32) * 16
+	}
+
+	/// Returns the amount of blocks in a column.
+	pub fn blocks(&self) -> u16 {
+		(self.sections as u16) * 16
+	}
+
+	pub fn contains_section(&self, section_y: i8) -> bool {
+		section_y >= self.min_section && section_y <= self.max_section()
+	}
+
+	pub fn contains_y(&self, y: i32) -> bool {
+		y >= self.min_y() && y < self.max_y()
+	}
+
+	/// Returns the index of the section relative to the lowest section, if it is in this extent.
+	pub fn section_index(&self, section_y: i8) -> Option<u8> {
+		if self.contains_section(section_y) {
+			Some((section_y as i16 - self.min_section as i16) as u8)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the section Y at the specified index. The opposite of `section_index`.
+	pub fn section_y(&self, index: u8) -> i8 {
+		assert!(index < self.sections, "Section index out of bounds");
+
+		(self.min_section as i16 + index as i16) as i8
+	}
+
+	/// Returns the range of sector Y values that contain at least one section of this extent.
+	pub fn sectors(&self) -> Range<i8> {
+		(self.min_section >> 4)..((self.max_section() >> 4) + 1)
+	}
+
+	/// Returns the range of layers within the sector at `sector_y` that are in this extent.
+	/// Only the top and bottom sectors may be partially outside of the extent.
+	pub fn sector_layers(&self, sector_y: i8) -> Range<u8> {
+		let base = (sector_y as i16) * 16;
+		let start = cmp::max(self.min_section as i16 - base, 0);
+		let end = cmp::min(self.max_section() as i16 + 1 - base, 16);
+
+		if start >= end {
+			0..0
+		} else {
+			(start as u8)..(end as u8)
+		}
+	}
+}
+
+impl Default for Height {
+	fn default() -> Self {
+		Height::CLASSIC
+	}
+}
+
+impl Display for Height {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "{}..{}", self.min_y(), self.max_y())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::position::Height;
+
+	#[test]
+	fn test_height() {
+		let classic = Height::CLASSIC;
+
+		assert_eq!((classic.min_y(), classic.max_y()), (0, 256));
+		assert_eq!(classic.sectors(), 0..1);
+		assert_eq!(classic.section_index(15), Some(15));
+		assert_eq!(classic.section_index(16), None);
+
+		let extended = Height::from_blocks(-64, 320);
+
+		assert_eq!((extended.min_section(), extended.max_section(), extended.sections()), (-4, 19, 24));
+		assert_eq!(extended.sectors(), -1..2);
+		assert_eq!(extended.sector_layers(-1), 12..16);
+		assert_eq!(extended.sector_layers(0), 0..16);
+		assert_eq!(extended.sector_layers(1), 0..4);
+		assert_eq!(extended.sector_layers(2), 0..0);
+		assert_eq!(extended.section_index(-4), Some(0));
+		assert_eq!(extended.section_index(-5), None);
+		assert_eq!(extended.section_y(23), 19);
+		assert!(extended.contains_y(-64) && !extended.contains_y(320));
+
+		let full = Height::new(-128, 255);
+		assert_eq!(full.max_section(), 126);
+		assert_eq!(full.sectors(), -8..8);
+	}
+}
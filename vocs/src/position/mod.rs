@@ -2,18 +2,27 @@ mod cube;
 mod layer;
 mod column;
 mod quad;
+mod neighborhood;
 mod direction;
+mod height;
+mod cuboid;
+mod region;
 
 /// Global positioning to complement the local positions.
 ///
-/// Unlike vanilla, positioning has well defined limits. rs25 defines a Minecraft World as a sparse 4294967296 by 256 by 4294967296 volume of blocks.
-/// This can also be represented as a sparse 268435456 by 16 by 268435456 volume of chunks.
-/// Finally, it can be represented as a sparse 16777216 by 16777216 grid of sectors.
+/// Unlike vanilla, positioning has well defined limits. rs25 defines a Minecraft World as a sparse 4294967296 by 4096 by 4294967296 volume of blocks,
+/// from Y=-2048 to Y=2047. The part of this volume that a world actually uses is described by a `Height`.
+/// This can also be represented as a sparse 268435456 by 256 by 268435456 volume of chunks.
+/// Finally, it can be represented as a sparse 16777216 by 16 by 16777216 volume of sectors.
 mod global;
 
 pub use self::cube::CubePosition;
 pub use self::layer::LayerPosition;
 pub use self::column::ColumnPosition;
 pub use self::quad::QuadPosition;
+pub use self::neighborhood::NeighborhoodPosition;
 pub use self::direction::{Offset, Dir, Axis, StaticAxis, StaticDirection, dir};
+pub use self::height::Height;
+pub use self::cuboid::{Cuboid, CuboidChunks};
+pub use self::region::{Horizontal, Rect, RectIter, RectChunks, Circle, CircleIter, Spiral};
 pub use self::global::{GlobalPosition, GlobalChunkPosition, GlobalColumnPosition, GlobalSectorPosition};
\ No newline at end of file
@@ -0,0 +1,176 @@
+use crate::position::{ColumnPosition, QuadPosition, Offset, dir};
+
+/// A position within a `NeighborhoodMut`, relative to the -X, -Z corner of the neighborhood and the bottom of the world.
+/// Unlike the other local positions, this may lie outside of the neighborhood, so that edits reaching past its
+/// edges can still be addressed and handled according to the spill policy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct NeighborhoodPosition {
+	x: i32,
+	y: i32,
+	z: i32
+}
+
+impl NeighborhoodPosition {
+	pub fn new(x: i32, y: i32, z: i32) -> Self {
+		NeighborhoodPosition { x, y, z }
+	}
+
+	/// Converts a position in a quad, for decorators written against `QuadMut`. The quad occupies the -X, -Z corner of the neighborhood.
+	pub fn from_quad(quad: QuadPosition) -> Self {
+		NeighborhoodPosition::new(quad.x() as i32, quad.y() as i32, quad.z() as i32)
+	}
+
+	/// Returns the X component.
+	pub fn x(&self) -> i32 {
+		self.x
+	}
+
+	/// Returns the Y component.
+	pub fn y(&self) -> i32 {
+		self.y
+	}
+
+	/// Returns the Z component.
+	pub fn z(&self) -> i32 {
+		self.z
+	}
+
+	/// Returns the X index of the column containing this position. This may be negative, or past the width of the neighborhood.
+	pub fn column_x(&self) -> i32 {
+		self.x >> 4
+	}
+
+	/// Returns the Z index of the column containing this position. This may be negative, or past the depth of the neighborhood.
+	pub fn column_z(&self) -> i32 {
+		self.z >> 4
+	}
+
+	/// Returns the position within the column containing this position, or None if the Y component is out of range.
+	pub fn column(&self) -> Option<ColumnPosition> {
+		if self.y < 0 || self.y > ColumnPosition::MAX_Y as i32 {
+			None
+		} else {
+			Some(ColumnPosition::new((self.x & 15) as u8, self.y as u16, (self.z & 15) as u8))
+		}
+	}
+}
+
+impl Offset<(i8, i8, i8)> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, (x, y, z): (i8, i8, i8)) -> Option<Self> {
+		Some(NeighborhoodPosition::new(
+			self.x.checked_add(x as i32)?,
+			self.y.checked_add(y as i32)?,
+			self.z.checked_add(z as i32)?
+		))
+	}
+
+	fn offset_wrapping(self, (x, y, z): (i8, i8, i8)) -> Self {
+		NeighborhoodPosition::new(
+			self.x.wrapping_add(x as i32),
+			self.y.wrapping_add(y as i32),
+			self.z.wrapping_add(z as i32)
+		)
+	}
+
+	fn offset_spilling(self, offs: (i8, i8, i8)) -> Result<Self, ()> {
+		self.offset(offs).ok_or(())
+	}
+}
+
+impl Offset<dir::Up> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, _: dir::Up) -> Option<Self> {
+		self.offset((0, 1, 0))
+	}
+
+	fn offset_wrapping(self, _: dir::Up) -> Self {
+		self.offset_wrapping((0, 1, 0))
+	}
+
+	fn offset_spilling(self, _: dir::Up) -> Result<Self, ()> {
+		self.offset_spilling((0, 1, 0))
+	}
+}
+
+impl Offset<dir::Down> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, _: dir::Down) -> Option<Self> {
+		self.offset((0, -1, 0))
+	}
+
+	fn offset_wrapping(self, _: dir::Down) -> Self {
+		self.offset_wrapping((0, -1, 0))
+	}
+
+	fn offset_spilling(self, _: dir::Down) -> Result<Self, ()> {
+		self.offset_spilling((0, -1, 0))
+	}
+}
+
+impl Offset<dir::PlusX> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, _: dir::PlusX) -> Option<Self> {
+		self.offset((1, 0, 0))
+	}
+
+	fn offset_wrapping(self, _: dir::PlusX) -> Self {
+		self.offset_wrapping((1, 0, 0))
+	}
+
+	fn offset_spilling(self, _: dir::PlusX) -> Result<Self, ()> {
+		self.offset_spilling((1, 0, 0))
+	}
+}
+
+impl Offset<dir::MinusX> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, _: dir::MinusX) -> Option<Self> {
+		self.offset((-1, 0, 0))
+	}
+
+	fn offset_wrapping(self, _: dir::MinusX) -> Self {
+		self.offset_wrapping((-1, 0, 0))
+	}
+
+	fn offset_spilling(self, _: dir::MinusX) -> Result<Self, ()> {
+		self.offset_spilling((-1, 0, 0))
+	}
+}
+
+impl Offset<dir::PlusZ> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, _: dir::PlusZ) -> Option<Self> {
+		self.offset((0, 0, 1))
+	}
+
+	fn offset_wrapping(self, _: dir::PlusZ) -> Self {
+		self.offset_wrapping((0, 0, 1))
+	}
+
+	fn offset_spilling(self, _: dir::PlusZ) -> Result<Self, ()> {
+		self.offset_spilling((0, 0, 1))
+	}
+}
+
+impl Offset<dir::MinusZ> for NeighborhoodPosition {
+	type Spill = ();
+
+	fn offset(self, _: dir::MinusZ) -> Option<Self> {
+		self.offset((0, 0, -1))
+	}
+
+	fn offset_wrapping(self, _: dir::MinusZ) -> Self {
+		self.offset_wrapping((0, 0, -1))
+	}
+
+	fn offset_spilling(self, _: dir::MinusZ) -> Result<Self, ()> {
+		self.offset_spilling((0, 0, -1))
+	}
+}
@@ -8,7 +8,7 @@ impl QuadPosition {
 	/// Creates a new QuadPosition from the X, Y, and Z components.
 	/// ### Out of bounds behavior
 	/// If the arguments are out of bounds, then they are truncated.
-	pub fn new(x: u8, y: u8, z: u8) -> Self {
+	pub fn new(x: u8, y: u16, z: u8) -> Self {
 		let q = ((x >> 4) & 1) | ((z >> 3) & 2);
 
 		QuadPosition(ColumnPosition::new(x, y, z), q)
@@ -31,7 +31,7 @@ impl QuadPosition {
 	}
 
 	/// Returns the Y component.
-	pub fn y(&self) -> u8 {
+	pub fn y(&self) -> u16 {
 		self.0.y()
 	}
 
@@ -65,24 +65,24 @@ impl Offset<(i8, i8, i8)> for QuadPosition {
 
 	fn offset(self, (x, y, z): (i8, i8, i8)) -> Option<Self> {
 		let x = (self.x() as i16) + (x as i16);
-		let y = (self.y() as i16) + (y as i16);
+		let y = (self.y() as i32) + (y as i32);
 		let z = (self.z() as i16) + (z as i16);
 
-		if x > 31 || y > 255 || z > 31 || x < 0 || y < 0 || z < 0 {
+		if x > 31 || y > (ColumnPosition::MAX_Y as i32) || z > 31 || x < 0 || y < 0 || z < 0 {
 			None
 		} else {
-			Some(QuadPosition::new(x as u8, y as u8, z as u8))
+			Some(QuadPosition::new(x as u8, y as u16, z as u8))
 		}
 	}
 
 	fn offset_wrapping(self, (x, y, z): (i8, i8, i8)) -> Self {
 		let x = (self.x() as i16) + (x as i16);
-		let y = (self.y() as i16) + (y as i16);
+		let y = (self.y() as i32) + (y as i32);
 		let z = (self.z() as i16) + (z as i16);
 
 		let (x, y, z) = (
 			(x as u8) & 31,
-			(y as u8),
+			(y as u16) & ColumnPosition::MAX_Y,
 			(z as u8) & 31
 		);
 
@@ -0,0 +1,435 @@
+use crate::position::{GlobalChunkPosition, GlobalColumnPosition, GlobalSectorPosition, Height, LayerPosition};
+use std::cmp;
+
+/// A global position on the horizontal plane, such as a column or a sector. These can be grouped into rectangles,
+/// circles, and spirals.
+pub trait Horizontal: Copy {
+	/// The lowest coordinate on either axis.
+	const MIN_H: i32;
+	/// The highest coordinate on either axis.
+	const MAX_H: i32;
+
+	/// Returns the X and Z coordinates of this position.
+	fn horizontal(&self) -> (i32, i32);
+
+	/// Creates a position from X and Z coordinates within `MIN_H..=MAX_H`.
+	fn from_horizontal(x: i32, z: i32) -> Self;
+
+	/// Creates a position from coordinates that may be outside of `MIN_H..=MAX_H`, returning None if they are.
+	fn checked_from_horizontal(x: i64, z: i64) -> Option<Self> {
+		let range = (Self::MIN_H as i64)..=(Self::MAX_H as i64);
+
+		if range.contains(&x) && range.contains(&z) {
+			Some(Self::from_horizontal(x as i32, z as i32))
+		} else {
+			None
+		}
+	}
+}
+
+impl Horizontal for GlobalColumnPosition {
+	const MIN_H: i32 = GlobalColumnPosition::MIN_H;
+	const MAX_H: i32 = GlobalColumnPosition::MAX_H;
+
+	fn horizontal(&self) -> (i32, i32) {
+		(self.x(), self.z())
+	}
+
+	fn from_horizontal(x: i32, z: i32) -> Self {
+		GlobalColumnPosition::new(x, z)
+	}
+}
+
+/// Sectors are treated as vertical stacks, and positions are created at sector Y=0.
+impl Horizontal for GlobalSectorPosition {
+	const MIN_H: i32 = GlobalSectorPosition::MIN_H;
+	const MAX_H: i32 = GlobalSectorPosition::MAX_H;
+
+	fn horizontal(&self) -> (i32, i32) {
+		(self.x(), self.z())
+	}
+
+	fn from_horizontal(x: i32, z: i32) -> Self {
+		GlobalSectorPosition::new(x, z)
+	}
+}
+
+/// An axis aligned rectangle of horizontal positions. Both corners are inclusive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect<P> {
+	min: P,
+	max: P
+}
+
+impl<P> Rect<P> where P: Horizontal {
+	/// Creates a rectangle spanning both corners, which may be passed in any order.
+	pub fn new(a: P, b: P) -> Self {
+		let ((a_x, a_z), (b_x, b_z)) = (a.horizontal(), b.horizontal());
+
+		Rect {
+			min: P::from_horizontal(cmp::min(a_x, b_x), cmp::min(a_z, b_z)),
+			max: P::from_horizontal(cmp::max(a_x, b_x), cmp::max(a_z, b_z))
+		}
+	}
+
+	/// Creates a square containing every position within `radius` of the center along both axes.
+	/// The square is clipped to the edge of the world.
+	pub fn around(center: P, radius: u32) -> Self {
+		let (x, z) = center.horizontal();
+		let clamp = |value: i64| cmp::max(cmp::min(value, P::MAX_H as i64), P::MIN_H as i64) as i32;
+		let radius = radius as i64;
+
+		Rect {
+			min: P::from_horizontal(clamp(x as i64 - radius), clamp(z as i64 - radius)),
+			max: P::from_horizontal(clamp(x as i64 + radius), clamp(z as i64 + radius))
+		}
+	}
+
+	/// Returns the corner with the lowest coordinates.
+	pub fn min(&self) -> P {
+		self.min
+	}
+
+	/// Returns the corner with the highest coordinates.
+	pub fn max(&self) -> P {
+		self.max
+	}
+
+	pub fn size_x(&self) -> u32 {
+		(self.max.horizontal().0 - self.min.horizontal().0) as u32 + 1
+	}
+
+	pub fn size_z(&self) -> u32 {
+		(self.max.horizontal().1 - self.min.horizontal().1) as u32 + 1
+	}
+
+	/// Returns the amount of positions in the rectangle.
+	pub fn area(&self) -> u64 {
+		(self.size_x() as u64) * (self.size_z() as u64)
+	}
+
+	pub fn contains(&self, position: P) -> bool {
+		let ((x, z), (min_x, min_z), (max_x, max_z)) = (position.horizontal(), self.min.horizontal(), self.max.horizontal());
+
+		x >= min_x && x <= max_x && z >= min_z && z <= max_z
+	}
+
+	/// Iterates over every position in the rectangle, in ZX order.
+	pub fn iter(&self) -> RectIter<P> {
+		RectIter {
+			rect: *self,
+			next: Some(self.min.horizontal())
+		}
+	}
+}
+
+impl Rect<GlobalColumnPosition> {
+	/// Returns the sectors that contain at least one column of this rectangle, at sector Y=0.
+	pub fn sectors(&self) -> Rect<GlobalSectorPosition> {
+		Rect {
+			min: self.min.global_sector(),
+			max: self.max.global_sector()
+		}
+	}
+
+	/// Iterates over every chunk within the height in the columns of this rectangle. Columns are visited in ZX order,
+	/// and the chunks of each column from bottom to top.
+	pub fn chunks(&self, height: Height) -> RectChunks {
+		RectChunks {
+			columns: self.iter(),
+			column: None,
+			height,
+			index: 0
+		}
+	}
+}
+
+impl Rect<GlobalSectorPosition> {
+	/// Returns the columns contained in the sectors of this rectangle.
+	pub fn columns(&self) -> Rect<GlobalColumnPosition> {
+		Rect {
+			min: GlobalColumnPosition::combine(self.min, LayerPosition::new(0, 0)),
+			max: GlobalColumnPosition::combine(self.max, LayerPosition::new(15, 15))
+		}
+	}
+}
+
+impl<P> IntoIterator for Rect<P> where P: Horizontal {
+	type Item = P;
+	type IntoIter = RectIter<P>;
+
+	fn into_iter(self) -> RectIter<P> {
+		self.iter()
+	}
+}
+
+/// Iterator over the positions in a rectangle, in ZX order. See `Rect::iter`.
+#[derive(Debug, Clone)]
+pub struct RectIter<P> {
+	rect: Rect<P>,
+	next: Option<(i32, i32)>
+}
+
+impl<P> Iterator for RectIter<P> where P: Horizontal {
+	type Item = P;
+
+	fn next(&mut self) -> Option<P> {
+		let (x, z) = self.next?;
+		let ((min_x, _), (max_x, max_z)) = (self.rect.min.horizontal(), self.rect.max.horizontal());
+
+		self.next = if x < max_x {
+			Some((x + 1, z))
+		} else if z < max_z {
+			Some((min_x, z + 1))
+		} else {
+			None
+		};
+
+		Some(P::from_horizontal(x, z))
+	}
+}
+
+/// Iterator over the chunks in the columns of a rectangle. See `Rect::chunks`.
+#[derive(Debug, Clone)]
+pub struct RectChunks {
+	columns: RectIter<GlobalColumnPosition>,
+	column: Option<GlobalColumnPosition>,
+	height: Height,
+	index: u8
+}
+
+impl Iterator for RectChunks {
+	type Item = GlobalChunkPosition;
+
+	fn next(&mut self) -> Option<GlobalChunkPosition> {
+		if self.height.sections() == 0 {
+			return None;
+		}
+
+		let column = match self.column {
+			Some(column) => column,
+			None => {
+				let column = self.columns.next()?;
+
+				self.column = Some(column);
+				self.index = 0;
+
+				column
+			}
+		};
+
+		let position = GlobalChunkPosition::from_column(column, self.height.section_y(self.index));
+
+		self.index += 1;
+
+		if self.index == self.height.sections() {
+			self.column = None;
+		}
+
+		Some(position)
+	}
+}
+
+/// A filled circle of horizontal positions, containing every position whose distance from the center is at most the
+/// radius.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Circle<P> {
+	center: P,
+	radius: u32
+}
+
+impl<P> Circle<P> where P: Horizontal {
+	pub fn new(center: P, radius: u32) -> Self {
+		Circle { center, radius }
+	}
+
+	pub fn center(&self) -> P {
+		self.center
+	}
+
+	pub fn radius(&self) -> u32 {
+		self.radius
+	}
+
+	pub fn contains(&self, position: P) -> bool {
+		let ((x, z), (center_x, center_z)) = (position.horizontal(), self.center.horizontal());
+		let (dx, dz) = ((x as i64 - center_x as i64), (z as i64 - center_z as i64));
+		let radius = self.radius as i64;
+
+		dx * dx + dz * dz <= radius * radius
+	}
+
+	/// Returns the smallest rectangle containing the circle, clipped to the edge of the world.
+	pub fn bounds(&self) -> Rect<P> {
+		Rect::around(self.center, self.radius)
+	}
+
+	/// Iterates over every position in the circle, in ZX order.
+	pub fn iter(&self) -> CircleIter<P> {
+		CircleIter {
+			circle: *self,
+			positions: self.bounds().iter()
+		}
+	}
+}
+
+impl<P> IntoIterator for Circle<P> where P: Horizontal {
+	type Item = P;
+	type IntoIter = CircleIter<P>;
+
+	fn into_iter(self) -> CircleIter<P> {
+		self.iter()
+	}
+}
+
+/// Iterator over the positions in a circle, in ZX order. See `Circle::iter`.
+#[derive(Debug, Clone)]
+pub struct CircleIter<P> {
+	circle: Circle<P>,
+	positions: RectIter<P>
+}
+
+impl<P> Iterator for CircleIter<P> where P: Horizontal {
+	type Item = P;
+
+	fn next(&mut self) -> Option<P> {
+		let circle = &self.circle;
+
+		self.positions.find(|&position| circle.contains(position))
+	}
+}
+
+/// Iterator over horizontal positions in an outward square spiral. The center comes first, followed by each ring of
+/// positions at increasing distance along either axis. This is the usual order for generating the area around a
+/// point, since the closest positions are finished first. Positions past the edge of the world are skipped.
+#[derive(Debug, Clone)]
+pub struct Spiral<P> {
+	center: P,
+	ring: u32,
+	index: u32,
+	max_ring: u32
+}
+
+impl<P> Spiral<P> where P: Horizontal {
+	/// Creates a spiral that ends after the ring at `radius`, visiting every position of `Rect::around(center, radius)`.
+	pub fn new(center: P, radius: u32) -> Self {
+		Spiral {
+			center,
+			ring: 0,
+			index: 0,
+			max_ring: cmp::min(radius, (P::MAX_H - P::MIN_H) as u32)
+		}
+	}
+
+	/// Creates a spiral that continues until it has covered the entire world.
+	pub fn unbounded(center: P) -> Self {
+		Spiral::new(center, u32::MAX)
+	}
+
+	/// Returns the offset of a position in a ring. Each ring starts next to the +X, -Z corner and runs counterclockwise
+	/// around the center, with 2 * ring positions on each side.
+	fn offset(ring: i64, index: i64) -> (i64, i64) {
+		let (side, step) = (index / (2 * ring), index % (2 * ring));
+
+		match side {
+			0 => (ring, -ring + 1 + step),
+			1 => (ring - 1 - step, ring),
+			2 => (-ring, ring - 1 - step),
+			_ => (-ring + 1 + step, -ring)
+		}
+	}
+}
+
+impl<P> Iterator for Spiral<P> where P: Horizontal {
+	type Item = P;
+
+	fn next(&mut self) -> Option<P> {
+		let (center_x, center_z) = self.center.horizontal();
+		let (center_x, center_z) = (center_x as i64, center_z as i64);
+		let (min, max) = (P::MIN_H as i64, P::MAX_H as i64);
+
+		loop {
+			if self.ring > self.max_ring {
+				return None;
+			}
+
+			let ring = self.ring as i64;
+
+			// Once a ring lies outside the world on every side, so do all of the following rings.
+			if center_x - ring < min && center_x + ring > max && center_z - ring < min && center_z + ring > max {
+				self.ring = self.max_ring + 1;
+
+				return None;
+			}
+
+			let (dx, dz) = if ring == 0 { (0, 0) } else { Spiral::<P>::offset(ring, self.index as i64) };
+
+			self.index += 1;
+
+			if self.index >= 8 * self.ring {
+				self.ring += 1;
+				self.index = 0;
+			}
+
+			if let Some(position) = P::checked_from_horizontal(center_x + dx, center_z + dz) {
+				return Some(position);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::collections::HashSet;
+
+	#[test]
+	fn test_regions() {
+		let rect = Rect::new(GlobalColumnPosition::new(17, -1), GlobalColumnPosition::new(-2, 0));
+		let columns: Vec<GlobalColumnPosition> = rect.iter().collect();
+
+		assert_eq!(rect.area(), 40);
+		assert_eq!(columns.len(), 40);
+		assert_eq!(columns[0], GlobalColumnPosition::new(-2, -1));
+		assert_eq!(columns[1], GlobalColumnPosition::new(-1, -1));
+		assert_eq!(columns[39], GlobalColumnPosition::new(17, 0));
+
+		let sectors: Vec<GlobalSectorPosition> = rect.sectors().iter().collect();
+
+		assert_eq!(sectors, vec![
+			GlobalSectorPosition::new(-1, -1), GlobalSectorPosition::new(0, -1), GlobalSectorPosition::new(1, -1),
+			GlobalSectorPosition::new(-1, 0), GlobalSectorPosition::new(0, 0), GlobalSectorPosition::new(1, 0)
+		]);
+
+		assert_eq!(rect.sectors().columns().area(), 6 * 256);
+		assert_eq!(rect.chunks(Height::new(-4, 24)).count(), 40 * 24);
+		assert_eq!(rect.chunks(Height::new(-4, 24)).nth(24), Some(GlobalChunkPosition::new(-1, -4, -1)));
+
+		let circle = Circle::new(GlobalColumnPosition::new(5, 5), 2);
+
+		assert_eq!(circle.iter().count(), 13);
+		assert!(circle.contains(GlobalColumnPosition::new(7, 5)));
+		assert!(!circle.contains(GlobalColumnPosition::new(7, 7)));
+	}
+
+	#[test]
+	fn test_spiral() {
+		let center = GlobalSectorPosition::new(3, -3);
+		let positions: Vec<GlobalSectorPosition> = Spiral::new(center, 3).collect();
+
+		assert_eq!(positions.len(), 49);
+		assert_eq!(positions[0], center);
+		assert_eq!(positions.iter().cloned().collect::<HashSet<_>>().len(), 49);
+		assert!(positions.iter().all(|&position| Rect::around(center, 3).contains(position)));
+
+		let distance = |position: &GlobalSectorPosition| cmp::max((position.x() - 3).abs(), (position.z() + 3).abs());
+
+		assert!(positions.windows(2).all(|pair| distance(&pair[0]) <= distance(&pair[1])));
+
+		// Spirals are clipped at the edge of the world.
+		let corner = GlobalColumnPosition::new(GlobalColumnPosition::MAX_H, GlobalColumnPosition::MIN_H);
+
+		assert_eq!(Spiral::new(corner, 2).count(), 9);
+		assert_eq!(Rect::around(corner, 2).area(), 9);
+	}
+}
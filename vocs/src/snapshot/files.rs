@@ -0,0 +1,190 @@
+use crate::position::{GlobalSectorPosition, Height};
+use crate::snapshot::{invalid, Codec};
+use crate::world::sector::Sector;
+use crate::world::shared::{Packed, SharedSector, SharedWorld};
+use crate::world::world::World;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: [u8; 4] = *b"VOCS";
+const VERSION: u16 = 2;
+const EXTENSION: &str = "vcs";
+/// Appended to the path of a sector while it is being written, so that an interrupted save never leaves a partial file.
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+/// Returns the path of the file holding a sector, in the form `name.x.y.z.vcs`.
+pub fn sector_path(directory: &Path, name: &str, position: GlobalSectorPosition) -> PathBuf {
+	directory.join(format!("{}.{}.{}.{}.{}", name, position.x(), position.y(), position.z(), EXTENSION))
+}
+
+/// Parses a file name created by `sector_path`, returning None if it belongs to a different name or is not a snapshot.
+fn parse_sector_name(file_name: &str, name: &str) -> Option<GlobalSectorPosition> {
+	let coordinates = file_name.strip_prefix(name)?.strip_prefix('.')?.strip_suffix(EXTENSION)?.strip_suffix('.')?;
+	let mut parts = coordinates.split('.');
+
+	// Reject coordinates that would wrap around, instead of loading them as a different sector.
+	let horizontal = GlobalSectorPosition::MIN_H..=GlobalSectorPosition::MAX_H;
+	let vertical = GlobalSectorPosition::MIN_Y..=GlobalSectorPosition::MAX_Y;
+
+	let x = parts.next()?.parse().ok().filter(|x| horizontal.contains(x))?;
+	let y = parts.next()?.parse().ok().filter(|y| vertical.contains(y))?;
+	let z = parts.next()?.parse().ok().filter(|z| horizontal.contains(z))?;
+
+	if parts.next().is_some() {
+		return None;
+	}
+
+	Some(GlobalSectorPosition::new(x, z).with_y(y))
+}
+
+/// Writes the file next to its final path first, and then renames it into place.
+fn write_file<T>(path: &Path, height: Option<Height>, value: &T) -> io::Result<()> where T: Codec {
+	let mut temporary = path.as_os_str().to_owned();
+	temporary.push(".");
+	temporary.push(TEMPORARY_EXTENSION);
+
+	let temporary = PathBuf::from(temporary);
+	let mut out = BufWriter::new(File::create(&temporary)?);
+
+	out.write_all(&MAGIC)?;
+	VERSION.encode(&mut out)?;
+	height.encode(&mut out)?;
+	value.encode(&mut out)?;
+
+	out.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+
+	fs::rename(&temporary, path)
+}
+
+/// Returns the height stored in the header, along with the value.
+fn read_file<T>(path: &Path) -> io::Result<(Option<Height>, T)> where T: Codec {
+	let mut input = BufReader::new(File::open(path)?);
+	let mut magic = [0; 4];
+
+	input.read_exact(&mut magic)?;
+
+	if magic != MAGIC {
+		return Err(invalid("Not a vocs snapshot"));
+	}
+
+	if u16::decode(&mut input)? != VERSION {
+		return Err(invalid("Unsupported snapshot version"));
+	}
+
+	let height = Option::<Height>::decode(&mut input)?;
+
+	Ok((height, T::decode(&mut input)?))
+}
+
+/// Writes each sector to its own file in the directory, replacing existing files. Files of sectors with this name
+/// that are not in `sectors` are removed, so the directory holds exactly the saved sectors afterwards. Works with
+/// maps of per-column layers such as heightmaps or biomes, see `save_world` for worlds.
+pub fn save_sectors<'a, I, T>(directory: &Path, name: &str, sectors: I) -> io::Result<()> where I: IntoIterator<Item=(&'a GlobalSectorPosition, &'a T)>, T: 'a + Codec {
+	save(directory, name, None, sectors)
+}
+
+/// Saves the sectors of a world, along with its height. Load it with `load_world`.
+pub fn save_world<T>(directory: &Path, name: &str, world: &World<T>) -> io::Result<()> where T: Codec {
+	save(directory, name, Some(world.height()), world.sectors())
+}
+
+/// Shared world version of `save_world`. Chunks must not be locked while saving.
+pub fn save_shared_world<T>(directory: &Path, name: &str, world: &SharedWorld<T>) -> io::Result<()> where T: Packed, T::Unpacked: Codec {
+	save(directory, name, Some(world.height()), world.sectors())
+}
+
+fn save<'a, I, T>(directory: &Path, name: &str, height: Option<Height>, sectors: I) -> io::Result<()> where I: IntoIterator<Item=(&'a GlobalSectorPosition, &'a T)>, T: 'a + Codec {
+	fs::create_dir_all(directory)?;
+
+	let mut saved = HashSet::new();
+
+	for (&position, sector) in sectors {
+		write_file(&sector_path(directory, name, position), height, sector)?;
+		saved.insert(position);
+	}
+
+	// Remove sectors left over from earlier saves, otherwise they would be loaded along with the new ones.
+	for entry in fs::read_dir(directory)? {
+		let entry = entry?;
+
+		let stale = entry.file_name().to_str()
+			.and_then(|file_name| parse_sector_name(file_name, name))
+			.is_some_and(|position| !saved.contains(&position));
+
+		if stale {
+			fs::remove_file(entry.path())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Reads every sector with this name from the directory, ignoring the height stored with them.
+pub fn load_sectors<T>(directory: &Path, name: &str) -> io::Result<Vec<(GlobalSectorPosition, T)>> where T: Codec {
+	load(directory, name, None)
+}
+
+/// Reads the sectors, checking that each of them was saved with the expected height if there is one.
+fn load<T>(directory: &Path, name: &str, expected: Option<Height>) -> io::Result<Vec<(GlobalSectorPosition, T)>> where T: Codec {
+	let mut sectors = Vec::new();
+
+	for entry in fs::read_dir(directory)? {
+		let entry = entry?;
+
+		let position = match entry.file_name().to_str().and_then(|file_name| parse_sector_name(file_name, name)) {
+			Some(position) => position,
+			None => continue
+		};
+
+		let (height, sector) = read_file(&entry.path())?;
+
+		if expected.is_some() && height != expected {
+			return Err(invalid("Snapshot was saved with a different world height"));
+		}
+
+		sectors.push((position, sector));
+	}
+
+	Ok(sectors)
+}
+
+/// Reads a world saved with `save_world`. Fails if the world was saved with a different height.
+pub fn load_world<T>(directory: &Path, name: &str, height: Height) -> io::Result<World<T>> where T: Codec {
+	let mut world = World::with_height(height);
+
+	for (position, sector) in load::<Sector<T>>(directory, name, Some(height))? {
+		*world.get_or_create_sector_mut(position) = sector;
+	}
+
+	Ok(world)
+}
+
+/// Shared world version of `load_world`.
+pub fn load_shared_world<T>(directory: &Path, name: &str, height: Height) -> io::Result<SharedWorld<T>> where T: Packed, T::Unpacked: Codec {
+	let mut world = SharedWorld::with_height(height);
+
+	for (position, sector) in load::<SharedSector<T>>(directory, name, Some(height))? {
+		*world.get_or_create_sector_mut(position) = sector;
+	}
+
+	Ok(world)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_parse_sector_name() {
+		assert_eq!(parse_sector_name("blocks.-1.7.3.vcs", "blocks"), Some(GlobalSectorPosition::new(-1, 3).with_y(7)));
+		assert_eq!(parse_sector_name("blocks.-1.7.3.vcs", "light"), None);
+		assert_eq!(parse_sector_name("blocks.-1.7.3.vcs.tmp", "blocks"), None);
+
+		// Coordinates outside of the range of sectors are not snapshots, even if they fit the integer types.
+		assert_eq!(parse_sector_name("blocks.0.8.0.vcs", "blocks"), None);
+		assert_eq!(parse_sector_name("blocks.0.-9.0.vcs", "blocks"), None);
+		assert_eq!(parse_sector_name("blocks.8388608.0.0.vcs", "blocks"), None);
+	}
+}
@@ -0,0 +1,448 @@
+//! A compact native binary format for keeping worlds on disk between pipeline stages.
+//!
+//! Chunks are written as their palette along with the raw words of their `PackedStorage`, and loaded back
+//! without repacking. Values are little endian. The format is not meant to be stable across versions of `vocs`,
+//! use the Minecraft formats for long term storage.
+//!
+//! Block types, and any other values stored in the world, are pluggable through the `Codec` trait.
+
+mod files;
+
+pub use self::files::{load_sectors, load_shared_world, load_world, save_sectors, save_shared_world, save_world, sector_path};
+
+use crate::indexed::{Delta, IndexedStorage, Target};
+use crate::nibbles::{u4, NibbleCube};
+use crate::packed::{Layout, PackedIndex, PackedStorage};
+use crate::position::{CubePosition, Height, LayerPosition};
+use crate::unpacked::Layer;
+use crate::world::sector::Sector;
+use crate::world::shared::{Packed, SharedSector};
+use std::convert::TryInto;
+use std::io::{self, Error, ErrorKind, Read, Write};
+
+/// A value that can be written to and read from a snapshot.
+pub trait Codec: Sized {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write;
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read;
+}
+
+fn invalid(message: &'static str) -> Error {
+	Error::new(ErrorKind::InvalidData, message)
+}
+
+impl Codec for u8 {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		out.write_all(&self.to_le_bytes())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut bytes = [0; 1];
+		input.read_exact(&mut bytes)?;
+
+		Ok(u8::from_le_bytes(bytes))
+	}
+}
+
+impl Codec for u16 {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		out.write_all(&self.to_le_bytes())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut bytes = [0; 2];
+		input.read_exact(&mut bytes)?;
+
+		Ok(u16::from_le_bytes(bytes))
+	}
+}
+
+impl Codec for u32 {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		out.write_all(&self.to_le_bytes())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut bytes = [0; 4];
+		input.read_exact(&mut bytes)?;
+
+		Ok(u32::from_le_bytes(bytes))
+	}
+}
+
+impl Codec for u64 {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		out.write_all(&self.to_le_bytes())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut bytes = [0; 8];
+		input.read_exact(&mut bytes)?;
+
+		Ok(u64::from_le_bytes(bytes))
+	}
+}
+
+impl Codec for i32 {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		out.write_all(&self.to_le_bytes())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut bytes = [0; 4];
+		input.read_exact(&mut bytes)?;
+
+		Ok(i32::from_le_bytes(bytes))
+	}
+}
+
+impl Codec for bool {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		(*self as u8).encode(out)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		match u8::decode(input)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(invalid("Invalid boolean"))
+		}
+	}
+}
+
+impl Codec for u4 {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		self.raw().encode(out)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		Ok(u4::new(u8::decode(input)?))
+	}
+}
+
+impl Codec for Height {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		(self.min_section() as u8).encode(out)?;
+		self.sections().encode(out)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let min_section = u8::decode(input)? as i8;
+		let sections = u8::decode(input)?;
+
+		if sections == 0 || (min_section as i16) + (sections as i16) > 128 {
+			return Err(invalid("Invalid world height"));
+		}
+
+		Ok(Height::new(min_section, sections))
+	}
+}
+
+impl<T> Codec for Option<T> where T: Codec {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		match self {
+			Some(value) => {
+				true.encode(out)?;
+				value.encode(out)
+			},
+			None => false.encode(out)
+		}
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		if bool::decode(input)? {
+			T::decode(input).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+}
+
+impl<T, const N: usize> Codec for [T; N] where T: Codec {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		for value in self.iter() {
+			value.encode(out)?;
+		}
+
+		Ok(())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let values = (0..N).map(|_| T::decode(input)).collect::<io::Result<Vec<T>>>()?;
+
+		values.try_into().map_err(|_| invalid("Wrong amount of array elements"))
+	}
+}
+
+impl<T> Codec for Layer<T> where T: Codec {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		for position in LayerPosition::enumerate() {
+			self[position].encode(out)?;
+		}
+
+		Ok(())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let values = (0..256).map(|_| T::decode(input)).collect::<io::Result<Vec<T>>>()?;
+
+		Ok(Layer::from_inner(values.into_boxed_slice()))
+	}
+}
+
+impl Codec for NibbleCube {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		out.write_all(&self.raw()[..])
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut raw = Box::new([0; 2048]);
+		input.read_exact(&mut raw[..])?;
+
+		Ok(NibbleCube::from_raw(raw))
+	}
+}
+
+/// Writes the bit count followed by the raw words, as one contiguous block.
+impl<P, L> Codec for PackedStorage<P, L> where P: PackedIndex, L: Layout {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		let words = self.raw_storage();
+		let mut bytes = Vec::with_capacity(words.len() * 8);
+
+		for word in words {
+			bytes.extend_from_slice(&word.to_le_bytes());
+		}
+
+		self.bits().encode(out)?;
+		(words.len() as u32).encode(out)?;
+		out.write_all(&bytes)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let bits = u8::decode(input)?;
+		let length = u32::decode(input)? as usize;
+
+		if length > 4096 * 32 / 64 {
+			return Err(invalid("Too many words in packed storage"));
+		}
+
+		let mut bytes = vec![0; length * 8];
+		input.read_exact(&mut bytes)?;
+
+		let words: Vec<u64> = bytes.chunks_exact(8)
+			.map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+			.collect();
+
+		PackedStorage::from_raw_storage(bits, words.into_boxed_slice())
+			.ok_or_else(|| invalid("Word count does not match the bit count"))
+	}
+}
+
+/// Writes the palette entries followed by the storage. Storages in direct mode are written with the registry entries
+/// as their palette, and are loaded back as regular storages.
+impl<B, P, L> Codec for IndexedStorage<B, P, L> where B: Target + Codec, P: PackedIndex, L: Layout {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		let (storage, entries) = self.freeze();
+
+		// Trailing empty slots are restored from the bit count.
+		let used = entries.iter().rposition(Option::is_some).map(|index| index + 1).unwrap_or(0);
+
+		(used as u32).encode(out)?;
+
+		for entry in &entries[..used] {
+			entry.encode(out)?;
+		}
+
+		storage.encode(out)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let used = u32::decode(input)? as usize;
+
+		if used > 1 << 16 {
+			return Err(invalid("Too many palette entries"));
+		}
+
+		let entries = (0..used).map(|_| Option::<B>::decode(input)).collect::<io::Result<Vec<_>>>()?;
+		let storage = PackedStorage::decode(input)?;
+
+		IndexedStorage::from_raw_parts(storage, entries)
+			.ok_or_else(|| invalid("Storage refers to a missing palette entry"))
+	}
+}
+
+/// Writes the palette followed by the changes. Palette indices take a single byte when the palette is small enough.
+impl<B> Codec for Delta<B> where B: Target + Codec {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		let palette = self.palette();
+		let narrow = palette.len() <= 256;
+
+		(palette.len() as u16).encode(out)?;
+
+		for entry in palette {
+			entry.encode(out)?;
+		}
+
+		let mut changes = Vec::with_capacity(self.len() * 6);
+
+		for &(position, old, new) in self.raw_changes() {
+			position.encode(&mut changes)?;
+
+			if narrow {
+				(old as u8).encode(&mut changes)?;
+				(new as u8).encode(&mut changes)?;
+			} else {
+				old.encode(&mut changes)?;
+				new.encode(&mut changes)?;
+			}
+		}
+
+		(self.len() as u16).encode(out)?;
+		out.write_all(&changes)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let length = u16::decode(input)? as usize;
+		let narrow = length <= 256;
+
+		let palette = (0..length).map(|_| B::decode(input)).collect::<io::Result<Vec<_>>>()?;
+
+		let index = |input: &mut R| if narrow { u8::decode(input).map(u16::from) } else { u16::decode(input) };
+		let mut changes = Vec::new();
+
+		for _ in 0..u16::decode(input)? {
+			changes.push((u16::decode(input)?, index(input)?, index(input)?));
+		}
+
+		Delta::from_raw_parts(palette, changes).ok_or_else(|| invalid("Invalid delta"))
+	}
+}
+
+/// Writes the amount of present chunks, followed by the position and contents of each one.
+impl<T> Codec for Sector<T> where T: Codec {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		(self.count_sectors() as u16).encode(out)?;
+
+		for (position, chunk) in self.enumerate_present() {
+			position.yzx().encode(out)?;
+			chunk.encode(out)?;
+		}
+
+		Ok(())
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let mut sector = Sector::new();
+
+		for _ in 0..u16::decode(input)? {
+			let position = u16::decode(input)?;
+
+			if position >= 4096 {
+				return Err(invalid("Chunk position is outside of the sector"));
+			}
+
+			sector.set(CubePosition::from_yzx(position), T::decode(input)?);
+		}
+
+		Ok(sector)
+	}
+}
+
+/// Uses the same layout as `Sector`. Chunks are unpacked one at a time while writing.
+impl<T> Codec for SharedSector<T> where T: Packed, T::Unpacked: Codec {
+	fn encode<W>(&self, out: &mut W) -> io::Result<()> where W: Write {
+		let mut chunks = Vec::new();
+		let mut count = 0u16;
+
+		for position in CubePosition::enumerate() {
+			if let Some(chunk) = self.get(position) {
+				position.yzx().encode(&mut chunks)?;
+				chunk.encode(&mut chunks)?;
+				count += 1;
+			}
+		}
+
+		count.encode(out)?;
+		out.write_all(&chunks)
+	}
+
+	fn decode<R>(input: &mut R) -> io::Result<Self> where R: Read {
+		let sector = SharedSector::new();
+
+		for _ in 0..u16::decode(input)? {
+			let position = u16::decode(input)?;
+
+			if position >= 4096 {
+				return Err(invalid("Chunk position is outside of the sector"));
+			}
+
+			sector.set(CubePosition::from_yzx(position), T::pack(T::Unpacked::decode(input)?));
+		}
+
+		Ok(sector)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::indexed::IndexedCube;
+	use crate::component::CubeStorage;
+	use crate::position::{GlobalChunkPosition, GlobalSectorPosition};
+	use crate::world::world::World;
+	use crate::world::shared::{NoPack, SharedWorld};
+	use std::collections::HashMap;
+
+	#[test]
+	fn test_round_trip() {
+		let mut chunk: IndexedCube<u16> = IndexedCube::new(4, 0);
+
+		for position in CubePosition::enumerate() {
+			chunk.set_immediate(position, &(position.y() as u16 * 3 + position.x() as u16 % 2));
+		}
+
+		let mut world: World<IndexedCube<u16>> = World::new();
+		world.set(GlobalChunkPosition::new(-1, 2, 40), chunk.clone());
+
+		let mut light = NibbleCube::default();
+		light.set(CubePosition::new(1, 2, 3), u4::new(9));
+
+		let mut shared: SharedWorld<NoPack<NibbleCube>> = SharedWorld::new();
+		shared.set(GlobalChunkPosition::new(0, 0, 0), NoPack(light.clone()));
+
+		let mut layers = HashMap::new();
+		layers.insert(GlobalSectorPosition::new(-1, 2), Layer::filled([7u8; 4]));
+
+		let directory = std::env::temp_dir().join(format!("vocs-snapshot-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+
+		// Left over from an earlier save, the sector no longer exists in the world.
+		let stale = GlobalSectorPosition::new(5, 5);
+		save_sectors(&directory, "blocks", std::iter::once((&stale, &layers[&GlobalSectorPosition::new(-1, 2)]))).unwrap();
+
+		save_world(&directory, "blocks", &world).unwrap();
+		save_shared_world(&directory, "light", &shared).unwrap();
+		save_sectors(&directory, "biomes", layers.iter()).unwrap();
+
+		assert!(!sector_path(&directory, "blocks", stale).exists());
+
+		let wrong_height = load_world::<IndexedCube<u16>>(&directory, "blocks", Height::new(-4, 24));
+		assert_eq!(wrong_height.err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+
+		let loaded: World<IndexedCube<u16>> = load_world(&directory, "blocks", world.height()).unwrap();
+		let loaded_chunk = loaded.get(GlobalChunkPosition::new(-1, 2, 40)).unwrap();
+
+		assert_eq!(loaded_chunk.freeze().0.raw_storage(), chunk.freeze().0.raw_storage());
+
+		for position in CubePosition::enumerate() {
+			assert_eq!(loaded_chunk.get(position), chunk.get(position));
+		}
+
+		let loaded_light: SharedWorld<NoPack<NibbleCube>> = load_shared_world(&directory, "light", shared.height()).unwrap();
+		assert_eq!(loaded_light.get(GlobalChunkPosition::new(0, 0, 0)).unwrap().raw()[..], light.raw()[..]);
+
+		let loaded_layers: HashMap<_, Layer<[u8; 4]>> = load_sectors(&directory, "biomes").unwrap().into_iter().collect();
+		assert_eq!(loaded_layers[&GlobalSectorPosition::new(-1, 2)], layers[&GlobalSectorPosition::new(-1, 2)]);
+
+		std::fs::remove_dir_all(&directory).unwrap();
+	}
+}
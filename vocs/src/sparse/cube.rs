@@ -0,0 +1,103 @@
+use crate::position::CubePosition;
+use rustc_hash::FxHashMap;
+use std::collections::hash_map::{Iter, IterMut};
+
+/// Sparse per-chunk storage of a component, keyed by the position within the chunk. Suitable for complex data that
+/// only a few blocks in a chunk have, such as chest inventories, spawner configuration, or sign text.
+///
+/// Unlike dense storage, positions without a value take up no memory. Chunks in a `World<SparseCube<T>>` can be
+/// kept next to the block chunks in a `World<IndexedCube<B>>`, see `World::replace_block` and `World::move_block`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SparseCube<T> {
+	values: FxHashMap<CubePosition, T>
+}
+
+impl<T> SparseCube<T> {
+	pub fn new() -> Self {
+		SparseCube {
+			values: FxHashMap::default()
+		}
+	}
+
+	pub fn get(&self, position: CubePosition) -> Option<&T> {
+		self.values.get(&position)
+	}
+
+	pub fn get_mut(&mut self, position: CubePosition) -> Option<&mut T> {
+		self.values.get_mut(&position)
+	}
+
+	pub fn contains(&self, position: CubePosition) -> bool {
+		self.values.contains_key(&position)
+	}
+
+	/// Sets the value at the position, returning the previous value if there was one.
+	pub fn insert(&mut self, position: CubePosition, value: T) -> Option<T> {
+		self.values.insert(position, value)
+	}
+
+	/// Removes the value at the position, returning it if there was one.
+	pub fn remove(&mut self, position: CubePosition) -> Option<T> {
+		self.values.remove(&position)
+	}
+
+	/// Moves the value at one position to another position, returning the value that was previously at the destination.
+	/// If there is no value at the source position, any value at the destination is still removed.
+	pub fn move_value(&mut self, from: CubePosition, to: CubePosition) -> Option<T> {
+		if from == to {
+			return None;
+		}
+
+		match self.values.remove(&from) {
+			Some(value) => self.values.insert(to, value),
+			None => self.values.remove(&to)
+		}
+	}
+
+	/// Keeps only the values that the predicate returns true for.
+	pub fn retain<F>(&mut self, mut f: F) where F: FnMut(CubePosition, &mut T) -> bool {
+		self.values.retain(|&position, value| f(position, value))
+	}
+
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.values.clear()
+	}
+
+	/// Iterates over the values in an arbitrary order.
+	pub fn iter(&self) -> Iter<'_, CubePosition, T> {
+		self.values.iter()
+	}
+
+	pub fn iter_mut(&mut self) -> IterMut<'_, CubePosition, T> {
+		self.values.iter_mut()
+	}
+}
+
+impl<T> Default for SparseCube<T> {
+	fn default() -> Self {
+		SparseCube::new()
+	}
+}
+
+impl<T> IntoIterator for SparseCube<T> {
+	type Item = (CubePosition, T);
+	type IntoIter = ::std::collections::hash_map::IntoIter<CubePosition, T>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.values.into_iter()
+	}
+}
+
+impl<T> Extend<(CubePosition, T)> for SparseCube<T> {
+	fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=(CubePosition, T)> {
+		self.values.extend(iter)
+	}
+}
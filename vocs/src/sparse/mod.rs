@@ -1,3 +1,7 @@
+mod cube;
+
+pub use self::cube::SparseCube;
+
 use crate::component::*;
 use rustc_hash::FxHashMap;
 use std::collections::hash_map::Entry;
@@ -1,7 +1,27 @@
 use std::ops::{Add, AddAssign, Index, IndexMut};
 use std::iter::FromIterator;
-use crate::position::LayerPosition;
+use crate::position::{CubePosition, LayerPosition};
+use crate::component::{Component, CubeStorage, LayerStorage};
 
+impl Component for u8 {
+	type Chunk = Cube<u8>;
+	type Layer = Layer<u8>;
+	type Bulk = ();
+}
+
+impl Component for f32 {
+	type Chunk = Cube<f32>;
+	type Layer = Layer<f32>;
+	type Bulk = ();
+}
+
+impl Component for f64 {
+	type Chunk = Cube<f64>;
+	type Layer = Layer<f64>;
+	type Bulk = ();
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Layer<T>(Box<[T]>);
 
 impl<T> Layer<T> {
@@ -14,6 +34,15 @@ impl<T> Layer<T> {
 	pub fn into_inner(self) -> Box<[T]> {
 		self.0
 	}
+
+	/// Creates a layer from values in ZX order, the inverse of `into_inner`.
+	/// ### Panics
+	/// If there are not exactly 256 values.
+	pub fn from_inner(values: Box<[T]>) -> Self {
+		assert_eq!(values.len(), 256, "A layer must have exactly 256 values");
+
+		Layer(values)
+	}
 }
 
 impl<T> Layer<T> where T: Clone {
@@ -72,6 +101,26 @@ where
 	}
 }
 
+impl<T> LayerStorage<T> for Layer<T> where T: Clone + PartialEq {
+	fn get(&self, position: LayerPosition) -> T {
+		self[position].clone()
+	}
+
+	fn is_filled(&self, value: T) -> bool {
+		self.0.iter().all(|entry| *entry == value)
+	}
+
+	fn set(&mut self, position: LayerPosition, value: T) {
+		self[position] = value;
+	}
+
+	fn fill(&mut self, value: T) {
+		for entry in self.0.iter_mut() {
+			*entry = value.clone();
+		}
+	}
+}
+
 // TODO: Implement FromParallelIterator
 
 impl<T> FromIterator<(LayerPosition, T)> for Layer<Option<T>> {
@@ -85,3 +134,84 @@ impl<T> FromIterator<(LayerPosition, T)> for Layer<Option<T>> {
 		target
 	}
 }
+
+/// A 16x16x16 array without packing, indexed with CubePosition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cube<T>(Box<[T]>);
+
+impl<T> Cube<T> {
+	pub fn map<F, V>(self, mapper: F) -> Cube<V> where F: FnMut(T) -> V {
+		let entries: Vec<V> = self.0.into_vec().into_iter().map(mapper).collect();
+
+		Cube(entries.into_boxed_slice())
+	}
+
+	/// Returns the values in YZX order.
+	pub fn into_inner(self) -> Box<[T]> {
+		self.0
+	}
+}
+
+impl<T> Cube<T> where T: Clone {
+	pub fn filled(value: T) -> Self {
+		Cube(vec![value; 4096].into_boxed_slice())
+	}
+}
+
+impl<T> Default for Cube<T> where T: Default {
+	fn default() -> Self {
+		let values: Vec<T> = (0..4096).map(|_| T::default()).collect();
+
+		Cube(values.into_boxed_slice())
+	}
+}
+
+impl<T> Index<CubePosition> for Cube<T> {
+	type Output = T;
+
+	fn index(&self, index: CubePosition) -> &Self::Output {
+		&self.0[index.yzx() as usize]
+	}
+}
+
+impl<T> IndexMut<CubePosition> for Cube<T> {
+	fn index_mut(&mut self, index: CubePosition) -> &mut Self::Output {
+		&mut self.0[index.yzx() as usize]
+	}
+}
+
+impl<T> CubeStorage<T> for Cube<T> where T: Clone {
+	fn get(&self, position: CubePosition) -> T {
+		self[position].clone()
+	}
+
+	fn set(&mut self, position: CubePosition, value: T) {
+		self[position] = value;
+	}
+
+	fn fill(&mut self, value: T) {
+		for entry in self.0.iter_mut() {
+			*entry = value.clone();
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::position::dir;
+	use crate::view::{SpillChunk, StorageOffset};
+
+	#[test]
+	fn test_spill_density() {
+		let mut density: SpillChunk<f32> = SpillChunk::default();
+
+		density.primary.fill(0.5);
+		density.set_offset(CubePosition::new(3, 15, 4), dir::Up, 2.0);
+		density.set_offset(CubePosition::new(3, 14, 4), dir::Up, 1.0);
+
+		assert_eq!(density.primary.get(CubePosition::new(3, 15, 4)), 1.0);
+		assert_eq!(density.spills[dir::Up].get(LayerPosition::new(3, 4)), 2.0);
+		assert!(density.spills[dir::Down].is_filled(0.0));
+	}
+}
@@ -1,35 +1,40 @@
-use crate::indexed::{Target, IndexedCube};
-use crate::indexed::Palette;
-use crate::position::ColumnPosition;
-use crate::packed::PackedCube;
-
+use crate::indexed::{Target, IndexedCube, Journal};
+use crate::indexed::{Association, FrozenPalette, FrozenStorage};
+use crate::position::{ColumnPosition, CubePosition};
+use crate::packed::Spanning;
+use smallvec::SmallVec;
+
+/// One entry for each chunk of a column, from the bottom to the top. Columns of up to 16 chunks are stored inline,
+/// so that building a view of a normal column does not allocate. Taller columns spill to the heap.
+pub type Sections<T> = SmallVec<[T; 16]>;
+
+/// A mutable view of a column of chunks, ordered from the bottom of the column to the top.
+/// The column may be of any height, see `Height`.
 #[derive(Debug)]
-pub struct ColumnMut<'c, B>(pub [&'c mut IndexedCube<B>; 16]) where B: 'c + Target;
+pub struct ColumnMut<'c, B>(pub Sections<&'c mut IndexedCube<B>>) where B: 'c + Target;
 
 impl<'c, B> ColumnMut<'c, B> where B: 'c + Target {
 	pub fn from_array(array: &'c mut [IndexedCube<B>; 16]) -> Self {
-		let (s0 , slice) = array.split_at_mut(1);
-		let (s1 , slice) = slice.split_at_mut(1);
-		let (s2 , slice) = slice.split_at_mut(1);
-		let (s3 , slice) = slice.split_at_mut(1);
-		let (s4 , slice) = slice.split_at_mut(1);
-		let (s5 , slice) = slice.split_at_mut(1);
-		let (s6 , slice) = slice.split_at_mut(1);
-		let (s7 , slice) = slice.split_at_mut(1);
-		let (s8 , slice) = slice.split_at_mut(1);
-		let (s9 , slice) = slice.split_at_mut(1);
-		let (s10, slice) = slice.split_at_mut(1);
-		let (s11, slice) = slice.split_at_mut(1);
-		let (s12, slice) = slice.split_at_mut(1);
-		let (s13, slice) = slice.split_at_mut(1);
-		let (s14, s15  ) = slice.split_at_mut(1);
-
-		ColumnMut([
-			&mut s0 [0], &mut s1 [0], &mut s2 [0], &mut s3 [0],
-			&mut s4 [0], &mut s5 [0], &mut s6 [0], &mut s7 [0],
-			&mut s8 [0], &mut s9 [0], &mut s10[0], &mut s11[0],
-			&mut s12[0], &mut s13[0], &mut s14[0], &mut s15[0]
-		])
+		ColumnMut::from_slice(array)
+	}
+
+	pub fn from_slice(slice: &'c mut [IndexedCube<B>]) -> Self {
+		ColumnMut::from_chunks(slice.iter_mut())
+	}
+
+	/// Creates a view from the chunks of a column, from the bottom up.
+	pub fn from_chunks<I>(chunks: I) -> Self where I: IntoIterator<Item=&'c mut IndexedCube<B>> {
+		ColumnMut(chunks.into_iter().collect())
+	}
+
+	/// Returns the amount of chunks in this column.
+	pub fn sections(&self) -> u8 {
+		self.0.len() as u8
+	}
+
+	/// Returns the height of this column in blocks. ColumnPosition Y values must be below this.
+	pub fn blocks(&self) -> u16 {
+		(self.0.len() as u16) * 16
 	}
 
 	pub fn get(&self, at: ColumnPosition) -> &B {
@@ -53,70 +58,59 @@ impl<'c, B> ColumnMut<'c, B> where B: 'c + Target {
 		}
 	}
 
-	fn chunks_tuple(&mut self) -> (&mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>, &mut IndexedCube<B>) {
-		let (s0 , slice) = self.0.split_at_mut(1);
-		let (s1 , slice) = slice.split_at_mut(1);
-		let (s2 , slice) = slice.split_at_mut(1);
-		let (s3 , slice) = slice.split_at_mut(1);
-		let (s4 , slice) = slice.split_at_mut(1);
-		let (s5 , slice) = slice.split_at_mut(1);
-		let (s6 , slice) = slice.split_at_mut(1);
-		let (s7 , slice) = slice.split_at_mut(1);
-		let (s8 , slice) = slice.split_at_mut(1);
-		let (s9 , slice) = slice.split_at_mut(1);
-		let (s10, slice) = slice.split_at_mut(1);
-		let (s11, slice) = slice.split_at_mut(1);
-		let (s12, slice) = slice.split_at_mut(1);
-		let (s13, slice) = slice.split_at_mut(1);
-		let (s14, s15  ) = slice.split_at_mut(1);
-
-		(
-			&mut s0 [0], &mut s1 [0], &mut s2 [0], &mut s3 [0],
-			&mut s4 [0], &mut s5 [0], &mut s6 [0], &mut s7 [0],
-			&mut s8 [0], &mut s9 [0], &mut s10[0], &mut s11[0],
-			&mut s12[0], &mut s13[0], &mut s14[0], &mut s15[0]
-		)
-	}
-
-	pub fn freeze_palette(&mut self) -> (ColumnBlocks, ColumnPalettes<B>) {
-		let chunks = self.chunks_tuple();
-
-		let frozen = (
-			chunks. 0.freeze_palette(), chunks. 1.freeze_palette(), chunks. 2.freeze_palette(), chunks. 3.freeze_palette(),
-			chunks. 4.freeze_palette(), chunks. 5.freeze_palette(), chunks. 6.freeze_palette(), chunks. 7.freeze_palette(),
-			chunks. 8.freeze_palette(), chunks. 9.freeze_palette(), chunks.10.freeze_palette(), chunks.11.freeze_palette(),
-			chunks.12.freeze_palette(), chunks.13.freeze_palette(), chunks.14.freeze_palette(), chunks.15.freeze_palette()
-		);
-
-		(
-			ColumnBlocks ([
-				(frozen. 0).0, (frozen. 1).0, (frozen. 2).0, (frozen. 3).0,
-				(frozen. 4).0, (frozen. 5).0, (frozen. 6).0, (frozen. 7).0,
-				(frozen. 8).0, (frozen. 9).0, (frozen.10).0, (frozen.11).0,
-				(frozen.12).0, (frozen.13).0, (frozen.14).0, (frozen.15).0
-			]),
-			ColumnPalettes([
-				(frozen. 0).1, (frozen. 1).1, (frozen. 2).1, (frozen. 3).1,
-				(frozen. 4).1, (frozen. 5).1, (frozen. 6).1, (frozen. 7).1,
-				(frozen. 8).1, (frozen. 9).1, (frozen.10).1, (frozen.11).1,
-				(frozen.12).1, (frozen.13).1, (frozen.14).1, (frozen.15).1
-			])
-		)
+	/// Freezes the palettes of every chunk in the column, and passes the storages to the editor.
+	/// Associations looked up in the frozen palettes are branded, so that they can only be set into
+	/// this column, and only during this call. See `IndexedStorage::edit`.
+	pub fn edit<F, R>(&mut self, editor: F) -> R where F: for<'b> FnOnce(ColumnBlocks<'_, 'b>, ColumnPalettes<'_, 'b, B>) -> R {
+		let result = {
+			let (blocks, palettes) = self.split_mut();
+
+			editor(blocks, palettes)
+		};
+
+		self.settle_journals();
+
+		result
+	}
+
+	/// Starts recording changes to every chunk in the column, see `IndexedStorage::track_changes`.
+	pub fn track_changes(&mut self) {
+		for chunk in self.0.iter_mut() {
+			chunk.track_changes();
+		}
+	}
+
+	/// Returns the changes recorded in each chunk from the bottom up, and starts new journals.
+	/// Chunks that are not tracking changes return None.
+	pub fn take_journals(&mut self) -> Vec<Option<Journal<B>>> {
+		self.0.iter_mut().map(|chunk| chunk.take_journal()).collect()
+	}
+
+	/// Records the changes of a bulk edit made through `split_mut`.
+	pub(crate) fn settle_journals(&mut self) {
+		for chunk in self.0.iter_mut() {
+			chunk.settle_journal();
+		}
+	}
+
+	/// Unbranded version of `edit`, for views that brand several columns at once.
+	pub(crate) fn split_mut<'b>(&mut self) -> (ColumnBlocks<'_, 'b>, ColumnPalettes<'_, 'b, B>) {
+		let (blocks, palettes) = self.0.iter_mut().map(|chunk| chunk.split_mut()).unzip();
+
+		(ColumnBlocks(blocks), ColumnPalettes(palettes))
 	}
 }
 
 #[derive(Debug)]
-pub struct ColumnBlocks<'a>([&'a mut PackedCube; 16]);
-impl<'a> ColumnBlocks<'a> {
-	pub fn get<'p, B>(&self, at: ColumnPosition, palettes: &ColumnPalettes<'p, B>) -> &'p B where B: Target {
+pub struct ColumnBlocks<'a, 'b>(Sections<FrozenStorage<'a, 'b, CubePosition, Spanning>>);
+impl<'a, 'b> ColumnBlocks<'a, 'b> {
+	pub fn get<'p, B>(&self, at: ColumnPosition, palettes: &ColumnPalettes<'p, 'b, B>) -> &'p B where B: Target {
 		let chunk_y = at.chunk_y() as usize;
 
-		let raw = self.0[chunk_y].get(at.chunk());
-
-		palettes.0[chunk_y].entries()[raw as usize].as_ref().expect("Column is corrupted; A user of freeze_palette has likely violated the API contract!")
+		palettes.0[chunk_y].get(self.0[chunk_y].get(at.chunk()))
 	}
 
-	pub fn set(&mut self, at: ColumnPosition, association: &ColumnAssociation) {
+	pub fn set(&mut self, at: ColumnPosition, association: &ColumnAssociation<'b>) {
 		let chunk_y = at.chunk_y() as usize;
 
 		self.0[chunk_y].set(at.chunk(), association.0[chunk_y])
@@ -124,30 +118,84 @@ impl<'a> ColumnBlocks<'a> {
 }
 
 #[derive(Debug)]
-pub struct ColumnPalettes<'a, B>([&'a Palette<B>; 16]) where B: 'a + Target;
-impl<'a, B> ColumnPalettes<'a, B> where B: 'a + Target {
+pub struct ColumnPalettes<'a, 'b, B>(Sections<FrozenPalette<'a, 'b, B>>) where B: 'a + Target;
+impl<'a, 'b, B> ColumnPalettes<'a, 'b, B> where B: 'a + Target {
 	/// Gets an association that will reference back to the target. Note that several indices may point to the same target, this returns one of them.
-	pub fn reverse_lookup(&self, target: &B) -> Result<ColumnAssociation, usize> {
-		Ok(ColumnAssociation ([
-			self.0 [0].reverse_lookup(target).ok_or( 0usize)?,
-			self.0 [1].reverse_lookup(target).ok_or( 1usize)?,
-			self.0 [2].reverse_lookup(target).ok_or( 2usize)?,
-			self.0 [3].reverse_lookup(target).ok_or( 3usize)?,
-			self.0 [4].reverse_lookup(target).ok_or( 4usize)?,
-			self.0 [5].reverse_lookup(target).ok_or( 5usize)?,
-			self.0 [6].reverse_lookup(target).ok_or( 6usize)?,
-			self.0 [7].reverse_lookup(target).ok_or( 7usize)?,
-			self.0 [8].reverse_lookup(target).ok_or( 8usize)?,
-			self.0 [9].reverse_lookup(target).ok_or( 9usize)?,
-			self.0[10].reverse_lookup(target).ok_or(10usize)?,
-			self.0[11].reverse_lookup(target).ok_or(11usize)?,
-			self.0[12].reverse_lookup(target).ok_or(12usize)?,
-			self.0[13].reverse_lookup(target).ok_or(13usize)?,
-			self.0[14].reverse_lookup(target).ok_or(14usize)?,
-			self.0[15].reverse_lookup(target).ok_or(15usize)?,
-		]))
+	pub fn reverse_lookup(&self, target: &B) -> Result<ColumnAssociation<'b>, usize> {
+		self.0.iter().enumerate()
+			.map(|(chunk_y, palette)| palette.reverse_lookup(target).ok_or(chunk_y))
+			.collect::<Result<Sections<_>, usize>>()
+			.map(ColumnAssociation)
 	}
 }
 
-#[derive(Debug)]
-pub struct ColumnAssociation([u32; 16]);
\ No newline at end of file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ColumnAssociation<'b>(Sections<Association<'b>>);
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{ColumnPosition, QuadPosition};
+	use crate::view::{ColumnMut, QuadMut};
+
+	#[test]
+	fn test_column_edit() {
+		let mut chunks: Vec<IndexedCube<u16>> = (0..2).map(|_| IndexedCube::new(4, 0)).collect();
+		chunks[1].ensure_available(5);
+
+		let mut column = ColumnMut::from_slice(&mut chunks);
+
+		column.edit(|_, palettes| {
+			// The bottom chunk is the first one missing the target.
+			assert_eq!(palettes.reverse_lookup(&5), Err(0));
+		});
+
+		column.ensure_available(5);
+
+		column.edit(|mut blocks, palettes| {
+			let five = palettes.reverse_lookup(&5).unwrap();
+
+			blocks.set(ColumnPosition::new(1, 4, 1), &five);
+			blocks.set(ColumnPosition::new(1, 20, 1), &five);
+
+			assert_eq!(*blocks.get(ColumnPosition::new(1, 20, 1), &palettes), 5);
+			assert_eq!(*blocks.get(ColumnPosition::new(1, 21, 1), &palettes), 0);
+		});
+
+		assert_eq!(*column.get(ColumnPosition::new(1, 4, 1)), 5);
+		drop(column);
+		assert_eq!(*chunks[1].get(ColumnPosition::new(1, 20, 1).chunk()), 5);
+	}
+
+	#[test]
+	fn test_quad_edit() {
+		let mut chunks: Vec<IndexedCube<u16>> = (0..4).map(|_| IndexedCube::new(4, 0)).collect();
+
+		let mut slices = chunks.chunks_mut(1);
+		let mut quad = QuadMut([
+			ColumnMut::from_slice(slices.next().unwrap()),
+			ColumnMut::from_slice(slices.next().unwrap()),
+			ColumnMut::from_slice(slices.next().unwrap()),
+			ColumnMut::from_slice(slices.next().unwrap())
+		]);
+
+		quad.ensure_available(3);
+
+		quad.edit(|mut blocks, palettes| {
+			let three = palettes.reverse_lookup(&3).unwrap();
+
+			for &(x, z) in &[(0, 0), (31, 0), (0, 31), (31, 31)] {
+				blocks.set(QuadPosition::new(x, 6, z), &three);
+			}
+
+			assert_eq!(*blocks.get(QuadPosition::new(31, 6, 31), &palettes), 3);
+		});
+
+		assert_eq!(*quad.get(QuadPosition::new(31, 6, 0)), 3);
+		assert_eq!(*quad.get(QuadPosition::new(30, 6, 0)), 0);
+		drop(quad);
+
+		for chunk in &chunks {
+			assert_eq!(chunk.freeze().0.raw_storage().iter().filter(|&&word| word != 0).count(), 1);
+		}
+	}
+}
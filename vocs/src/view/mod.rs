@@ -1,9 +1,11 @@
 mod column;
 mod quad;
+mod neighborhood;
 mod directional;
 mod spill;
 
 pub use self::column::*;
 pub use self::quad::*;
+pub use self::neighborhood::*;
 pub use self::directional::*;
 pub use self::spill::*;
\ No newline at end of file
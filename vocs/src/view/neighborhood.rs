@@ -0,0 +1,309 @@
+use crate::indexed::Target;
+use crate::position::{ColumnPosition, GlobalColumnPosition, GlobalPosition, Height, NeighborhoodPosition};
+use super::{ColumnAssociation, ColumnBlocks, ColumnMut, ColumnPalettes};
+
+/// What a `NeighborhoodMut` does with an edit that falls outside of it, or into a column that is missing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SpillPolicy {
+	/// Fail the edit with `Spilled`.
+	Error,
+	/// Silently drop the edit.
+	Clip,
+	/// Record the edit, so that it can be applied to the world once the chunk exists. See `NeighborhoodMut::take_spilled`
+	/// and `PendingEdits`.
+	Record
+}
+
+/// An edit that fell outside of a neighborhood with the `Error` spill policy, or that could not be recorded because
+/// no chunk can contain it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Spilled(pub NeighborhoodPosition);
+
+/// Where a neighborhood is in the world, and what happens to edits that fall outside of it.
+#[derive(Debug)]
+struct Spill<B> {
+	origin: GlobalColumnPosition,
+	height: Height,
+	width: u8,
+	depth: u8,
+	policy: SpillPolicy,
+	recorded: Vec<(GlobalPosition, B)>
+}
+
+impl<B> Spill<B> where B: Target {
+	/// Returns the index of the column and the position within it, or None if the position is outside of the neighborhood.
+	fn locate(&self, at: NeighborhoodPosition) -> Option<(usize, ColumnPosition)> {
+		let (x, z) = (at.column_x(), at.column_z());
+
+		if x < 0 || z < 0 || x >= self.width as i32 || z >= self.depth as i32 || at.y() >= self.height.blocks() as i32 {
+			return None;
+		}
+
+		at.column().map(|column| ((z as usize) * (self.width as usize) + (x as usize), column))
+	}
+
+	fn global(&self, at: NeighborhoodPosition) -> Option<GlobalPosition> {
+		Some(GlobalPosition::new(
+			self.origin.x().checked_mul(16)?.checked_add(at.x())?,
+			self.height.min_y().checked_add(at.y())?,
+			self.origin.z().checked_mul(16)?.checked_add(at.z())?
+		))
+	}
+
+	fn spill(&mut self, at: NeighborhoodPosition, target: &B) -> Result<(), Spilled> {
+		match self.policy {
+			SpillPolicy::Error => Err(Spilled(at)),
+			SpillPolicy::Clip => Ok(()),
+			SpillPolicy::Record => {
+				// Edits past the edge of the world or outside of the chunk range cannot be recorded, since they could never be applied.
+				let global = self.global(at).filter(|global| global.checked_global_chunk().is_some()).ok_or(Spilled(at))?;
+				self.recorded.push((global, target.clone()));
+
+				Ok(())
+			}
+		}
+	}
+}
+
+/// A mutable view of an arbitrary rectangle of columns, for edits that reach further than a `QuadMut`.
+/// Columns are stored in rows of increasing X, with the rows ordered by increasing Z. A column may be
+/// missing, in which case edits to it are handled according to the spill policy, just like edits that
+/// fall outside of the neighborhood entirely.
+#[derive(Debug)]
+pub struct NeighborhoodMut<'c, B> where B: 'c + Target {
+	columns: Vec<Option<ColumnMut<'c, B>>>,
+	spill: Spill<B>
+}
+
+impl<'c, B> NeighborhoodMut<'c, B> where B: 'c + Target {
+	/// Creates a neighborhood from `width * depth` columns, with the column at `origin` in the -X, -Z corner.
+	/// Every present column must have the amount of sections specified by the height.
+	pub fn new(origin: GlobalColumnPosition, height: Height, width: u8, depth: u8, columns: Vec<Option<ColumnMut<'c, B>>>, policy: SpillPolicy) -> Self {
+		assert_eq!(columns.len(), (width as usize) * (depth as usize), "Neighborhood must have exactly width * depth columns");
+		assert!(columns.iter().flatten().all(|column| column.sections() == height.sections()), "Neighborhood columns must match the height");
+
+		NeighborhoodMut {
+			columns,
+			spill: Spill { origin, height, width, depth, policy, recorded: Vec::new() }
+		}
+	}
+
+	/// Returns the width of the neighborhood in columns.
+	pub fn width(&self) -> u8 {
+		self.spill.width
+	}
+
+	/// Returns the depth of the neighborhood in columns.
+	pub fn depth(&self) -> u8 {
+		self.spill.depth
+	}
+
+	/// Returns the position of the column in the -X, -Z corner.
+	pub fn origin(&self) -> GlobalColumnPosition {
+		self.spill.origin
+	}
+
+	pub fn height(&self) -> Height {
+		self.spill.height
+	}
+
+	pub fn policy(&self) -> SpillPolicy {
+		self.spill.policy
+	}
+
+	pub fn set_policy(&mut self, policy: SpillPolicy) {
+		self.spill.policy = policy;
+	}
+
+	/// Converts a neighborhood position to a global position. This works for positions outside of the neighborhood as well,
+	/// but returns None for positions past the edge of the world.
+	pub fn global(&self, at: NeighborhoodPosition) -> Option<GlobalPosition> {
+		self.spill.global(at)
+	}
+
+	/// Returns the column at the specified column offsets from the origin, or None if it is missing or out of bounds.
+	pub fn column_mut(&mut self, x: u8, z: u8) -> Option<&mut ColumnMut<'c, B>> {
+		if x >= self.spill.width || z >= self.spill.depth {
+			return None;
+		}
+
+		let index = (z as usize) * (self.spill.width as usize) + (x as usize);
+
+		self.columns[index].as_mut()
+	}
+
+	/// Returns the block at the position, or None if it is outside of the neighborhood or in a missing column.
+	pub fn get(&self, at: NeighborhoodPosition) -> Option<&B> {
+		let (index, position) = self.spill.locate(at)?;
+
+		self.columns[index].as_ref().map(|column| column.get(position))
+	}
+
+	/// Preforms the ensure_available, reverse_lookup, and set calls all in one.
+	/// If the position is outside of the neighborhood or in a missing column, the edit is handled according to the spill policy.
+	pub fn set_immediate(&mut self, at: NeighborhoodPosition, target: &B) -> Result<(), Spilled> {
+		let column = self.spill.locate(at)
+			.and_then(|(index, position)| self.columns[index].as_mut().map(|column| (column, position)));
+
+		match column {
+			Some((column, position)) => {
+				column.set_immediate(position, target);
+				Ok(())
+			},
+			None => self.spill.spill(at, target)
+		}
+	}
+
+	/// Makes sure that a future lookup for the target will succeed, unless the entry has changed since this call.
+	pub fn ensure_available(&mut self, target: B) {
+		for column in self.columns.iter_mut().flatten() {
+			column.ensure_available(target.clone());
+		}
+	}
+
+	/// Returns the edits recorded under the `Record` policy, in the order that they were made.
+	pub fn spilled(&self) -> &[(GlobalPosition, B)] {
+		&self.spill.recorded
+	}
+
+	/// Takes the edits recorded under the `Record` policy, in the order that they were made.
+	pub fn take_spilled(&mut self) -> Vec<(GlobalPosition, B)> {
+		::std::mem::take(&mut self.spill.recorded)
+	}
+
+	/// Freezes the palettes of every chunk in the neighborhood, and passes the storages to the editor.
+	/// Associations looked up in the frozen palettes are branded, so that they can only be set into
+	/// this neighborhood, and only during this call. See `IndexedStorage::edit`.
+	pub fn edit<F, R>(&mut self, editor: F) -> R where F: for<'b> FnOnce(NeighborhoodBlocks<'_, 'b, B>, NeighborhoodPalettes<'_, 'b, B>) -> R {
+		let result = {
+			let (blocks, palettes) = self.columns.iter_mut()
+				.map(|column| match column.as_mut().map(ColumnMut::split_mut) {
+					Some((blocks, palettes)) => (Some(blocks), Some(palettes)),
+					None => (None, None)
+				})
+				.unzip();
+
+			editor(
+				NeighborhoodBlocks { columns: blocks, spill: &mut self.spill },
+				NeighborhoodPalettes(palettes)
+			)
+		};
+
+		for column in self.columns.iter_mut().flatten() {
+			column.settle_journals();
+		}
+
+		result
+	}
+}
+
+#[derive(Debug)]
+pub struct NeighborhoodBlocks<'a, 'b, B> where B: 'a + Target {
+	columns: Vec<Option<ColumnBlocks<'a, 'b>>>,
+	spill: &'a mut Spill<B>
+}
+
+impl<'a, 'b, B> NeighborhoodBlocks<'a, 'b, B> where B: 'a + Target {
+	/// Returns the block at the position, or None if it is outside of the neighborhood or in a missing column.
+	pub fn get<'p>(&self, at: NeighborhoodPosition, palettes: &NeighborhoodPalettes<'p, 'b, B>) -> Option<&'p B> {
+		let (index, position) = self.spill.locate(at)?;
+
+		match (&self.columns[index], &palettes.0[index]) {
+			(Some(blocks), Some(palettes)) => Some(blocks.get(position, palettes)),
+			_ => None
+		}
+	}
+
+	/// Sets the block at the position. If the position is outside of the neighborhood or in a missing column,
+	/// the edit is handled according to the spill policy.
+	pub fn set(&mut self, at: NeighborhoodPosition, association: &NeighborhoodAssociation<'b, B>) -> Result<(), Spilled> {
+		let located = self.spill.locate(at);
+
+		match located.and_then(|(index, position)| self.columns[index].as_mut().map(|blocks| (index, blocks, position))) {
+			Some((index, blocks, position)) => {
+				// Columns are only missing from the association if they are also missing from the neighborhood.
+				blocks.set(position, association.columns[index].as_ref().unwrap());
+				Ok(())
+			},
+			None => self.spill.spill(at, &association.target)
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct NeighborhoodPalettes<'a, 'b, B>(Vec<Option<ColumnPalettes<'a, 'b, B>>>) where B: 'a + Target;
+impl<'a, 'b, B> NeighborhoodPalettes<'a, 'b, B> where B: 'a + Target {
+	/// Gets an association that will reference back to the target. Note that several indices may point to the same target, this returns one of them.
+	/// On failure, returns the index of the first column that is missing the target.
+	pub fn reverse_lookup(&self, target: &B) -> Result<NeighborhoodAssociation<'b, B>, usize> {
+		let columns = self.0.iter().enumerate()
+			.map(|(index, palettes)| match palettes {
+				Some(palettes) => palettes.reverse_lookup(target).map(Some).map_err(|_| index),
+				None => Ok(None)
+			})
+			.collect::<Result<Vec<_>, usize>>()?;
+
+		Ok(NeighborhoodAssociation { columns, target: target.clone() })
+	}
+}
+
+/// An association for every column in a neighborhood. This also holds the target itself, so that
+/// edits that spill out of the neighborhood can be recorded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NeighborhoodAssociation<'b, B> where B: Target {
+	columns: Vec<Option<ColumnAssociation<'b>>>,
+	target: B
+}
+
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{ColumnPosition, GlobalColumnPosition, GlobalPosition, Height, NeighborhoodPosition};
+	use crate::view::{ColumnMut, NeighborhoodMut, SpillPolicy, Spilled};
+
+	#[test]
+	fn test_spill_policies() {
+		let height = Height::new(-1, 2);
+		let mut chunks: Vec<IndexedCube<u16>> = (0..4).map(|_| IndexedCube::new(4, 0)).collect();
+
+		let (left, right) = chunks.split_at_mut(2);
+		let columns = vec![Some(ColumnMut::from_slice(left)), None, Some(ColumnMut::from_slice(right))];
+
+		let mut neighborhood = NeighborhoodMut::new(GlobalColumnPosition::new(-1, 3), height, 3, 1, columns, SpillPolicy::Error);
+
+		let inside = NeighborhoodPosition::new(33, 20, 15);
+		let missing = NeighborhoodPosition::new(16, 0, 0);
+		let outside = NeighborhoodPosition::new(-1, 0, 0);
+		let too_high = NeighborhoodPosition::new(0, 32, 0);
+
+		assert_eq!(neighborhood.set_immediate(inside, &1), Ok(()));
+		assert_eq!(neighborhood.get(inside), Some(&1));
+		assert_eq!(neighborhood.get(missing), None);
+
+		assert_eq!(neighborhood.set_immediate(missing, &2), Err(Spilled(missing)));
+		assert_eq!(neighborhood.set_immediate(too_high, &2), Err(Spilled(too_high)));
+
+		neighborhood.set_policy(SpillPolicy::Clip);
+		assert_eq!(neighborhood.set_immediate(outside, &3), Ok(()));
+		assert!(neighborhood.spilled().is_empty());
+
+		neighborhood.set_policy(SpillPolicy::Record);
+		neighborhood.ensure_available(4);
+		neighborhood.edit(|mut blocks, palettes| {
+			let association = palettes.reverse_lookup(&4).unwrap();
+
+			blocks.set(outside, &association).unwrap();
+			blocks.set(missing, &association).unwrap();
+		});
+
+		assert_eq!(neighborhood.take_spilled(), vec![
+			(GlobalPosition::new(-17, -16, 48), 4),
+			(GlobalPosition::new(0, -16, 48), 4)
+		]);
+
+		assert!(neighborhood.spilled().is_empty());
+		drop(neighborhood);
+
+		assert_eq!(*chunks[3].get(ColumnPosition::new(1, 20, 15).chunk()), 1);
+	}
+}
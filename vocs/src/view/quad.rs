@@ -1,4 +1,4 @@
-use crate::indexed::Target;
+use crate::indexed::{Journal, Target};
 use crate::position::QuadPosition;
 use super::{ColumnAssociation, ColumnBlocks, ColumnMut, ColumnPalettes};
 
@@ -29,34 +29,59 @@ impl<'c, B> QuadMut<'c, B> where B: 'c + Target {
 		
 	}
 
-	pub fn freeze_palette(&mut self) -> (QuadBlocks, QuadPalettes<B>) {
+	/// Freezes the palettes of every chunk in the quad, and passes the storages to the editor.
+	/// Associations looked up in the frozen palettes are branded, so that they can only be set into
+	/// this quad, and only during this call. See `IndexedStorage::edit`.
+	pub fn edit<F, R>(&mut self, editor: F) -> R where F: for<'b> FnOnce(QuadBlocks<'_, 'b>, QuadPalettes<'_, 'b, B>) -> R {
+		let result = {
+			let columns = slice_to_tuple_mut_4(&mut self.0);
+
+			let frozen = (
+				columns. 0.split_mut(), columns. 1.split_mut(), columns. 2.split_mut(), columns. 3.split_mut()
+			);
+
+			editor(
+				QuadBlocks ([
+					(frozen. 0).0, (frozen. 1).0, (frozen. 2).0, (frozen. 3).0
+				]),
+				QuadPalettes([
+					(frozen. 0).1, (frozen. 1).1, (frozen. 2).1, (frozen. 3).1
+				])
+			)
+		};
+
+		for column in self.0.iter_mut() {
+			column.settle_journals();
+		}
+
+		result
+	}
+
+	/// Starts recording changes to every chunk in the quad, see `IndexedStorage::track_changes`.
+	pub fn track_changes(&mut self) {
+		for column in self.0.iter_mut() {
+			column.track_changes();
+		}
+	}
+
+	/// Returns the changes recorded in each chunk of each column, see `ColumnMut::take_journals`.
+	pub fn take_journals(&mut self) -> [Vec<Option<Journal<B>>>; 4] {
 		let columns = slice_to_tuple_mut_4(&mut self.0);
 
-		let frozen = (
-			columns. 0.freeze_palette(), columns. 1.freeze_palette(), columns. 2.freeze_palette(), columns. 3.freeze_palette()
-		);
-
-		(
-			QuadBlocks ([
-				(frozen. 0).0, (frozen. 1).0, (frozen. 2).0, (frozen. 3).0
-			]),
-			QuadPalettes([
-				(frozen. 0).1, (frozen. 1).1, (frozen. 2).1, (frozen. 3).1
-			])
-		)
+		[columns.0.take_journals(), columns.1.take_journals(), columns.2.take_journals(), columns.3.take_journals()]
 	}
 }
 
 #[derive(Debug)]
-pub struct QuadBlocks<'a>([ColumnBlocks<'a>; 4]);
-impl<'a> QuadBlocks<'a> {
-	pub fn get<'p, B>(&self, at: QuadPosition, palettes: &QuadPalettes<'p, B>) -> &'p B where B: Target {
+pub struct QuadBlocks<'a, 'b>([ColumnBlocks<'a, 'b>; 4]);
+impl<'a, 'b> QuadBlocks<'a, 'b> {
+	pub fn get<'p, B>(&self, at: QuadPosition, palettes: &QuadPalettes<'p, 'b, B>) -> &'p B where B: Target {
 		let q = at.q() as usize;
 
 		self.0[q].get(at.column(), &palettes.0[q])
 	}
 
-	pub fn set(&mut self, at: QuadPosition, association: &QuadAssociation) {
+	pub fn set(&mut self, at: QuadPosition, association: &QuadAssociation<'b>) {
 		let q = at.q() as usize;
 
 		self.0[q].set(at.column(), &association.0[q])
@@ -64,10 +89,10 @@ impl<'a> QuadBlocks<'a> {
 }
 
 #[derive(Debug)]
-pub struct QuadPalettes<'a, B>([ColumnPalettes<'a, B>; 4]) where B: 'a + Target;
-impl<'a, B> QuadPalettes<'a, B> where B: 'a + Target {
+pub struct QuadPalettes<'a, 'b, B>([ColumnPalettes<'a, 'b, B>; 4]) where B: 'a + Target;
+impl<'a, 'b, B> QuadPalettes<'a, 'b, B> where B: 'a + Target {
 	/// Gets an association that will reference back to the target. Note that several indices may point to the same target, this returns one of them.
-	pub fn reverse_lookup(&self, target: &B) -> Result<QuadAssociation, ()> {
+	pub fn reverse_lookup(&self, target: &B) -> Result<QuadAssociation<'b>, ()> {
 		Ok(QuadAssociation ([
 			self.0 [0].reverse_lookup(target).map_err(|_| ())?,
 			self.0 [1].reverse_lookup(target).map_err(|_| ())?,
@@ -77,8 +102,8 @@ impl<'a, B> QuadPalettes<'a, B> where B: 'a + Target {
 	}
 }
 
-#[derive(Debug)]
-pub struct QuadAssociation([ColumnAssociation; 4]);
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QuadAssociation<'b>([ColumnAssociation<'b>; 4]);
 
 pub fn slice_to_tuple_mut_4<T>(slice: &mut [T; 4]) -> (&mut T, &mut T, &mut T, &mut T)
 {
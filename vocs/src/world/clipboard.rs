@@ -0,0 +1,457 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::position::{Axis, CubePosition, Cuboid, GlobalChunkPosition, GlobalPosition};
+use crate::world::world::{ChunkAccess, World};
+
+/// Marks a block of the clipboard that was not copied, because its chunk was missing.
+const EMPTY: u32 = u32::MAX;
+
+/// A volume of blocks copied out of a world, detached from any chunks. Blocks that were copied from
+/// missing chunks are empty, and are skipped when pasting. See `World::copy` and `World::paste`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clipboard<B> where B: Target {
+	size: (u32, u32, u32),
+	palette: Vec<B>,
+	/// Indices into the palette, in YZX order.
+	blocks: Vec<u32>
+}
+
+impl<B> Clipboard<B> where B: Target {
+	/// Creates an empty clipboard of the specified size.
+	pub fn new(size_x: u32, size_y: u32, size_z: u32) -> Self {
+		Clipboard {
+			size: (size_x, size_y, size_z),
+			palette: Vec::new(),
+			blocks: vec![EMPTY; (size_x as usize) * (size_y as usize) * (size_z as usize)]
+		}
+	}
+
+	pub fn size_x(&self) -> u32 {
+		self.size.0
+	}
+
+	pub fn size_y(&self) -> u32 {
+		self.size.1
+	}
+
+	pub fn size_z(&self) -> u32 {
+		self.size.2
+	}
+
+	fn index(&self, x: u32, y: u32, z: u32) -> usize {
+		assert!(x < self.size.0 && y < self.size.1 && z < self.size.2, "Clipboard position out of bounds");
+
+		((y as usize * self.size.2 as usize) + z as usize) * self.size.0 as usize + x as usize
+	}
+
+	/// Returns the palette index of the target, adding it to the palette if needed.
+	fn insert(&mut self, target: &B) -> u32 {
+		match self.palette.iter().position(|entry| entry == target) {
+			Some(index) => index as u32,
+			None => {
+				self.palette.push(target.clone());
+				(self.palette.len() - 1) as u32
+			}
+		}
+	}
+
+	/// Returns the block at the position, or None if it is empty.
+	pub fn get(&self, x: u32, y: u32, z: u32) -> Option<&B> {
+		match self.blocks[self.index(x, y, z)] {
+			EMPTY => None,
+			value => Some(&self.palette[value as usize])
+		}
+	}
+
+	pub fn set(&mut self, x: u32, y: u32, z: u32, target: &B) {
+		let index = self.index(x, y, z);
+
+		self.blocks[index] = self.insert(target);
+	}
+
+	/// Makes the block at the position empty, so that it is skipped when pasting.
+	pub fn clear(&mut self, x: u32, y: u32, z: u32) {
+		let index = self.index(x, y, z);
+
+		self.blocks[index] = EMPTY;
+	}
+
+	/// Rearranges the blocks into a clipboard of a new size. `source` maps positions in the new clipboard to positions in this one.
+	fn transform<F>(&self, size: (u32, u32, u32), source: F) -> Self where F: Fn(u32, u32, u32) -> (u32, u32, u32) {
+		let mut blocks = Vec::with_capacity(self.blocks.len());
+
+		for y in 0..size.1 {
+			for z in 0..size.2 {
+				for x in 0..size.0 {
+					let (x, y, z) = source(x, y, z);
+
+					blocks.push(self.blocks[self.index(x, y, z)]);
+				}
+			}
+		}
+
+		Clipboard {
+			size,
+			palette: self.palette.clone(),
+			blocks
+		}
+	}
+
+	/// Returns a copy of this clipboard rotated clockwise around the Y axis, as seen from above, by the amount of quarter turns.
+	/// A quarter turn moves the -Z side of the clipboard to the +X side.
+	pub fn rotate_y(&self, quarter_turns: u8) -> Self {
+		let (size_x, size_y, size_z) = self.size;
+
+		match quarter_turns & 3 {
+			0 => self.clone(),
+			1 => self.transform((size_z, size_y, size_x), |x, y, z| (z, y, size_z - 1 - x)),
+			2 => self.transform(self.size, |x, y, z| (size_x - 1 - x, y, size_z - 1 - z)),
+			_ => self.transform((size_z, size_y, size_x), |x, y, z| (size_x - 1 - z, y, x))
+		}
+	}
+
+	/// Returns a copy of this clipboard mirrored along the axis, such that the coordinates on that axis are reversed.
+	pub fn mirror(&self, axis: Axis) -> Self {
+		let (size_x, size_y, size_z) = self.size;
+
+		match axis {
+			Axis::X => self.transform(self.size, |x, y, z| (size_x - 1 - x, y, z)),
+			Axis::Y => self.transform(self.size, |x, y, z| (x, size_y - 1 - y, z)),
+			Axis::Z => self.transform(self.size, |x, y, z| (x, y, size_z - 1 - z))
+		}
+	}
+}
+
+/// Iterates over the local positions within the inclusive bounds, in YZX order.
+fn local_positions(min: CubePosition, max: CubePosition) -> impl Iterator<Item=CubePosition> {
+	(min.y()..=max.y()).flat_map(move |y|
+		(min.z()..=max.z()).flat_map(move |z|
+			(min.x()..=max.x()).map(move |x| CubePosition::new(x, y, z))
+		)
+	)
+}
+
+fn is_whole_chunk(min: CubePosition, max: CubePosition) -> bool {
+	min == CubePosition::new(0, 0, 0) && max == CubePosition::new(15, 15, 15)
+}
+
+/// Sets every block of the chunk within the local bounds to the target. See `ChunkAccess::fill_cube` for whole chunks.
+fn fill_chunk<B>(chunk: &mut IndexedCube<B>, min: CubePosition, max: CubePosition, target: &B) where B: Target {
+	let (mut setter, _) = chunk.setter(target.clone());
+
+	for position in local_positions(min, max) {
+		setter.set(position);
+	}
+}
+
+/// Replaces every block of the chunk within the local bounds that matches the predicate with the target.
+fn replace_in_chunk<B, F>(chunk: &mut IndexedCube<B>, min: CubePosition, max: CubePosition, matches: &mut F, target: &B) where B: Target, F: FnMut(&B) -> bool {
+	// Whole chunks can usually get away with only swapping palette entries. The registry
+	// of a chunk in direct mode is too large to check every entry, however.
+	if is_whole_chunk(min, max) && !chunk.is_direct() {
+		let (_, entries) = chunk.freeze();
+
+		let replaced: Vec<B> = entries.iter()
+			.flatten()
+			.filter(|&entry| entry != target && matches(entry))
+			.cloned()
+			.collect();
+
+		for entry in &replaced {
+			chunk.replace(entry, target.clone());
+		}
+
+		return;
+	}
+
+	let (mut setter, entries) = chunk.setter(target.clone());
+	let mut cache: Vec<Option<bool>> = vec![None; entries.len()];
+
+	for position in local_positions(min, max) {
+		let value = setter.get(position) as usize;
+
+		let matched = *cache[value].get_or_insert_with(||
+			entries[value].as_ref().map(&mut *matches).unwrap_or(false)
+		);
+
+		if matched {
+			setter.set(position);
+		}
+	}
+}
+
+/// Counts the blocks of the chunk within the local bounds that match the predicate.
+fn count_in_chunk<B, F>(chunk: &IndexedCube<B>, min: CubePosition, max: CubePosition, matches: &mut F) -> u64 where B: Target, F: FnMut(&B) -> bool {
+	let (storage, entries) = chunk.freeze();
+	let mut cache: Vec<Option<bool>> = vec![None; entries.len()];
+	let mut count = 0;
+
+	for position in local_positions(min, max) {
+		let value = storage.get(position) as usize;
+
+		let matched = *cache[value].get_or_insert_with(||
+			entries[value].as_ref().map(&mut *matches).unwrap_or(false)
+		);
+
+		if matched {
+			count += 1;
+		}
+	}
+
+	count
+}
+
+impl<B> Clipboard<B> where B: Target {
+	/// Returns the index of the block at a position within a chunk, relative to the -X, -Y, -Z corner at the origin.
+	fn index_in_chunk(&self, position: GlobalChunkPosition, local: CubePosition, origin: GlobalPosition) -> usize {
+		self.index(
+			(position.x() * 16 + local.x() as i32 - origin.x()) as u32,
+			(position.y() as i32 * 16 + local.y() as i32 - origin.y()) as u32,
+			(position.z() * 16 + local.z() as i32 - origin.z()) as u32
+		)
+	}
+
+	/// Copies the blocks of the chunk within the local bounds into the clipboard.
+	fn copy_chunk(&mut self, chunk: &IndexedCube<B>, position: GlobalChunkPosition, min: CubePosition, max: CubePosition, origin: GlobalPosition) {
+		let (storage, entries) = chunk.freeze();
+
+		// Maps values in the chunk to indices in the clipboard palette.
+		let mut mapping: Vec<u32> = vec![EMPTY; entries.len()];
+
+		for local in local_positions(min, max) {
+			let value = storage.get(local) as usize;
+
+			if mapping[value] == EMPTY {
+				let entry = entries[value].as_ref().expect("IndexedStorage is corrupted; the storage referred to an empty palette entry");
+
+				mapping[value] = self.insert(entry);
+			}
+
+			let index = self.index_in_chunk(position, local, origin);
+			self.blocks[index] = mapping[value];
+		}
+	}
+
+	/// Returns which palette entries would be pasted into the chunk within the local bounds,
+	/// or None if every block there is empty, in which case the chunk is left untouched.
+	fn used_in_chunk(&self, position: GlobalChunkPosition, min: CubePosition, max: CubePosition, origin: GlobalPosition) -> Option<Vec<bool>> {
+		let mut used = vec![false; self.palette.len()];
+		let mut any = false;
+
+		for local in local_positions(min, max) {
+			match self.blocks[self.index_in_chunk(position, local, origin)] {
+				EMPTY => (),
+				value => {
+					used[value as usize] = true;
+					any = true;
+				}
+			}
+		}
+
+		if any { Some(used) } else { None }
+	}
+
+	/// Pastes the blocks within the local bounds of the chunk. `used` is the result of `used_in_chunk`.
+	fn paste_chunk(&self, chunk: &mut IndexedCube<B>, position: GlobalChunkPosition, min: CubePosition, max: CubePosition, origin: GlobalPosition, used: &[bool]) {
+		// Only add the entries that will actually end up in this chunk to its palette.
+		for (entry, _) in self.palette.iter().zip(used).filter(|&(_, &used)| used) {
+			chunk.ensure_available(entry.clone());
+		}
+
+		chunk.edit(|mut storage, palette| {
+			let associations: Vec<_> = self.palette.iter().zip(used)
+				.map(|(entry, &used)| if used { palette.reverse_lookup(entry) } else { None })
+				.collect();
+
+			for local in local_positions(min, max) {
+				match self.blocks[self.index_in_chunk(position, local, origin)] {
+					EMPTY => (),
+					value => storage.set(local, associations[value as usize].expect("Entry was made available before editing"))
+				}
+			}
+		});
+	}
+}
+
+/// Cuboid operations. These work a chunk at a time, using setters and palette replacement where possible
+/// instead of setting each block individually. Chunks that are missing from the world are skipped.
+/// Shared chunks, see `ChunkAccess::is_shared`, are only written to if the operation changes them.
+impl<C> World<C> where C: ChunkAccess {
+	/// Sets every block in the cuboid to the target.
+	pub fn fill(&mut self, cuboid: Cuboid, target: &C::Block) {
+		for (position, min, max) in cuboid.chunks() {
+			let chunk = match self.get_mut(position) {
+				Some(chunk) => chunk,
+				None => continue
+			};
+
+			if is_whole_chunk(min, max) {
+				chunk.fill_cube(target);
+			} else {
+				fill_chunk(chunk.cube_mut(), min, max, target);
+			}
+		}
+	}
+
+	/// Replaces every block in the cuboid that matches the predicate with the target.
+	pub fn replace_matching<F>(&mut self, cuboid: Cuboid, mut matches: F, target: &C::Block) where F: FnMut(&C::Block) -> bool {
+		for (position, min, max) in cuboid.chunks() {
+			let chunk = match self.get_mut(position) {
+				Some(chunk) => chunk,
+				None => continue
+			};
+
+			if chunk.is_shared() && count_in_chunk(chunk.cube(), min, max, &mut |block| block != target && matches(block)) == 0 {
+				continue;
+			}
+
+			replace_in_chunk(chunk.cube_mut(), min, max, &mut matches, target);
+		}
+	}
+
+	/// Counts the blocks in the cuboid that match the predicate.
+	pub fn count_matching<F>(&self, cuboid: Cuboid, mut matches: F) -> u64 where F: FnMut(&C::Block) -> bool {
+		cuboid.chunks()
+			.filter_map(|(position, min, max)| self.get(position).map(|chunk| count_in_chunk(chunk.cube(), min, max, &mut matches)))
+			.sum()
+	}
+
+	/// Copies the blocks in the cuboid to a new clipboard. Blocks in missing chunks are left empty.
+	pub fn copy(&self, cuboid: Cuboid) -> Clipboard<C::Block> {
+		let mut clipboard = Clipboard::new(cuboid.size_x(), cuboid.size_y(), cuboid.size_z());
+
+		for (position, min, max) in cuboid.chunks() {
+			if let Some(chunk) = self.get(position) {
+				clipboard.copy_chunk(chunk.cube(), position, min, max, cuboid.min());
+			}
+		}
+
+		clipboard
+	}
+
+	/// Pastes the clipboard with its -X, -Y, -Z corner at the origin. Empty blocks in the clipboard are skipped.
+	/// Use `Clipboard::rotate_y` and `Clipboard::mirror` to paste the clipboard in a different orientation.
+	pub fn paste(&mut self, clipboard: &Clipboard<C::Block>, origin: GlobalPosition) {
+		if clipboard.blocks.is_empty() {
+			return;
+		}
+
+		let cuboid = Cuboid::from_size(origin, clipboard.size_x(), clipboard.size_y(), clipboard.size_z());
+
+		for (position, min, max) in cuboid.chunks() {
+			let chunk = match self.get_mut(position) {
+				Some(chunk) => chunk,
+				None => continue
+			};
+
+			if let Some(used) = clipboard.used_in_chunk(position, min, max, origin) {
+				clipboard.paste_chunk(chunk.cube_mut(), position, min, max, origin, &used);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{Axis, Cuboid, GlobalChunkPosition, GlobalPosition};
+	use crate::world::clipboard::Clipboard;
+	use crate::world::world::World;
+
+	fn world() -> World<IndexedCube<u16>> {
+		let mut world = World::new();
+
+		for x in -1..1 {
+			for z in -1..1 {
+				world.set(GlobalChunkPosition::new(x, 0, z), IndexedCube::new(4, 0));
+			}
+		}
+
+		world
+	}
+
+	#[test]
+	fn test_fill_replace_count() {
+		let mut world = world();
+		let cuboid = Cuboid::new(GlobalPosition::new(-16, 0, -16), GlobalPosition::new(15, 15, 15));
+		let small = Cuboid::new(GlobalPosition::new(3, 4, 5), GlobalPosition::new(-2, 2, -1));
+
+		world.fill(small, &1);
+
+		assert_eq!(small.volume(), 6 * 3 * 7);
+		assert_eq!(world.count_matching(cuboid, |&block| block == 1), small.volume());
+		assert_eq!(world.get_block(GlobalPosition::new(-2, 2, -1)), Some(&1));
+		assert_eq!(world.get_block(GlobalPosition::new(-3, 2, -1)), Some(&0));
+
+		// Whole chunks go through palette replacement, the others through a setter.
+		world.replace_matching(Cuboid::new(GlobalPosition::new(-16, 0, -16), GlobalPosition::new(15, 15, 0)), |&block| block == 1, &2);
+
+		assert_eq!(world.count_matching(cuboid, |&block| block == 1), 6 * 3 * 5);
+		assert_eq!(world.count_matching(cuboid, |&block| block == 2), 6 * 3 * 2);
+		assert_eq!(world.count_matching(cuboid, |&block| block == 0), cuboid.volume() - small.volume());
+
+		// Blocks in missing chunks are skipped.
+		world.fill(Cuboid::new(GlobalPosition::new(0, 0, 0), GlobalPosition::new(31, 0, 0)), &3);
+		assert_eq!(world.count_matching(cuboid, |&block| block == 3), 16);
+	}
+
+	#[test]
+	fn test_copy_paste() {
+		let mut world = world();
+
+		world.set_block(GlobalPosition::new(-1, 0, -1), &1).unwrap();
+		world.set_block(GlobalPosition::new(0, 0, -1), &2).unwrap();
+		world.set_block(GlobalPosition::new(-1, 1, 0), &3).unwrap();
+
+		let clipboard = world.copy(Cuboid::new(GlobalPosition::new(-1, 0, -1), GlobalPosition::new(0, 1, 1)));
+
+		assert_eq!((clipboard.size_x(), clipboard.size_y(), clipboard.size_z()), (2, 2, 3));
+		assert_eq!(clipboard.get(0, 0, 0), Some(&1));
+		assert_eq!(clipboard.get(1, 0, 0), Some(&2));
+		assert_eq!(clipboard.get(0, 1, 1), Some(&3));
+
+		let rotated = clipboard.rotate_y(1);
+
+		assert_eq!((rotated.size_x(), rotated.size_z()), (3, 2));
+		assert_eq!(rotated.get(2, 0, 0), Some(&1));
+		assert_eq!(rotated.get(2, 0, 1), Some(&2));
+		assert_eq!(rotated.get(1, 1, 0), Some(&3));
+		assert_eq!(rotated.rotate_y(3), clipboard);
+		assert_eq!(clipboard.rotate_y(2), clipboard.mirror(Axis::X).mirror(Axis::Z));
+
+		let mut mirrored = rotated.mirror(Axis::Y);
+		mirrored.clear(0, 0, 0);
+
+		world.paste(&mirrored, GlobalPosition::new(-2, 10, -1));
+
+		assert_eq!(world.get_block(GlobalPosition::new(0, 11, -1)), Some(&1));
+		assert_eq!(world.get_block(GlobalPosition::new(0, 11, 0)), Some(&2));
+		assert_eq!(world.get_block(GlobalPosition::new(-1, 10, -1)), Some(&3));
+
+		// Pasting also works with an empty clipboard.
+		world.paste(&Clipboard::new(1, 1, 1), GlobalPosition::new(0, 0, 0));
+		assert_eq!(world.get_block(GlobalPosition::new(0, 0, 0)), Some(&0));
+	}
+
+	#[test]
+	fn test_out_of_range_y() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+		world.set(GlobalChunkPosition::new(0, 127, 0), IndexedCube::new(4, 0));
+
+		// Only the part of the cuboid below Y=2048 is in a chunk, the rest is skipped.
+		let cuboid = Cuboid::new(GlobalPosition::new(0, 2046, 0), GlobalPosition::new(0, 2100, 0));
+
+		world.fill(cuboid, &1);
+		assert_eq!(world.count_matching(cuboid, |&block| block == 1), 2);
+
+		world.replace_matching(cuboid, |&block| block == 1, &2);
+		assert_eq!(world.get_block(GlobalPosition::new(0, 2047, 0)), Some(&2));
+
+		let clipboard = world.copy(cuboid);
+		assert_eq!(clipboard.get(0, 1, 0), Some(&2));
+		assert_eq!(clipboard.get(0, 2, 0), None);
+
+		world.fill(cuboid, &0);
+		world.paste(&clipboard, GlobalPosition::new(0, 2047, 0));
+		assert_eq!(world.count_matching(cuboid, |&block| block == 2), 1);
+	}
+}
@@ -0,0 +1,150 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::position::GlobalPosition;
+use crate::sparse::SparseCube;
+use crate::world::world::{BlockError, World};
+
+impl<T> World<SparseCube<T>> {
+	/// Gets the component value at the global position, if there is one.
+	pub fn get_component(&self, position: GlobalPosition) -> Option<&T> {
+		self.get(position.checked_global_chunk()?).and_then(|chunk| chunk.get(position.local_block()))
+	}
+
+	pub fn get_component_mut(&mut self, position: GlobalPosition) -> Option<&mut T> {
+		self.get_mut(position.checked_global_chunk()?).and_then(|chunk| chunk.get_mut(position.local_block()))
+	}
+
+	/// Sets the component value at the global position, creating its chunk if needed. Returns the previous value,
+	/// or an error if no chunk can contain the position.
+	pub fn insert_component(&mut self, position: GlobalPosition, value: T) -> Result<Option<T>, BlockError> {
+		let chunk_position = BlockError::chunk_of(position)?;
+
+		Ok(self.get_or_create_mut(chunk_position).insert(position.local_block(), value))
+	}
+
+	/// Removes the component value at the global position. Chunks that become empty are removed from the world, so
+	/// that the world only stores chunks that actually have values.
+	pub fn remove_component(&mut self, position: GlobalPosition) -> Option<T> {
+		let chunk_position = position.checked_global_chunk()?;
+
+		let (value, empty) = {
+			let chunk = self.get_mut(chunk_position)?;
+
+			(chunk.remove(position.local_block()), chunk.is_empty())
+		};
+
+		if empty {
+			self.remove(chunk_position);
+		}
+
+		value
+	}
+
+	/// Moves the component value from one position to another, possibly across chunks. Returns the value that was
+	/// previously at the destination. If there is no value at the source, any value at the destination is still
+	/// removed, since the destination now holds whatever was at the source.
+	///
+	/// If no chunk can contain the destination, returns an error and leaves the world unchanged.
+	pub fn move_component(&mut self, from: GlobalPosition, to: GlobalPosition) -> Result<Option<T>, BlockError> {
+		BlockError::chunk_of(to)?;
+
+		if from == to {
+			return Ok(None);
+		}
+
+		match self.remove_component(from) {
+			Some(value) => self.insert_component(to, value),
+			None => Ok(self.remove_component(to))
+		}
+	}
+
+	/// Returns the total amount of component values in the world.
+	pub fn component_count(&self) -> usize {
+		self.sectors().flat_map(|(_, sector)| sector.iter()).flatten().map(SparseCube::len).sum()
+	}
+
+	/// Replaces the block at the position, keeping the component storage in sync. If the block changes, the component
+	/// value belonging to the old block is removed and returned, in the same way that breaking a chest drops its
+	/// inventory. Setting a block to itself keeps the value.
+	///
+	/// If the block cannot be set, returns the reason and leaves both worlds unchanged. See `World::set_block`.
+	pub fn replace_block<B>(&mut self, blocks: &mut World<IndexedCube<B>>, position: GlobalPosition, target: &B) -> Result<Option<T>, BlockError> where B: Target {
+		let chunk_position = BlockError::chunk_of(position)?;
+		let chunk = blocks.get_mut(chunk_position).ok_or(BlockError::MissingChunk(chunk_position))?;
+
+		if chunk.get(position.local_block()) == target {
+			return Ok(None);
+		}
+
+		chunk.set_immediate(position.local_block(), target);
+
+		Ok(self.remove_component(position))
+	}
+
+	/// Moves a block along with its component value, leaving `empty` behind, such as when a piston pushes a block.
+	/// Returns the component value that belonged to the block that was replaced at the destination.
+	///
+	/// If either block cannot be set, returns the reason for the source first and leaves both worlds unchanged.
+	pub fn move_block<B>(&mut self, blocks: &mut World<IndexedCube<B>>, from: GlobalPosition, to: GlobalPosition, empty: &B) -> Result<Option<T>, BlockError> where B: Target {
+		if from == to {
+			return Ok(None);
+		}
+
+		let (from_chunk, to_chunk) = (BlockError::chunk_of(from)?, BlockError::chunk_of(to)?);
+		let source = blocks.get_block(from).ok_or(BlockError::MissingChunk(from_chunk))?.clone();
+
+		if blocks.get(to_chunk).is_none() {
+			return Err(BlockError::MissingChunk(to_chunk));
+		}
+
+		blocks.set_block(from, empty)?;
+		blocks.set_block(to, &source)?;
+
+		self.move_component(from, to)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::position::GlobalChunkPosition;
+
+	#[test]
+	fn test_replace_and_move() {
+		let mut blocks: World<IndexedCube<u16>> = World::new();
+		let mut signs: World<SparseCube<String>> = World::new();
+
+		blocks.set(GlobalChunkPosition::new(0, 0, 0), IndexedCube::new(4, 0));
+		blocks.set(GlobalChunkPosition::new(1, 0, 0), IndexedCube::new(4, 0));
+
+		let (a, b) = (GlobalPosition::new(15, 3, 2), GlobalPosition::new(16, 3, 2));
+
+		blocks.set_block(a, &63).unwrap();
+		signs.insert_component(a, "Hello".to_string()).unwrap();
+
+		assert_eq!(signs.replace_block(&mut blocks, a, &63), Ok(None));
+		assert_eq!(signs.get_component(a).map(String::as_str), Some("Hello"));
+
+		assert_eq!(signs.move_block(&mut blocks, a, b, &0), Ok(None));
+		assert_eq!(blocks.get_block(a), Some(&0));
+		assert_eq!(blocks.get_block(b), Some(&63));
+		assert_eq!(signs.get_component(a), None);
+		assert_eq!(signs.get_component(b).map(String::as_str), Some("Hello"));
+
+		// The now empty chunk at the source is dropped from the component world.
+		assert!(signs.get(GlobalChunkPosition::new(0, 0, 0)).is_none());
+		assert_eq!(signs.component_count(), 1);
+
+		assert_eq!(signs.replace_block(&mut blocks, b, &0), Ok(Some("Hello".to_string())));
+		assert_eq!(signs.component_count(), 0);
+
+		let missing = GlobalPosition::new(40, 0, 0);
+		assert_eq!(signs.replace_block(&mut blocks, missing, &1), Err(BlockError::MissingChunk(GlobalChunkPosition::new(2, 0, 0))));
+
+		// Positions that no chunk can contain are errors instead of panics.
+		let too_low = GlobalPosition::new(0, -2049, 0);
+		assert_eq!(signs.insert_component(too_low, "Lost".to_string()), Err(BlockError::OutOfRange(too_low)));
+		assert_eq!(signs.move_block(&mut blocks, a, too_low, &0), Err(BlockError::OutOfRange(too_low)));
+		assert_eq!(signs.move_component(b, too_low), Err(BlockError::OutOfRange(too_low)));
+		assert_eq!(blocks.get_block(a), Some(&0));
+	}
+}
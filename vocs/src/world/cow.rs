@@ -0,0 +1,245 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::packed::{Layout, Spanning};
+use crate::position::GlobalChunkPosition;
+use crate::world::world::{ChunkAccess, World};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A copy-on-write handle to a chunk. Cloning the handle only clones the `Arc`, the chunk itself is cloned the first
+/// time it is modified while shared. Dereferences to the chunk for reading.
+#[derive(Debug, Clone)]
+pub struct CowCube<B, L = Spanning> where B: Target, L: Layout {
+	chunk: Arc<IndexedCube<B, L>>
+}
+
+impl<B, L> CowCube<B, L> where B: Target, L: Layout {
+	pub fn new(chunk: IndexedCube<B, L>) -> Self {
+		CowCube { chunk: Arc::new(chunk) }
+	}
+
+	/// Returns a mutable reference to the chunk, cloning it first if another handle shares it.
+	pub fn make_mut(&mut self) -> &mut IndexedCube<B, L> {
+		Arc::make_mut(&mut self.chunk)
+	}
+
+	/// Returns true if another handle, such as one in a snapshot, points to the same chunk.
+	pub fn is_shared(&self) -> bool {
+		Arc::strong_count(&self.chunk) > 1
+	}
+
+	/// Returns true if both handles point to the same chunk.
+	pub fn ptr_eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.chunk, &other.chunk)
+	}
+
+	/// Returns the chunk, cloning it only if it is shared.
+	pub fn into_inner(self) -> IndexedCube<B, L> {
+		Arc::try_unwrap(self.chunk).unwrap_or_else(|shared| (*shared).clone())
+	}
+}
+
+impl<B, L> Deref for CowCube<B, L> where B: Target, L: Layout {
+	type Target = IndexedCube<B, L>;
+
+	fn deref(&self) -> &IndexedCube<B, L> {
+		&self.chunk
+	}
+}
+
+impl<B, L> From<IndexedCube<B, L>> for CowCube<B, L> where B: Target, L: Layout {
+	fn from(chunk: IndexedCube<B, L>) -> Self {
+		CowCube::new(chunk)
+	}
+}
+
+impl<B> World<IndexedCube<B>> where B: Target {
+	/// Wraps every chunk in a copy-on-write handle, so that the world can be snapshotted.
+	pub fn into_cow(self) -> World<CowCube<B>> {
+		let mut world = World::with_height(self.height());
+
+		for (position, mut sector) in self.into_sectors() {
+			let target = world.get_or_create_sector_mut(position);
+
+			while let Some((position, chunk)) = sector.pop_first() {
+				target.set(position, CowCube::new(chunk));
+			}
+		}
+
+		world
+	}
+}
+
+impl<B> ChunkAccess for CowCube<B> where B: Target {
+	type Block = B;
+
+	fn cube(&self) -> &IndexedCube<B> {
+		&self.chunk
+	}
+
+	fn cube_mut(&mut self) -> &mut IndexedCube<B> {
+		self.make_mut()
+	}
+
+	fn is_shared(&self) -> bool {
+		CowCube::is_shared(self)
+	}
+
+	/// Replaces a shared chunk with a new one instead of cloning it, unless its changes are being tracked.
+	fn fill_cube(&mut self, target: &B) {
+		if self.is_shared() && !self.is_tracking() {
+			*self = CowCube::new(self.filled(target.clone()));
+		} else {
+			self.make_mut().fill(target.clone());
+		}
+	}
+}
+
+/// The block, cuboid, and view operations of `World` also work on copy-on-write worlds, see `ChunkAccess`.
+/// Shared chunks are cloned by the first operation that changes them, or by views that borrow them.
+impl<B> World<CowCube<B>> where B: Target {
+	/// Returns a consistent view of the world as it is now, which is not affected by later changes to this world.
+	/// Chunks are shared between both worlds until one of them modifies a chunk, at which point only that chunk is
+	/// cloned. Only the sector tables are copied up front, so memory grows with the amount of modified chunks.
+	pub fn snapshot(&self) -> Self {
+		self.clone()
+	}
+
+	/// Gets a mutable reference to a chunk, cloning it first if a snapshot still shares it.
+	pub fn get_chunk_mut(&mut self, position: GlobalChunkPosition) -> Option<&mut IndexedCube<B>> {
+		self.get_mut(position).map(CowCube::make_mut)
+	}
+
+	/// Returns the amount of chunks that are shared with at least one other world.
+	pub fn shared_chunks(&self) -> usize {
+		self.sectors().flat_map(|(_, sector)| sector.iter()).flatten().filter(|chunk| chunk.is_shared()).count()
+	}
+
+	/// Unwraps every chunk, cloning the ones that are still shared with a snapshot.
+	pub fn into_unique(self) -> World<IndexedCube<B>> {
+		let mut world = World::with_height(self.height());
+
+		for (position, mut sector) in self.into_sectors() {
+			let target = world.get_or_create_sector_mut(position);
+
+			while let Some((position, chunk)) = sector.pop_first() {
+				target.set(position, chunk.into_inner());
+			}
+		}
+
+		world
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::position::{CubePosition, Cuboid, GlobalColumnPosition, GlobalPosition, Height, NeighborhoodPosition, QuadPosition};
+	use crate::view::SpillPolicy;
+
+	#[test]
+	fn test_snapshot_isolation() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		for x in 0..4 {
+			world.set(GlobalChunkPosition::new(x, 0, 0), IndexedCube::new(4, 0));
+		}
+
+		let mut world = world.into_cow();
+		let snapshot = world.snapshot();
+
+		assert_eq!(world.shared_chunks(), 4);
+
+		let position = GlobalPosition::new(17, 3, 4);
+
+		// Setting a block to its current value does not unshare the chunk.
+		world.set_block(position, &0).unwrap();
+		assert_eq!(world.shared_chunks(), 4);
+
+		world.set_block(position, &5).unwrap();
+
+		assert_eq!(world.get_block(position), Some(&5));
+		assert_eq!(snapshot.get_block(position), Some(&0));
+		assert_eq!(world.shared_chunks(), 3);
+
+		let (chunk, other) = (GlobalChunkPosition::new(0, 0, 0), GlobalChunkPosition::new(1, 0, 0));
+
+		assert!(world.get(chunk).unwrap().ptr_eq(snapshot.get(chunk).unwrap()));
+		assert!(!world.get(other).unwrap().ptr_eq(snapshot.get(other).unwrap()));
+
+		drop(snapshot);
+
+		let world = world.into_unique();
+		assert_eq!(world.get_block(position), Some(&5));
+	}
+
+	#[test]
+	fn test_views_unshare_borrowed_chunks() {
+		let mut world: World<IndexedCube<u16>> = World::with_height(Height::new(0, 1));
+
+		for x in 0..4 {
+			for z in 0..2 {
+				world.set(GlobalChunkPosition::new(x, 0, z), IndexedCube::new(4, 0));
+			}
+		}
+
+		let mut world = world.into_cow();
+		let snapshot = world.snapshot();
+
+		world.get_quad_mut(GlobalColumnPosition::new(0, 0)).unwrap().set_immediate(QuadPosition::new(17, 0, 0), &1);
+		assert_eq!(world.shared_chunks(), 4);
+
+		{
+			let mut neighborhood = world.get_neighborhood_mut(GlobalColumnPosition::new(2, 0), 3, 1, SpillPolicy::Clip);
+			assert_eq!(neighborhood.set_immediate(NeighborhoodPosition::new(0, 0, 0), &2), Ok(()));
+		}
+
+		// Only the chunks in the neighborhood are unshared, even though nothing was set in one of them.
+		assert_eq!(world.shared_chunks(), 2);
+		assert_eq!(world.get_block(GlobalPosition::new(17, 0, 0)), Some(&1));
+		assert_eq!(snapshot.get_block(GlobalPosition::new(17, 0, 0)), Some(&0));
+
+		let mut world = snapshot.snapshot();
+		let everything = Cuboid::new(GlobalPosition::new(0, 0, 0), GlobalPosition::new(63, 15, 31));
+
+		// Nothing matches, so no chunks are unshared.
+		world.replace_matching(everything, |&block| block == 1, &3);
+		assert_eq!(world.shared_chunks(), 8);
+
+		world.fill(Cuboid::new(GlobalPosition::new(0, 0, 0), GlobalPosition::new(0, 0, 0)), &4);
+		assert_eq!(world.shared_chunks(), 7);
+
+		let mut copied = world.copy(Cuboid::new(GlobalPosition::new(0, 0, 0), GlobalPosition::new(1, 0, 0)));
+		assert_eq!(copied.get(0, 0, 0), Some(&4));
+
+		// The second block of the clipboard is air, and clearing it means nothing is pasted into the second chunk.
+		copied.clear(1, 0, 0);
+		world.paste(&copied, GlobalPosition::new(31, 0, 16));
+
+		assert_eq!(world.get_block(GlobalPosition::new(31, 0, 16)), Some(&4));
+		assert_eq!(world.shared_chunks(), 6);
+		assert_eq!(world.count_matching(everything, |&block| block == 4), 2);
+	}
+
+	#[test]
+	fn test_fill_whole_chunk() {
+		let mut chunk = IndexedCube::new(4, 0);
+		chunk.set_immediate(CubePosition::new(1, 2, 3), &1);
+
+		let mut world: World<IndexedCube<u16>> = World::new();
+		world.set(GlobalChunkPosition::new(0, 0, 0), chunk);
+
+		let mut world = world.into_cow();
+		let snapshot = world.snapshot();
+		let original = snapshot.get(GlobalChunkPosition::new(0, 0, 0)).unwrap();
+
+		world.fill(Cuboid::new(GlobalPosition::new(0, 0, 0), GlobalPosition::new(15, 15, 15)), &2);
+
+		// The shared chunk is replaced by a new uniform chunk, rather than cloned and then overwritten.
+		let filled = world.get(GlobalChunkPosition::new(0, 0, 0)).unwrap();
+
+		assert!(!filled.ptr_eq(original));
+		assert_eq!(filled.uniform_value(), Some(&2));
+		assert_eq!(*original.get(CubePosition::new(1, 2, 3)), 1);
+		assert_eq!(world.shared_chunks(), 0);
+	}
+}
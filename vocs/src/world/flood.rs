@@ -0,0 +1,349 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::mask::{BitCube, BitLayer, Mask};
+use crate::mask::sparse::BlocksMask;
+use crate::position::{dir, Dir, CubePosition, GlobalChunkPosition, GlobalPosition};
+use crate::view::{Directional, MaskOffset, SpillBitCube};
+use crate::world::world::World;
+use crate::component::{CubeStorage, LayerStorage};
+use rustc_hash::FxHashMap;
+use std::mem;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+const DIRECTIONS: [Dir; 6] = [Dir::PlusX, Dir::MinusX, Dir::Up, Dir::Down, Dir::PlusZ, Dir::MinusZ];
+
+/// The part of a connected component that lies inside a single chunk.
+struct Fragment {
+	mask: BitCube,
+	/// The blocks of the fragment on each face of the chunk, as spilled by the flood fill. Fragments in neighboring
+	/// chunks are connected if their spills towards each other intersect, since spills on opposite faces share the
+	/// same layer coordinates.
+	spills: Directional<BitLayer>
+}
+
+/// Splits the matching blocks of a chunk into fragments that are connected within the chunk.
+fn fragments(matching: &BitCube) -> Vec<Fragment> {
+	let mut remaining = matching.clone();
+	let mut fragments = Vec::new();
+
+	while let Some(seed) = remaining.pop_first() {
+		let mut mask = BitCube::default();
+		let mut front = BitCube::default();
+		let mut queue = SpillBitCube::default();
+
+		queue.primary.set_true(seed);
+
+		loop {
+			mem::swap(&mut front, &mut queue.primary);
+
+			if front.empty() {
+				break;
+			}
+
+			while let Some(position) = front.pop_first() {
+				if !matching[position] || mask[position] {
+					continue;
+				}
+
+				mask.set_true(position);
+				remaining.set_false(position);
+
+				queue.set_offset_true(position, dir::PlusX);
+				queue.set_offset_true(position, dir::MinusX);
+				queue.set_offset_true(position, dir::Up);
+				queue.set_offset_true(position, dir::Down);
+				queue.set_offset_true(position, dir::PlusZ);
+				queue.set_offset_true(position, dir::MinusZ);
+			}
+		}
+
+		fragments.push(Fragment { mask, spills: queue.spills });
+	}
+
+	fragments
+}
+
+/// Returns a mask of the blocks in the chunk that match the predicate.
+fn matching<B, F>(chunk: &IndexedCube<B>, predicate: &F) -> BitCube where B: Target, F: Fn(&B) -> bool {
+	let mut mask = BitCube::default();
+
+	if let Some(value) = chunk.uniform_value() {
+		if predicate(value) {
+			mask.fill(true);
+		}
+
+		return mask;
+	}
+
+	let (storage, entries) = chunk.freeze();
+	let matches: Vec<bool> = entries.iter().map(|entry| entry.as_ref().map(predicate).unwrap_or(false)).collect();
+
+	for position in CubePosition::enumerate() {
+		if matches[storage.get(position) as usize] {
+			mask.set_true(position);
+		}
+	}
+
+	mask
+}
+
+/// How a flood fill reached a chunk.
+enum Reached {
+	/// From the start position inside the chunk.
+	Start(CubePosition),
+	/// From a neighboring chunk, through the spill layer in the direction it was travelling.
+	Spill(Dir, BitLayer)
+}
+
+fn neighbor(position: GlobalChunkPosition, direction: Dir) -> Option<GlobalChunkPosition> {
+	match direction {
+		Dir::PlusX => position.plus_x(),
+		Dir::MinusX => position.minus_x(),
+		Dir::Up => position.plus_y(),
+		Dir::Down => position.minus_y(),
+		Dir::PlusZ => position.plus_z(),
+		Dir::MinusZ => position.minus_z()
+	}
+}
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+	while parents[index] != index {
+		parents[index] = parents[parents[index]];
+		index = parents[index];
+	}
+
+	index
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize) {
+	let (a, b) = (find(parents, a), find(parents, b));
+
+	// The lower index stays the root, which keeps the order of components stable.
+	parents[a.max(b)] = a.min(b);
+}
+
+/// Calls the function with the index of every position that is set in the layer.
+fn for_each_set(layer: &BitLayer, mut f: impl FnMut(usize)) {
+	for (index, &word) in layer.blocks().iter().enumerate() {
+		let mut word = word;
+
+		while word != 0 {
+			f(index * 64 + word.trailing_zeros() as usize);
+			word &= word - 1;
+		}
+	}
+}
+
+/// Joins fragments that touch across chunk boundaries, and merges each group into a single mask.
+/// Components are ordered by the first chunk they appear in, in XYZ order.
+fn label(mut chunks: Vec<(GlobalChunkPosition, Vec<Fragment>)>) -> Vec<BlocksMask> {
+	chunks.sort_unstable_by_key(|&(position, _)| (position.x(), position.y(), position.z()));
+
+	let mut indices = FxHashMap::default();
+	let mut starts = Vec::with_capacity(chunks.len());
+	let mut total = 0;
+
+	for (index, &(position, ref fragments)) in chunks.iter().enumerate() {
+		indices.insert(position, index);
+		starts.push(total);
+		total += fragments.len();
+	}
+
+	let mut parents: Vec<usize> = (0..total).collect();
+
+	for (index, &(position, ref fragments)) in chunks.iter().enumerate() {
+		// Every pair of neighbors is visited from the chunk in the minus direction.
+		for &direction in &[Dir::PlusX, Dir::Up, Dir::PlusZ] {
+			let other = match neighbor(position, direction).and_then(|other| indices.get(&other)) {
+				Some(&other) => other,
+				None => continue
+			};
+
+			// The fragment of this chunk that spills into each position of the shared face. Looking fragments up by
+			// position keeps joining linear in the size of the spills, instead of comparing every pair of fragments.
+			let mut owners: [Option<usize>; 256] = [None; 256];
+
+			for (a, fragment) in fragments.iter().enumerate() {
+				for_each_set(&fragment.spills[direction], |at| owners[at] = Some(starts[index] + a));
+			}
+
+			for (b, facing) in chunks[other].1.iter().enumerate() {
+				for_each_set(&facing.spills[direction.opposite()], |at| {
+					if let Some(a) = owners[at] {
+						union(&mut parents, a, starts[other] + b);
+					}
+				});
+			}
+		}
+	}
+
+	let mut components = Vec::new();
+	let mut roots = FxHashMap::default();
+
+	for (index, (position, fragments)) in chunks.into_iter().enumerate() {
+		for (offset, fragment) in fragments.into_iter().enumerate() {
+			let root = find(&mut parents, starts[index] + offset);
+
+			let component = *roots.entry(root).or_insert_with(|| {
+				components.push(BlocksMask::new());
+
+				components.len() - 1
+			});
+
+			components[component].combine_chunk(position, &fragment.mask);
+		}
+	}
+
+	components
+}
+
+impl<B> World<IndexedCube<B>> where B: Target {
+	/// Finds every connected component of the blocks that match the predicate, such as air pockets, caves, or bodies
+	/// of water. Blocks are connected to their 6 direct neighbors, including across chunk boundaries. Missing chunks
+	/// are treated as not matching. Components are ordered by the first chunk they appear in, in XYZ order.
+	pub fn components<F>(&self, predicate: F) -> Vec<BlocksMask> where F: Fn(&B) -> bool {
+		let chunks = self.sectors().flat_map(|(&sector, chunks)| {
+			let predicate = &predicate;
+
+			chunks.enumerate_present().map(move |(local, chunk)| (GlobalChunkPosition::combine(sector, local), fragments(&matching(chunk, predicate))))
+		}).collect();
+
+		label(chunks)
+	}
+
+	/// Finds the connected component of matching blocks that contains the start position, visiting only the chunks that
+	/// the component reaches. Returns an empty mask if the start position does not match, its chunk is missing, or
+	/// no chunk can contain it.
+	pub fn flood_fill<F>(&self, start: GlobalPosition, predicate: F) -> BlocksMask where F: Fn(&B) -> bool {
+		let mut filled = BlocksMask::new();
+		let mut visited: FxHashMap<GlobalChunkPosition, (Vec<Fragment>, Vec<bool>)> = FxHashMap::default();
+
+		let start_chunk = match start.checked_global_chunk() {
+			Some(chunk) => chunk,
+			None => return filled
+		};
+
+		let mut pending = vec![(start_chunk, Reached::Start(start.local_block()))];
+
+		while let Some((position, reached)) = pending.pop() {
+			let chunk = match self.get(position) {
+				Some(chunk) => chunk,
+				None => continue
+			};
+
+			let (fragments, done) = visited.entry(position).or_insert_with(|| {
+				let fragments = fragments(&matching(chunk, &predicate));
+				let done = vec![false; fragments.len()];
+
+				(fragments, done)
+			});
+
+			for (fragment, done) in fragments.iter().zip(done.iter_mut()) {
+				let contains = match reached {
+					Reached::Start(block) => fragment.mask[block],
+					Reached::Spill(direction, ref layer) => layer.intersects(&fragment.spills[direction.opposite()])
+				};
+
+				if *done || !contains {
+					continue;
+				}
+
+				*done = true;
+				filled.combine_chunk(position, &fragment.mask);
+
+				for &direction in DIRECTIONS.iter() {
+					let spill = &fragment.spills[direction];
+
+					if let (Some(next), false) = (neighbor(position, direction), spill.is_filled(false)) {
+						pending.push((next, Reached::Spill(direction, spill.clone())));
+					}
+				}
+			}
+		}
+
+		filled
+	}
+}
+
+#[cfg(feature = "rayon")]
+impl<B> World<IndexedCube<B>> where B: Target + Send + Sync {
+	/// Parallel version of `components`. Chunks are split into fragments in parallel, and then joined together.
+	pub fn par_components<F>(&self, predicate: F) -> Vec<BlocksMask> where F: Fn(&B) -> bool + Sync {
+		let chunks = self.par_sectors().flat_map(|(&sector, chunks)| {
+			let predicate = &predicate;
+
+			chunks.par_enumerate_present().map(move |(local, chunk)| (GlobalChunkPosition::combine(sector, local), fragments(&matching(chunk, predicate))))
+		}).collect();
+
+		label(chunks)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_components() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		for x in -1..2 {
+			world.set(GlobalChunkPosition::new(x, 0, 0), IndexedCube::uniform(1));
+		}
+
+		// A tunnel of air crossing two chunk boundaries, and a separate pocket in the middle chunk.
+		for x in -10..26 {
+			world.set_block(GlobalPosition::new(x, 4, 4), &0).unwrap();
+		}
+
+		world.set_block(GlobalPosition::new(8, 10, 10), &0).unwrap();
+		world.set_block(GlobalPosition::new(8, 11, 10), &0).unwrap();
+
+		let components = world.components(|&block| block == 0);
+
+		assert_eq!(components.len(), 2);
+		assert_eq!(components[0].count_ones(), 36);
+		assert_eq!(components[0].chunks().count(), 3);
+		assert_eq!(components[1].count_ones(), 2);
+		assert!(components[1][GlobalPosition::new(8, 11, 10)]);
+
+		let filled = world.flood_fill(GlobalPosition::new(25, 4, 4), |&block| block == 0);
+
+		assert_eq!(filled, components[0]);
+		assert!(world.flood_fill(GlobalPosition::new(0, 0, 0), |&block| block == 0).is_empty());
+		assert!(world.flood_fill(GlobalPosition::new(0, 4096, 0), |_| true).is_empty());
+
+		// Everything else is a single solid component wrapping around the tunnel.
+		assert_eq!(world.components(|&block| block == 1).len(), 1);
+
+		#[cfg(feature = "rayon")]
+		assert_eq!(world.par_components(|&block| block == 0), components);
+	}
+
+	#[test]
+	fn test_many_fragments_on_a_face() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		for x in -1..2 {
+			world.set(GlobalChunkPosition::new(x, 0, 0), IndexedCube::uniform(1));
+		}
+
+		// A grid of parallel tunnels crossing both chunk boundaries, each of which is its own component.
+		for y in (0..16).step_by(2) {
+			for z in (0..16).step_by(2) {
+				for x in -16..32 {
+					world.set_block(GlobalPosition::new(x, y, z), &0).unwrap();
+				}
+			}
+		}
+
+		let components = world.components(|&block| block == 0);
+
+		assert_eq!(components.len(), 64);
+		assert!(components.iter().all(|component| component.count_ones() == 48 && component.chunks().count() == 3));
+
+		let filled = world.flood_fill(GlobalPosition::new(31, 14, 6), |&block| block == 0);
+		assert_eq!(components.iter().filter(|&component| *component == filled).count(), 1);
+	}
+}
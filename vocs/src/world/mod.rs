@@ -1,3 +1,9 @@
 pub mod world;
 pub mod sector;
-pub mod shared;
\ No newline at end of file
+pub mod shared;
+pub mod pending;
+pub mod clipboard;
+pub mod raycast;
+pub mod components;
+pub mod cow;
+pub mod flood;
\ No newline at end of file
@@ -0,0 +1,199 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::memory::{self, MemoryReport, MemoryUsage};
+use crate::position::{CubePosition, GlobalChunkPosition, GlobalPosition};
+use crate::world::world::{BlockError, World};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::mem;
+
+/// A buffer of block edits to chunks that do not exist yet, such as a structure or decoration reaching into the
+/// next batch of terrain. Edits are kept per chunk, and replayed in the order that they were recorded once the
+/// chunk is created. See `set_chunk` and `flush`.
+#[derive(Debug, Clone)]
+pub struct PendingEdits<B> where B: Target {
+	chunks: HashMap<GlobalChunkPosition, Vec<(CubePosition, B)>>,
+	edits: usize
+}
+
+impl<B> PendingEdits<B> where B: Target {
+	pub fn new() -> Self {
+		PendingEdits {
+			chunks: HashMap::new(),
+			edits: 0
+		}
+	}
+
+	/// Returns the total amount of pending edits.
+	pub fn len(&self) -> usize {
+		self.edits
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.edits == 0
+	}
+
+	/// Returns the amount of distinct chunks with pending edits.
+	pub fn chunks(&self) -> usize {
+		self.chunks.len()
+	}
+
+	pub fn contains_chunk(&self, position: GlobalChunkPosition) -> bool {
+		self.chunks.contains_key(&position)
+	}
+
+	/// Records an edit, to be applied once the chunk containing the position is created.
+	/// Fails if no chunk can contain the position, since the edit could never be applied.
+	pub fn push(&mut self, position: GlobalPosition, target: B) -> Result<(), BlockError> {
+		let chunk_position = BlockError::chunk_of(position)?;
+
+		self.chunks.entry(chunk_position).or_default().push((position.local_block(), target));
+		self.edits += 1;
+
+		Ok(())
+	}
+
+	/// Merges the edits from another buffer, as if they were recorded after the edits in this one.
+	pub fn append(&mut self, other: PendingEdits<B>) {
+		for (position, mut edits) in other.chunks {
+			self.edits += edits.len();
+
+			match self.chunks.entry(position) {
+				Entry::Occupied(mut occupied) => occupied.get_mut().append(&mut edits),
+				Entry::Vacant(vacant) => { vacant.insert(edits); }
+			}
+		}
+	}
+
+	/// Removes the pending edits for a chunk without applying them, in the order that they were recorded.
+	pub fn take(&mut self, position: GlobalChunkPosition) -> Vec<(CubePosition, B)> {
+		let edits = self.chunks.remove(&position).unwrap_or_default();
+		self.edits -= edits.len();
+
+		edits
+	}
+
+	/// Applies the pending edits for a chunk to it, returning the amount of edits applied.
+	pub fn apply(&mut self, position: GlobalChunkPosition, chunk: &mut IndexedCube<B>) -> usize {
+		let edits = self.take(position);
+
+		for &(block, ref target) in &edits {
+			chunk.set_immediate(block, target);
+		}
+
+		edits.len()
+	}
+
+	/// Sets the block if its chunk exists, otherwise records the edit. Pending edits to the chunk are applied first,
+	/// so that edits always land in the order that they were made. Fails if no chunk can contain the position.
+	pub fn set_block(&mut self, world: &mut World<IndexedCube<B>>, position: GlobalPosition, target: &B) -> Result<(), BlockError> {
+		let chunk_position = BlockError::chunk_of(position)?;
+
+		match world.get_mut(chunk_position) {
+			Some(chunk) => {
+				self.apply(chunk_position, chunk);
+				chunk.set_immediate(position.local_block(), target);
+
+				Ok(())
+			},
+			None => self.push(position, target.clone())
+		}
+	}
+
+	/// Applies the pending edits to a newly created chunk, then adds it to the world.
+	pub fn set_chunk(&mut self, world: &mut World<IndexedCube<B>>, position: GlobalChunkPosition, mut chunk: IndexedCube<B>) {
+		self.apply(position, &mut chunk);
+
+		world.set(position, chunk);
+	}
+
+	/// Applies the pending edits of every chunk that now exists in the world, for chunks that were added without
+	/// going through `set_chunk`. Returns the amount of edits applied.
+	pub fn flush(&mut self, world: &mut World<IndexedCube<B>>) -> usize {
+		let mut applied = 0;
+
+		self.chunks.retain(|&position, edits| {
+			let chunk = match world.get_mut(position) {
+				Some(chunk) => chunk,
+				None => return true
+			};
+
+			for &(block, ref target) in edits.iter() {
+				chunk.set_immediate(block, target);
+			}
+
+			applied += edits.len();
+			false
+		});
+
+		self.edits -= applied;
+		applied
+	}
+}
+
+impl<B> Default for PendingEdits<B> where B: Target {
+	fn default() -> Self {
+		PendingEdits::new()
+	}
+}
+
+impl<B> MemoryUsage for PendingEdits<B> where B: Target {
+	/// Returns the memory used by the recorded edits, along with the table of chunks. This does not include any
+	/// heap memory owned by the blocks themselves.
+	fn memory_usage(&self) -> MemoryReport {
+		let edit = mem::size_of::<(CubePosition, B)>();
+
+		MemoryReport {
+			pending: self.chunks.values().map(|edits| edits.capacity() * edit).sum(),
+			structure: memory::hash_map_bytes(&self.chunks),
+			..MemoryReport::default()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{CubePosition, GlobalChunkPosition, GlobalPosition};
+	use crate::memory::MemoryUsage;
+	use crate::world::pending::PendingEdits;
+	use crate::world::world::{BlockError, World};
+
+	#[test]
+	fn test_replay_in_order() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+		let mut pending = PendingEdits::new();
+
+		let chunk = GlobalChunkPosition::new(-1, 2, 0);
+		let position = GlobalPosition::new(-3, 40, 5);
+
+		pending.set_block(&mut world, position, &1).unwrap();
+		pending.set_block(&mut world, position, &2).unwrap();
+		pending.push(GlobalPosition::new(100, 0, 100), 3).unwrap();
+
+		let too_high = GlobalPosition::new(0, 2048, 0);
+		assert_eq!(pending.push(too_high, 5), Err(BlockError::OutOfRange(too_high)));
+		assert_eq!(pending.set_block(&mut world, too_high, &5), Err(BlockError::OutOfRange(too_high)));
+
+		assert_eq!(pending.len(), 3);
+		assert_eq!(pending.chunks(), 2);
+		assert!(pending.contains_chunk(chunk));
+		assert!(pending.memory_usage().pending > 0);
+
+		pending.set_chunk(&mut world, chunk, IndexedCube::new(4, 0));
+
+		assert_eq!(pending.len(), 1);
+		assert!(!pending.contains_chunk(chunk));
+		assert_eq!(*world.get(chunk).unwrap().get(CubePosition::new(13, 8, 5)), 2);
+
+		// The chunk exists now, so further edits are applied immediately.
+		pending.set_block(&mut world, position, &4).unwrap();
+		assert_eq!(pending.len(), 1);
+		assert_eq!(*world.get(chunk).unwrap().get(CubePosition::new(13, 8, 5)), 4);
+
+		world.set(GlobalChunkPosition::new(6, 0, 6), IndexedCube::new(4, 0));
+
+		assert_eq!(pending.flush(&mut world), 1);
+		assert!(pending.is_empty());
+		assert_eq!(*world.get(GlobalChunkPosition::new(6, 0, 6)).unwrap().get(CubePosition::new(4, 0, 4)), 3);
+	}
+}
@@ -0,0 +1,390 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::position::{CubePosition, Dir, GlobalChunkPosition, GlobalPosition};
+use crate::world::shared::{ReadGuard, ReadPacked, SharedWorld};
+use crate::world::world::World;
+use std::ops::{Deref, RangeInclusive};
+
+/// Global Y values outside of this range do not have a chunk, see `GlobalPosition::global_chunk`.
+const Y_RANGE: RangeInclusive<i32> = -2048..=2047;
+
+/// A single block visited by a `VoxelRay`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayStep {
+	pub position: GlobalPosition,
+	/// The face of the block that the ray entered through, or None for the block that the ray starts in.
+	pub face: Option<Dir>,
+	/// The distance along the ray to where it entered the block, in blocks.
+	pub distance: f64
+}
+
+/// Visits every block that a ray passes through in order, using the DDA algorithm of Amanatides and Woo.
+/// This knows nothing about the contents of the world, see `World::raycast` for that.
+#[derive(Debug, Clone)]
+pub struct VoxelRay {
+	position: (i32, i32, i32),
+	step: (i32, i32, i32),
+	/// Distance along the ray at which the next boundary on each axis is crossed.
+	t_max: (f64, f64, f64),
+	/// Distance along the ray between boundaries on each axis.
+	t_delta: (f64, f64, f64),
+	max_distance: f64,
+	started: bool
+}
+
+impl VoxelRay {
+	/// Creates a ray from the origin towards the direction, ending after `max_distance` blocks.
+	/// The direction does not need to be normalized, but may not be zero.
+	pub fn new(origin: (f64, f64, f64), direction: (f64, f64, f64), max_distance: f64) -> Self {
+		let length = (direction.0 * direction.0 + direction.1 * direction.1 + direction.2 * direction.2).sqrt();
+
+		assert!(length > 0.0, "Ray direction must not be zero");
+
+		let axis = |origin: f64, direction: f64| {
+			let direction = direction / length;
+			let block = origin.floor();
+
+			if direction > 0.0 {
+				(block as i32, 1, (block + 1.0 - origin) / direction, 1.0 / direction)
+			} else if direction < 0.0 {
+				(block as i32, -1, (origin - block) / -direction, 1.0 / -direction)
+			} else {
+				(block as i32, 0, f64::INFINITY, f64::INFINITY)
+			}
+		};
+
+		let x = axis(origin.0, direction.0);
+		let y = axis(origin.1, direction.1);
+		let z = axis(origin.2, direction.2);
+
+		VoxelRay {
+			position: (x.0, y.0, z.0),
+			step: (x.1, y.1, z.1),
+			t_max: (x.2, y.2, z.2),
+			t_delta: (x.3, y.3, z.3),
+			max_distance,
+			started: false
+		}
+	}
+}
+
+impl VoxelRay {
+	/// Advances the ray to the last block that it visits in the current chunk, without returning the blocks
+	/// in between. The next step is the first block in the following chunk, or the end of the ray.
+	pub fn skip_chunk(&mut self) {
+		let axis = |position: i32, step: i32| match step {
+			// Amount of blocks until the edge of the chunk in the direction of travel.
+			1 => 15 - (position & 15),
+			-1 => position & 15,
+			_ => 0
+		};
+
+		let remaining = (
+			axis(self.position.0, self.step.0),
+			axis(self.position.1, self.step.1),
+			axis(self.position.2, self.step.2)
+		);
+
+		// The distances are accumulated the same way as in `next`, so that the skipped ray lines up exactly.
+		let exit_at = |t_max: f64, t_delta: f64, remaining: i32| (0..remaining).fold(t_max, |t, _| t + t_delta);
+
+		let exit = exit_at(self.t_max.0, self.t_delta.0, remaining.0)
+			.min(exit_at(self.t_max.1, self.t_delta.1, remaining.1))
+			.min(exit_at(self.t_max.2, self.t_delta.2, remaining.2));
+
+		if exit > self.max_distance {
+			// The ray ends before it leaves the chunk.
+			self.max_distance = f64::NEG_INFINITY;
+			return;
+		}
+
+		let advance = |position: &mut i32, step: i32, t_max: &mut f64, t_delta: f64, remaining: i32| {
+			for _ in 0..remaining {
+				if *t_max >= exit {
+					break;
+				}
+
+				*position += step;
+				*t_max += t_delta;
+			}
+		};
+
+		advance(&mut self.position.0, self.step.0, &mut self.t_max.0, self.t_delta.0, remaining.0);
+		advance(&mut self.position.1, self.step.1, &mut self.t_max.1, self.t_delta.1, remaining.1);
+		advance(&mut self.position.2, self.step.2, &mut self.t_max.2, self.t_delta.2, remaining.2);
+	}
+}
+
+impl Iterator for VoxelRay {
+	type Item = RayStep;
+
+	fn next(&mut self) -> Option<RayStep> {
+		if !self.started {
+			self.started = true;
+
+			let (x, y, z) = self.position;
+
+			return if !Y_RANGE.contains(&y) {
+				None
+			} else {
+				Some(RayStep { position: GlobalPosition::new(x, y, z), face: None, distance: 0.0 })
+			};
+		}
+
+		let (distance, face) = if self.t_max.0 <= self.t_max.1 && self.t_max.0 <= self.t_max.2 {
+			let distance = self.t_max.0;
+
+			self.position.0 += self.step.0;
+			self.t_max.0 += self.t_delta.0;
+
+			(distance, if self.step.0 > 0 { Dir::MinusX } else { Dir::PlusX })
+		} else if self.t_max.1 <= self.t_max.2 {
+			let distance = self.t_max.1;
+
+			self.position.1 += self.step.1;
+			self.t_max.1 += self.t_delta.1;
+
+			(distance, if self.step.1 > 0 { Dir::Down } else { Dir::Up })
+		} else {
+			let distance = self.t_max.2;
+
+			self.position.2 += self.step.2;
+			self.t_max.2 += self.t_delta.2;
+
+			(distance, if self.step.2 > 0 { Dir::MinusZ } else { Dir::PlusZ })
+		};
+
+		let (x, y, z) = self.position;
+
+		if distance > self.max_distance || !Y_RANGE.contains(&y) {
+			// Make sure that the ray stays finished.
+			self.max_distance = f64::NEG_INFINITY;
+			return None;
+		}
+
+		Some(RayStep { position: GlobalPosition::new(x, y, z), face: Some(face), distance })
+	}
+}
+
+/// A solid block hit by a raycast.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayHit<B> where B: Target {
+	pub position: GlobalPosition,
+	/// The face of the block that the ray entered through, or None if the ray started inside of the block.
+	pub face: Option<Dir>,
+	/// The distance along the ray to where it entered the block, in blocks.
+	pub distance: f64,
+	pub block: B
+}
+
+/// Something that rays can be cast through, providing access to one chunk at a time.
+pub trait RaycastSource<B> where B: Target {
+	type Chunk: Deref<Target=IndexedCube<B>>;
+
+	fn chunk(&self, position: GlobalChunkPosition) -> Option<Self::Chunk>;
+}
+
+impl<'w, B> RaycastSource<B> for &'w World<IndexedCube<B>> where B: Target {
+	type Chunk = &'w IndexedCube<B>;
+
+	fn chunk(&self, position: GlobalChunkPosition) -> Option<&'w IndexedCube<B>> {
+		self.get(position)
+	}
+}
+
+impl<'w, B, T> RaycastSource<B> for &'w SharedWorld<T> where T: ReadPacked<Unpacked=IndexedCube<B>>, B: Target {
+	type Chunk = ReadGuard<'w, T>;
+
+	fn chunk(&self, position: GlobalChunkPosition) -> Option<ReadGuard<'w, T>> {
+		self.read(position)
+	}
+}
+
+/// Iterator over the solid blocks hit by a ray, in order of distance. See `World::raycast`.
+pub struct Raycast<B, S, F> where B: Target, S: RaycastSource<B>, F: FnMut(&B) -> bool {
+	ray: VoxelRay,
+	source: S,
+	solid: F,
+	/// The chunk the ray is currently in. The chunk is None if it is missing, or known to be free of solid blocks.
+	current: Option<(GlobalChunkPosition, Option<S::Chunk>)>
+}
+
+impl<B, S, F> Raycast<B, S, F> where B: Target, S: RaycastSource<B>, F: FnMut(&B) -> bool {
+	pub fn new(ray: VoxelRay, source: S, solid: F) -> Self {
+		Raycast { ray, source, solid, current: None }
+	}
+}
+
+impl<B, S, F> Iterator for Raycast<B, S, F> where B: Target, S: RaycastSource<B>, F: FnMut(&B) -> bool {
+	type Item = RayHit<B>;
+
+	fn next(&mut self) -> Option<RayHit<B>> {
+		loop {
+			let step = self.ray.next()?;
+
+			// No chunks exist past the vertical range of chunk positions, so the ray ends there.
+			let chunk_position = step.position.checked_global_chunk()?;
+
+			let entered = match self.current {
+				Some((position, _)) => position != chunk_position,
+				None => true
+			};
+
+			if entered {
+				// Drop the old chunk first, in case it holds a lock.
+				self.current = None;
+
+				let chunk = self.source.chunk(chunk_position).filter(|chunk| {
+					// Uniform chunks without any solid blocks can be skipped without looking at each block.
+					let first = chunk.get(CubePosition::new(0, 0, 0));
+
+					!chunk.is_filled_heuristic(first) || (self.solid)(first)
+				});
+
+				self.current = Some((chunk_position, chunk));
+			}
+
+			let chunk = match self.current {
+				Some((_, Some(ref chunk))) => chunk,
+				_ => {
+					// Nothing in this chunk can be hit, so go straight to the block where the ray leaves it.
+					self.ray.skip_chunk();
+					continue
+				}
+			};
+
+			let block = chunk.get(step.position.local_block());
+
+			if (self.solid)(block) {
+				return Some(RayHit {
+					position: step.position,
+					face: step.face,
+					distance: step.distance,
+					block: block.clone()
+				});
+			}
+		}
+	}
+}
+
+impl<B> World<IndexedCube<B>> where B: Target {
+	/// Casts a ray from the origin towards the direction, returning the blocks matching the solidity predicate
+	/// that it hits within `max_distance` blocks. Missing chunks are treated as empty.
+	pub fn raycast<F>(&self, origin: (f64, f64, f64), direction: (f64, f64, f64), max_distance: f64, solid: F) -> Raycast<B, &World<IndexedCube<B>>, F> where F: FnMut(&B) -> bool {
+		Raycast::new(VoxelRay::new(origin, direction, max_distance), self, solid)
+	}
+
+	/// Returns true if there are no solid blocks between the two points, including the blocks that contain them.
+	pub fn line_of_sight<F>(&self, from: (f64, f64, f64), to: (f64, f64, f64), solid: F) -> bool where F: FnMut(&B) -> bool {
+		line_of_sight(self, from, to, solid)
+	}
+}
+
+impl<B, T> SharedWorld<T> where T: ReadPacked<Unpacked=IndexedCube<B>>, B: Target {
+	/// Shared version of `World::raycast`. The returned iterator holds a read lock on the chunk that the ray is
+	/// currently in, so other rays can pass through it at the same time, but writes to it have to wait.
+	pub fn raycast<F>(&self, origin: (f64, f64, f64), direction: (f64, f64, f64), max_distance: f64, solid: F) -> Raycast<B, &SharedWorld<T>, F> where F: FnMut(&B) -> bool {
+		Raycast::new(VoxelRay::new(origin, direction, max_distance), self, solid)
+	}
+
+	/// Shared version of `World::line_of_sight`.
+	pub fn line_of_sight<F>(&self, from: (f64, f64, f64), to: (f64, f64, f64), solid: F) -> bool where F: FnMut(&B) -> bool {
+		line_of_sight(self, from, to, solid)
+	}
+}
+
+fn line_of_sight<B, S, F>(source: S, from: (f64, f64, f64), to: (f64, f64, f64), solid: F) -> bool where B: Target, S: RaycastSource<B>, F: FnMut(&B) -> bool {
+	let direction = (to.0 - from.0, to.1 - from.1, to.2 - from.2);
+	let distance = (direction.0 * direction.0 + direction.1 * direction.1 + direction.2 * direction.2).sqrt();
+
+	if distance == 0.0 {
+		// Any direction works, the ray ends in the block it starts in.
+		return Raycast::new(VoxelRay::new(from, (1.0, 0.0, 0.0), 0.0), source, solid).next().is_none();
+	}
+
+	Raycast::new(VoxelRay::new(from, direction, distance), source, solid).next().is_none()
+}
+
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{Dir, GlobalChunkPosition, GlobalPosition};
+	use crate::world::raycast::VoxelRay;
+	use crate::world::shared::{NoPack, SharedWorld};
+	use crate::world::world::World;
+
+	#[test]
+	fn test_voxel_ray() {
+		let steps: Vec<_> = VoxelRay::new((0.5, 0.5, 0.5), (-1.0, 0.0, 0.0), 2.0).collect();
+
+		assert_eq!(steps.len(), 3);
+		assert_eq!(steps[0].face, None);
+		assert_eq!(steps[1].position, GlobalPosition::new(-1, 0, 0));
+		assert_eq!(steps[1].face, Some(Dir::PlusX));
+		assert_eq!(steps[2].distance, 1.5);
+
+		// A diagonal ray visits one more block for each boundary that it crosses.
+		let steps = VoxelRay::new((0.25, 0.5, 0.5), (1.0, 1.0, 0.0), 4.0).count();
+		assert_eq!(steps, 7);
+	}
+
+	#[test]
+	fn test_skip_chunk() {
+		let rays = [
+			((0.5, 8.5, 8.5), (1.0, 0.0, 0.0)),
+			((3.25, 7.5, 12.75), (0.7, -0.2, 0.45)),
+			((-20.5, 30.125, 5.5), (-0.3, -1.0, -0.6))
+		];
+
+		for &(origin, direction) in rays.iter() {
+			let steps: Vec<_> = VoxelRay::new(origin, direction, 100.0).collect();
+
+			let mut ray = VoxelRay::new(origin, direction, 100.0);
+			let start = ray.next().unwrap().position.global_chunk();
+
+			ray.skip_chunk();
+
+			// Skipping lands on the same step as walking through the chunk one block at a time.
+			let expected = steps.iter().find(|step| step.position.global_chunk() != start);
+			assert_eq!(ray.next().as_ref(), expected);
+		}
+
+		// Rays that end inside of the chunk end when it is skipped.
+		let mut ray = VoxelRay::new((0.5, 8.5, 8.5), (1.0, 0.0, 0.0), 4.0);
+		ray.next();
+		ray.skip_chunk();
+
+		assert_eq!(ray.next(), None);
+	}
+
+	#[test]
+	fn test_raycast() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		// The first chunk is uniform and empty, so it is skipped. The second chunk is missing.
+		world.set(GlobalChunkPosition::new(0, 0, 0), IndexedCube::new(4, 0));
+		world.set(GlobalChunkPosition::new(2, 0, 0), IndexedCube::new(4, 0));
+		world.set_block(GlobalPosition::new(40, 8, 8), &1).unwrap();
+		world.set_block(GlobalPosition::new(42, 8, 8), &2).unwrap();
+
+		let hits: Vec<_> = world.raycast((0.5, 8.5, 8.5), (1.0, 0.0, 0.0), 64.0, |&block| block != 0).collect();
+
+		assert_eq!(hits.len(), 2);
+		assert_eq!(hits[0].position, GlobalPosition::new(40, 8, 8));
+		assert_eq!(hits[0].face, Some(Dir::MinusX));
+		assert_eq!(hits[0].distance, 39.5);
+		assert_eq!(hits[1].block, 2);
+
+		assert!(world.line_of_sight((0.5, 8.5, 8.5), (39.5, 8.5, 8.5), |&block| block != 0));
+		assert!(!world.line_of_sight((0.5, 8.5, 8.5), (40.5, 8.5, 8.5), |&block| block != 0));
+
+		let shared: SharedWorld<NoPack<IndexedCube<u16>>> = SharedWorld::from_world(world);
+
+		// Rays only take read locks, so they can pass through chunks that are being read elsewhere.
+		let held = shared.read(GlobalChunkPosition::new(2, 0, 0)).unwrap();
+		assert_eq!(*held.get(GlobalPosition::new(42, 8, 8).local_block()), 2);
+
+		let hit = shared.raycast((50.5, 8.5, 8.5), (-1.0, 0.0, 0.0), 64.0, |&block| block != 0).next().unwrap();
+		assert_eq!(hit.position, GlobalPosition::new(42, 8, 8));
+		assert_eq!(hit.face, Some(Dir::PlusX));
+	}
+}
@@ -1,10 +1,17 @@
 use crate::position::{CubePosition, LayerPosition};
-use crate::indexed::{IndexedCube, Target};
-use crate::mask::{Mask, BitCube};
-use crate::view::{ColumnMut, QuadMut};
+use crate::indexed::{Compaction, IndexedCube, IndexedStorage, Target};
+use crate::packed::Layout;
+use crate::mask::{Mask, BitCube, u1x64};
+use crate::view::{ColumnMut, QuadMut, Sections};
+use crate::memory::{MemoryReport, MemoryUsage};
+use std::mem;
 use std::slice;
-use std::ops::Index;
+use std::ops::{Index, Range};
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[derive(Clone)]
 pub struct Sector<T> {
 	chunks: Box<[Option<T>]>,
 	present: BitCube
@@ -86,20 +93,29 @@ impl<T> Sector<T> {
 		self.chunks[position.yzx() as usize].as_mut()
 	}
 
-	pub fn enumerate_present(&self) -> SectorEnumeratePresent<T> {
-		unimplemented!()
+	/// Enumerates the chunks that are present in this sector, using the presence mask to skip empty slots.
+	pub fn enumerate_present(&self) -> SectorEnumeratePresent<'_, T> {
+		SectorEnumeratePresent {
+			chunks: &self.chunks,
+			indices: PresentIndices::new(self.present.blocks())
+		}
 	}
 
-	pub fn enumerate_present_mut(&self) -> SectorEnumeratePresentMut<T> {
-		unimplemented!()
+	/// Mutable version of `enumerate_present`.
+	pub fn enumerate_present_mut(&mut self) -> SectorEnumeratePresentMut<'_, T> {
+		SectorEnumeratePresentMut {
+			chunks: self.chunks.iter_mut(),
+			next: 0,
+			indices: PresentIndices::new(self.present.blocks())
+		}
 	}
 
-	pub fn iter(&self) -> slice::Iter<Option<T>> {
+	pub fn iter(&self) -> slice::Iter<'_, Option<T>> {
 		self.chunks.iter()
 	}
 
 	// TODO: This can result in the present mask getting out of sync.
-	pub fn iter_mut(&mut self) -> slice::IterMut<Option<T>> {
+	pub fn iter_mut(&mut self) -> slice::IterMut<'_, Option<T>> {
 		self.chunks.iter_mut()
 	}
 
@@ -111,13 +127,23 @@ impl<T> Sector<T> {
 		self.present.count_ones()
 	}
 
-	pub fn columns(&self) -> SectorColumns<T> {
+	fn column(&self, position: LayerPosition) -> [Option<&T>; 16] {
+		let mut chunks = [None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None];
+
+		for y in 0..16 {
+			chunks[y as usize] = self[CubePosition::from_layer(y, position)].as_ref();
+		}
+
+		chunks
+	}
+
+	pub fn columns(&self) -> SectorColumns<'_, T> {
 		SectorColumns {
 			iterator: self.enumerate_columns()
 		}
 	}
 
-	pub fn enumerate_columns(&self) -> SectorEnumerateColumns<T> {
+	pub fn enumerate_columns(&self) -> SectorEnumerateColumns<'_, T> {
 		SectorEnumerateColumns {
 			sector: &self,
 			column: LayerPosition::from_zx(0),
@@ -125,7 +151,7 @@ impl<T> Sector<T> {
 		}
 	}
 
-	pub fn columns_mut(&mut self) -> SectorColumnsMut<T> {
+	pub fn columns_mut(&mut self) -> SectorColumnsMut<'_, T> {
 		let s = self.layers_mut();
 
 		SectorColumnsMut {
@@ -208,6 +234,34 @@ impl<T> Sector<T> {
 		))
 	}
 
+	/// Gets the chunks of several columns at once, restricted to the layers in `layers`. This is used for
+	/// columns that span multiple sectors, where only part of each sector is within the world height.
+	/// Each column is None if any of its chunks within the layers are missing.
+	/// The positions must be distinct and in ascending ZX order.
+	pub fn get_columns_mut(&mut self, positions: &[LayerPosition], layers: Range<u8>) -> Vec<Option<Sections<&mut T>>> {
+		let mut columns: Vec<Option<Sections<&mut T>>> = positions.iter().map(|_| Some(Sections::new())).collect();
+		let mut chunks = self.chunks.iter_mut();
+		let mut next = 0;
+
+		for y in layers {
+			for (column, position) in columns.iter_mut().zip(positions) {
+				let index = ((y as usize) << 8) | (position.zx() as usize);
+
+				assert!(index >= next, "Column positions must be distinct and in ascending order");
+
+				let chunk = chunks.nth(index - next).unwrap();
+				next = index + 1;
+
+				match (column.as_mut(), chunk.as_mut()) {
+					(Some(column), Some(chunk)) => column.push(chunk),
+					_ => *column = None
+				}
+			}
+		}
+
+		columns
+	}
+
 	fn get4_column_mut(&mut self, a: LayerPosition, b: LayerPosition, c: LayerPosition, d: LayerPosition) -> Option<([&mut T; 16], [&mut T; 16], [&mut T; 16], [&mut T; 16])> {
 		let a = a.zx() as usize;
 		let b = b.zx() as usize;
@@ -260,6 +314,15 @@ impl<T> Sector<T> where T: Default {
 	}
 }
 
+impl<T> MemoryUsage for Sector<T> where T: MemoryUsage {
+	fn memory_usage(&self) -> MemoryReport {
+		let mut report: MemoryReport = self.iter().flatten().map(|chunk| MemoryReport { chunks: 1, ..chunk.memory_usage() }).sum();
+		report.structure += self.chunks.len() * mem::size_of::<Option<T>>();
+
+		report
+	}
+}
+
 impl<B> Sector<IndexedCube<B>> where B: Target {
 	pub fn set_block_immediate(&mut self, x: u8, y: u8, z: u8, target: &B) -> Option<()> {
 		let (chunk, block) = (
@@ -279,18 +342,33 @@ impl<B> Sector<IndexedCube<B>> where B: Target {
 		self[chunk].as_ref().map(|chunk| chunk.get(block))
 	}
 
-	pub fn get_quad_mut(&mut self, position: LayerPosition) -> Option<QuadMut<B>> {
+	pub fn get_quad_mut(&mut self, position: LayerPosition) -> Option<QuadMut<'_, B>> {
 		self.get4_column_mut(
 			position,
 			LayerPosition::new(position.x() + 1, position.z()),
 			LayerPosition::new(position.x(), position.z() + 1),
 			LayerPosition::new(position.x() + 1, position.z() + 1)
 		).map(|(primary, plus_x, plus_z, plus_xz)|
-			QuadMut([ColumnMut(primary), ColumnMut(plus_x), ColumnMut(plus_z), ColumnMut(plus_xz)])
+			QuadMut([ColumnMut::from_chunks(primary), ColumnMut::from_chunks(plus_x), ColumnMut::from_chunks(plus_z), ColumnMut::from_chunks(plus_xz)])
 		)
 	}
 }
 
+impl<B, L> Sector<IndexedCube<B, L>> where B: Target, L: Layout {
+	/// Compacts the palette of every chunk in this sector. See `IndexedStorage::compact`.
+	pub fn compact_palettes(&mut self) -> Compaction {
+		self.chunks.iter_mut().flatten().map(IndexedStorage::compact).sum()
+	}
+}
+
+#[cfg(feature = "rayon")]
+impl<B, L> Sector<IndexedCube<B, L>> where B: Target + Send, L: Layout + Send {
+	/// Parallel version of `compact_palettes`.
+	pub fn par_compact_palettes(&mut self) -> Compaction {
+		self.chunks.par_iter_mut().flatten().map(IndexedStorage::compact).sum()
+	}
+}
+
 impl<T> Index<CubePosition> for Sector<T> {
 	type Output = Option<T>;
 
@@ -325,14 +403,7 @@ impl<'a, T> Iterator for SectorEnumerateColumns<'a, T> where T: 'a {
 			return None;
 		}
 
-		let mut chunks = [None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None];
-
-		for y in 0..16 {
-			let position = CubePosition::from_layer(y, self.column);
-
-			chunks[y as usize] = self.sector[position].as_ref();
-		}
-
+		let chunks = self.sector.column(self.column);
 		let position = self.column;
 
 		if self.column == LayerPosition::from_zx(255) {
@@ -391,15 +462,226 @@ impl<'a, T> Iterator for SectorColumnsMut<'a, T> where T: 'a  {
 	}
 }
 
-// TODO: Enumerate iterators
+/// Iterates over the indices of the set bits in a BitCube.
+struct PresentIndices<'a> {
+	blocks: &'a [u1x64; 64],
+	block:  usize,
+	bits:   u64
+}
+
+impl<'a> PresentIndices<'a> {
+	fn new(blocks: &'a [u1x64; 64]) -> Self {
+		PresentIndices { blocks, block: 0, bits: blocks[0].to_bits() }
+	}
+}
+
+impl<'a> Iterator for PresentIndices<'a> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		while self.bits == 0 {
+			self.block += 1;
+
+			if self.block >= 64 {
+				return None;
+			}
+
+			self.bits = self.blocks[self.block].to_bits();
+		}
+
+		let sub_index = self.bits.trailing_zeros() as usize;
+		self.bits &= self.bits - 1;
+
+		Some(self.block * 64 + sub_index)
+	}
+}
+
 pub struct SectorEnumeratePresent<'a, T> where T: 'a {
-	_sector: &'a Sector<T>,
-	// TODO: BitCube iter
+	chunks:  &'a [Option<T>],
+	indices: PresentIndices<'a>
+}
+
+impl<'a, T> Iterator for SectorEnumeratePresent<'a, T> where T: 'a {
+	type Item = (CubePosition, &'a T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		for index in &mut self.indices {
+			// The mask can get out of sync through iter_mut, so skip slots that were emptied.
+			if let Some(chunk) = self.chunks[index].as_ref() {
+				return Some((CubePosition::from_yzx(index as u16), chunk));
+			}
+		}
+
+		None
+	}
 }
 
 pub struct SectorEnumeratePresentMut<'a, T> where T: 'a {
-	_sector: &'a mut Sector<T>,
-	// TODO: BitCube iter_mut
+	chunks:  slice::IterMut<'a, Option<T>>,
+	/// The index of the next slot in the chunks iterator.
+	next:    usize,
+	indices: PresentIndices<'a>
+}
+
+impl<'a, T> Iterator for SectorEnumeratePresentMut<'a, T> where T: 'a {
+	type Item = (CubePosition, &'a mut T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		for index in &mut self.indices {
+			let slot = self.chunks.nth(index - self.next).unwrap();
+			self.next = index + 1;
+
+			if let Some(chunk) = slot.as_mut() {
+				return Some((CubePosition::from_yzx(index as u16), chunk));
+			}
+		}
+
+		None
+	}
+}
+
+#[cfg(feature = "rayon")]
+/// The present chunks in one block of 64 slots, found through the matching word of the presence mask.
+/// This is the sequential part of `par_enumerate_present`.
+pub struct BlockPresent<'a, T> where T: 'a {
+	slots: &'a [Option<T>],
+	base:  usize,
+	bits:  u64
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T> Iterator for BlockPresent<'a, T> where T: 'a {
+	type Item = (CubePosition, &'a T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.bits != 0 {
+			let sub_index = self.bits.trailing_zeros() as usize;
+			self.bits &= self.bits - 1;
+
+			// The mask can get out of sync through iter_mut, so skip slots that were emptied.
+			if let Some(chunk) = self.slots[sub_index].as_ref() {
+				return Some((CubePosition::from_yzx((self.base + sub_index) as u16), chunk));
+			}
+		}
+
+		None
+	}
+}
+
+#[cfg(feature = "rayon")]
+/// Mutable version of `BlockPresent`, used by `par_enumerate_present_mut`.
+pub struct BlockPresentMut<'a, T> where T: 'a {
+	slots: slice::IterMut<'a, Option<T>>,
+	/// The index of the next slot in the slots iterator, relative to the start of the block.
+	next:  usize,
+	base:  usize,
+	bits:  u64
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T> Iterator for BlockPresentMut<'a, T> where T: 'a {
+	type Item = (CubePosition, &'a mut T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.bits != 0 {
+			let sub_index = self.bits.trailing_zeros() as usize;
+			self.bits &= self.bits - 1;
+
+			let slot = self.slots.nth(sub_index - self.next).unwrap();
+			self.next = sub_index + 1;
+
+			if let Some(chunk) = slot.as_mut() {
+				return Some((CubePosition::from_yzx((self.base + sub_index) as u16), chunk));
+			}
+		}
+
+		None
+	}
+}
+
+#[cfg(feature = "rayon")]
+pub type SectorParEnumeratePresent<'a, T> = rayon::iter::FlatMapIter<
+	rayon::iter::Enumerate<rayon::iter::Zip<rayon::slice::Chunks<'a, Option<T>>, rayon::slice::Iter<'a, u1x64>>>,
+	fn((usize, (&'a [Option<T>], &'a u1x64))) -> BlockPresent<'a, T>
+>;
+
+#[cfg(feature = "rayon")]
+pub type SectorParEnumeratePresentMut<'a, T> = rayon::iter::FlatMapIter<
+	rayon::iter::Enumerate<rayon::iter::Zip<rayon::slice::ChunksMut<'a, Option<T>>, rayon::slice::Iter<'a, u1x64>>>,
+	fn((usize, (&'a mut [Option<T>], &'a u1x64))) -> BlockPresentMut<'a, T>
+>;
+
+#[cfg(feature = "rayon")]
+impl<T> Sector<T> where T: Sync {
+	/// Parallel version of `enumerate_present`. Each word of the presence mask covers 64 slots, which are searched
+	/// in parallel with the other words, so empty slots are skipped without being visited.
+	pub fn par_enumerate_present(&self) -> SectorParEnumeratePresent<'_, T> {
+		self.chunks.par_chunks(64).zip(self.present.blocks().par_iter()).enumerate().flat_map_iter(
+			|(block, (slots, bits))| BlockPresent { slots, base: block * 64, bits: bits.to_bits() }
+		)
+	}
+
+	/// Parallel version of `enumerate_columns`.
+	pub fn par_enumerate_columns(&self) -> impl IndexedParallelIterator<Item=(LayerPosition, [Option<&T>; 16])> {
+		(0..256u16).into_par_iter().map(move |zx| {
+			let position = LayerPosition::from_zx(zx as u8);
+
+			(position, self.column(position))
+		})
+	}
+}
+
+#[cfg(feature = "rayon")]
+impl<T> Sector<T> where T: Send {
+	/// Parallel version of `enumerate_present_mut`.
+	pub fn par_enumerate_present_mut(&mut self) -> SectorParEnumeratePresentMut<'_, T> {
+		self.chunks.par_chunks_mut(64).zip(self.present.blocks().par_iter()).enumerate().flat_map_iter(
+			|(block, (slots, bits))| BlockPresentMut { slots: slots.iter_mut(), next: 0, base: block * 64, bits: bits.to_bits() }
+		)
+	}
+
+	/// Parallel version of `columns_mut`, with the position of each column.
+	pub fn par_enumerate_columns_mut(&mut self) -> impl IndexedParallelIterator<Item=(LayerPosition, [Option<&mut T>; 16])> {
+		// The chunks of a column are not contiguous. Each layer is split into 16 runs of 16 columns, and the runs
+		// covering the same columns are grouped together, so that every group can build its columns in parallel.
+		let mut groups: Vec<Vec<slice::IterMut<Option<T>>>> = (0..16).map(|_| Vec::with_capacity(16)).collect();
+
+		for layer in self.chunks.chunks_mut(256) {
+			for (group, run) in groups.iter_mut().zip(layer.chunks_mut(16)) {
+				group.push(run.iter_mut());
+			}
+		}
+
+		let columns: Vec<_> = groups.into_par_iter().enumerate().flat_map_iter(|(group, mut layers)| {
+			(0..16).map(move |offset| {
+				let position = LayerPosition::from_zx((group * 16 + offset) as u8);
+
+				(position, std::array::from_fn(|y| layers[y].next().unwrap().as_mut()))
+			})
+		}).collect();
+
+		columns.into_par_iter()
+	}
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T> IntoParallelIterator for &'a Sector<T> where T: Sync {
+	type Iter = SectorParEnumeratePresent<'a, T>;
+	type Item = (CubePosition, &'a T);
+
+	fn into_par_iter(self) -> Self::Iter {
+		self.par_enumerate_present()
+	}
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T> IntoParallelIterator for &'a mut Sector<T> where T: Send {
+	type Iter = SectorParEnumeratePresentMut<'a, T>;
+	type Item = (CubePosition, &'a mut T);
+
+	fn into_par_iter(self) -> Self::Iter {
+		self.par_enumerate_present_mut()
+	}
 }
 
 pub struct LayerSector<T> {
@@ -448,4 +730,62 @@ impl<T> LayerSector<T> {
 	pub fn is_empty(&self) -> bool {
 		self.present == 0
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+	use crate::position::CubePosition;
+	use crate::world::sector::Sector;
+
+	#[test]
+	fn test_enumerate_present() {
+		let mut sector: Sector<u16> = Sector::new();
+		let positions = [0, 63, 64, 1000, 4095];
+
+		for &index in positions.iter() {
+			sector.set(CubePosition::from_yzx(index), index);
+		}
+
+		sector.remove(CubePosition::from_yzx(1000));
+
+		let present: Vec<(CubePosition, u16)> = sector.enumerate_present().map(|(position, &chunk)| (position, chunk)).collect();
+		assert_eq!(present, vec![
+			(CubePosition::from_yzx(0), 0),
+			(CubePosition::from_yzx(63), 63),
+			(CubePosition::from_yzx(64), 64),
+			(CubePosition::from_yzx(4095), 4095)
+		]);
+
+		for (position, chunk) in sector.enumerate_present_mut() {
+			*chunk = position.yzx() + 1;
+		}
+
+		assert_eq!(sector[CubePosition::from_yzx(4095)], Some(4096));
+		assert_eq!(sector.enumerate_present().count(), 4);
+
+		#[cfg(feature = "rayon")]
+		{
+			use crate::position::LayerPosition;
+			use rayon::prelude::*;
+
+			let sum: u16 = (&sector).into_par_iter().map(|(_, &chunk)| chunk).sum();
+			assert_eq!(sum, 1 + 64 + 65 + 4096);
+
+			(&mut sector).into_par_iter().for_each(|(position, chunk)| *chunk = position.yzx());
+
+			let mut present: Vec<(CubePosition, u16)> = sector.par_enumerate_present().map(|(position, &chunk)| (position, chunk)).collect();
+			present.sort_by_key(|&(position, _)| position.yzx());
+			assert_eq!(present, sector.enumerate_present().map(|(position, &chunk)| (position, chunk)).collect::<Vec<_>>());
+
+			let columns: Vec<(LayerPosition, Vec<Option<u16>>)> = sector.par_enumerate_columns_mut()
+				.map(|(position, column)| (position, column.iter().map(|chunk| chunk.as_ref().map(|&&mut chunk| chunk)).collect()))
+				.collect();
+
+			assert_eq!(columns.len(), 256);
+			assert_eq!(columns[0].1[0], Some(0));
+			assert_eq!(columns[63].0, LayerPosition::from_zx(63));
+			assert_eq!(columns[63].1[0], Some(63));
+			assert_eq!(columns[255].1[15], Some(4095));
+			assert_eq!(columns.iter().flat_map(|(_, column)| column.iter().flatten()).count(), 4);
+		}
+	}
+}
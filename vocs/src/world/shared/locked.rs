@@ -0,0 +1,92 @@
+use crate::indexed::{IndexedCube, Target};
+use crate::position::{GlobalColumnPosition, Height};
+use crate::view::{ColumnMut, NeighborhoodMut, QuadMut, SpillPolicy};
+use crate::world::shared::{Packed, Guard};
+
+/// A group of chunks locked at once from a SharedWorld. See `SharedWorld::lock_chunks`.
+/// The chunks are unlocked, and packed again if needed, when this is dropped.
+pub struct LockedChunks<'s, T> where T: 's + Packed {
+	guards: Vec<Guard<'s, T>>
+}
+
+impl<'s, T> LockedChunks<'s, T> where T: 's + Packed {
+	pub(crate) fn new(guards: Vec<Guard<'s, T>>) -> Self {
+		LockedChunks { guards }
+	}
+
+	pub fn len(&self) -> usize {
+		self.guards.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.guards.is_empty()
+	}
+
+	/// Returns the chunk at the index, in the order that the positions were passed to `lock_chunks`.
+	pub fn get(&self, index: usize) -> &T::Unpacked {
+		&self.guards[index]
+	}
+
+	pub fn get_mut(&mut self, index: usize) -> &mut T::Unpacked {
+		&mut self.guards[index]
+	}
+}
+
+/// A locked column of chunks, from the bottom of the world to the top.
+pub struct SharedColumn<'s, T> where T: 's + Packed {
+	chunks: LockedChunks<'s, T>
+}
+
+impl<'s, T> SharedColumn<'s, T> where T: 's + Packed {
+	pub(crate) fn new(chunks: LockedChunks<'s, T>) -> Self {
+		SharedColumn { chunks }
+	}
+}
+
+impl<'s, B, T> SharedColumn<'s, T> where T: 's + Packed<Unpacked=IndexedCube<B>>, B: Target {
+	pub fn column_mut(&mut self) -> ColumnMut<'_, B> {
+		ColumnMut::from_chunks(self.chunks.guards.iter_mut().map(|guard| &mut **guard))
+	}
+}
+
+/// A locked 2x2 group of columns, in the same order as `QuadMut`.
+pub struct SharedQuad<'s, T> where T: 's + Packed {
+	chunks: LockedChunks<'s, T>,
+	origin: GlobalColumnPosition,
+	height: Height
+}
+
+impl<'s, T> SharedQuad<'s, T> where T: 's + Packed {
+	pub(crate) fn new(chunks: LockedChunks<'s, T>, origin: GlobalColumnPosition, height: Height) -> Self {
+		SharedQuad { chunks, origin, height }
+	}
+
+	/// Returns the position of the column in the -X, -Z corner.
+	pub fn origin(&self) -> GlobalColumnPosition {
+		self.origin
+	}
+}
+
+impl<'s, B, T> SharedQuad<'s, T> where T: 's + Packed<Unpacked=IndexedCube<B>>, B: Target {
+	pub fn quad_mut(&mut self) -> QuadMut<'_, B> {
+		let mut columns = self.columns_mut();
+
+		QuadMut([columns.remove(0), columns.remove(0), columns.remove(0), columns.remove(0)])
+	}
+
+	/// Returns a 2x2 neighborhood of the locked columns. Edits that fall outside of the quad are handled according
+	/// to the spill policy, so that with `SpillPolicy::Record` they can be passed on to a `PendingEdits`.
+	pub fn neighborhood_mut(&mut self, policy: SpillPolicy) -> NeighborhoodMut<'_, B> {
+		let (origin, height) = (self.origin, self.height);
+		let columns = self.columns_mut().into_iter().map(Some).collect();
+
+		NeighborhoodMut::new(origin, height, 2, 2, columns, policy)
+	}
+
+	fn columns_mut(&mut self) -> Vec<ColumnMut<'_, B>> {
+		let sections = self.height.sections() as usize;
+		let mut chunks = self.chunks.guards.iter_mut().map(|guard| &mut **guard);
+
+		(0..4).map(|_| ColumnMut::from_chunks(chunks.by_ref().take(sections))).collect()
+	}
+}
@@ -1,10 +1,13 @@
 pub mod sector;
 pub mod world;
+mod locked;
 
 pub use self::sector::SharedSector;
 pub use self::world::SharedWorld;
+pub use self::locked::{LockedChunks, SharedColumn, SharedQuad};
 
-use spin::RwLockWriteGuard;
+use crate::memory::{MemoryReport, MemoryUsage};
+use spin::{RwLockReadGuard, RwLockWriteGuard};
 use std::ops::{Deref, DerefMut};
 
 pub trait Packed {
@@ -14,6 +17,11 @@ pub trait Packed {
 	fn pack(unpacked: Self::Unpacked) -> Self;
 }
 
+/// Packed forms that can be read without unpacking them, allowing chunks to be shared between readers.
+pub trait ReadPacked: Packed {
+	fn get_unpacked(&self) -> &Self::Unpacked;
+}
+
 pub struct NoPack<T>(pub T);
 
 impl<T> Packed for NoPack<T> {
@@ -28,6 +36,18 @@ impl<T> Packed for NoPack<T> {
 	}
 }
 
+impl<T> ReadPacked for NoPack<T> {
+	fn get_unpacked(&self) -> &T {
+		&self.0
+	}
+}
+
+impl<T> MemoryUsage for NoPack<T> where T: MemoryUsage {
+	fn memory_usage(&self) -> MemoryReport {
+		self.0.memory_usage()
+	}
+}
+
 impl<T> Default for NoPack<T> where T: Default {
 	fn default() -> Self {
 		NoPack(T::default())
@@ -57,4 +77,16 @@ impl<'s, T> Drop for Guard<'s, T> where  T: 's + Packed {
 	fn drop(&mut self) {
 		*self.slot = Some(T::pack(self.value.take().unwrap()));
 	}
-}
\ No newline at end of file
+}
+/// A chunk locked for reading. Other readers may lock the chunk at the same time, but writers have to wait.
+pub struct ReadGuard<'s, T> where T: 's + ReadPacked {
+	slot: RwLockReadGuard<'s, Option<T>>
+}
+
+impl<'s, T> Deref for ReadGuard<'s, T> where T: 's + ReadPacked {
+	type Target = T::Unpacked;
+
+	fn deref(&self) -> &T::Unpacked {
+		self.slot.as_ref().unwrap().get_unpacked()
+	}
+}
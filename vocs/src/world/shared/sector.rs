@@ -2,7 +2,10 @@ use spin::RwLock;
 use std::ops::Index;
 use crate::position::{LayerPosition, CubePosition};
 use std::slice;
-use crate::world::shared::{Packed, Guard};
+use crate::world::shared::{Packed, Guard, ReadPacked, ReadGuard};
+use crate::indexed::{IndexedCube, Target};
+use crate::memory::{MemoryReport, MemoryUsage};
+use std::mem;
 
 // TODO: Design: Should locks panic by default? This would make sense with the scheduler design, but could be a similar footgun to RefCell.
 
@@ -57,6 +60,40 @@ impl<T> SharedSector<T> where T: Packed {
 	pub fn iter(&self) -> slice::Iter<RwLock<Option<T>>> {
 		self.chunks.iter()
 	}
+
+	/// Consumes the sector, returning the chunks that are present.
+	pub fn into_enumerate_present(self) -> impl Iterator<Item=(CubePosition, T)> {
+		self.chunks.into_vec().into_iter().enumerate().filter_map(|(index, slot)|
+			slot.into_inner().map(|chunk| (CubePosition::from_yzx(index as u16), chunk))
+		)
+	}
+}
+
+impl<T> SharedSector<T> where T: ReadPacked {
+	/// Locks the chunk for reading, without taking it out of the sector.
+	pub fn read(&self, position: CubePosition) -> Option<ReadGuard<'_, T>> {
+		let slot = self[position].read();
+
+		if slot.is_none() {
+			return None;
+		}
+
+		Some(ReadGuard { slot })
+	}
+}
+
+impl<T> MemoryUsage for SharedSector<T> where T: Packed + MemoryUsage {
+	/// Chunks that are locked for writing while the report is made are not counted, since their packed form is
+	/// temporarily taken out of the sector.
+	fn memory_usage(&self) -> MemoryReport {
+		let mut report: MemoryReport = self.chunks.iter().filter_map(|slot| {
+			slot.try_read().and_then(|chunk| chunk.as_ref().map(|chunk| MemoryReport { chunks: 1, ..chunk.memory_usage() }))
+		}).sum();
+
+		report.structure += self.chunks.len() * mem::size_of::<RwLock<Option<T>>>();
+
+		report
+	}
 }
 
 impl<T> SharedSector<T> where T: Packed + Default {
@@ -68,26 +105,26 @@ impl<T> SharedSector<T> where T: Packed + Default {
 	}
 }
 
-/* TODO: Reimplement
-impl<B> SharedSector<IndexedCube<B>> where B: Target {
-	pub fn set_block_immediate(&mut self, x: u8, y: u8, z: u8, target: &B) -> Option<()> {
+impl<B, T> SharedSector<T> where T: Packed<Unpacked=IndexedCube<B>>, B: Target {
+	pub fn set_block_immediate(&self, x: u8, y: u8, z: u8, target: &B) -> Option<()> {
 		let (chunk, block) = (
 			CubePosition::new(x / 16, y / 16, z / 16),
 			CubePosition::new(x % 16, y % 16, z % 16)
 		);
 
-		self.get_mut(chunk).map(|chunk| chunk.set_immediate(block, &target))
+		self.get(chunk).map(|mut chunk| chunk.set_immediate(block, target))
 	}
 
-	pub fn get_block(&self, x: u8, y: u8, z: u8) -> Option<&B> {
+	/// Returns a copy of the block, since the chunk is unlocked before this returns.
+	pub fn get_block(&self, x: u8, y: u8, z: u8) -> Option<B> {
 		let (chunk, block) = (
 			CubePosition::new(x / 16, y / 16, z / 16),
 			CubePosition::new(x % 16, y % 16, z % 16)
 		);
 
-		self[chunk].as_ref().map(|chunk| chunk.get(block))
+		self.get(chunk).map(|chunk| chunk.get(block).clone())
 	}
-}*/
+}
 
 impl<T> Index<CubePosition> for SharedSector<T> where T: Packed {
 	type Output = RwLock<Option<T>>;
@@ -1,19 +1,33 @@
-use crate::position::{GlobalChunkPosition, GlobalColumnPosition, GlobalSectorPosition};
-use crate::world::shared::{Packed, Guard, SharedSector};
+use crate::position::{GlobalChunkPosition, GlobalColumnPosition, GlobalSectorPosition, Height};
+use crate::world::shared::{Packed, Guard, ReadPacked, ReadGuard, SharedSector, LockedChunks, SharedColumn, SharedQuad};
+use crate::world::world::World;
+use crate::memory::{self, MemoryReport, MemoryUsage};
 use std::collections::hash_map::{HashMap, Entry, Iter, IterMut};
 
 // TODO: Concurrent Hash Map
 pub struct SharedWorld<T> where T: Packed {
-	sectors: HashMap<GlobalSectorPosition, SharedSector<T>>
+	sectors: HashMap<GlobalSectorPosition, SharedSector<T>>,
+	height: Height
 }
 
 impl<T> SharedWorld<T> where T: Packed {
+	/// Creates a new world with the classic 0..256 height.
 	pub fn new() -> Self {
+		SharedWorld::with_height(Height::CLASSIC)
+	}
+
+	/// Creates a new world with the specified height. See `World::with_height`.
+	pub fn with_height(height: Height) -> Self {
 		SharedWorld {
-			sectors: HashMap::new()
+			sectors: HashMap::new(),
+			height
 		}
 	}
 
+	pub fn height(&self) -> Height {
+		self.height
+	}
+
 	pub fn set(&mut self, position: GlobalChunkPosition, chunk: T) {
 		let sector = position.global_sector();
 		let inner = position.local_chunk();
@@ -21,11 +35,20 @@ impl<T> SharedWorld<T> where T: Packed {
 		self.sectors.entry(sector).or_insert_with(SharedSector::new).set(inner, chunk);
 	}
 
-	pub fn set_column(&mut self, position: GlobalColumnPosition, column: [T; 16]) {
-		let sector = position.global_sector();
-		let inner = position.local_layer();
+	/// Sets every chunk in the column, from the bottom of the world to the top.
+	/// ### Panics
+	/// If the amount of chunks does not match the height of the world.
+	pub fn set_column<C>(&mut self, position: GlobalColumnPosition, column: C) where C: IntoIterator<Item=T> {
+		let mut count = 0;
+
+		for (index, chunk) in column.into_iter().enumerate() {
+			assert!(index < self.height.sections() as usize, "Column is taller than the world");
+
+			self.set(GlobalChunkPosition::from_column(position, self.height.section_y(index as u8)), chunk);
+			count += 1;
+		}
 
-		self.sectors.entry(sector).or_insert_with(SharedSector::new).set_column(inner, column);
+		assert_eq!(count, self.height.sections() as usize, "Column is shorter than the world");
 	}
 
 	pub fn remove(&mut self, position: GlobalChunkPosition) -> Option<T> {
@@ -74,7 +97,7 @@ impl<T> SharedWorld<T> where T: Packed {
 		self.sectors.entry(position).or_insert_with(SharedSector::new)
 	}
 
-	/*pub fn get_column_mut(&mut self, position: GlobalColumnPosition) -> Option<[&mut T; 16]> {
+	/*pub fn get_column_mut(&mut self, position: GlobalColumnPosition) -> Option<Vec<&mut T>> {
 		let sector = position.global_sector();
 		let inner = position.local_layer();
 
@@ -92,6 +115,112 @@ impl<T> SharedWorld<T> where T: Packed {
 	pub fn into_sectors(self) -> HashMap<GlobalSectorPosition, SharedSector<T>> {
 		self.sectors
 	}
+
+	/// Locks several distinct chunks at once, returning them in the same order as the positions.
+	/// Returns None if any of the chunks are missing, or if a position is repeated.
+	///
+	/// The locks are always acquired in the same global order regardless of the order of the positions,
+	/// so threads locking overlapping groups of chunks will wait for each other instead of deadlocking.
+	pub fn lock_chunks(&self, positions: &[GlobalChunkPosition]) -> Option<LockedChunks<'_, T>> {
+		let mut order: Vec<usize> = (0..positions.len()).collect();
+		order.sort_by_key(|&index| lock_order(positions[index]));
+
+		if order.windows(2).any(|pair| positions[pair[0]] == positions[pair[1]]) {
+			return None;
+		}
+
+		let mut guards: Vec<Option<Guard<'_, T>>> = positions.iter().map(|_| None).collect();
+
+		for &index in &order {
+			// If this fails, the locks that were already acquired are released as the guards are dropped.
+			guards[index] = Some(self.get(positions[index])?);
+		}
+
+		Some(LockedChunks::new(guards.into_iter().map(Option::unwrap).collect()))
+	}
+
+	/// Locks every chunk in the column, from the bottom of the world to the top.
+	pub fn lock_column(&self, position: GlobalColumnPosition) -> Option<SharedColumn<'_, T>> {
+		let positions: Vec<GlobalChunkPosition> = (0..self.height.sections())
+			.map(|index| GlobalChunkPosition::from_column(position, self.height.section_y(index)))
+			.collect();
+
+		self.lock_chunks(&positions).map(SharedColumn::new)
+	}
+
+	/// Locks the 2x2 group of columns with this column in the -X, -Z corner. See `World::get_quad_mut`.
+	pub fn lock_quad(&self, position: GlobalColumnPosition) -> Option<SharedQuad<'_, T>> {
+		let columns = [
+			position,
+			position.checked_offset(1, 0)?,
+			position.checked_offset(0, 1)?,
+			position.checked_offset(1, 1)?
+		];
+
+		let positions: Vec<GlobalChunkPosition> = columns.iter()
+			.flat_map(|&column| (0..self.height.sections())
+				.map(move |index| GlobalChunkPosition::from_column(column, self.height.section_y(index)))
+			)
+			.collect();
+
+		self.lock_chunks(&positions).map(|chunks| SharedQuad::new(chunks, position, self.height))
+	}
+
+	/// Converts a world into a shared world, packing every chunk.
+	pub fn from_world(world: World<T::Unpacked>) -> Self {
+		let mut shared = SharedWorld::with_height(world.height());
+
+		for (position, mut sector) in world.into_sectors() {
+			let shared_sector = shared.get_or_create_sector_mut(position);
+
+			while let Some((position, chunk)) = sector.pop_first() {
+				shared_sector.set(position, T::pack(chunk));
+			}
+		}
+
+		shared
+	}
+
+	/// Converts this shared world back into a world, unpacking every chunk.
+	pub fn into_world(self) -> World<T::Unpacked> {
+		let mut world = World::with_height(self.height);
+
+		for (position, sector) in self.sectors {
+			let unpacked = world.get_or_create_sector_mut(position);
+
+			for (position, chunk) in sector.into_enumerate_present() {
+				unpacked.set(position, chunk.unpack());
+			}
+		}
+
+		world
+	}
+}
+
+/// The global order that chunk locks are acquired in.
+fn lock_order(position: GlobalChunkPosition) -> (i32, i8, i32, u16) {
+	let sector = position.global_sector();
+
+	(sector.x(), sector.y(), sector.z(), position.local_chunk().yzx())
+}
+
+impl<T> MemoryUsage for SharedWorld<T> where T: Packed + MemoryUsage {
+	fn memory_usage(&self) -> MemoryReport {
+		let mut report: MemoryReport = self.sectors.values().map(SharedSector::memory_usage).sum();
+		report.structure += memory::hash_map_bytes(&self.sectors);
+
+		report
+	}
+}
+
+impl<T> SharedWorld<T> where T: ReadPacked {
+	/// Locks the chunk for reading. Unlike `get`, any amount of readers can hold the chunk at the same time.
+	pub fn read(&self, position: GlobalChunkPosition) -> Option<ReadGuard<'_, T>> {
+		let sector = position.global_sector();
+		let inner = position.local_chunk();
+
+		self.sectors.get(&sector).and_then(|sector| sector.read(inner))
+	}
 }
 
 impl<T> SharedWorld<T> where T: Packed + Default {
@@ -101,4 +230,59 @@ impl<T> SharedWorld<T> where T: Packed + Default {
 
 		self.sectors.entry(sector).or_insert_with(SharedSector::new).get_or_create(inner)
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{GlobalChunkPosition, GlobalColumnPosition, QuadPosition};
+	use crate::world::shared::{NoPack, SharedWorld};
+	use crate::world::world::World;
+	use std::thread;
+
+	#[test]
+	fn test_lock_quads() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+
+		for x in 0..3 {
+			for z in 0..3 {
+				world.set_column(GlobalColumnPosition::new(x, z), (0..16).map(|_| IndexedCube::new(4, 0)));
+			}
+		}
+
+		let shared: SharedWorld<NoPack<IndexedCube<u16>>> = SharedWorld::from_world(world);
+
+		// Overlapping quads, locked in opposite orders by different threads.
+		thread::scope(|scope| {
+			for thread in 0..4u16 {
+				let shared = &shared;
+
+				scope.spawn(move || {
+					for iteration in 0..64 {
+						// Both quads contain the column at (1, 1).
+						let (x, z, at) = if (iteration + thread) % 2 == 0 {
+							(0, 0, QuadPosition::new(16, 0, 16))
+						} else {
+							(1, 1, QuadPosition::new(0, 0, 0))
+						};
+
+						let mut locked = shared.lock_quad(GlobalColumnPosition::new(x, z)).unwrap();
+						let mut quad = locked.quad_mut();
+
+						let count = *quad.get(at);
+						quad.set_immediate(at, &(count + 1));
+					}
+				});
+			}
+		});
+
+		let position = GlobalChunkPosition::new(1, 0, 1);
+		let chunks = [position, GlobalChunkPosition::new(2, 0, 2), position];
+		assert!(shared.lock_chunks(&chunks).is_none());
+		assert!(shared.lock_quad(GlobalColumnPosition::new(2, 2)).is_none());
+
+		let world = shared.into_world();
+		let chunk = world.get(position).unwrap();
+
+		assert_eq!(*chunk.get(QuadPosition::new(16, 0, 16).column().chunk()), 256);
+	}
+}
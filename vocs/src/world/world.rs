@@ -1,20 +1,56 @@
-use crate::position::{GlobalChunkPosition, GlobalColumnPosition, GlobalSectorPosition, LayerPosition};
+use crate::position::{GlobalChunkPosition, GlobalColumnPosition, GlobalPosition, GlobalSectorPosition, Height, LayerPosition};
 use crate::world::sector::Sector;
 use std::collections::hash_map::{HashMap, Entry, Iter, IterMut};
-use crate::indexed::{Target, IndexedCube};
-use crate::view::{QuadMut, ColumnMut};
+use crate::indexed::{Compaction, Target, IndexedCube};
+use crate::packed::Layout;
+use crate::view::{QuadMut, ColumnMut, NeighborhoodMut, Sections, SpillPolicy};
+use crate::memory::{self, MemoryReport, MemoryUsage};
 use splitmut::SplitMut;
+use rustc_hash::FxHashMap;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Why a block could not be set in a world.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BlockError {
+	/// The chunk containing the block is missing.
+	MissingChunk(GlobalChunkPosition),
+	/// The block is outside of Y=-2048 to Y=2047, so no chunk can contain it. See `GlobalPosition::checked_global_chunk`.
+	OutOfRange(GlobalPosition)
+}
+
+impl BlockError {
+	/// Returns the position of the chunk containing the block, or an `OutOfRange` error if there is no such chunk.
+	pub fn chunk_of(position: GlobalPosition) -> Result<GlobalChunkPosition, BlockError> {
+		position.checked_global_chunk().ok_or(BlockError::OutOfRange(position))
+	}
+}
+
+#[derive(Clone)]
 pub struct World<T> {
-	sectors: HashMap<GlobalSectorPosition, Sector<T>>
+	sectors: HashMap<GlobalSectorPosition, Sector<T>>,
+	height: Height
 }
 
 impl<T> World<T> {
+	/// Creates a new world with the classic 0..256 height.
 	pub fn new() -> Self {
+		World::with_height(Height::CLASSIC)
+	}
+
+	/// Creates a new world with the specified height. This determines the extent of columns and quads,
+	/// individual chunks may still be placed at any Y.
+	pub fn with_height(height: Height) -> Self {
 		World {
-			sectors: HashMap::new()
+			sectors: HashMap::new(),
+			height
 		}
 	}
+
+	pub fn height(&self) -> Height {
+		self.height
+	}
 	
 	pub fn set(&mut self, position: GlobalChunkPosition, chunk: T) {
 		let sector = position.global_sector();
@@ -23,11 +59,18 @@ impl<T> World<T> {
 		self.sectors.entry(sector).or_insert_with(Sector::new).set(inner, chunk);
 	}
 
-	pub fn set_column(&mut self, position: GlobalColumnPosition, column: [T; 16]) {
-		let sector = position.global_sector();
-		let inner = position.local_layer();
+	/// Sets every chunk in the column, from the bottom of the world to the top.
+	/// ### Panics
+	/// If the amount of chunks does not match the height of the world. The world is left unchanged in that case.
+	pub fn set_column<C>(&mut self, position: GlobalColumnPosition, column: C) where C: IntoIterator<Item=T> {
+		let column: Vec<T> = column.into_iter().collect();
+
+		assert!(column.len() <= self.height.sections() as usize, "Column is taller than the world");
+		assert!(column.len() >= self.height.sections() as usize, "Column is shorter than the world");
 
-		self.sectors.entry(sector).or_insert_with(Sector::new).set_column(inner, column);
+		for (index, chunk) in column.into_iter().enumerate() {
+			self.set(GlobalChunkPosition::from_column(position, self.height.section_y(index as u8)), chunk);
+		}
 	}
 
 	pub fn remove(&mut self, position: GlobalChunkPosition) -> Option<T> {
@@ -74,11 +117,94 @@ impl<T> World<T> {
 		self.sectors.entry(position).or_insert_with(Sector::new)
 	}
 
-	pub fn get_column_mut(&mut self, position: GlobalColumnPosition) -> Option<[&mut T; 16]> {
-		let sector = position.global_sector();
-		let inner = position.local_layer();
+	/// Gets every chunk in the column, from the bottom of the world to the top.
+	/// Returns None if any of the chunks are missing.
+	pub fn get_column_mut(&mut self, position: GlobalColumnPosition) -> Option<Sections<&mut T>> {
+		self.get_columns_mut(&[position]).and_then(|mut columns| columns.pop())
+	}
 
-		self.sectors.get_mut(&sector).and_then(|sector| sector.get_column_mut(inner))
+	/// Gets several distinct columns at once, which may span multiple sectors both horizontally and vertically.
+	/// Columns in the same sector must be in ascending ZX order.
+	fn get_columns_mut(&mut self, columns: &[GlobalColumnPosition]) -> Option<Vec<Sections<&mut T>>> {
+		self.get_partial_columns_mut(columns).into_iter().collect()
+	}
+
+	/// Gets the chunks of the 2x2 group of columns with this column in the -X, -Z corner, in the order of `QuadMut`.
+	fn get_quad_columns_mut(&mut self, position: GlobalColumnPosition) -> Option<Vec<Sections<&mut T>>> {
+		self.get_columns_mut(&[
+			position,
+			position.checked_offset(1, 0)?,
+			position.checked_offset(0, 1)?,
+			position.checked_offset(1, 1)?
+		])
+	}
+
+	/// Gets the chunks of a `width` by `depth` group of columns, in the order of `NeighborhoodMut`.
+	/// Columns that are missing chunks or past the edge of the world are None.
+	fn get_neighborhood_columns_mut(&mut self, origin: GlobalColumnPosition, width: u8, depth: u8) -> Vec<Option<Sections<&mut T>>> {
+		let mut positions = Vec::with_capacity((width as usize) * (depth as usize));
+
+		// Z-major order keeps the columns of each sector in ascending ZX order.
+		for z in 0..depth as i32 {
+			for x in 0..width as i32 {
+				positions.push(origin.checked_offset(x, z));
+			}
+		}
+
+		let present: Vec<GlobalColumnPosition> = positions.iter().flatten().cloned().collect();
+		let mut present = self.get_partial_columns_mut(&present).into_iter();
+
+		positions.iter().map(|position| position.and_then(|_| present.next().unwrap())).collect()
+	}
+
+	/// Like `get_columns_mut`, but only the columns that are missing chunks are None.
+	fn get_partial_columns_mut(&mut self, columns: &[GlobalColumnPosition]) -> Vec<Option<Sections<&mut T>>> {
+		let height = self.height;
+		let sector_ys: Vec<i8> = height.sectors().collect();
+
+		// Key references passed to get_muts have to outlive it, so compute them all up front.
+		let keys: Vec<Vec<GlobalSectorPosition>> = sector_ys.iter().map(|&sector_y|
+			columns.iter().map(|column| column.global_sector().with_y(sector_y)).collect()
+		).collect();
+
+		let mut result: Vec<Option<Sections<&mut T>>> = columns.iter().map(|_| Some(Sections::new())).collect();
+		let mut sectors = self.sectors.get_muts();
+
+		for (&sector_y, keys) in sector_ys.iter().zip(&keys) {
+			let layers = height.sector_layers(sector_y);
+
+			// Group the columns by sector, keeping each group in the order that the columns were passed in.
+			let mut groups: FxHashMap<&GlobalSectorPosition, Vec<usize>> = FxHashMap::default();
+
+			for (index, key) in keys.iter().enumerate() {
+				groups.entry(key).or_default().push(index);
+			}
+
+			for (key, members) in groups {
+				let sector = match sectors.at(key) {
+					Ok(sector) => sector,
+					Err(_) => {
+						for member in members {
+							result[member] = None;
+						}
+
+						continue;
+					}
+				};
+
+				let positions: Vec<LayerPosition> = members.iter().map(|&member| columns[member].local_layer()).collect();
+				let chunks = sector.get_columns_mut(&positions, layers.clone());
+
+				for (member, chunks) in members.into_iter().zip(chunks) {
+					match (result[member].as_mut(), chunks) {
+						(Some(column), Some(chunks)) => column.extend(chunks),
+						_ => result[member] = None
+					}
+				}
+			}
+		}
+
+		result
 	}
 
 	pub fn sectors(&self) -> Iter<GlobalSectorPosition, Sector<T>> {
@@ -103,72 +229,256 @@ impl<T> World<T> where T: Default {
 	}
 }
 
-impl<B> World<IndexedCube<B>> where B: Target {
-	pub fn get_quad_mut(&mut self, position: GlobalColumnPosition) -> Option<QuadMut<B>> {
-		let sector = position.global_sector();
-		let inner = position.local_layer();
+impl<T> MemoryUsage for World<T> where T: MemoryUsage {
+	/// Returns the memory used by every sector in the world, along with the table of sectors.
+	fn memory_usage(&self) -> MemoryReport {
+		let mut report: MemoryReport = self.sectors.values().map(Sector::memory_usage).sum();
+		report.structure += memory::hash_map_bytes(&self.sectors);
 
-		// TODO: Overflow checking for the edge case
+		report
+	}
+}
+
+impl<B, L> World<IndexedCube<B, L>> where B: Target, L: Layout {
+	/// Compacts the palette of every chunk in the world. See `IndexedStorage::compact`.
+	pub fn compact_palettes(&mut self) -> Compaction {
+		self.sectors.values_mut().map(Sector::compact_palettes).sum()
+	}
+}
 
-		match (inner.x() == 15, inner.z() == 15) {
-			(false, false) => return self.sectors.get_mut(&sector).and_then(|sector| sector.get_quad_mut(inner)),
-			(true, false) => {
-				let (primary, plus_x) = self.sectors.get2_mut(
-					&sector,
-					&GlobalSectorPosition::new(sector.x() + 1, sector.z()),
-				);
+#[cfg(feature = "rayon")]
+impl<T> World<T> where T: Sync {
+	/// Parallel version of `sectors`.
+	pub fn par_sectors(&self) -> rayon::collections::hash_map::Iter<'_, GlobalSectorPosition, Sector<T>> {
+		self.sectors.par_iter()
+	}
+}
 
-				let (primary, plus_x) = match (primary, plus_x) {
-					(Ok(primary), Ok(plus_x)) => (primary, plus_x),
-					_ => return None
-				};
+#[cfg(feature = "rayon")]
+impl<T> World<T> where T: Send {
+	/// Parallel version of `sectors_mut`.
+	pub fn par_sectors_mut(&mut self) -> rayon::collections::hash_map::IterMut<'_, GlobalSectorPosition, Sector<T>> {
+		self.sectors.par_iter_mut()
+	}
+}
 
-				let (primary, plus_z) = match primary.get2_column_mut(LayerPosition::new(15, inner.z()), LayerPosition::new(15, inner.z() + 1)) {Some(x) => x, None => return None};
-				let (plus_x, plus_xz) = match plus_x.get2_column_mut(LayerPosition::new(0, inner.z()), LayerPosition::new(0, inner.z() + 1)) {Some(x) => x, None => return None};
+#[cfg(feature = "rayon")]
+impl<'a, T> IntoParallelIterator for &'a World<T> where T: Sync {
+	type Iter = rayon::collections::hash_map::Iter<'a, GlobalSectorPosition, Sector<T>>;
+	type Item = (&'a GlobalSectorPosition, &'a Sector<T>);
 
-				Some(QuadMut([ColumnMut(primary), ColumnMut(plus_x), ColumnMut(plus_z), ColumnMut(plus_xz)]))
-			},
-			(false, true) => {
-				let (primary, plus_z) = self.sectors.get2_mut(
-					&sector,
-					&GlobalSectorPosition::new(sector.x(), sector.z() + 1)
-				);
+	fn into_par_iter(self) -> Self::Iter {
+		self.par_sectors()
+	}
+}
 
-				let (primary, plus_z) = match (primary, plus_z) {
-					(Ok(primary), Ok(plus_z)) => (primary, plus_z),
-					_ => return None
-				};
+#[cfg(feature = "rayon")]
+impl<'a, T> IntoParallelIterator for &'a mut World<T> where T: Send {
+	type Iter = rayon::collections::hash_map::IterMut<'a, GlobalSectorPosition, Sector<T>>;
+	type Item = (&'a GlobalSectorPosition, &'a mut Sector<T>);
 
-				let (primary, plus_x) = match primary.get2_column_mut(LayerPosition::new(inner.x(), 15), LayerPosition::new(inner.x() + 1, 15)) {Some(x) => x, None => return None};
-				let (plus_z, plus_xz) = match plus_z.get2_column_mut(LayerPosition::new(inner.x(), 0), LayerPosition::new(inner.x() + 1, 0)) {Some(x) => x, None => return None};
-
-				Some(QuadMut([ColumnMut(primary), ColumnMut(plus_x), ColumnMut(plus_z), ColumnMut(plus_xz)]))
-			},
-			(true, true) => {
-				let (primary, plus_x, plus_z, plus_xz) = self.sectors.get4_mut(
-					&sector,
-					&GlobalSectorPosition::new(sector.x() + 1, sector.z()),
-					&GlobalSectorPosition::new(sector.x(), sector.z() + 1),
-					&GlobalSectorPosition::new(sector.x() + 1, sector.z() + 1)
-				);
-
-				let (primary, plus_x, plus_z, plus_xz) = match (primary, plus_x, plus_z, plus_xz) {
-					(Ok(primary), Ok(plus_x), Ok(plus_z), Ok(plus_xz)) => (
-						primary.get_column_mut(LayerPosition::new(15, 15)).map(ColumnMut),
-						plus_x.get_column_mut(LayerPosition::new(0, 15)).map(ColumnMut),
-						plus_z.get_column_mut(LayerPosition::new(15, 0)).map(ColumnMut),
-						plus_xz.get_column_mut(LayerPosition::new(0, 0)).map(ColumnMut)
-					),
-					_ => return None
-				};
+	fn into_par_iter(self) -> Self::Iter {
+		self.par_sectors_mut()
+	}
+}
 
-				match (primary, plus_x, plus_z, plus_xz) {
-					(Some(primary), Some(plus_x), Some(plus_z), Some(plus_xz)) => Some(QuadMut([primary, plus_x, plus_z, plus_xz])),
-					_ => None
-				}
-			}
+#[cfg(feature = "rayon")]
+impl<B, L> World<IndexedCube<B, L>> where B: Target + Send, L: Layout + Send {
+	/// Parallel version of `compact_palettes`.
+	pub fn par_compact_palettes(&mut self) -> Compaction {
+		self.par_sectors_mut().map(|(_, sector)| sector.par_compact_palettes()).sum()
+	}
+}
+
+/// A chunk that the block and cuboid operations of a world can work on, either a plain `IndexedCube` or a handle
+/// to one such as `CowCube`. Handles may need to do extra work, like cloning a shared chunk, before it can be written.
+pub trait ChunkAccess {
+	type Block: Target;
+
+	/// Returns the chunk for reading.
+	fn cube(&self) -> &IndexedCube<Self::Block>;
+
+	/// Returns the chunk for writing.
+	fn cube_mut(&mut self) -> &mut IndexedCube<Self::Block>;
+
+	/// Returns true if writing to the chunk would first have to copy it. Operations check whether they change
+	/// anything before writing to such chunks.
+	fn is_shared(&self) -> bool {
+		false
+	}
+
+	/// Sets every block of the chunk to the target.
+	fn fill_cube(&mut self, target: &Self::Block) {
+		self.cube_mut().fill(target.clone());
+	}
+}
+
+impl<B> ChunkAccess for IndexedCube<B> where B: Target {
+	type Block = B;
+
+	fn cube(&self) -> &IndexedCube<B> {
+		self
+	}
+
+	fn cube_mut(&mut self) -> &mut IndexedCube<B> {
+		self
+	}
+}
+
+impl<C> World<C> where C: ChunkAccess {
+	/// Gets the block at the global position, or None if its chunk is missing or cannot exist.
+	pub fn get_block(&self, position: GlobalPosition) -> Option<&C::Block> {
+		self.get(position.checked_global_chunk()?).map(|chunk| chunk.cube().get(position.local_block()))
+	}
+
+	/// Sets the block at the global position. Fails if its chunk is missing, or if no chunk can contain it.
+	/// See `PendingEdits` for keeping the edit until the chunk is created.
+	pub fn set_block(&mut self, position: GlobalPosition, target: &C::Block) -> Result<(), BlockError> {
+		let chunk_position = BlockError::chunk_of(position)?;
+		let chunk = self.get_mut(chunk_position).ok_or(BlockError::MissingChunk(chunk_position))?;
+
+		if !chunk.is_shared() || chunk.cube().get(position.local_block()) != target {
+			chunk.cube_mut().set_immediate(position.local_block(), target);
 		}
+
+		Ok(())
 	}
+
+	/// Gets the 2x2 group of columns with this column in the -X, -Z corner.
+	/// Returns None if any of the chunks are missing, or if the quad would extend past the edge of the world.
+	pub fn get_quad_mut(&mut self, position: GlobalColumnPosition) -> Option<QuadMut<'_, C::Block>> {
+		let mut columns = self.get_quad_columns_mut(position)?.into_iter().map(column_mut);
+
+		Some(QuadMut([
+			columns.next().unwrap(),
+			columns.next().unwrap(),
+			columns.next().unwrap(),
+			columns.next().unwrap()
+		]))
+	}
+
+	/// Gets a `width` by `depth` group of columns with this column in the -X, -Z corner. Unlike `get_quad_mut`,
+	/// missing columns do not prevent the rest of the neighborhood from being borrowed, instead edits to them
+	/// are handled according to the spill policy. Columns past the edge of the world are treated as missing.
+	pub fn get_neighborhood_mut(&mut self, origin: GlobalColumnPosition, width: u8, depth: u8, policy: SpillPolicy) -> NeighborhoodMut<'_, C::Block> {
+		let height = self.height;
+		let columns = self.get_neighborhood_columns_mut(origin, width, depth).into_iter()
+			.map(|column| column.map(column_mut))
+			.collect();
+
+		NeighborhoodMut::new(origin, height, width, depth, columns, policy)
+	}
+}
+
+/// Borrows the chunks of a column for writing. Shared chunks are copied, even if nothing ends up being written to them.
+fn column_mut<C>(column: Sections<&mut C>) -> ColumnMut<'_, C::Block> where C: ChunkAccess {
+	ColumnMut::from_chunks(column.into_iter().map(ChunkAccess::cube_mut))
 }
 
-// TODO: Add test for Columns/ColumnsMut returning 256 results
\ No newline at end of file
+// TODO: Add test for Columns/ColumnsMut returning 256 results
+#[cfg(test)]
+mod test {
+	use crate::indexed::IndexedCube;
+	use crate::position::{ColumnPosition, GlobalChunkPosition, GlobalColumnPosition, GlobalPosition, Height, NeighborhoodPosition, QuadPosition};
+	use crate::view::{SpillPolicy, Spilled};
+	use crate::world::world::{BlockError, World};
+	use std::panic::{self, AssertUnwindSafe};
+
+	#[test]
+	fn test_tall_column() {
+		let height = Height::from_blocks(-64, 320);
+		let mut world: World<IndexedCube<u16>> = World::with_height(height);
+
+		for x in 0..2 {
+			for z in 0..2 {
+				let column = (0..height.sections()).map(|_| IndexedCube::new(4, 0));
+
+				world.set_column(GlobalColumnPosition::new(x - 1, z - 1), column);
+			}
+		}
+
+		assert!(world.get(GlobalChunkPosition::new(-1, -4, -1)).is_some());
+		assert!(world.get(GlobalChunkPosition::new(-1, 19, -1)).is_some());
+		assert!(world.get(GlobalChunkPosition::new(-1, 20, -1)).is_none());
+
+		{
+			let mut quad = world.get_quad_mut(GlobalColumnPosition::new(-1, -1)).unwrap();
+
+			assert_eq!(quad.0[3].sections(), 24);
+
+			// Y=0 is 64 blocks above the bottom of the column, and the top block is at Y=319.
+			quad.set_immediate(QuadPosition::new(16, 64, 16), &1);
+			quad.set_immediate(QuadPosition::new(0, 383, 0), &2);
+		}
+
+		let top = world.get(GlobalChunkPosition::new(-1, 19, -1)).unwrap();
+		assert_eq!(*top.get(ColumnPosition::new(0, 383, 0).chunk()), 2);
+
+		let column = world.get_column_mut(GlobalColumnPosition::new(0, 0)).unwrap();
+		assert_eq!(column.len(), 24);
+		assert_eq!(*column[4].get(ColumnPosition::new(0, 0, 0).chunk()), 1);
+		drop(column);
+
+		assert!(world.get_quad_mut(GlobalColumnPosition::new(0, 0)).is_none());
+
+		let mut neighborhood = world.get_neighborhood_mut(GlobalColumnPosition::new(-1, -1), 3, 2, SpillPolicy::Error);
+		let at = NeighborhoodPosition::new(16, 64, 16);
+
+		assert_eq!(neighborhood.get(at), Some(&1));
+		assert!(neighborhood.column_mut(1, 1).is_some());
+		assert!(neighborhood.column_mut(2, 1).is_none());
+		assert!(neighborhood.set_immediate(NeighborhoodPosition::new(32, 0, 0), &3).is_err());
+	}
+
+	#[test]
+	fn test_set_block_errors() {
+		let mut world: World<IndexedCube<u16>> = World::new();
+		let chunk = GlobalChunkPosition::new(0, 0, 0);
+
+		world.set(chunk, IndexedCube::new(4, 0));
+
+		let too_high = GlobalPosition::new(0, 2048, 0);
+
+		assert_eq!(world.set_block(GlobalPosition::new(1, 2, 3), &1), Ok(()));
+		assert_eq!(world.set_block(GlobalPosition::new(16, 0, 0), &1), Err(BlockError::MissingChunk(GlobalChunkPosition::new(1, 0, 0))));
+		assert_eq!(world.set_block(too_high, &1), Err(BlockError::OutOfRange(too_high)));
+		assert_eq!(world.get_block(too_high), None);
+	}
+
+	#[test]
+	fn test_neighborhood_at_edge() {
+		let mut world: World<IndexedCube<u16>> = World::with_height(Height::new(0, 1));
+		let edge = GlobalColumnPosition::new(GlobalColumnPosition::MAX_H, 0);
+
+		world.set_column(edge, (0..1).map(|_| IndexedCube::new(4, 0)));
+		world.set_column(GlobalColumnPosition::new(GlobalColumnPosition::MIN_H, 0), (0..1).map(|_| IndexedCube::new(4, 0)));
+
+		let origin = GlobalColumnPosition::new(GlobalColumnPosition::MAX_H - 1, 0);
+		let mut neighborhood = world.get_neighborhood_mut(origin, 3, 1, SpillPolicy::Record);
+
+		// The column past the edge must not wrap around to the other side of the world.
+		assert!(neighborhood.column_mut(1, 0).is_some());
+		assert!(neighborhood.column_mut(2, 0).is_none());
+
+		let missing = NeighborhoodPosition::new(0, 0, 0);
+		let past_edge = NeighborhoodPosition::new(40, 0, 0);
+
+		assert_eq!(neighborhood.set_immediate(missing, &1), Ok(()));
+		assert_eq!(neighborhood.set_immediate(past_edge, &1), Err(Spilled(past_edge)));
+		assert_eq!(neighborhood.take_spilled(), vec![(GlobalPosition::new(origin.x() * 16, 0, 0), 1)]);
+	}
+
+	#[test]
+	fn test_set_column_validates_first() {
+		let mut world: World<IndexedCube<u16>> = World::with_height(Height::new(0, 4));
+		let position = GlobalColumnPosition::new(2, 3);
+
+		let result = panic::catch_unwind(AssertUnwindSafe(|| {
+			world.set_column(position, (0..3).map(|_| IndexedCube::new(4, 0)));
+		}));
+
+		assert!(result.is_err());
+		assert!(world.get(GlobalChunkPosition::from_column(position, 0)).is_none());
+		assert_eq!(world.sectors().count(), 0);
+	}
+}